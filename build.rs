@@ -1,4 +1,4 @@
-const COMMANDS: &[&str] = &["ping", "create_temp_file", "remove_temp_file", "get_printers", "get_printers_by_name","print_html", "print_pdf", "custom_get_printers_by_name", "custom_print_pdf", "get_jobs", "get_jobs_by_id", "resume_job", "restart_job", "pause_job", "remove_job"];
+const COMMANDS: &[&str] = &["ping", "create_temp_file", "remove_temp_file", "get_printers", "get_printers_by_name","print_html", "print_pdf", "custom_get_printers_by_name", "custom_print_pdf", "get_jobs", "get_jobs_by_id", "resume_job", "restart_job", "pause_job", "remove_job", "raster_image", "generate_barcode", "add_virtual_printer", "remove_virtual_printer", "print_raw", "discover_printers", "get_printer_capabilities", "validate_print_job", "get_printer_status", "start_status_monitor", "stop_status_monitor", "get_printer_supplies", "get_print_history", "export_print_history", "queue_print_pdf", "list_queue", "cancel_queued", "retry_now", "add_printer_group", "remove_printer_group", "get_printer_groups", "print_batch", "cancel_batch", "schedule_print_pdf", "list_schedules", "cancel_schedule", "merge_pdfs", "extract_pdf_pages", "remove_pdf_pages", "rotate_pdf_pages", "split_pdf", "stamp_pdf", "inspect_pdf", "print_image", "print_text", "print_markdown", "export_markdown", "register_template", "remove_template", "get_templates", "render_template", "print_template", "print_table"];
 
 fn main() {
   tauri_plugin::Builder::new(COMMANDS)
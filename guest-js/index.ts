@@ -1,4 +1,5 @@
 import { invoke } from '@tauri-apps/api/core'
+import { listen, type UnlistenFn } from '@tauri-apps/api/event'
 
 export async function ping(value: string): Promise<string | null> {
   return await invoke<{value?: string}>('plugin:printer|ping', {
@@ -18,20 +19,51 @@ export async function getPrinterByName(printerName: string): Promise<string> {
   });
 }
 
+export type DuplexMode = 'one-sided' | 'two-sided-long-edge' | 'two-sided-short-edge';
+export type ColorMode = 'monochrome' | 'color';
+export type ValidationMode = 'strict' | 'lenient';
+/** tile 把大页面按原始尺寸拆分到多张纸上，custom 为百分比，如 { custom: 80 } */
+export type Scaling = 'fit' | 'shrink' | 'none' | 'tile' | { custom: number };
+
+export interface PrintSettings {
+  page_size?: string;
+  duplex?: DuplexMode;
+  color?: ColorMode;
+  tray?: string;
+  copies?: number;
+  resolution?: number;
+  scaling?: Scaling;
+  /** 页面方向与纸张不同时旋转 90 度 */
+  auto_rotate?: boolean;
+}
+
 export interface PrintPdfOptions {
   id: string;
   path: string;
   printer_setting: string;
   remove_after_print: boolean;
+  settings?: PrintSettings;
+  validation?: ValidationMode;
+  watermark?: Watermark;
+  /** 加密文档的打开密码 */
+  password?: string;
 }
 
+/**
+ * 打印PDF，文件不是有效的 PDF 时以“无效的 PDF 文档”错误拒绝，
+ * 加密文档缺少密码或密码错误时以“PDF 需要密码”/“PDF 密码错误”拒绝
+ */
 export async function printPdf(options: PrintPdfOptions): Promise<string> {
-  console.log('打印配置pdf:', options);
+  console.log('打印配置pdf:', { ...options, password: options.password && '***' });
   return await invoke<string>('plugin:printer|print_pdf', {
     id: options.id,
     path: options.path,
     printer_setting: options.printer_setting,
     remove_after_print: options.remove_after_print,
+    settings: options.settings,
+    validation: options.validation,
+    watermark: options.watermark,
+    password: options.password,
   });
 }
 
@@ -54,6 +86,7 @@ export interface PrintHtmlOptions {
   quality?: number;
   grayscale?: boolean;
   copies?: number;
+  watermark?: Watermark;
 }
 
 export async function printHtml(options: PrintHtmlOptions): Promise<string> {
@@ -62,3 +95,935 @@ export async function printHtml(options: PrintHtmlOptions): Promise<string> {
     options: options
   });
 }
+
+export type DitherMode = 'threshold' | 'floyd_steinberg' | 'atkinson';
+
+export type RasterLanguage = 'escpos' | 'zpl';
+
+export interface RasterImageOptions {
+  dot_width?: number;
+  dither?: DitherMode;
+  threshold?: number;
+  language?: RasterLanguage;
+}
+
+/**
+ * 图片（PNG/JPEG base64）转热敏/标签打印机点阵指令，返回指令的 base64 字符串
+ */
+export async function rasterImage(bufferData: string, options: RasterImageOptions = {}): Promise<string> {
+  return await invoke<string>('plugin:printer|raster_image', {
+    buffer_data: bufferData,
+    options,
+  });
+}
+
+export type BarcodeSymbology = 'qr' | 'code128' | 'ean13' | 'pdf417';
+
+export type BarcodeOutput = 'escpos' | 'zpl' | 'svg' | 'png';
+
+export interface BarcodeOptions {
+  symbology: BarcodeSymbology;
+  data: string;
+  output?: BarcodeOutput;
+  module_size?: number;
+  height?: number;
+  show_text?: boolean;
+  error_correction?: 'L' | 'M' | 'Q' | 'H';
+  security_level?: number;
+}
+
+/**
+ * 生成条码：escpos/zpl 返回指令的 base64 字符串，svg 返回 SVG 标记，png 返回 data URI
+ */
+export async function generateBarcode(options: BarcodeOptions): Promise<string> {
+  return await invoke<string>('plugin:printer|generate_barcode', {
+    options,
+  });
+}
+
+export interface RawSocketPrinter {
+  name: string;
+  backend: 'raw';
+  host: string;
+  port?: number;
+  connect_timeout_ms?: number;
+  read_timeout_ms?: number;
+  retries?: number;
+  retry_delay_ms?: number;
+  status_query?: string;
+}
+
+export interface LpdPrinter {
+  name: string;
+  backend: 'lpd';
+  host: string;
+  port?: number;
+  queue: string;
+  user?: string;
+  format?: string;
+  banner?: boolean;
+  connect_timeout_ms?: number;
+}
+
+export interface IppPrinter {
+  name: string;
+  backend: 'ipp';
+  uri: string;
+  user?: string;
+  document_format?: string;
+  connect_timeout_ms?: number;
+}
+
+export type VirtualPrinter = RawSocketPrinter | LpdPrinter | IppPrinter;
+
+export interface RawPrintResult {
+  printer: string;
+  bytes_sent: number;
+  attempts: number;
+  job_id: string | null;
+  status: string | null;
+}
+
+/**
+ * 注册虚拟打印机，注册后会出现在 getPrinters 的结果中，并可按名称打印
+ */
+export async function addVirtualPrinter(printer: VirtualPrinter): Promise<void> {
+  return await invoke<void>('plugin:printer|add_virtual_printer', {
+    printer,
+  });
+}
+
+export async function removeVirtualPrinter(printerName: string): Promise<boolean> {
+  return await invoke<boolean>('plugin:printer|remove_virtual_printer', {
+    printername: printerName,
+  });
+}
+
+/**
+ * 发送原始数据（base64）到虚拟打印机
+ */
+export async function printRaw(
+  printerName: string,
+  bufferData: string,
+  jobName?: string,
+  copies?: number,
+): Promise<RawPrintResult> {
+  return await invoke<RawPrintResult>('plugin:printer|print_raw', {
+    printername: printerName,
+    buffer_data: bufferData,
+    job_name: jobName,
+    copies,
+  });
+}
+
+export interface DiscoveredPrinter {
+  name: string;
+  service_type: 'ipp' | 'ipps' | 'pdl-datastream';
+  host: string;
+  addresses: string[];
+  port: number;
+  make_and_model: string | null;
+  formats: string[];
+  color: boolean | null;
+  duplex: boolean | null;
+  resource_path: string | null;
+  uuid: string | null;
+  printer: VirtualPrinter | null;
+}
+
+/**
+ * 通过 mDNS/DNS-SD 发现局域网打印机，结果中的 printer 可直接传给 addVirtualPrinter
+ */
+export async function discoverPrinters(timeoutMs?: number): Promise<DiscoveredPrinter[]> {
+  return await invoke<DiscoveredPrinter[]>('plugin:printer|discover_printers', {
+    timeout_ms: timeoutMs,
+  });
+}
+
+export interface MediaSize {
+  name: string;
+  width_mm: number | null;
+  height_mm: number | null;
+}
+
+export interface PrinterCapabilities {
+  printer: string;
+  media_sizes: MediaSize[];
+  default_media: string | null;
+  trays: string[];
+  duplex_modes: string[];
+  color_modes: string[];
+  resolutions: { x_dpi: number; y_dpi: number }[];
+  max_copies: number | null;
+  document_formats: string[];
+}
+
+/**
+ * 查询打印机支持的纸张、纸盒、双面、彩色、分辨率等能力
+ */
+export async function getPrinterCapabilities(printerName: string): Promise<PrinterCapabilities> {
+  return await invoke<PrinterCapabilities>('plugin:printer|get_printer_capabilities', {
+    printername: printerName,
+  });
+}
+
+export interface ValidationIssue {
+  setting: string;
+  requested: string;
+  substitute: string | null;
+  message: string;
+}
+
+export interface ValidationReport {
+  valid: boolean;
+  mode: ValidationMode;
+  conflicts: ValidationIssue[];
+  substitutions: ValidationIssue[];
+  settings: PrintSettings;
+}
+
+/**
+ * 按打印机能力校验打印参数，lenient 模式会自动替换不支持的参数（如 A4→Letter）
+ */
+export async function validatePrintJob(
+  printerName: string,
+  settings: PrintSettings,
+  mode?: ValidationMode,
+): Promise<ValidationReport> {
+  return await invoke<ValidationReport>('plugin:printer|validate_print_job', {
+    printername: printerName,
+    settings,
+    mode,
+  });
+}
+
+export type PrinterState =
+  | 'offline'
+  | 'paper_jam'
+  | 'door_open'
+  | 'out_of_paper'
+  | 'error'
+  | 'toner_low'
+  | 'printing'
+  | 'idle'
+  | 'unknown';
+
+export interface PrinterStatus {
+  printer: string;
+  /** 最严重的状态 */
+  state: PrinterState;
+  /** 同时存在的全部状态，按严重程度排序 */
+  conditions: PrinterState[];
+  message?: string | null;
+}
+
+export interface PrinterStatusChange {
+  printer: string;
+  /** 首次查询时为空 */
+  previous?: PrinterStatus | null;
+  current: PrinterStatus;
+}
+
+/**
+ * 查询打印机当前状态
+ */
+export async function getPrinterStatus(printerName: string): Promise<PrinterStatus> {
+  return await invoke<PrinterStatus>('plugin:printer|get_printer_status', {
+    printername: printerName,
+  });
+}
+
+/**
+ * 启动后台状态监控，已在运行的监控会被替换
+ */
+export async function startStatusMonitor(printers: string[], intervalMs?: number): Promise<void> {
+  await invoke('plugin:printer|start_status_monitor', {
+    printers,
+    interval_ms: intervalMs,
+  });
+}
+
+/**
+ * 停止后台状态监控，返回之前是否在运行
+ */
+export async function stopStatusMonitor(): Promise<boolean> {
+  return await invoke<boolean>('plugin:printer|stop_status_monitor');
+}
+
+/**
+ * 监听 `printer://status-changed` 事件
+ */
+export async function onPrinterStatusChanged(
+  handler: (change: PrinterStatusChange) => void,
+): Promise<UnlistenFn> {
+  return await listen<PrinterStatusChange>('printer://status-changed', (event) => handler(event.payload));
+}
+
+export type SupplyState = 'ok' | 'low' | 'empty' | 'unknown';
+
+export interface Supply {
+  name: string;
+  /** IPP marker-types 关键字，如 toner、ink-cartridge、opc、waste-toner；纸盒为 paper */
+  kind: string;
+  /** black/cyan/magenta/yellow 或打印机给出的颜色值 */
+  color?: string | null;
+  /** 余量百分比，打印机未报告具体数值时为空 */
+  level_percent?: number | null;
+  state: SupplyState;
+}
+
+/**
+ * 查询耗材和纸盒余量，SNMP 查询时可以指定 community（默认 public）
+ */
+export async function getPrinterSupplies(printerName: string, community?: string): Promise<Supply[]> {
+  return await invoke<Supply[]>('plugin:printer|get_printer_supplies', {
+    printername: printerName,
+    community,
+  });
+}
+
+export type JobOutcome = 'submitted' | 'failed';
+
+export interface HistoryEntry {
+  id: number;
+  job_id?: string | null;
+  document_name: string;
+  /** 文档内容的 SHA-256 */
+  document_hash?: string | null;
+  printer: string;
+  settings?: PrintSettings | null;
+  pages?: number | null;
+  user?: string | null;
+  outcome: JobOutcome;
+  message?: string | null;
+  /** 毫秒时间戳 */
+  submitted_at: number;
+  completed_at?: number | null;
+}
+
+export interface HistoryFilter {
+  printer?: string;
+  user?: string;
+  outcome?: JobOutcome;
+  /** 文档名包含该文本 */
+  document_name?: string;
+  document_hash?: string;
+  /** 提交时间下限（毫秒时间戳，含） */
+  from?: number;
+  /** 提交时间上限（毫秒时间戳，不含） */
+  to?: number;
+  /** 默认 1000，导出时不限 */
+  limit?: number;
+  offset?: number;
+}
+
+/**
+ * 查询打印历史，最新的在前
+ */
+export async function getPrintHistory(filter?: HistoryFilter): Promise<HistoryEntry[]> {
+  return await invoke<HistoryEntry[]>('plugin:printer|get_print_history', {
+    filter,
+  });
+}
+
+/**
+ * 导出打印历史为 CSV（默认）或 JSON 文件，返回导出的条数
+ */
+export async function exportPrintHistory(
+  path: string,
+  format?: 'csv' | 'json',
+  filter?: HistoryFilter,
+): Promise<number> {
+  return await invoke<number>('plugin:printer|export_print_history', {
+    path,
+    format,
+    filter,
+  });
+}
+
+export type QueueStatus = 'pending' | 'printing' | 'failed';
+
+export interface QueuedJob {
+  id: string;
+  printer: string;
+  document_name: string;
+  /** 队列目录中的文档副本 */
+  path: string;
+  settings?: PrintSettings | null;
+  /** 数值大的先打印 */
+  priority: number;
+  status: QueueStatus;
+  attempts: number;
+  last_error?: string | null;
+  /** 毫秒时间戳 */
+  created_at: number;
+  next_attempt_at: number;
+}
+
+export interface QueuePrintOptions {
+  printerName: string;
+  path: string;
+  settings?: PrintSettings;
+  validation?: ValidationMode;
+  priority?: number;
+  /** 加入队列后删除原文件 */
+  removeAfterPrint?: boolean;
+}
+
+/**
+ * 加入打印队列，打印机离线时自动重试，应用重启后继续打印
+ */
+export async function queuePrintPdf(options: QueuePrintOptions): Promise<QueuedJob> {
+  return await invoke<QueuedJob>('plugin:printer|queue_print_pdf', {
+    printer_setting: options.printerName,
+    path: options.path,
+    settings: options.settings,
+    validation: options.validation,
+    priority: options.priority,
+    remove_after_print: options.removeAfterPrint,
+  });
+}
+
+/**
+ * 查看打印队列，按打印顺序排列
+ */
+export async function listQueue(): Promise<QueuedJob[]> {
+  return await invoke<QueuedJob[]>('plugin:printer|list_queue');
+}
+
+/**
+ * 取消队列中的作业，返回是否找到该作业
+ */
+export async function cancelQueued(id: string): Promise<boolean> {
+  return await invoke<boolean>('plugin:printer|cancel_queued', { id });
+}
+
+/**
+ * 立即重试队列中的作业
+ */
+export async function retryNow(id: string): Promise<boolean> {
+  return await invoke<boolean>('plugin:printer|retry_now', { id });
+}
+
+export type GroupStrategy = 'failover' | 'least_busy';
+
+export interface PrinterGroup {
+  name: string;
+  /** 系统打印机或虚拟打印机名称，按优先顺序 */
+  members: string[];
+  /** 默认 failover */
+  strategy?: GroupStrategy;
+}
+
+/**
+ * 注册打印机组，按组名打印时发往第一台可用的成员
+ */
+export async function addPrinterGroup(group: PrinterGroup): Promise<void> {
+  await invoke('plugin:printer|add_printer_group', { group });
+}
+
+/**
+ * 删除打印机组
+ */
+export async function removePrinterGroup(name: string): Promise<boolean> {
+  return await invoke<boolean>('plugin:printer|remove_printer_group', { name });
+}
+
+/**
+ * 获取已注册的打印机组
+ */
+export async function getPrinterGroups(): Promise<PrinterGroup[]> {
+  return await invoke<PrinterGroup[]>('plugin:printer|get_printer_groups');
+}
+
+export interface BatchDocument {
+  path: string;
+  settings?: PrintSettings;
+}
+
+export interface PrintBatchOptions {
+  /** 批量打印ID，用于 cancelBatch，只能包含字母、数字、_ 和 - */
+  id: string;
+  /** 打印机名称或打印机组名 */
+  printerName: string;
+  documents: BatchDocument[];
+  /** 合并为一个打印作业，只合并相邻且打印参数相同的文档 */
+  merge?: boolean;
+  /** 合并时在文档之间插入空白分隔页 */
+  separatorPages?: boolean;
+  validation?: ValidationMode;
+  /** 提交成功后删除文件 */
+  removeAfterPrint?: boolean;
+}
+
+export type BatchItemStatus = 'submitted' | 'failed' | 'cancelled';
+
+export interface BatchItemResult {
+  path: string;
+  status: BatchItemStatus;
+  message?: string;
+  /** 所在的打印作业序号，从 1 开始 */
+  job: number;
+}
+
+export interface BatchResult {
+  id: string;
+  cancelled: boolean;
+  items: BatchItemResult[];
+}
+
+/**
+ * 批量打印，按顺序提交并返回每个文档的结果
+ */
+export async function printBatch(options: PrintBatchOptions): Promise<BatchResult> {
+  return await invoke<BatchResult>('plugin:printer|print_batch', {
+    id: options.id,
+    printer_setting: options.printerName,
+    documents: options.documents,
+    merge: options.merge,
+    separator_pages: options.separatorPages,
+    validation: options.validation,
+    remove_after_print: options.removeAfterPrint,
+  });
+}
+
+/**
+ * 取消批量打印，尚未提交的文档不再打印
+ */
+export async function cancelBatch(id: string): Promise<boolean> {
+  return await invoke<boolean>('plugin:printer|cancel_batch', { id });
+}
+
+export interface Schedule {
+  id: string;
+  printer: string;
+  document_name: string;
+  /** 计划目录中的文档副本 */
+  path: string;
+  settings?: PrintSettings | null;
+  priority: number;
+  /** 重复执行的 cron 表达式（本地时间），为空时只执行一次 */
+  cron?: string | null;
+  /** 毫秒时间戳 */
+  next_run_at: number;
+  last_run_at?: number | null;
+  last_error?: string | null;
+  created_at: number;
+}
+
+export interface SchedulePrintOptions {
+  /** 打印机名称或打印机组名 */
+  printerName: string;
+  path: string;
+  /** 最早执行时间（毫秒时间戳） */
+  notBefore?: number;
+  /** 重复执行的 cron 表达式：分 时 日 月 周，如 '0 6 * * 1-5' */
+  cron?: string;
+  settings?: PrintSettings;
+  validation?: ValidationMode;
+  /** 加入队列时的优先级 */
+  priority?: number;
+  /** 添加计划后删除原文件 */
+  removeAfterPrint?: boolean;
+}
+
+export interface ScheduleFired {
+  schedule: Schedule;
+  /** 加入打印队列后的作业ID */
+  job_id?: string | null;
+  error?: string | null;
+}
+
+/**
+ * 计划打印，到时间后加入打印队列，至少指定 notBefore 或 cron
+ */
+export async function schedulePrintPdf(options: SchedulePrintOptions): Promise<Schedule> {
+  return await invoke<Schedule>('plugin:printer|schedule_print_pdf', {
+    printer_setting: options.printerName,
+    path: options.path,
+    not_before: options.notBefore,
+    cron: options.cron,
+    settings: options.settings,
+    validation: options.validation,
+    priority: options.priority,
+    remove_after_print: options.removeAfterPrint,
+  });
+}
+
+/**
+ * 查看打印计划，按下次执行时间排列
+ */
+export async function listSchedules(): Promise<Schedule[]> {
+  return await invoke<Schedule[]>('plugin:printer|list_schedules');
+}
+
+/**
+ * 取消打印计划，已加入队列的作业不受影响
+ */
+export async function cancelSchedule(id: string): Promise<boolean> {
+  return await invoke<boolean>('plugin:printer|cancel_schedule', { id });
+}
+
+/**
+ * 监听 `printer://schedule-fired` 事件
+ */
+export async function onScheduleFired(handler: (event: ScheduleFired) => void): Promise<UnlistenFn> {
+  return await listen<ScheduleFired>('printer://schedule-fired', (event) => handler(event.payload));
+}
+
+/**
+ * 按顺序合并PDF，返回输出文件路径，可直接传给 printPdf
+ */
+export async function mergePdfs(paths: string[], separatorPages?: boolean): Promise<string> {
+  return await invoke<string>('plugin:printer|merge_pdfs', { paths, separator_pages: separatorPages });
+}
+
+/**
+ * 提取页面，按页码范围的书写顺序输出，如 '1-3,5,8-'；'3,1,2' 可用于重排页面
+ */
+export async function extractPdfPages(path: string, pages: string): Promise<string> {
+  return await invoke<string>('plugin:printer|extract_pdf_pages', { path, pages });
+}
+
+/**
+ * 删除页面，如空白页
+ */
+export async function removePdfPages(path: string, pages: string): Promise<string> {
+  return await invoke<string>('plugin:printer|remove_pdf_pages', { path, pages });
+}
+
+/**
+ * 顺时针旋转页面，角度为 90 的倍数，不指定 pages 时旋转全部页面
+ */
+export async function rotatePdfPages(path: string, degrees: number, pages?: string): Promise<string> {
+  return await invoke<string>('plugin:printer|rotate_pdf_pages', { path, degrees, pages });
+}
+
+/**
+ * 拆分PDF，每个页码范围一个文件，不指定 ranges 时每页一个文件
+ */
+export async function splitPdf(path: string, ranges?: string[]): Promise<string[]> {
+  return await invoke<string[]>('plugin:printer|split_pdf', { path, ranges });
+}
+
+export type WatermarkPosition =
+  | 'top_left' | 'top' | 'top_right'
+  | 'left' | 'center' | 'right'
+  | 'bottom_left' | 'bottom' | 'bottom_right';
+
+export interface Watermark {
+  /** 文字，支持 {user}、{date}、{time} 占位符 */
+  text?: string;
+  /** PNG/JPEG 图片 base64 字符串 */
+  image?: string;
+  /** 图片宽度（pt） */
+  image_width?: number;
+  font_size?: number;
+  /** #RRGGBB，默认 #808080 */
+  color?: string;
+  /** 0-1，默认 0.3 */
+  opacity?: number;
+  /** 逆时针旋转角度 */
+  angle?: number;
+  /** 默认 center */
+  position?: WatermarkPosition;
+  /** 与页面边缘的距离（pt），默认 36 */
+  margin?: number;
+  /** 页码范围，如 '1'、'2-'，默认全部页面 */
+  pages?: string;
+}
+
+/**
+ * 给PDF添加水印或印章，返回输出文件路径，原文件不变
+ */
+export async function stampPdf(path: string, watermark: Watermark): Promise<string> {
+  return await invoke<string>('plugin:printer|stamp_pdf', { path, watermark });
+}
+
+export type PageOrientation = 'portrait' | 'landscape';
+
+export interface PdfPage {
+  number: number;
+  /** [x0, y0, x1, y1]，单位 pt */
+  media_box: [number, number, number, number];
+  rotate: number;
+  orientation: PageOrientation;
+}
+
+export interface PdfInfo {
+  valid: boolean;
+  error: string | null;
+  version: string | null;
+  pages: number;
+  page_sizes: PdfPage[];
+  encrypted: boolean;
+  /** 加密且没有提供正确的密码，页面和元数据无法读取 */
+  locked: boolean;
+  title: string | null;
+  author: string | null;
+  subject: string | null;
+  creator: string | null;
+  producer: string | null;
+}
+
+/**
+ * 检查PDF的页数、页面尺寸、元数据、是否加密和文件结构，文件无效时 valid 为 false
+ */
+export async function inspectPdf(path: string, password?: string): Promise<PdfInfo> {
+  return await invoke<PdfInfo>('plugin:printer|inspect_pdf', { path, password });
+}
+
+export type ImageFit = 'fit' | 'fill' | 'actual_size';
+export type ImageOrientation = 'auto' | 'portrait' | 'landscape';
+
+export interface ImageLayout {
+  /** fit：完整显示；fill：铺满，超出部分裁掉；actual_size：按分辨率的实际尺寸。默认 fit */
+  fit?: ImageFit;
+  /** 默认 auto，按图片宽高选择 */
+  orientation?: ImageOrientation;
+  /** 与纸张边缘的距离（pt），默认 18 */
+  margin?: number;
+  /** 覆盖图片自带的分辨率，图片没有时按 96 */
+  dpi?: number;
+}
+
+export interface PrintImageOptions {
+  id: string;
+  /** PNG、JPEG 或 TIFF 文件，每张图片（多页 TIFF 的每一页）一页 */
+  paths: string[];
+  printer_setting: string;
+  remove_after_print: boolean;
+  layout?: ImageLayout;
+  /** 纸张取 page_size，默认 A4 */
+  settings?: PrintSettings;
+  validation?: ValidationMode;
+}
+
+/**
+ * 打印图片，转换为PDF后按 printPdf 提交，图片无法读取时以“图片转换失败”错误拒绝
+ */
+export async function printImage(options: PrintImageOptions): Promise<string> {
+  return await invoke<string>('plugin:printer|print_image', {
+    id: options.id,
+    paths: options.paths,
+    printer_setting: options.printer_setting,
+    remove_after_print: options.remove_after_print,
+    layout: options.layout,
+    settings: options.settings,
+    validation: options.validation,
+  });
+}
+
+export type TextFont = 'courier' | 'helvetica' | 'times';
+export type CjkFont = 'simplified_chinese' | 'traditional_chinese' | 'japanese' | 'korean';
+export type TextEncoding = 'utf8' | 'gbk';
+export type TextWrap = 'char' | 'word' | 'none';
+
+export interface TextOptions {
+  /** 西文字体，默认 courier（等宽） */
+  font?: TextFont;
+  /** 字号（pt），默认 10 */
+  font_size?: number;
+  /** 中日韩文字使用的字体，默认 simplified_chinese */
+  cjk_font?: CjkFont;
+  /** 文件编码，只用于 path，默认 utf8，有 BOM 时按 BOM */
+  encoding?: TextEncoding;
+  line_numbers?: boolean;
+  /** 默认 4 */
+  tab_width?: number;
+  /** char：任意字符处换行；word：空格或 CJK 字符处换行；none：超出部分裁掉。默认 char */
+  wrap?: TextWrap;
+  /** 页眉：标题、打印时间和页码 */
+  header?: boolean;
+  /** 页眉标题，默认文件名 */
+  title?: string;
+  /** 与纸张边缘的距离（pt），默认 36 */
+  margin?: number;
+}
+
+export interface PrintTextOptions {
+  id: string;
+  /** 文本内容，与 path 二选一 */
+  text?: string;
+  /** 文本文件路径，与 text 二选一 */
+  path?: string;
+  printer_setting: string;
+  options?: TextOptions;
+  /** 纸张取 page_size，默认 A4 */
+  settings?: PrintSettings;
+  validation?: ValidationMode;
+}
+
+/**
+ * 打印纯文本，排版为PDF后按 printPdf 提交，换页符（\f）开始新的一页
+ */
+export async function printText(options: PrintTextOptions): Promise<string> {
+  return await invoke<string>('plugin:printer|print_text', {
+    id: options.id,
+    text: options.text,
+    path: options.path,
+    printer_setting: options.printer_setting,
+    options: options.options,
+    settings: options.settings,
+    validation: options.validation,
+  });
+}
+
+export interface MarkdownStyle {
+  /** 替换内置的打印样式表 */
+  stylesheet?: string;
+  /** 追加在样式表之后，用于微调内置样式 */
+  extra_css?: string;
+  /** HTML 标题，默认文件名 */
+  title?: string;
+  /** 相对图片路径的目录，默认为 Markdown 文件所在目录 */
+  base_dir?: string;
+}
+
+export interface PrintMarkdownOptions extends Omit<PrintHtmlOptions, 'html'> {
+  /** Markdown 内容，与 path 二选一 */
+  markdown?: string;
+  /** Markdown 文件路径，与 markdown 二选一 */
+  path?: string;
+  style?: MarkdownStyle;
+}
+
+/**
+ * 打印 Markdown（表格、代码块、图片），转换为 HTML 后按 printHtml 提交
+ */
+export async function printMarkdown(options: PrintMarkdownOptions): Promise<string> {
+  return await invoke<string>('plugin:printer|print_markdown', { options });
+}
+
+export interface ExportMarkdownOptions {
+  markdown?: string;
+  path?: string;
+  style?: MarkdownStyle;
+  /** 保存 HTML 的文件路径 */
+  output?: string;
+}
+
+/**
+ * 把 Markdown 转换为带打印样式的 HTML，提供 output 时同时保存到文件
+ */
+export async function exportMarkdown(options: ExportMarkdownOptions): Promise<string> {
+  return await invoke<string>('plugin:printer|export_markdown', {
+    markdown: options.markdown,
+    path: options.path,
+    style: options.style,
+    output: options.output,
+  });
+}
+
+export interface TemplateSource {
+  name: string;
+  /** 模板内容（Jinja2 语法），与 path 二选一 */
+  source?: string;
+  /** 模板文件，注册时读取，与 source 二选一 */
+  path?: string;
+}
+
+export interface TemplateInfo {
+  name: string;
+  path: string | null;
+  /** 同名模板每注册一次加 1 */
+  version: number;
+  /** 模板内容的 SHA-256 */
+  hash: string;
+  /** 毫秒时间戳 */
+  registered_at: number;
+}
+
+/**
+ * 注册 HTML 模板，同名的会被替换，模板有语法错误时以“模板: ...”错误拒绝
+ */
+export async function registerTemplate(template: TemplateSource): Promise<TemplateInfo> {
+  return await invoke<TemplateInfo>('plugin:printer|register_template', { template });
+}
+
+/**
+ * 删除模板
+ */
+export async function removeTemplate(name: string): Promise<boolean> {
+  return await invoke<boolean>('plugin:printer|remove_template', { name });
+}
+
+/**
+ * 获取已注册的模板
+ */
+export async function getTemplates(): Promise<TemplateInfo[]> {
+  return await invoke<TemplateInfo[]>('plugin:printer|get_templates');
+}
+
+/**
+ * 用数据渲染模板，返回 HTML，提供 output 时同时保存到文件
+ */
+export async function renderTemplate(name: string, data?: unknown, output?: string): Promise<string> {
+  return await invoke<string>('plugin:printer|render_template', { name, data, output });
+}
+
+export interface PrintTemplateOptions extends Omit<PrintHtmlOptions, 'html'> {
+  /** 模板名称 */
+  template: string;
+  data?: unknown;
+}
+
+/**
+ * 用数据渲染模板后按 printHtml 提交
+ */
+export async function printTemplate(options: PrintTemplateOptions): Promise<string> {
+  return await invoke<string>('plugin:printer|print_template', { options });
+}
+
+export type ColumnAlign = 'left' | 'center' | 'right';
+export type TableTotal = 'sum' | 'average' | 'count' | 'min' | 'max';
+
+export interface NumberFormat {
+  /** 小数位数，默认原样显示 */
+  decimals?: number;
+  /** 整数部分每三位加逗号 */
+  thousands?: boolean;
+  /** 如 ¥ */
+  prefix?: string;
+  /** 如 % */
+  suffix?: string;
+}
+
+export interface TableColumn {
+  /** JSON 对象的字段名或 CSV 表头中的列名，为空时按列的顺序取值 */
+  key?: string;
+  /** 表头，默认为 key */
+  header?: string;
+  /** CSS 宽度，如 20%、30mm */
+  width?: string;
+  /** 默认数字列右对齐，其他左对齐 */
+  align?: ColumnAlign;
+  format?: NumberFormat;
+  /** 在合计行中显示的统计值 */
+  total?: TableTotal;
+}
+
+export interface PrintTableOptions extends Omit<PrintHtmlOptions, 'html'> {
+  /** CSV 内容，与 rows 二选一 */
+  csv?: string;
+  /** JSON 数组，元素为对象或数组，与 csv 二选一 */
+  rows?: unknown[];
+  /** 为空时显示全部列 */
+  columns?: TableColumn[];
+  /** CSV 第一行是否为表头，默认 true */
+  csv_header?: boolean;
+  /** CSV 分隔符，默认逗号 */
+  delimiter?: string;
+  title?: string;
+  /** 合计行的标题，默认“合计” */
+  totals_label?: string;
+  /** 追加在内置样式表之后 */
+  extra_css?: string;
+}
+
+/**
+ * 打印表格，转换为 HTML 后按 printHtml 提交，分页时每页重复表头
+ */
+export async function printTable(options: PrintTableOptions): Promise<string> {
+  return await invoke<string>('plugin:printer|print_table', { options });
+}
@@ -0,0 +1,670 @@
+use base64::{Engine as _, engine::general_purpose};
+use image::{GrayImage, Luma};
+use qrcode::{Color, EcLevel, QrCode};
+
+use crate::declare::{BarcodeOptions, BarcodeOutput, BarcodeSymbology, QrErrorCorrection};
+use crate::pdf417;
+
+/// Code 128 条空宽度表，下标即码值，最后一项为终止符
+const CODE128_PATTERNS: [&str; 107] = [
+    "212222", "222122", "222221", "121223", "121322", "131222", "122213", "122312", "132212", "221213",
+    "221312", "231212", "112232", "122132", "122231", "113222", "123122", "123221", "223211", "221132",
+    "221231", "213212", "223112", "312131", "311222", "321122", "321221", "312212", "322112", "322211",
+    "212123", "212321", "232121", "111323", "131123", "131321", "112313", "132113", "132311", "211313",
+    "231113", "231311", "112133", "112331", "132131", "113123", "113321", "133121", "313121", "211331",
+    "231131", "213113", "213311", "213131", "311123", "311321", "331121", "312113", "312311", "332111",
+    "314111", "221411", "431111", "111224", "111422", "121124", "121421", "141122", "141221", "112214",
+    "112412", "122114", "122411", "142112", "142211", "241211", "221114", "413111", "241112", "134111",
+    "111242", "121142", "121241", "114212", "124112", "124211", "411212", "421112", "421211", "212141",
+    "214121", "412121", "111143", "111341", "131141", "114113", "114311", "411113", "411311", "113141",
+    "114131", "311141", "411131", "211412", "211214", "211232", "2331112",
+];
+
+const CODE128_START: [u8; 3] = [103, 104, 105];
+const CODE128_SWITCH_A: u8 = 101;
+const CODE128_SWITCH_B: u8 = 100;
+const CODE128_SWITCH_C: u8 = 99;
+const CODE128_STOP: u8 = 106;
+
+/// EAN-13 左侧 L 编码，G 编码为 R 编码的逆序，R 编码为 L 编码取反
+const EAN_L: [&str; 10] = [
+    "0001101", "0011001", "0010011", "0111101", "0100011",
+    "0110001", "0101111", "0111011", "0110111", "0001011",
+];
+
+/// EAN-13 首位数字决定左侧六位的 L/G 奇偶组合
+const EAN_PARITY: [&str; 10] = [
+    "LLLLLL", "LLGLGG", "LLGGLG", "LLGGGL", "LGLLGG",
+    "LGGLLG", "LGGGLL", "LGLGLG", "LGLGGL", "LGGLGL",
+];
+
+/// 一维码左右静区（模块数）
+const LINEAR_QUIET_ZONE: usize = 10;
+/// 二维码四周静区（模块数）
+const MATRIX_QUIET_ZONE: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Code128Set {
+    A,
+    B,
+    C,
+}
+
+/// 条码模块矩阵，一维码只有一行，`true` 表示深色模块
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub width: usize,
+    pub height: usize,
+    pub modules: Vec<bool>,
+    /// 一维码下方的人眼可读文字
+    pub text: Option<String>,
+}
+
+impl Symbol {
+    pub fn is_linear(&self) -> bool {
+        self.height == 1
+    }
+
+    fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.width + x]
+    }
+}
+
+/**
+ * 生成条码
+ *
+ * - `escpos` / `zpl`：打印机原生指令，返回 base64 字符串
+ * - `svg`：返回 SVG 标记，可直接嵌入 HTML
+ * - `png`：返回 `data:image/png;base64,...`，可直接作为 `<img>` 的 src
+ */
+pub fn generate(options: &BarcodeOptions) -> Result<String, String> {
+    match options.output.unwrap_or_default() {
+        BarcodeOutput::Escpos => Ok(general_purpose::STANDARD.encode(escpos_command(options)?)),
+        BarcodeOutput::Zpl => Ok(general_purpose::STANDARD.encode(zpl_command(options)?)),
+        BarcodeOutput::Svg => {
+            let symbol = encode(options)?;
+            Ok(to_svg(&symbol, module_size(options), bar_height(options), show_text(options)))
+        }
+        BarcodeOutput::Png => {
+            let symbol = encode(options)?;
+            let png = to_png(&symbol, module_size(options), bar_height(options))?;
+            Ok(format!("data:image/png;base64,{}", general_purpose::STANDARD.encode(png)))
+        }
+    }
+}
+
+fn module_size(options: &BarcodeOptions) -> u32 {
+    let default = match options.symbology {
+        BarcodeSymbology::Qr => 4,
+        _ => 2,
+    };
+    options.module_size.unwrap_or(default).max(1)
+}
+
+fn bar_height(options: &BarcodeOptions) -> u32 {
+    options.height.unwrap_or(80).max(1)
+}
+
+fn show_text(options: &BarcodeOptions) -> bool {
+    options.show_text.unwrap_or(true)
+}
+
+/// 将数据编码为模块矩阵
+pub fn encode(options: &BarcodeOptions) -> Result<Symbol, String> {
+    if options.data.is_empty() {
+        return Err("条码内容不能为空".to_string());
+    }
+    match options.symbology {
+        BarcodeSymbology::Qr => encode_qr(&options.data, options.error_correction.unwrap_or_default()),
+        BarcodeSymbology::Code128 => encode_code128(&options.data),
+        BarcodeSymbology::Ean13 => encode_ean13(&options.data),
+        BarcodeSymbology::Pdf417 => pdf417::encode(options.data.as_bytes(), options.security_level.unwrap_or(2)),
+    }
+}
+
+fn encode_qr(data: &str, level: QrErrorCorrection) -> Result<Symbol, String> {
+    let ec_level = match level {
+        QrErrorCorrection::L => EcLevel::L,
+        QrErrorCorrection::M => EcLevel::M,
+        QrErrorCorrection::Q => EcLevel::Q,
+        QrErrorCorrection::H => EcLevel::H,
+    };
+    let code = QrCode::with_error_correction_level(data, ec_level)
+        .map_err(|e| format!("QR 码生成失败: {}", e))?;
+    let width = code.width();
+    let modules = code.to_colors().into_iter().map(|c| c == Color::Dark).collect();
+    Ok(Symbol { width, height: width, modules, text: None })
+}
+
+/// 计算 Code 128 码值序列（含起始符、校验位和终止符）
+pub fn code128_values(data: &str) -> Result<Vec<u8>, String> {
+    let bytes = data.as_bytes();
+    if bytes.iter().any(|&b| b > 127) {
+        return Err("Code128 仅支持 ASCII 字符".to_string());
+    }
+
+    let mut values = Vec::with_capacity(bytes.len() + 4);
+    let mut set: Option<Code128Set> = None;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let digits = bytes[i..].iter().take_while(|b| b.is_ascii_digit()).count();
+        let use_c = if set == Some(Code128Set::C) {
+            digits >= 2
+        } else {
+            // 数字串在首尾时 4 位即可切换到 C 集，在中间需要 6 位才划算
+            let at_edge = i == 0 || i + digits == bytes.len();
+            digits >= if at_edge { 4 } else { 6 }
+        };
+
+        if use_c {
+            switch_code128_set(&mut values, &mut set, Code128Set::C);
+            for pair in bytes[i..i + digits / 2 * 2].chunks(2) {
+                values.push((pair[0] - b'0') * 10 + (pair[1] - b'0'));
+            }
+            i += digits / 2 * 2;
+            continue;
+        }
+
+        let byte = bytes[i];
+        let target = if byte < 32 {
+            Code128Set::A
+        } else if byte >= 96 {
+            Code128Set::B
+        } else {
+            match set {
+                Some(Code128Set::A) => Code128Set::A,
+                Some(_) => Code128Set::B,
+                None if prefers_code128_set_a(&bytes[i..]) => Code128Set::A,
+                None => Code128Set::B,
+            }
+        };
+        switch_code128_set(&mut values, &mut set, target);
+        values.push(match target {
+            Code128Set::A if byte < 32 => byte + 64,
+            _ => byte - 32,
+        });
+        i += 1;
+    }
+
+    let checksum = values
+        .iter()
+        .enumerate()
+        .map(|(position, &value)| position.max(1) as u32 * value as u32)
+        .sum::<u32>()
+        % 103;
+    values.push(checksum as u8);
+    values.push(CODE128_STOP);
+    Ok(values)
+}
+
+/// 后续先出现控制字符还是小写字符，决定起始使用 A 集还是 B 集
+fn prefers_code128_set_a(rest: &[u8]) -> bool {
+    rest.iter()
+        .find(|&&b| !(32..96).contains(&b))
+        .is_some_and(|&b| b < 32)
+}
+
+fn switch_code128_set(values: &mut Vec<u8>, current: &mut Option<Code128Set>, target: Code128Set) {
+    if *current == Some(target) {
+        return;
+    }
+    let value = match (*current, target) {
+        (None, set) => CODE128_START[set as usize],
+        (_, Code128Set::A) => CODE128_SWITCH_A,
+        (_, Code128Set::B) => CODE128_SWITCH_B,
+        (_, Code128Set::C) => CODE128_SWITCH_C,
+    };
+    values.push(value);
+    *current = Some(target);
+}
+
+fn encode_code128(data: &str) -> Result<Symbol, String> {
+    let mut modules = Vec::new();
+    for value in code128_values(data)? {
+        for (index, width) in CODE128_PATTERNS[value as usize].bytes().enumerate() {
+            let dark = index % 2 == 0;
+            modules.extend(std::iter::repeat(dark).take((width - b'0') as usize));
+        }
+    }
+    Ok(Symbol {
+        width: modules.len(),
+        height: 1,
+        modules,
+        text: Some(data.to_string()),
+    })
+}
+
+/// 校验并补全 EAN-13 数字（接受 12 位或 13 位）
+pub fn ean13_digits(data: &str) -> Result<String, String> {
+    if !data.bytes().all(|b| b.is_ascii_digit()) || !(data.len() == 12 || data.len() == 13) {
+        return Err("EAN-13 需要 12 或 13 位数字".to_string());
+    }
+    let sum: u32 = data
+        .bytes()
+        .take(12)
+        .enumerate()
+        .map(|(i, b)| (b - b'0') as u32 * if i % 2 == 0 { 1 } else { 3 })
+        .sum();
+    let check = char::from(b'0' + ((10 - sum % 10) % 10) as u8);
+
+    if data.len() == 13 && data.as_bytes()[12] != check as u8 {
+        return Err(format!("EAN-13 校验位错误，应为 {}", check));
+    }
+    Ok(format!("{}{}", &data[..12], check))
+}
+
+fn encode_ean13(data: &str) -> Result<Symbol, String> {
+    let digits: Vec<usize> = ean13_digits(data)?.bytes().map(|b| (b - b'0') as usize).collect();
+    let mut pattern = String::from("101");
+
+    for (i, &digit) in digits[1..7].iter().enumerate() {
+        if EAN_PARITY[digits[0]].as_bytes()[i] == b'L' {
+            pattern.push_str(EAN_L[digit]);
+        } else {
+            pattern.extend(ean_r(digit).chars().rev());
+        }
+    }
+    pattern.push_str("01010");
+    for &digit in &digits[7..] {
+        pattern.push_str(&ean_r(digit));
+    }
+    pattern.push_str("101");
+
+    let modules: Vec<bool> = pattern.bytes().map(|b| b == b'1').collect();
+    Ok(Symbol {
+        width: modules.len(),
+        height: 1,
+        modules,
+        text: Some(digits.iter().map(|d| d.to_string()).collect()),
+    })
+}
+
+fn ean_r(digit: usize) -> String {
+    EAN_L[digit].chars().map(|c| if c == '0' { '1' } else { '0' }).collect()
+}
+
+/// 生成 ESC/POS 原生条码指令
+pub fn escpos_command(options: &BarcodeOptions) -> Result<Vec<u8>, String> {
+    let data = options.data.as_bytes();
+    if data.is_empty() {
+        return Err("条码内容不能为空".to_string());
+    }
+    let module = module_size(options);
+    let mut out = Vec::new();
+
+    match options.symbology {
+        BarcodeSymbology::Qr => {
+            let level = 48 + options.error_correction.unwrap_or_default() as u8;
+            out.extend_from_slice(&[0x1D, 0x28, 0x6B, 0x04, 0x00, 0x31, 0x41, 0x32, 0x00]);
+            out.extend_from_slice(&[0x1D, 0x28, 0x6B, 0x03, 0x00, 0x31, 0x43, module.clamp(1, 16) as u8]);
+            out.extend_from_slice(&[0x1D, 0x28, 0x6B, 0x03, 0x00, 0x31, 0x45, level]);
+            out.extend(escpos_store(0x31, data)?);
+            out.extend_from_slice(&[0x1D, 0x28, 0x6B, 0x03, 0x00, 0x31, 0x51, 0x30]);
+        }
+        BarcodeSymbology::Pdf417 => {
+            let security = options.security_level.unwrap_or(2).min(8);
+            out.extend_from_slice(&[0x1D, 0x28, 0x6B, 0x03, 0x00, 0x30, 0x41, 0x00]);
+            out.extend_from_slice(&[0x1D, 0x28, 0x6B, 0x03, 0x00, 0x30, 0x42, 0x00]);
+            out.extend_from_slice(&[0x1D, 0x28, 0x6B, 0x03, 0x00, 0x30, 0x43, module.clamp(2, 8) as u8]);
+            out.extend_from_slice(&[0x1D, 0x28, 0x6B, 0x03, 0x00, 0x30, 0x44, 3]);
+            out.extend_from_slice(&[0x1D, 0x28, 0x6B, 0x04, 0x00, 0x30, 0x45, 0x30, 48 + security]);
+            out.extend(escpos_store(0x30, data)?);
+            out.extend_from_slice(&[0x1D, 0x28, 0x6B, 0x03, 0x00, 0x30, 0x51, 0x30]);
+        }
+        BarcodeSymbology::Code128 | BarcodeSymbology::Ean13 => {
+            let hri = if show_text(options) { 2 } else { 0 };
+            out.extend_from_slice(&[0x1D, 0x48, hri]);
+            out.extend_from_slice(&[0x1D, 0x68, bar_height(options).min(255) as u8]);
+            out.extend_from_slice(&[0x1D, 0x77, module.clamp(2, 6) as u8]);
+
+            let (kind, payload) = if options.symbology == BarcodeSymbology::Code128 {
+                if data.iter().any(|&b| b > 127) {
+                    return Err("Code128 仅支持 ASCII 字符".to_string());
+                }
+                // 使用 B 字符集，`{` 需要转义为 `{{`
+                let mut payload = b"{B".to_vec();
+                for &b in data {
+                    if b == b'{' {
+                        payload.push(b'{');
+                    }
+                    payload.push(b);
+                }
+                (73, payload)
+            } else {
+                (67, ean13_digits(&options.data)?.into_bytes())
+            };
+            if payload.len() > 255 {
+                return Err("条码内容过长".to_string());
+            }
+            out.extend_from_slice(&[0x1D, 0x6B, kind, payload.len() as u8]);
+            out.extend(payload);
+        }
+    }
+    out.push(0x0A);
+    Ok(out)
+}
+
+/// `GS ( k` 存储数据指令，`cn` 为 0x30 (PDF417) 或 0x31 (QR)
+fn escpos_store(cn: u8, data: &[u8]) -> Result<Vec<u8>, String> {
+    let length = data.len() + 3;
+    if length > 0xFFFF {
+        return Err("条码内容过长".to_string());
+    }
+    let mut out = vec![0x1D, 0x28, 0x6B, (length & 0xFF) as u8, (length >> 8) as u8, cn, 0x50, 0x30];
+    out.extend_from_slice(data);
+    Ok(out)
+}
+
+/// 生成 ZPL 原生条码标签
+pub fn zpl_command(options: &BarcodeOptions) -> Result<String, String> {
+    Ok(format!("^XA^FO20,20{}^XZ", zpl_field(options)?))
+}
+
+/// 仅生成条码字段（`^B? ... ^FS`），便于嵌入到已有的标签中
+pub fn zpl_field(options: &BarcodeOptions) -> Result<String, String> {
+    if options.data.is_empty() {
+        return Err("条码内容不能为空".to_string());
+    }
+    let module = module_size(options);
+    let height = bar_height(options);
+    let text = if show_text(options) { "Y" } else { "N" };
+
+    let field = match options.symbology {
+        BarcodeSymbology::Qr => {
+            let level = match options.error_correction.unwrap_or_default() {
+                QrErrorCorrection::L => 'L',
+                QrErrorCorrection::M => 'M',
+                QrErrorCorrection::Q => 'Q',
+                QrErrorCorrection::H => 'H',
+            };
+            format!(
+                "^BQN,2,{}{}",
+                module.clamp(1, 10),
+                zpl_data(&format!("{}A,{}", level, options.data))
+            )
+        }
+        BarcodeSymbology::Code128 => format!(
+            "^BY{}^BCN,{},{},N,N,A{}",
+            module.clamp(1, 10),
+            height,
+            text,
+            zpl_data(&options.data)
+        ),
+        BarcodeSymbology::Ean13 => {
+            let digits = ean13_digits(&options.data)?;
+            format!("^BY{}^BEN,{},{},N{}", module.clamp(1, 10), height, text, zpl_data(&digits[..12]))
+        }
+        BarcodeSymbology::Pdf417 => format!(
+            "^BY{}^B7N,{},{},,,N{}",
+            module.clamp(1, 10),
+            module * 3,
+            options.security_level.unwrap_or(2).min(8),
+            zpl_data(&options.data)
+        ),
+    };
+    Ok(format!("{}^FS", field))
+}
+
+/// `^FD` 字段内容，包含 `^`、`~` 或 `_` 时使用 `^FH` 十六进制转义
+fn zpl_data(data: &str) -> String {
+    if !data.contains(['^', '~', '_']) {
+        return format!("^FD{}", data);
+    }
+    let escaped = data
+        .replace('_', "_5F")
+        .replace('^', "_5E")
+        .replace('~', "_7E");
+    format!("^FH^FD{}", escaped)
+}
+
+/// 渲染为 SVG，一维码的 `bar_height` 以像素计
+pub fn to_svg(symbol: &Symbol, module: u32, bar_height: u32, show_text: bool) -> String {
+    let module = module as usize;
+    let (quiet, rows_height) = if symbol.is_linear() {
+        (LINEAR_QUIET_ZONE, bar_height as usize)
+    } else {
+        (MATRIX_QUIET_ZONE, symbol.height * module)
+    };
+    let text = symbol.text.as_deref().filter(|_| show_text && symbol.is_linear());
+    let font_size = (module * 6).max(10);
+
+    let width = (symbol.width + quiet * 2) * module;
+    let top = if symbol.is_linear() { 0 } else { quiet * module };
+    let mut height = rows_height + top * 2;
+    if text.is_some() {
+        height += font_size + module * 2;
+    }
+
+    let mut path = String::new();
+    let row_height = if symbol.is_linear() { rows_height } else { module };
+    for y in 0..symbol.height {
+        let mut x = 0;
+        while x < symbol.width {
+            if !symbol.is_dark(x, y) {
+                x += 1;
+                continue;
+            }
+            let start = x;
+            while x < symbol.width && symbol.is_dark(x, y) {
+                x += 1;
+            }
+            path.push_str(&format!(
+                "M{},{}h{}v{}h-{}z",
+                (start + quiet) * module,
+                top + y * row_height,
+                (x - start) * module,
+                row_height,
+                (x - start) * module
+            ));
+        }
+    }
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" shape-rendering=\"crispEdges\">\
+<rect width=\"100%\" height=\"100%\" fill=\"#fff\"/><path fill=\"#000\" d=\"{path}\"/>",
+        w = width,
+        h = height,
+        path = path
+    );
+    if let Some(text) = text {
+        svg.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" font-family=\"monospace\" font-size=\"{}\" text-anchor=\"middle\">{}</text>",
+            width / 2,
+            height - module,
+            font_size,
+            escape_xml(text)
+        ));
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// 渲染为 PNG 字节（不含人眼可读文字）
+pub fn to_png(symbol: &Symbol, module: u32, bar_height: u32) -> Result<Vec<u8>, String> {
+    let image = to_image(symbol, module, bar_height);
+    let mut png = Vec::new();
+    image::DynamicImage::ImageLuma8(image)
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .map_err(|e| format!("PNG 编码失败: {}", e))?;
+    Ok(png)
+}
+
+/// 渲染为灰度位图
+pub fn to_image(symbol: &Symbol, module: u32, bar_height: u32) -> GrayImage {
+    let quiet = if symbol.is_linear() { LINEAR_QUIET_ZONE } else { MATRIX_QUIET_ZONE } as u32;
+    let width = (symbol.width as u32 + quiet * 2) * module;
+    let (top, row_height, height) = if symbol.is_linear() {
+        (0, bar_height, bar_height)
+    } else {
+        let side = (symbol.height as u32 + quiet * 2) * module;
+        (quiet * module, module, side)
+    };
+
+    let mut image = GrayImage::from_pixel(width, height, Luma([255]));
+    for y in 0..symbol.height {
+        for x in 0..symbol.width {
+            if !symbol.is_dark(x, y) {
+                continue;
+            }
+            let left = (x as u32 + quiet) * module;
+            let upper = top + y as u32 * row_height;
+            for py in upper..upper + row_height {
+                for px in left..left + module {
+                    image.put_pixel(px, py, Luma([0]));
+                }
+            }
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(symbology: BarcodeSymbology, data: &str) -> BarcodeOptions {
+        BarcodeOptions {
+            symbology,
+            data: data.to_string(),
+            output: None,
+            module_size: None,
+            height: None,
+            show_text: None,
+            error_correction: None,
+            security_level: None,
+        }
+    }
+
+    #[test]
+    fn code128_patterns_are_well_formed() {
+        for (value, pattern) in CODE128_PATTERNS.iter().enumerate() {
+            let widths: Vec<u32> = pattern.bytes().map(|b| (b - b'0') as u32).collect();
+            let bars: u32 = widths.iter().step_by(2).sum();
+            let spaces: u32 = widths.iter().skip(1).step_by(2).sum();
+            let expected = if value == CODE128_STOP as usize { 13 } else { 11 };
+            assert_eq!(bars + spaces, expected, "value {}", value);
+            assert_eq!(bars % 2, 0, "value {}", value);
+        }
+    }
+
+    #[test]
+    fn code128_uses_set_b_for_text() {
+        // 中间三位数字不值得切换到 C 集
+        let values = code128_values("PJJ123C").unwrap();
+        assert_eq!(values, vec![104, 48, 42, 42, 17, 18, 19, 35, 55, 106]);
+    }
+
+    #[test]
+    fn code128_switches_to_set_c_for_digits() {
+        let values = code128_values("12345678").unwrap();
+        assert_eq!(values, vec![105, 12, 34, 56, 78, 47, 106]);
+
+        let values = code128_values("AB123456").unwrap();
+        assert_eq!(&values[..6], &[104, 33, 34, 99, 12, 34]);
+    }
+
+    #[test]
+    fn code128_uses_set_a_for_control_characters() {
+        let values = code128_values("A\tB").unwrap();
+        assert_eq!(&values[..4], &[103, 33, 73, 34]);
+    }
+
+    #[test]
+    fn ean13_check_digit() {
+        assert_eq!(ean13_digits("400638133393").unwrap(), "4006381333931");
+        assert_eq!(ean13_digits("5901234123457").unwrap(), "5901234123457");
+        assert!(ean13_digits("5901234123458").is_err());
+        assert!(ean13_digits("59012341234").is_err());
+    }
+
+    #[test]
+    fn ean13_has_95_modules_with_guards() {
+        let symbol = encode(&options(BarcodeSymbology::Ean13, "5901234123457")).unwrap();
+        assert_eq!(symbol.width, 95);
+        let bits: String = symbol.modules.iter().map(|&m| if m { '1' } else { '0' }).collect();
+        assert!(bits.starts_with("101"));
+        assert_eq!(&bits[45..50], "01010");
+        assert!(bits.ends_with("101"));
+        // 首位 5 → LGGLLG，第二位 9 使用 L 编码
+        assert_eq!(&bits[3..10], EAN_L[9]);
+    }
+
+    #[test]
+    fn qr_matrix_is_square() {
+        let symbol = encode(&options(BarcodeSymbology::Qr, "https://tauri.app")).unwrap();
+        assert_eq!(symbol.width, symbol.height);
+        assert_eq!(symbol.modules.len(), symbol.width * symbol.width);
+        // 左上角定位图案
+        assert!(symbol.is_dark(0, 0) && symbol.is_dark(6, 6) && !symbol.is_dark(1, 1));
+    }
+
+    #[test]
+    fn pdf417_renders_svg_and_png() {
+        let mut pdf417 = options(BarcodeSymbology::Pdf417, "SN-2024-0001");
+        let symbol = encode(&pdf417).unwrap();
+        assert!(!symbol.is_linear() && symbol.text.is_none());
+
+        pdf417.output = Some(BarcodeOutput::Svg);
+        let svg = generate(&pdf417).unwrap();
+        let width = (symbol.width + 2 * MATRIX_QUIET_ZONE) * 2;
+        let height = (symbol.height + 2 * MATRIX_QUIET_ZONE) * 2;
+        assert!(svg.starts_with(&format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\"", width, height)));
+        // 左上角为起始符的 8 个模块宽的条
+        assert!(svg.contains(&format!("M{},{}h16v2h-16z", MATRIX_QUIET_ZONE * 2, MATRIX_QUIET_ZONE * 2)));
+
+        pdf417.output = Some(BarcodeOutput::Png);
+        let png = generate(&pdf417).unwrap();
+        let bytes = general_purpose::STANDARD.decode(png.strip_prefix("data:image/png;base64,").unwrap()).unwrap();
+        let image = image::load_from_memory(&bytes).unwrap().to_luma8();
+        assert_eq!((image.width() as usize, image.height() as usize), (width, height));
+        assert_eq!(image.get_pixel(MATRIX_QUIET_ZONE as u32 * 2, MATRIX_QUIET_ZONE as u32 * 2).0, [0]);
+        assert_eq!(image.get_pixel(0, 0).0, [255]);
+
+        assert!(escpos_command(&options(BarcodeSymbology::Pdf417, "data")).is_ok());
+    }
+
+    #[test]
+    fn escpos_qr_store_length() {
+        let out = escpos_command(&options(BarcodeSymbology::Qr, "hello")).unwrap();
+        let store = [0x1D, 0x28, 0x6B, 8, 0, 0x31, 0x50, 0x30, b'h'];
+        assert!(out.windows(store.len()).any(|w| w == store));
+    }
+
+    #[test]
+    fn escpos_code128_escapes_brace() {
+        let out = escpos_command(&options(BarcodeSymbology::Code128, "a{b")).unwrap();
+        let tail = [0x1D, 0x6B, 73, 6, b'{', b'B', b'a', b'{', b'{', b'b', 0x0A];
+        assert!(out.ends_with(&tail));
+    }
+
+    #[test]
+    fn zpl_fields() {
+        assert_eq!(
+            zpl_field(&options(BarcodeSymbology::Ean13, "5901234123457")).unwrap(),
+            "^BY2^BEN,80,Y,N^FD590123412345^FS"
+        );
+        assert_eq!(
+            zpl_field(&options(BarcodeSymbology::Qr, "A^B")).unwrap(),
+            "^BQN,2,4^FH^FDMA,A_5EB^FS"
+        );
+    }
+
+    #[test]
+    fn svg_and_png_dimensions() {
+        let symbol = encode(&options(BarcodeSymbology::Code128, "ABC")).unwrap();
+        let svg = to_svg(&symbol, 2, 50, true);
+        assert!(svg.starts_with("<svg") && svg.contains(">ABC</text>"));
+
+        let image = to_image(&symbol, 2, 50);
+        assert_eq!(image.width() as usize, (symbol.width + 2 * LINEAR_QUIET_ZONE) * 2);
+        assert_eq!(image.height(), 50);
+    }
+}
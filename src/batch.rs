@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+use serde::Serialize;
+
+use crate::declare::{BatchDocument, PrintSettings};
+use crate::pdf;
+
+/// 正在进行的批量打印及其取消标记
+fn registry() -> MutexGuard<'static, HashMap<String, Arc<AtomicBool>>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<String, Arc<AtomicBool>>>> = OnceLock::new();
+    REGISTRY
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchItemStatus {
+    Submitted,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchItemResult {
+    pub path: String,
+    pub status: BatchItemStatus,
+    /// 后端返回的文本或失败原因
+    pub message: Option<String>,
+    /// 合并打印时所在的作业序号，从 1 开始
+    pub job: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchResult {
+    pub id: String,
+    pub cancelled: bool,
+    pub items: Vec<BatchItemResult>,
+}
+
+/// 批量打印的参数
+pub struct Batch<'a> {
+    pub id: &'a str,
+    pub documents: &'a [BatchDocument],
+    pub merge: bool,
+    pub separator_pages: bool,
+    /// 合并后的临时文件目录
+    pub temp_dir: &'a Path,
+}
+
+/// 正在进行的批量打印，离开作用域（包括出错返回）时从注册表中移除
+struct Running<'a> {
+    id: &'a str,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        registry().remove(self.id);
+    }
+}
+
+/// 标记批量打印开始，同一 ID 不能同时运行两次
+/// ID 会用在合并文件名中，只允许字母、数字、`_` 和 `-`
+fn begin(id: &str) -> Result<Running<'_>, String> {
+    if id.is_empty() || !id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-') {
+        return Err(format!("批量打印 ID {:?} 只能包含字母、数字、_ 和 -", id));
+    }
+    let mut batches = registry();
+    if batches.contains_key(id) {
+        return Err(format!("批量打印 {} 正在进行", id));
+    }
+    let cancelled = Arc::new(AtomicBool::new(false));
+    batches.insert(id.to_string(), cancelled.clone());
+    Ok(Running { id, cancelled })
+}
+
+/**
+ * 取消批量打印，尚未提交的文档不再打印
+ * 已经提交给打印后台的作业不会撤回
+ * @returns 是否找到正在进行的批量打印
+ */
+pub fn cancel(id: &str) -> bool {
+    match registry().get(id) {
+        Some(flag) => {
+            flag.store(true, Ordering::SeqCst);
+            true
+        }
+        None => false,
+    }
+}
+
+fn same_settings(a: &Option<PrintSettings>, b: &Option<PrintSettings>) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+}
+
+/**
+ * 划分打印作业
+ * 合并时相邻且打印参数相同的文档放在同一个作业中，否则每个文档一个作业
+ */
+pub fn jobs(documents: &[BatchDocument], merge: bool) -> Vec<Range<usize>> {
+    let mut jobs: Vec<Range<usize>> = Vec::new();
+    for (index, document) in documents.iter().enumerate() {
+        match jobs.last_mut() {
+            Some(job) if merge && same_settings(&documents[job.start].settings, &document.settings) => {
+                job.end = index + 1;
+            }
+            _ => jobs.push(index..index + 1),
+        }
+    }
+    jobs
+}
+
+/**
+ * 按顺序提交批量打印
+ * @param submit 提交一个 PDF，参数为作业 ID、文件路径和打印参数，返回后端的结果文本
+ * @param is_failure 判断结果文本是否表示失败
+ */
+pub fn run(
+    batch: Batch,
+    mut submit: impl FnMut(String, String, Option<PrintSettings>) -> String,
+    is_failure: impl Fn(&str) -> bool,
+) -> Result<BatchResult, String> {
+    if batch.documents.is_empty() {
+        return Err("批量打印没有文档".to_string());
+    }
+    let running = begin(batch.id)?;
+    let mut items = Vec::with_capacity(batch.documents.len());
+    for (number, range) in jobs(batch.documents, batch.merge).into_iter().enumerate() {
+        let number = number + 1;
+        let documents = &batch.documents[range];
+        let result = if running.cancelled.load(Ordering::SeqCst) {
+            Err(None)
+        } else if documents.len() == 1 {
+            let document = &documents[0];
+            Ok(submit(format!("{}-{}", batch.id, number), document.path.clone(), document.settings.clone()))
+        } else {
+            let merged = batch.temp_dir.join(format!("{}-{}.pdf", batch.id, number));
+            submit_merged(&batch, documents, &merged, number, &mut submit)
+        };
+        let (status, message) = match result {
+            Ok(text) if is_failure(&text) => (BatchItemStatus::Failed, Some(text)),
+            Ok(text) => (BatchItemStatus::Submitted, (!text.trim().is_empty()).then(|| text.trim().to_string())),
+            Err(Some(e)) => (BatchItemStatus::Failed, Some(format!("打印失败: {}", e))),
+            Err(None) => (BatchItemStatus::Cancelled, None),
+        };
+        for document in documents {
+            items.push(BatchItemResult { path: document.path.clone(), status, message: message.clone(), job: number });
+        }
+    }
+    let cancelled = running.cancelled.load(Ordering::SeqCst);
+    drop(running);
+    Ok(BatchResult { id: batch.id.to_string(), cancelled, items })
+}
+
+fn submit_merged(
+    batch: &Batch,
+    documents: &[BatchDocument],
+    merged: &Path,
+    number: usize,
+    submit: &mut impl FnMut(String, String, Option<PrintSettings>) -> String,
+) -> Result<String, Option<String>> {
+    let inputs: Vec<&Path> = documents.iter().map(|d| Path::new(&d.path)).collect();
+    pdf::merge_files(&inputs, batch.separator_pages, merged).map_err(|e| Some(format!("合并失败: {}", e)))?;
+    let text = submit(
+        format!("{}-{}", batch.id, number),
+        merged.to_string_lossy().to_string(),
+        documents[0].settings.clone(),
+    );
+    let _ = std::fs::remove_file(merged);
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn document(path: &Path, copies: Option<u32>) -> BatchDocument {
+        BatchDocument {
+            path: path.to_string_lossy().to_string(),
+            settings: copies.map(|copies| PrintSettings { copies: Some(copies), ..PrintSettings::default() }),
+        }
+    }
+
+    #[test]
+    fn submits_in_order_and_merges_consecutive_documents() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths: Vec<PathBuf> = (0..4)
+            .map(|i| {
+                let path = dir.path().join(format!("{}.pdf", i));
+                let mut sample = pdf::tests::sample(i + 1, [0, 0, 595, 842]);
+                pdf::save(&mut sample, &path).unwrap();
+                path
+            })
+            .collect();
+        let documents = vec![
+            document(&paths[0], None),
+            document(&paths[1], None),
+            document(&paths[2], Some(2)),
+            document(&paths[3], None),
+        ];
+        assert_eq!(jobs(&documents, true), vec![0..2, 2..3, 3..4]);
+        assert_eq!(jobs(&documents, false).len(), 4);
+
+        let mut submitted = Vec::new();
+        let batch = Batch { id: "eod", documents: &documents, merge: true, separator_pages: true, temp_dir: dir.path() };
+        let result = run(
+            batch,
+            |id, path, _| {
+                let pages = pdf::load(Path::new(&path)).map(|d| d.get_pages().len()).unwrap_or(0);
+                submitted.push((id, pages));
+                if pages == 3 { "打印失败: 卡纸".to_string() } else { String::new() }
+            },
+            |text| text.starts_with("打印失败"),
+        )
+        .unwrap();
+        // 1 页 + 空白页 + 2 页合并为一个作业
+        assert_eq!(submitted, vec![("eod-1".to_string(), 4), ("eod-2".to_string(), 3), ("eod-3".to_string(), 4)]);
+        let statuses: Vec<BatchItemStatus> = result.items.iter().map(|i| i.status).collect();
+        use BatchItemStatus::*;
+        assert_eq!(statuses, vec![Submitted, Submitted, Failed, Submitted]);
+        assert_eq!(result.items[1].job, 1);
+        assert!(!dir.path().join("eod-1.pdf").exists());
+    }
+
+    #[test]
+    fn cancels_remaining_documents() {
+        let documents: Vec<BatchDocument> =
+            (0..3).map(|i| document(Path::new(&format!("{}.pdf", i)), None)).collect();
+        let dir = tempfile::tempdir().unwrap();
+        let batch = Batch { id: "cancel", documents: &documents, merge: false, separator_pages: false, temp_dir: dir.path() };
+        let result = run(
+            batch,
+            |id, _, _| {
+                if id == "cancel-1" {
+                    assert!(begin("cancel").is_err());
+                    assert!(cancel("cancel"));
+                }
+                String::new()
+            },
+            |_| false,
+        )
+        .unwrap();
+        assert!(result.cancelled);
+        let statuses: Vec<BatchItemStatus> = result.items.iter().map(|i| i.status).collect();
+        use BatchItemStatus::*;
+        assert_eq!(statuses, vec![Submitted, Cancelled, Cancelled]);
+        assert!(!cancel("cancel"));
+    }
+
+    #[test]
+    fn rejects_unsafe_ids_and_releases_on_panic() {
+        let documents = vec![document(Path::new("0.pdf"), None)];
+        let dir = tempfile::tempdir().unwrap();
+        for id in ["../escape", "a/b", "a b", ""] {
+            let batch = Batch { id, documents: &documents, merge: false, separator_pages: false, temp_dir: dir.path() };
+            assert!(run(batch, |_, _, _| String::new(), |_| false).is_err(), "{}", id);
+        }
+
+        let batch = Batch { id: "panics", documents: &documents, merge: false, separator_pages: false, temp_dir: dir.path() };
+        let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            run(batch, |_, _, _| panic!("后端崩溃"), |_| false)
+        }));
+        assert!(outcome.is_err());
+        assert!(!cancel("panics"));
+        assert!(begin("panics").is_ok());
+    }
+}
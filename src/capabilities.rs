@@ -0,0 +1,292 @@
+use serde_json::Value;
+
+use crate::declare::{IppSettings, MediaSize, PrinterBackend, PrinterCapabilities, Resolution, VirtualPrinter};
+use crate::ipp::{self, IppGroup, IppValue};
+
+/// 查询 IPP 打印机时请求的属性
+const IPP_ATTRIBUTES: [&str; 8] = [
+    "media-supported",
+    "media-default",
+    "media-source-supported",
+    "sides-supported",
+    "print-color-mode-supported",
+    "printer-resolution-supported",
+    "copies-supported",
+    "document-format-supported",
+];
+
+/// Windows 下 print_pdf（SumatraPDF）和 print_html（wkhtmltopdf）接受的格式
+const WINDOWS_FORMATS: [&str; 2] = ["application/pdf", "text/html"];
+
+/**
+ * 查询虚拟打印机的能力，只有 IPP 后端可以查询
+ */
+pub fn for_virtual(printer: &VirtualPrinter) -> Result<PrinterCapabilities, String> {
+    match &printer.backend {
+        PrinterBackend::Ipp(settings) => query_ipp(&printer.name, settings),
+        backend => Err(format!("{} 后端不支持查询打印机能力", backend.kind())),
+    }
+}
+
+pub fn query_ipp(printer: &str, settings: &IppSettings) -> Result<PrinterCapabilities, String> {
+    let attributes = ipp::get_printer_attributes(settings, &IPP_ATTRIBUTES)?;
+    Ok(from_ipp_attributes(printer, &attributes))
+}
+
+/// 将 Get-Printer-Attributes 的结果转换为打印机能力
+pub fn from_ipp_attributes(printer: &str, attributes: &IppGroup) -> PrinterCapabilities {
+    let strings = |name: &str| attributes.get(name).map(|a| a.strings()).unwrap_or_default();
+
+    let resolutions = attributes
+        .get("printer-resolution-supported")
+        .map(|a| {
+            a.values
+                .iter()
+                .filter_map(|value| match value {
+                    // units: 3 = dpi，4 = 每厘米点数
+                    IppValue::Resolution { x, y, units } => {
+                        let factor = if *units == 4 { 2.54 } else { 1.0 };
+                        Some(Resolution {
+                            x_dpi: (*x as f64 * factor).round() as u32,
+                            y_dpi: (*y as f64 * factor).round() as u32,
+                        })
+                    }
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let max_copies = attributes
+        .get("copies-supported")
+        .and_then(|a| a.values.first())
+        .and_then(|value| match value {
+            IppValue::Range(_, upper) => Some((*upper).max(1) as u32),
+            _ => None,
+        });
+
+    PrinterCapabilities {
+        printer: printer.to_string(),
+        media_sizes: strings("media-supported")
+            .into_iter()
+            .map(|name| {
+                let (width_mm, height_mm) = pwg_media_size(&name).unzip();
+                MediaSize { name, width_mm, height_mm }
+            })
+            .collect(),
+        default_media: strings("media-default").into_iter().next(),
+        trays: strings("media-source-supported"),
+        duplex_modes: strings("sides-supported"),
+        color_modes: strings("print-color-mode-supported"),
+        resolutions,
+        max_copies,
+        document_formats: strings("document-format-supported"),
+    }
+}
+
+/**
+ * 解析 PWG 5101.1 自描述纸张名中的尺寸
+ * 如 `iso_a4_210x297mm`、`na_letter_8.5x11in`
+ */
+pub fn pwg_media_size(name: &str) -> Option<(f64, f64)> {
+    let size = name.rsplit('_').next()?;
+    let (size, factor) = if let Some(size) = size.strip_suffix("mm") {
+        (size, 1.0)
+    } else {
+        (size.strip_suffix("in")?, 25.4)
+    };
+    let (width, height) = size.split_once('x')?;
+    let round = |v: f64| (v * factor * 10.0).round() / 10.0;
+    Some((round(width.parse().ok()?), round(height.parse().ok()?)))
+}
+
+/// 常用纸张的尺寸（毫米）
+const STANDARD_MEDIA: [(&str, f64, f64); 12] = [
+    ("A3", 297.0, 420.0),
+    ("A4", 210.0, 297.0),
+    ("A5", 148.0, 210.0),
+    ("A6", 105.0, 148.0),
+    ("B4", 250.0, 353.0),
+    ("B5", 176.0, 250.0),
+    ("Letter", 215.9, 279.4),
+    ("Legal", 215.9, 355.6),
+    ("Tabloid", 279.4, 431.8),
+    ("Executive", 184.2, 266.7),
+    ("Env10", 104.8, 241.3),
+    ("EnvDL", 110.0, 220.0),
+];
+
+/**
+ * 纸张名称对应的尺寸，支持常用名称（不区分大小写）和 PWG 自描述名称
+ */
+pub fn standard_media_size(name: &str) -> Option<(f64, f64)> {
+    STANDARD_MEDIA
+        .iter()
+        .find(|(standard, _, _)| standard.eq_ignore_ascii_case(name.trim()))
+        .map(|(_, width, height)| (*width, *height))
+        .or_else(|| pwg_media_size(name))
+}
+
+/// 尺寸是否相同，允许 2 毫米误差，不区分横竖
+pub fn same_size(a: (f64, f64), b: (f64, f64)) -> bool {
+    let close = |x: f64, y: f64| (x - y).abs() <= 2.0;
+    (close(a.0, b.0) && close(a.1, b.1)) || (close(a.0, b.1) && close(a.1, b.0))
+}
+
+/// ConvertTo-Json 对单元素数组会输出对象，PowerShell 5.1 有时还会包一层 `{ value, Count }`
+fn as_list(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Array(items) => items.iter().collect(),
+        Value::Object(map) if map.contains_key("value") && map.contains_key("Count") => as_list(&map["value"]),
+        Value::Null => Vec::new(),
+        other => vec![other],
+    }
+}
+
+/**
+ * 解析 `windows::get_printer_capabilities` 的输出
+ * @returns 打印机不存在时返回 None
+ */
+pub fn from_windows_json(printer: &str, json: &str) -> Result<Option<PrinterCapabilities>, String> {
+    let value: Value = serde_json::from_str(json.trim()).map_err(|e| format!("解析打印机能力失败: {}", e))?;
+    if value["IsValid"].as_bool() != Some(true) {
+        return Ok(None);
+    }
+
+    // PaperSize 的 Width/Height 单位为百分之一英寸
+    let hundredths_to_mm = |v: &Value| v.as_f64().map(|v| (v * 0.254 * 10.0).round() / 10.0);
+    let media_sizes = as_list(&value["PaperSizes"])
+        .into_iter()
+        .filter_map(|paper| {
+            Some(MediaSize {
+                name: paper["PaperName"].as_str()?.to_string(),
+                width_mm: hundredths_to_mm(&paper["Width"]),
+                height_mm: hundredths_to_mm(&paper["Height"]),
+            })
+        })
+        .collect();
+
+    let duplex_modes = if value["CanDuplex"].as_bool() == Some(true) {
+        vec!["one-sided", "two-sided-long-edge", "two-sided-short-edge"]
+    } else {
+        vec!["one-sided"]
+    };
+    let color_modes = if value["SupportsColor"].as_bool() == Some(true) {
+        vec!["monochrome", "color"]
+    } else {
+        vec!["monochrome"]
+    };
+
+    let mut resolutions: Vec<Resolution> = Vec::new();
+    for resolution in as_list(&value["Resolutions"]) {
+        let (Some(x), Some(y)) = (resolution["X"].as_u64(), resolution["Y"].as_u64()) else {
+            continue;
+        };
+        let resolution = Resolution { x_dpi: x as u32, y_dpi: y as u32 };
+        if !resolutions.contains(&resolution) {
+            resolutions.push(resolution);
+        }
+    }
+
+    Ok(Some(PrinterCapabilities {
+        printer: printer.to_string(),
+        media_sizes,
+        default_media: value["PaperSize"].as_str().filter(|s| !s.is_empty()).map(str::to_string),
+        trays: as_list(&value["PaperSources"])
+            .into_iter()
+            .filter_map(|source| source.as_str().map(str::to_string))
+            .collect(),
+        duplex_modes: duplex_modes.into_iter().map(str::to_string).collect(),
+        color_modes: color_modes.into_iter().map(str::to_string).collect(),
+        resolutions,
+        max_copies: value["MaximumCopies"].as_u64().map(|n| n as u32),
+        document_formats: WINDOWS_FORMATS.iter().map(|f| f.to_string()).collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipp::IppAttribute;
+
+    fn attribute(name: &str, values: Vec<IppValue>) -> IppAttribute {
+        IppAttribute { name: name.to_string(), values }
+    }
+
+    fn texts(values: &[&str]) -> Vec<IppValue> {
+        values.iter().map(|v| IppValue::Text(v.to_string())).collect()
+    }
+
+    #[test]
+    fn parses_pwg_media_names() {
+        assert_eq!(pwg_media_size("iso_a4_210x297mm"), Some((210.0, 297.0)));
+        assert_eq!(pwg_media_size("na_letter_8.5x11in"), Some((215.9, 279.4)));
+        assert_eq!(pwg_media_size("custom_min_25.4x25.4mm"), Some((25.4, 25.4)));
+        assert_eq!(pwg_media_size("auto"), None);
+        assert_eq!(standard_media_size("letter"), Some((215.9, 279.4)));
+        assert!(same_size(standard_media_size("A4").unwrap(), (296.9, 210.1)));
+    }
+
+    #[test]
+    fn converts_ipp_attributes() {
+        let group = IppGroup {
+            tag: ipp::PRINTER_ATTRIBUTES,
+            attributes: vec![
+                attribute("media-supported", texts(&["iso_a4_210x297mm", "na_letter_8.5x11in"])),
+                attribute("media-default", texts(&["iso_a4_210x297mm"])),
+                attribute("media-source-supported", texts(&["auto", "tray-1", "manual"])),
+                attribute("sides-supported", texts(&["one-sided", "two-sided-long-edge"])),
+                attribute("print-color-mode-supported", texts(&["monochrome"])),
+                attribute(
+                    "printer-resolution-supported",
+                    vec![
+                        IppValue::Resolution { x: 600, y: 600, units: 3 },
+                        IppValue::Resolution { x: 118, y: 118, units: 4 },
+                    ],
+                ),
+                attribute("copies-supported", vec![IppValue::Range(1, 999)]),
+                attribute("document-format-supported", texts(&["application/pdf", "image/urf"])),
+            ],
+        };
+
+        let capabilities = from_ipp_attributes("Office", &group);
+        assert_eq!(capabilities.media_sizes.len(), 2);
+        assert_eq!(capabilities.media_sizes[1].width_mm, Some(215.9));
+        assert_eq!(capabilities.default_media.as_deref(), Some("iso_a4_210x297mm"));
+        assert_eq!(capabilities.trays, vec!["auto", "tray-1", "manual"]);
+        assert_eq!(capabilities.duplex_modes, vec!["one-sided", "two-sided-long-edge"]);
+        assert_eq!(
+            capabilities.resolutions,
+            vec![Resolution { x_dpi: 600, y_dpi: 600 }, Resolution { x_dpi: 300, y_dpi: 300 }]
+        );
+        assert_eq!(capabilities.max_copies, Some(999));
+        assert_eq!(capabilities.document_formats, vec!["application/pdf", "image/urf"]);
+    }
+
+    #[test]
+    fn parses_windows_output() {
+        let json = r#"{
+            "IsValid": true,
+            "PaperSizes": [
+                { "PaperName": "A4", "Width": 827, "Height": 1169 },
+                { "PaperName": "Letter", "Width": 850, "Height": 1100 }
+            ],
+            "PaperSources": "Automatically Select",
+            "CanDuplex": true,
+            "SupportsColor": false,
+            "Resolutions": { "value": [{ "X": 600, "Y": 600 }, { "X": 600, "Y": 600 }], "Count": 2 },
+            "MaximumCopies": 9999,
+            "PaperSize": "A4"
+        }"#;
+        let capabilities = from_windows_json("HP", json).unwrap().unwrap();
+        assert_eq!(capabilities.media_sizes[0], MediaSize { name: "A4".to_string(), width_mm: Some(210.1), height_mm: Some(296.9) });
+        assert_eq!(capabilities.trays, vec!["Automatically Select"]);
+        assert_eq!(capabilities.duplex_modes.len(), 3);
+        assert_eq!(capabilities.color_modes, vec!["monochrome"]);
+        assert_eq!(capabilities.resolutions, vec![Resolution { x_dpi: 600, y_dpi: 600 }]);
+        assert_eq!(capabilities.max_copies, Some(9999));
+        assert_eq!(capabilities.default_media.as_deref(), Some("A4"));
+
+        assert!(from_windows_json("missing", r#"{ "IsValid": false }"#).unwrap().is_none());
+    }
+}
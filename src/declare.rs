@@ -6,7 +6,8 @@ pub struct PrintOptions {
     pub id: String,
     pub path: String,
     pub print_setting: String,
-    pub remove_after_print: bool
+    pub remove_after_print: bool,
+    pub settings: Option<PrintSettings>,  // 已校验的打印参数
 }
 
 
@@ -22,6 +23,41 @@ pub struct PrintHtmlOptions {
     pub quality: Option<u32>,  // 1-100
     pub grayscale: Option<bool>,
     pub copies: Option<u32>,
+    pub watermark: Option<Watermark>,
+}
+
+/// PrintHtmlOptions 中除 html 以外的参数，由转换为 HTML 后打印的选项共用
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct HtmlJobOptions {
+    pub printer_id: Option<String>,
+    pub print_settings: Option<String>,
+    pub remove_after_print: Option<bool>,
+    pub page_size: Option<String>,
+    pub orientation: Option<String>,
+    pub margin: Option<PrintMargin>,
+    pub quality: Option<u32>,
+    pub grayscale: Option<bool>,
+    pub copies: Option<u32>,
+    pub watermark: Option<Watermark>,
+}
+
+impl HtmlJobOptions {
+    /// 与转换好的 HTML 组成 print_html 的参数
+    pub fn with_html(self, html: String) -> PrintHtmlOptions {
+        PrintHtmlOptions {
+            html,
+            printer_id: self.printer_id,
+            print_settings: self.print_settings,
+            remove_after_print: self.remove_after_print,
+            page_size: self.page_size,
+            orientation: self.orientation,
+            margin: self.margin,
+            quality: self.quality,
+            grayscale: self.grayscale,
+            copies: self.copies,
+            watermark: self.watermark,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -31,4 +67,547 @@ pub struct PrintMargin {
     pub bottom: Option<f64>,
     pub left: Option<f64>,
     pub unit: Option<String>,  // mm, cm, inch
-}
\ No newline at end of file
+}
+
+/// 点阵抖动算法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DitherMode {
+    Threshold,
+    #[default]
+    FloydSteinberg,
+    Atkinson,
+}
+
+/// 热敏/标签打印机指令语言
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RasterLanguage {
+    #[default]
+    Escpos,
+    Zpl,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RasterImageOptions {
+    pub dot_width: Option<u32>,  // 384 (58mm), 576 (80mm)
+    pub dither: Option<DitherMode>,
+    pub threshold: Option<u8>,  // 0-255，默认 128
+    pub language: Option<RasterLanguage>,
+}
+
+/// 条码类型
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BarcodeSymbology {
+    Qr,
+    Code128,
+    Ean13,
+    Pdf417,
+}
+
+/// 条码输出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BarcodeOutput {
+    Escpos,
+    Zpl,
+    #[default]
+    Svg,
+    Png,
+}
+
+/// QR 码纠错等级，取值顺序与 ESC/POS `GS ( k` 的 48-51 对应
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum QrErrorCorrection {
+    L = 0,
+    #[default]
+    M = 1,
+    Q = 2,
+    H = 3,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BarcodeOptions {
+    pub symbology: BarcodeSymbology,
+    pub data: String,
+    pub output: Option<BarcodeOutput>,
+    pub module_size: Option<u32>,  // 模块宽度（点/像素），QR 默认 4，其余默认 2
+    pub height: Option<u32>,  // 一维码条高（点/像素），默认 80
+    pub show_text: Option<bool>,  // 一维码下方是否显示文字，默认 true
+    pub error_correction: Option<QrErrorCorrection>,  // 仅 QR
+    pub security_level: Option<u8>,  // 仅 PDF417，0-8
+}
+
+/// AppSocket/JetDirect（TCP 9100）连接参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RawSocketSettings {
+    pub host: String,
+    pub port: Option<u16>,  // 默认 9100
+    pub connect_timeout_ms: Option<u64>,  // 默认 5000
+    pub read_timeout_ms: Option<u64>,  // 状态回读超时，默认 2000
+    pub retries: Option<u32>,  // 失败重试次数，默认 2
+    pub retry_delay_ms: Option<u64>,  // 默认 1000
+    pub status_query: Option<String>,  // 状态查询指令（base64），如 ESC/POS `DLE EOT 1` 为 "EAQB"
+}
+
+/// LPD/LPR（RFC 1179）连接参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LpdSettings {
+    pub host: String,
+    pub port: Option<u16>,  // 默认 515
+    pub queue: String,
+    pub user: Option<String>,  // 默认当前系统用户
+    pub format: Option<char>,  // 控制文件中的格式字母，默认 'l'（原样打印）
+    pub banner: Option<bool>,  // 是否打印横幅页，默认 false
+    pub connect_timeout_ms: Option<u64>,  // 默认 5000
+}
+
+/// IPP/1.1 连接参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IppSettings {
+    pub uri: String,  // 如 "ipp://192.168.1.20:631/ipp/print"，暂不支持 ipps
+    pub user: Option<String>,  // 默认当前系统用户
+    pub document_format: Option<String>,  // 默认 "application/octet-stream"（由打印机识别）
+    pub connect_timeout_ms: Option<u64>,  // 默认 5000
+}
+
+/// 虚拟打印机的后端
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum PrinterBackend {
+    Raw(RawSocketSettings),
+    Lpd(LpdSettings),
+    Ipp(IppSettings),
+}
+
+impl PrinterBackend {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PrinterBackend::Raw(_) => "raw",
+            PrinterBackend::Lpd(_) => "lpd",
+            PrinterBackend::Ipp(_) => "ipp",
+        }
+    }
+}
+
+/// 没有安装驱动、由插件直接通信的打印机
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VirtualPrinter {
+    pub name: String,
+    #[serde(flatten)]
+    pub backend: PrinterBackend,
+}
+
+/// 打印队列中的一项，字段名与 `Get-PrintJob` 的输出保持一致
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct JobEntry {
+    pub id: String,
+    pub document_name: String,
+    pub user_name: String,
+    pub size: u64,
+    pub position: u32,
+    pub job_status: String,
+    pub printer_name: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DuplexMode {
+    OneSided,
+    TwoSidedLongEdge,
+    TwoSidedShortEdge,
+}
+
+impl DuplexMode {
+    /// 与打印机能力中 duplex_modes 一致的名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DuplexMode::OneSided => "one-sided",
+            DuplexMode::TwoSidedLongEdge => "two-sided-long-edge",
+            DuplexMode::TwoSidedShortEdge => "two-sided-short-edge",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorMode {
+    Monochrome,
+    Color,
+}
+
+impl ColorMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ColorMode::Monochrome => "monochrome",
+            ColorMode::Color => "color",
+        }
+    }
+}
+
+/// 页面缩放方式，custom 为百分比，如 {"custom": 80}
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scaling {
+    Fit,  // 缩放到纸张大小，小页面会放大
+    Shrink,  // 只缩小超出纸张的页面
+    None,  // 按原始尺寸打印
+    Tile,  // 按原始尺寸拆分到多张纸上（海报打印）
+    Custom(f64),
+}
+
+impl std::fmt::Display for Scaling {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scaling::Fit => f.write_str("fit"),
+            Scaling::Shrink => f.write_str("shrink"),
+            Scaling::None => f.write_str("none"),
+            Scaling::Tile => f.write_str("tile"),
+            Scaling::Custom(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+/// 打印参数
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrintSettings {
+    pub page_size: Option<String>,  // A4、Letter 等，或打印机给出的纸张名称
+    pub duplex: Option<DuplexMode>,
+    pub color: Option<ColorMode>,
+    pub tray: Option<String>,  // 纸盒名称
+    pub copies: Option<u32>,
+    pub resolution: Option<u32>,  // dpi
+    pub scaling: Option<Scaling>,
+    pub auto_rotate: Option<bool>,  // 页面方向与纸张不同时旋转 90 度
+}
+
+/// 水印在页面上的位置
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatermarkPosition {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    #[default]
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+/// 水印或印章，文字和图片至少指定一个
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Watermark {
+    pub text: Option<String>,  // 支持 {user}、{date}、{time} 占位符
+    pub image: Option<String>,  // PNG/JPEG 图片 base64 字符串
+    pub image_width: Option<f64>,  // 图片宽度（pt），默认按 96 dpi 的原始尺寸
+    pub font_size: Option<f64>,  // 默认 48
+    pub color: Option<String>,  // #RRGGBB，默认 #808080
+    pub opacity: Option<f64>,  // 0-1，默认 0.3
+    pub angle: Option<f64>,  // 逆时针旋转角度，默认 0
+    #[serde(default)]
+    pub position: WatermarkPosition,
+    pub margin: Option<f64>,  // 与页面边缘的距离（pt），默认 36
+    pub pages: Option<String>,  // 页码范围，如 "1"、"2-"，默认全部页面
+}
+
+/// 图片在页面上的缩放方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageFit {
+    #[default]
+    Fit,  // 完整显示，保持比例
+    Fill,  // 铺满可打印区域，超出部分裁掉
+    ActualSize,  // 按图片分辨率的实际尺寸，超出部分裁掉
+}
+
+/// 图片页面的方向
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageOrientation {
+    #[default]
+    Auto,  // 按图片宽高选择
+    Portrait,
+    Landscape,
+}
+
+/// 图片打印的排版，每张图片（多页 TIFF 的每一页）占一页
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImageLayout {
+    #[serde(default)]
+    pub fit: ImageFit,
+    #[serde(default)]
+    pub orientation: ImageOrientation,
+    pub margin: Option<f64>,  // 与纸张边缘的距离（pt），默认 18
+    pub dpi: Option<f64>,  // 覆盖图片自带的分辨率，图片没有时按 96
+}
+
+/// 文本打印的西文字体（PDF 标准字体，不嵌入）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextFont {
+    #[default]
+    Courier,  // 等宽
+    Helvetica,
+    Times,
+}
+
+/// 西文字体无法显示的字符使用的 CJK 字体（不嵌入，由阅读器替换为系统中的同类字体）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CjkFont {
+    #[default]
+    SimplifiedChinese,  // STSong-Light
+    TraditionalChinese,  // MSung-Light
+    Japanese,  // KozMinPro-Regular
+    Korean,  // HYSMyeongJo-Medium
+}
+
+/// 文本文件的编码
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    Gbk,  // 按 GB18030 解码，兼容 GB2312
+}
+
+/// 长行的换行方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextWrap {
+    #[default]
+    Char,  // 在任意字符处换行
+    Word,  // 在空格或 CJK 字符处换行，单词过长时按字符
+    None,  // 不换行，超出部分裁掉
+}
+
+/// 文本打印选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TextOptions {
+    #[serde(default)]
+    pub font: TextFont,
+    pub font_size: Option<f64>,  // pt，默认 10
+    #[serde(default)]
+    pub cjk_font: CjkFont,
+    #[serde(default)]
+    pub encoding: TextEncoding,  // 只用于从文件读取的文本
+    #[serde(default)]
+    pub line_numbers: bool,
+    pub tab_width: Option<u32>,  // 默认 4
+    #[serde(default)]
+    pub wrap: TextWrap,
+    #[serde(default)]
+    pub header: bool,  // 页眉：标题、打印时间和页码
+    pub title: Option<String>,  // 页眉标题，默认文件名
+    pub margin: Option<f64>,  // 与纸张边缘的距离（pt），默认 36
+}
+
+/// Markdown 的排版样式
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MarkdownStyle {
+    pub stylesheet: Option<String>,  // 替换内置的打印样式表
+    pub extra_css: Option<String>,  // 追加在样式表之后
+    pub title: Option<String>,  // HTML 标题，默认文件名
+    pub base_dir: Option<String>,  // 相对图片路径的目录，默认为 Markdown 文件所在目录
+}
+
+/// Markdown 打印选项，markdown 与 path 二选一，其余参数见 HtmlJobOptions
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrintMarkdownOptions {
+    pub markdown: Option<String>,
+    pub path: Option<String>,  // Markdown 文件路径，按 UTF-8 读取
+    #[serde(default)]
+    pub style: MarkdownStyle,
+    #[serde(flatten)]
+    pub job: HtmlJobOptions,
+}
+
+/// 要注册的 HTML 模板，source 与 path 二选一
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateSource {
+    pub name: String,
+    pub source: Option<String>,
+    pub path: Option<String>,  // 模板文件，注册时读取
+}
+
+/// 已注册的模板
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TemplateInfo {
+    pub name: String,
+    pub path: Option<String>,
+    pub version: u32,  // 同名模板每注册一次加 1
+    pub hash: String,  // 模板内容的 SHA-256
+    pub registered_at: i64,  // 毫秒时间戳
+}
+
+/// 模板打印选项，其余参数见 HtmlJobOptions
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrintTemplateOptions {
+    pub template: String,  // 模板名称
+    #[serde(default)]
+    pub data: serde_json::Value,
+    #[serde(flatten)]
+    pub job: HtmlJobOptions,
+}
+
+/// 表格列的对齐方式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnAlign {
+    Left,
+    Center,
+    Right,
+}
+
+/// 数字列的格式
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NumberFormat {
+    pub decimals: Option<u32>,  // 小数位数，默认原样显示
+    #[serde(default)]
+    pub thousands: bool,  // 整数部分每三位加逗号
+    pub prefix: Option<String>,  // 如 ¥
+    pub suffix: Option<String>,  // 如 %
+}
+
+/// 合计行中的统计方式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TableTotal {
+    Sum,
+    Average,
+    Count,  // 非空单元格的个数
+    Min,
+    Max,
+}
+
+/// 表格列
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TableColumn {
+    pub key: Option<String>,  // JSON 对象的字段名或 CSV 表头中的列名，为空时按列的顺序取值
+    pub header: Option<String>,  // 表头，默认为 key
+    pub width: Option<String>,  // CSS 宽度，如 20%、30mm
+    pub align: Option<ColumnAlign>,  // 默认数字列右对齐，其他左对齐
+    pub format: Option<NumberFormat>,
+    pub total: Option<TableTotal>,
+}
+
+/// 表格数据和排版，csv 与 rows 二选一
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TableOptions {
+    pub csv: Option<String>,
+    pub rows: Option<serde_json::Value>,  // JSON 数组，元素为对象或数组
+    #[serde(default)]
+    pub columns: Vec<TableColumn>,  // 为空时显示全部列
+    pub csv_header: Option<bool>,  // CSV 第一行是否为表头，默认 true
+    pub delimiter: Option<char>,  // CSV 分隔符，默认逗号
+    pub title: Option<String>,
+    pub totals_label: Option<String>,  // 合计行的标题，默认“合计”
+    pub extra_css: Option<String>,  // 追加在内置样式表之后
+}
+
+/// 表格打印选项，其余参数见 HtmlJobOptions
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrintTableOptions {
+    #[serde(flatten)]
+    pub table: TableOptions,
+    #[serde(flatten)]
+    pub job: HtmlJobOptions,
+}
+
+/// 批量打印中的一个文档
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchDocument {
+    pub path: String,  // PDF文件路径
+    #[serde(default)]
+    pub settings: Option<PrintSettings>,
+}
+
+/// 纸张尺寸，name 为后端给出的名称（Windows 纸张名或 PWG 名称）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MediaSize {
+    pub name: String,
+    pub width_mm: Option<f64>,
+    pub height_mm: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Resolution {
+    pub x_dpi: u32,
+    pub y_dpi: u32,
+}
+
+/// 打印机能力
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PrinterCapabilities {
+    pub printer: String,
+    pub media_sizes: Vec<MediaSize>,
+    pub default_media: Option<String>,
+    pub trays: Vec<String>,
+    pub duplex_modes: Vec<String>,  // "one-sided" / "two-sided-long-edge" / "two-sided-short-edge"
+    pub color_modes: Vec<String>,  // "monochrome" / "color"
+    pub resolutions: Vec<Resolution>,
+    pub max_copies: Option<u32>,
+    pub document_formats: Vec<String>,  // MIME 类型
+}
+
+/// 打印机组选择成员的方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupStrategy {
+    #[default]
+    Failover,  // 按成员顺序，使用第一台可用的
+    LeastBusy,  // 使用作业最少的
+}
+
+/// 打印机组，按组名打印时自动选择成员
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrinterGroup {
+    pub name: String,
+    pub members: Vec<String>,  // 系统打印机或虚拟打印机名称，按优先顺序
+    #[serde(default)]
+    pub strategy: GroupStrategy,
+}
+
+/// 后台状态监控配置
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StatusMonitorConfig {
+    pub printers: Vec<String>,
+    pub interval_ms: Option<u64>,  // 轮询间隔，默认 5000 毫秒
+}
+
+/// 打印历史配置
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HistoryConfig {
+    pub enabled: Option<bool>,  // 默认 true
+    pub retention_days: Option<u32>,  // 保留天数，默认 365，0 表示永久保留
+}
+
+/// 打印队列配置
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct QueueConfig {
+    pub max_attempts: Option<u32>,  // 最多尝试次数，默认 10，0 表示一直重试
+    pub retry_delay_ms: Option<u64>,  // 首次重试间隔，之后每次翻倍，默认 5000
+    pub max_retry_delay_ms: Option<u64>,  // 重试间隔上限，默认 300000
+}
+
+/// 插件配置，对应 tauri.conf.json 中的 `plugins.printer`
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub virtual_printers: Vec<VirtualPrinter>,
+    #[serde(default)]
+    pub printer_groups: Vec<PrinterGroup>,
+    #[serde(default)]
+    pub templates: Vec<TemplateSource>,
+    #[serde(default)]
+    pub status_monitor: Option<StatusMonitorConfig>,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    #[serde(default)]
+    pub queue: QueueConfig,
+}
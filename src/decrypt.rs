@@ -0,0 +1,634 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+use aes::cipher::block_padding::{NoPadding, Pkcs7};
+use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use lopdf::encryption::{get_encryption_key, DecryptionError};
+use lopdf::xref::XrefEntry;
+use lopdf::{Dictionary, Document, Object, ObjectId, ObjectStream};
+use md5::Md5;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::pdf;
+
+type Aes128CbcEnc = cbc::Encryptor<aes::Aes128>;
+type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
+type Aes256CbcDec = cbc::Decryptor<aes::Aes256>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecryptError {
+    /// 文档需要打开密码，但没有提供
+    PasswordRequired,
+    IncorrectPassword,
+    Failed(String),
+}
+
+/// 字符串或流的加密算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Method {
+    Identity,
+    Rc4,
+    Aes128,
+    Aes256,
+}
+
+/// 解密一个文档所需的参数
+struct Cipher {
+    key: Vec<u8>,
+    strings: Method,
+    streams: Method,
+    /// 加密字典本身不加密
+    encrypt_id: Option<ObjectId>,
+    /// EncryptMetadata 为 false 时元数据流不加密
+    metadata: bool,
+    /// 对象流中的对象随对象流一起解密，不再单独解密
+    compressed: HashSet<ObjectId>,
+    /// 长度为间接对象的流在读取时内容为空，读取完成后再解密
+    deferred: Mutex<Vec<ObjectId>>,
+}
+
+/**
+ * 当前正在读取的文档的解密参数
+ * lopdf 的过滤函数只能是函数指针，只能通过全局变量传入；
+ * 启用 rayon 时对象在多个线程中读取，所以不能用线程局部变量
+ */
+fn current() -> MutexGuard<'static, Option<Arc<Cipher>>> {
+    static CURRENT: OnceLock<Mutex<Option<Arc<Cipher>>>> = OnceLock::new();
+    CURRENT.get_or_init(|| Mutex::new(None)).lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 同一时间只读取一个加密文档
+fn loading() -> MutexGuard<'static, ()> {
+    static LOADING: Mutex<()> = Mutex::new(());
+    LOADING.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn rc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut state: [u8; 256] = std::array::from_fn(|i| i as u8);
+    let mut j = 0u8;
+    for i in 0..256 {
+        j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+        state.swap(i, j as usize);
+    }
+    let (mut i, mut j) = (0u8, 0u8);
+    data.iter()
+        .map(|&byte| {
+            i = i.wrapping_add(1);
+            j = j.wrapping_add(state[i as usize]);
+            state.swap(i as usize, j as usize);
+            byte ^ state[state[i as usize].wrapping_add(state[j as usize]) as usize]
+        })
+        .collect()
+}
+
+/// 算法 1：RC4 和 AES-128 的对象密钥由文件密钥、对象号和代号生成
+fn object_key(key: &[u8], method: Method, id: ObjectId) -> Vec<u8> {
+    let mut seed = key.to_vec();
+    seed.extend_from_slice(&id.0.to_le_bytes()[..3]);
+    seed.extend_from_slice(&id.1.to_le_bytes()[..2]);
+    if method == Method::Aes128 {
+        seed.extend_from_slice(b"sAlT");
+    }
+    let digest = Md5::digest(&seed);
+    digest[..(key.len() + 5).min(16)].to_vec()
+}
+
+/// 解密字符串或流的内容，数据不像是加密的（如长度不对）时返回 None，保留原样
+fn decrypt_bytes(cipher: &Cipher, method: Method, id: ObjectId, data: &[u8]) -> Option<Vec<u8>> {
+    // AES-256 直接使用文件密钥
+    let object_key = || object_key(&cipher.key, method, id);
+    match method {
+        Method::Identity => None,
+        Method::Rc4 => Some(rc4(&object_key(), data)),
+        Method::Aes128 | Method::Aes256 => {
+            if data.len() < 16 || data.len() % 16 != 0 {
+                return None;
+            }
+            let (iv, body) = data.split_at(16);
+            let mut buffer = body.to_vec();
+            let plain = if method == Method::Aes128 {
+                Aes128CbcDec::new_from_slices(&object_key(), iv).ok()?.decrypt_padded_mut::<Pkcs7>(&mut buffer).ok()?
+            } else {
+                Aes256CbcDec::new_from_slices(&cipher.key, iv).ok()?.decrypt_padded_mut::<Pkcs7>(&mut buffer).ok()?
+            };
+            Some(plain.to_vec())
+        }
+    }
+}
+
+/// 解密对象中所有的字符串和流
+fn decrypt_object(cipher: &Cipher, id: ObjectId, object: &mut Object) {
+    match object {
+        Object::String(bytes, _) => {
+            if let Some(plain) = decrypt_bytes(cipher, cipher.strings, id, bytes) {
+                *bytes = plain;
+            }
+        }
+        Object::Array(items) => items.iter_mut().for_each(|item| decrypt_object(cipher, id, item)),
+        Object::Dictionary(dictionary) => decrypt_dictionary(cipher, id, dictionary),
+        Object::Stream(stream) => {
+            decrypt_dictionary(cipher, id, &mut stream.dict);
+            // 交叉引用流不加密
+            if stream.dict.has_type(b"XRef") || (!cipher.metadata && stream.dict.has_type(b"Metadata")) {
+                return;
+            }
+            if stream.content.is_empty() {
+                cipher.deferred.lock().unwrap_or_else(|e| e.into_inner()).push(id);
+            } else if let Some(plain) = decrypt_bytes(cipher, cipher.streams, id, &stream.content) {
+                stream.set_content(plain);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn decrypt_dictionary(cipher: &Cipher, id: ObjectId, dictionary: &mut Dictionary) {
+    for (_, value) in dictionary.iter_mut() {
+        decrypt_object(cipher, id, value);
+    }
+}
+
+/// 读取时解密每个对象，在解析对象流之前执行
+fn filter(id: ObjectId, object: &mut Object) -> Option<(ObjectId, Object)> {
+    let Some(cipher) = current().clone() else {
+        return Some((id, object.clone()));
+    };
+    if cipher.compressed.contains(&id) {
+        // 对象流中的对象使用返回值
+        return Some((id, object.clone()));
+    }
+    if cipher.encrypt_id != Some(id) {
+        decrypt_object(&cipher, id, object);
+    }
+    // 顶层对象由读取器继续使用 object，返回值会被忽略
+    Some((id, Object::Null))
+}
+
+/// 加密字典中 StmF / StrF 指定的算法
+fn method(encrypt: &Dictionary, filter: &[u8]) -> Result<Method, DecryptError> {
+    let version = encrypt.get(b"V").and_then(Object::as_i64).unwrap_or(0);
+    if version < 4 {
+        return Ok(Method::Rc4);
+    }
+    let name = encrypt.get(filter).and_then(Object::as_name).unwrap_or(b"Identity");
+    if name == b"Identity" {
+        return Ok(Method::Identity);
+    }
+    let cfm = encrypt
+        .get(b"CF")
+        .and_then(Object::as_dict)
+        .and_then(|filters| filters.get(name))
+        .and_then(Object::as_dict)
+        .and_then(|filter| filter.get(b"CFM"))
+        .and_then(Object::as_name)
+        .unwrap_or(b"None");
+    match cfm {
+        b"None" => Ok(Method::Identity),
+        b"V2" => Ok(Method::Rc4),
+        b"AESV2" => Ok(Method::Aes128),
+        b"AESV3" => Ok(Method::Aes256),
+        other => Err(DecryptError::Failed(format!("不支持的加密算法: {}", String::from_utf8_lossy(other)))),
+    }
+}
+
+/// 算法 2.B：R6 的密码散列，R5 只做一次 SHA-256
+fn hash_r6(password: &[u8], salt: &[u8], user_key: &[u8], revision: i64) -> Vec<u8> {
+    let mut k = Sha256::new().chain_update(password).chain_update(salt).chain_update(user_key).finalize().to_vec();
+    if revision == 5 {
+        return k;
+    }
+    let mut round: u32 = 0;
+    loop {
+        let mut k1 = Vec::with_capacity(64 * (password.len() + k.len() + user_key.len()));
+        for _ in 0..64 {
+            k1.extend_from_slice(password);
+            k1.extend_from_slice(&k);
+            k1.extend_from_slice(user_key);
+        }
+        let length = k1.len();
+        let e = match Aes128CbcEnc::new_from_slices(&k[..16], &k[16..32]) {
+            Ok(encryptor) => encryptor.encrypt_padded_mut::<NoPadding>(&mut k1, length).map(<[u8]>::to_vec),
+            Err(_) => return k,
+        };
+        let Ok(e) = e else {
+            return k;
+        };
+        // 前 16 字节作为大整数对 3 取模，等于各字节之和对 3 取模
+        k = match e[..16].iter().map(|&b| b as u32).sum::<u32>() % 3 {
+            0 => Sha256::digest(&e).to_vec(),
+            1 => Sha384::digest(&e).to_vec(),
+            _ => Sha512::digest(&e).to_vec(),
+        };
+        round += 1;
+        if round >= 64 && u32::from(e[e.len() - 1]) + 32 <= round {
+            break;
+        }
+    }
+    k.truncate(32);
+    k
+}
+
+/// AES-256 加密（V5）的文件密钥，先按用户密码验证，再按所有者密码验证（算法 2.A）
+fn key_r6(encrypt: &Dictionary, password: &[u8]) -> Result<Vec<u8>, DecryptError> {
+    let field = |key: &[u8]| {
+        encrypt
+            .get(key)
+            .and_then(Object::as_str)
+            .map(<[u8]>::to_vec)
+            .map_err(|_| DecryptError::Failed(format!("加密字典缺少 {}", String::from_utf8_lossy(key))))
+    };
+    let revision = encrypt.get(b"R").and_then(Object::as_i64).unwrap_or(0);
+    let (u, ue) = (field(b"U")?, field(b"UE")?);
+    if u.len() < 48 || ue.len() < 32 {
+        return Err(DecryptError::Failed("加密字典无效".to_string()));
+    }
+    let password = &password[..password.len().min(127)];
+    let (intermediate, encrypted_key) = if hash_r6(password, &u[32..40], &[], revision) == u[..32] {
+        (hash_r6(password, &u[40..48], &[], revision), ue)
+    } else {
+        // 所有者密码的散列还要加上 U 的 48 字节
+        let (o, oe) = (field(b"O")?, field(b"OE")?);
+        if o.len() < 48 || oe.len() < 32 || hash_r6(password, &o[32..40], &u[..48], revision) != o[..32] {
+            return Err(DecryptError::IncorrectPassword);
+        }
+        (hash_r6(password, &o[40..48], &u[..48], revision), oe)
+    };
+    let mut key = encrypted_key[..32].to_vec();
+    Aes256CbcDec::new_from_slices(&intermediate, &[0u8; 16])
+        .map_err(|e| DecryptError::Failed(e.to_string()))?
+        .decrypt_padded_mut::<NoPadding>(&mut key)
+        .map_err(|e| DecryptError::Failed(e.to_string()))?;
+    Ok(key)
+}
+
+/// 密码补足 32 字节时使用的填充串
+const PASSWORD_PADDING: [u8; 32] = [
+    0x28, 0xBF, 0x4E, 0x5E, 0x4E, 0x75, 0x8A, 0x41, 0x64, 0x00, 0x4E, 0x56, 0xFF, 0xFA, 0x01, 0x08,
+    0x2E, 0x2E, 0x00, 0xB6, 0xD0, 0x68, 0x3E, 0x80, 0x2F, 0x0C, 0xA9, 0xFE, 0x64, 0x53, 0x69, 0x7A,
+];
+
+/// 算法 3 的第 1-4 步：由所有者密码生成加密 O 值的 RC4 密钥
+fn owner_key(encrypt: &Dictionary, password: &[u8]) -> Vec<u8> {
+    let revision = encrypt.get(b"R").and_then(Object::as_i64).unwrap_or(2);
+    let length = match revision {
+        2 => 5,
+        _ => (encrypt.get(b"Length").and_then(Object::as_i64).unwrap_or(40) / 8).clamp(5, 16) as usize,
+    };
+    let password = &password[..password.len().min(32)];
+    let mut digest = Md5::digest([password, &PASSWORD_PADDING[..32 - password.len()]].concat()).to_vec();
+    if revision >= 3 {
+        for _ in 0..50 {
+            digest = Md5::digest(&digest[..length]).to_vec();
+        }
+    }
+    digest.truncate(length);
+    digest
+}
+
+/// 算法 7：用所有者密码从 O 中解出（补足 32 字节的）用户密码
+fn user_password_from_owner(encrypt: &Dictionary, password: &[u8]) -> Option<Vec<u8>> {
+    let revision = encrypt.get(b"R").and_then(Object::as_i64).unwrap_or(2);
+    let o = encrypt.get(b"O").and_then(Object::as_str).ok()?;
+    let key = owner_key(encrypt, password);
+    if revision == 2 {
+        return Some(rc4(&key, &o[..o.len().min(32)]));
+    }
+    let mut user = o[..o.len().min(32)].to_vec();
+    for i in (0..20u8).rev() {
+        let round_key: Vec<u8> = key.iter().map(|b| b ^ i).collect();
+        user = rc4(&round_key, &user);
+    }
+    Some(user)
+}
+
+fn file_key(document: &Document, encrypt: &Dictionary, password: &str) -> Result<Vec<u8>, DecryptError> {
+    if encrypt.get(b"Filter").and_then(Object::as_name).unwrap_or(b"Standard") != b"Standard" {
+        return Err(DecryptError::Failed("只支持标准安全处理程序（密码加密）".to_string()));
+    }
+    if encrypt.get(b"V").and_then(Object::as_i64).unwrap_or(0) == 5 {
+        return key_r6(encrypt, password.as_bytes());
+    }
+    let key = get_encryption_key(document, password, true).or_else(|e| match e {
+        // 不是用户密码时按所有者密码解出用户密码再试
+        DecryptionError::IncorrectPassword if !password.is_empty() => match user_password_from_owner(encrypt, password.as_bytes()) {
+            Some(user) => get_encryption_key(document, user, true),
+            None => Err(e),
+        },
+        e => Err(e),
+    });
+    key.map_err(|e| match e {
+        DecryptionError::IncorrectPassword => DecryptError::IncorrectPassword,
+        e => DecryptError::Failed(e.to_string()),
+    })
+}
+
+/**
+ * 用密码解密文档
+ * 没有加密的文档原样读取，password 为空时尝试空密码（只设置了权限密码的文档）
+ */
+pub fn decrypt(path: &Path, password: Option<&str>) -> Result<Document, DecryptError> {
+    let probe = Document::load(path).map_err(|e| DecryptError::Failed(format!("{}: {}", path.display(), e)))?;
+    let Ok(encrypt) = probe.get_encrypted() else {
+        return Ok(probe);
+    };
+    let key = match file_key(&probe, encrypt, password.unwrap_or("")) {
+        Err(DecryptError::IncorrectPassword) if password.unwrap_or("").is_empty() => {
+            return Err(DecryptError::PasswordRequired);
+        }
+        result => result?,
+    };
+    let compressed = probe
+        .reference_table
+        .entries
+        .iter()
+        .filter(|(_, entry)| matches!(entry, XrefEntry::Compressed { .. }))
+        .map(|(&number, _)| (number, 0))
+        .collect();
+    let cipher = Arc::new(Cipher {
+        key,
+        strings: method(encrypt, b"StrF")?,
+        streams: method(encrypt, b"StmF")?,
+        encrypt_id: probe.trailer.get(b"Encrypt").and_then(Object::as_reference).ok(),
+        metadata: encrypt.get(b"EncryptMetadata").and_then(Object::as_bool).unwrap_or(true),
+        compressed,
+        deferred: Mutex::new(Vec::new()),
+    });
+
+    let mut document = {
+        let _loading = loading();
+        *current() = Some(cipher.clone());
+        let document = Document::load_filtered(path, filter);
+        *current() = None;
+        document.map_err(|e| DecryptError::Failed(format!("{}: {}", path.display(), e)))?
+    };
+    let deferred = std::mem::take(&mut *cipher.deferred.lock().unwrap_or_else(|e| e.into_inner()));
+    for id in deferred {
+        let Ok(Object::Stream(stream)) = document.get_object_mut(id) else {
+            continue;
+        };
+        if let Some(plain) = decrypt_bytes(&cipher, cipher.streams, id, &stream.content) {
+            stream.set_content(plain);
+        }
+        if !stream.dict.has_type(b"ObjStm") {
+            continue;
+        }
+        if let Ok(objects) = ObjectStream::new(stream).map(|s| s.objects) {
+            for (id, object) in objects {
+                document.objects.entry(id).or_insert(object);
+            }
+        }
+    }
+    if let Some(id) = cipher.encrypt_id {
+        document.objects.remove(&id);
+    }
+    document.trailer.remove(b"Encrypt");
+    Ok(document)
+}
+
+/**
+ * 解密到输出目录，返回临时副本的路径
+ * 副本只有当前用户可以读取，打印后应调用 secure_remove 删除
+ */
+pub fn decrypt_file(path: &Path, password: Option<&str>) -> Result<PathBuf, DecryptError> {
+    let mut document = decrypt(path, password)?;
+    let output = pdf::spool_path(&pdf::file_stem(path)).map_err(DecryptError::Failed)?;
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let saved = options
+        .open(&output)
+        .map_err(|e| e.to_string())
+        .and_then(|mut file| document.save_to(&mut file).map_err(|e| e.to_string()));
+    if let Err(e) = saved {
+        let _ = secure_remove(&output);
+        return Err(DecryptError::Failed(format!("{}: {}", output.display(), e)));
+    }
+    Ok(output)
+}
+
+/// 先用零覆盖文件内容再删除
+pub fn secure_remove(path: &Path) -> std::io::Result<()> {
+    if let Ok(mut file) = OpenOptions::new().write(true).open(path) {
+        let length = file.metadata()?.len();
+        file.seek(SeekFrom::Start(0))?;
+        let zeros = [0u8; 8192];
+        let mut remaining = length;
+        while remaining > 0 {
+            let chunk = remaining.min(zeros.len() as u64) as usize;
+            file.write_all(&zeros[..chunk])?;
+            remaining -= chunk as u64;
+        }
+        File::sync_all(&file)?;
+    }
+    fs::remove_file(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::{dictionary, StringFormat};
+
+    use super::*;
+
+    type Aes256CbcEnc = cbc::Encryptor<aes::Aes256>;
+
+    fn aes256(key: &[u8], iv: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut buffer = data.to_vec();
+        buffer.resize(data.len() + 16, 0);
+        let encrypted = Aes256CbcEnc::new_from_slices(key, iv).unwrap().encrypt_padded_mut::<Pkcs7>(&mut buffer, data.len()).unwrap();
+        [iv, encrypted].concat()
+    }
+
+    /// 对每个对象中的字符串和流调用 encrypt，参数为对象号和明文
+    fn encrypt_objects(document: &mut Document, skip: Option<ObjectId>, encrypt: impl Fn(ObjectId, &[u8]) -> Vec<u8>) {
+        for (&id, object) in document.objects.iter_mut() {
+            if Some(id) == skip {
+                continue;
+            }
+            match object {
+                Object::Stream(stream) => stream.set_content(encrypt(id, &stream.content)),
+                Object::Dictionary(dictionary) => {
+                    for (_, value) in dictionary.iter_mut() {
+                        if let Object::String(bytes, _) = value {
+                            *bytes = encrypt(id, bytes);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn set_file_id(document: &mut Document) {
+        let id = Object::String(vec![9; 16], StringFormat::Hexadecimal);
+        document.trailer.set("ID", vec![id.clone(), id]);
+    }
+
+    /// 按 R6 生成加密字典，用同一个文件密钥加密所有字符串和流
+    fn encrypt_r6(document: &mut Document, user: &str, owner: &str) {
+        let file_key = [7u8; 32];
+        let wrap_key = |key: &[u8]| {
+            let mut wrapped = file_key.to_vec();
+            Aes256CbcEnc::new_from_slices(key, &[0u8; 16]).unwrap().encrypt_padded_mut::<NoPadding>(&mut wrapped, 32).unwrap();
+            wrapped
+        };
+        let (validation_salt, key_salt) = ([1u8; 8], [2u8; 8]);
+        let user = user.as_bytes();
+        let u = [hash_r6(user, &validation_salt, &[], 6), validation_salt.to_vec(), key_salt.to_vec()].concat();
+        let ue = wrap_key(&hash_r6(user, &key_salt, &[], 6));
+        let (validation_salt, key_salt) = ([3u8; 8], [4u8; 8]);
+        let owner = owner.as_bytes();
+        let o = [hash_r6(owner, &validation_salt, &u, 6), validation_salt.to_vec(), key_salt.to_vec()].concat();
+        let oe = wrap_key(&hash_r6(owner, &key_salt, &u, 6));
+
+        encrypt_objects(document, None, |id, data| aes256(&file_key, &[id.0 as u8; 16], data));
+        let encrypt_id = document.add_object(dictionary! {
+            "Filter" => "Standard",
+            "V" => 5,
+            "R" => 6,
+            "Length" => 256,
+            "CF" => dictionary! { "StdCF" => dictionary! { "CFM" => "AESV3", "AuthEvent" => "DocOpen", "Length" => 32 } },
+            "StmF" => "StdCF",
+            "StrF" => "StdCF",
+            "U" => Object::String(u, StringFormat::Hexadecimal),
+            "UE" => Object::String(ue, StringFormat::Hexadecimal),
+            "O" => Object::String(o, StringFormat::Hexadecimal),
+            "OE" => Object::String(oe, StringFormat::Hexadecimal),
+            "P" => -4,
+        });
+        document.trailer.set("Encrypt", encrypt_id);
+        set_file_id(document);
+    }
+
+    /// 按 R2-R4 生成加密字典（算法 2-5），R4 时可以使用 AES-128
+    fn encrypt_legacy(document: &mut Document, revision: i64, aes: bool, user: &str, owner: &str) {
+        let version = match revision {
+            2 => 1,
+            3 => 2,
+            _ => 4,
+        };
+        let mut encrypt = dictionary! {
+            "Filter" => "Standard",
+            "V" => version,
+            "R" => revision,
+            "Length" => if revision == 2 { 40 } else { 128 },
+            "P" => -4,
+        };
+        if version == 4 {
+            let cfm = if aes { "AESV2" } else { "V2" };
+            encrypt.set("CF", dictionary! { "StdCF" => dictionary! { "CFM" => cfm, "AuthEvent" => "DocOpen", "Length" => 16 } });
+            encrypt.set("StmF", "StdCF");
+            encrypt.set("StrF", "StdCF");
+        }
+        let rounds = |key: &[u8], data: Vec<u8>| {
+            let rounds = if revision == 2 { 0 } else { 19 };
+            (1..=rounds).fold(rc4(key, &data), |data, i| rc4(&key.iter().map(|b| b ^ i).collect::<Vec<_>>(), &data))
+        };
+        let padded = [user.as_bytes(), &PASSWORD_PADDING[..32 - user.len()]].concat();
+        let o = rounds(&owner_key(&encrypt, owner.as_bytes()), padded);
+        encrypt.set("O", Object::String(o, StringFormat::Hexadecimal));
+        set_file_id(document);
+        let encrypt_id = document.add_object(encrypt);
+        document.trailer.set("Encrypt", encrypt_id);
+
+        let file_key = get_encryption_key(&*document, user, false).unwrap();
+        let mut u = if revision == 2 {
+            rc4(&file_key, &PASSWORD_PADDING)
+        } else {
+            rounds(&file_key, Md5::digest([&PASSWORD_PADDING[..], &[9; 16]].concat()).to_vec())
+        };
+        u.resize(32, 0);
+        document.get_dictionary_mut(encrypt_id).unwrap().set("U", Object::String(u, StringFormat::Hexadecimal));
+
+        let method = if aes { Method::Aes128 } else { Method::Rc4 };
+        encrypt_objects(document, Some(encrypt_id), |id, data| {
+            let key = object_key(&file_key, method, id);
+            if !aes {
+                return rc4(&key, data);
+            }
+            let iv = [id.0 as u8; 16];
+            let mut buffer = data.to_vec();
+            buffer.resize(data.len() + 16, 0);
+            let encrypted = Aes128CbcEnc::new_from_slices(&key, &iv).unwrap().encrypt_padded_mut::<Pkcs7>(&mut buffer, data.len()).unwrap();
+            [&iv[..], encrypted].concat()
+        });
+    }
+
+    /// 两页文档，第 1 页有内容流，Info 中有标题
+    fn statement() -> Document {
+        let mut document = pdf::tests::sample(2, [0, 0, 595, 842]);
+        let page = *document.get_pages().get(&1).unwrap();
+        let content = document.add_object(lopdf::Stream::new(dictionary! {}, b"BT /F1 12 Tf (Balance) Tj ET".to_vec()));
+        document.get_dictionary_mut(page).unwrap().set("Contents", content);
+        let info = document.add_object(dictionary! { "Title" => Object::string_literal("Statement") });
+        document.trailer.set("Info", info);
+        document
+    }
+
+    fn first_page_content(document: &Document) -> Vec<u8> {
+        let page = *document.get_pages().get(&1).unwrap();
+        document.get_page_content(page).unwrap()
+    }
+
+    #[test]
+    fn rc4_matches_reference_vector() {
+        assert_eq!(rc4(b"Key", b"Plaintext"), [0xBB, 0xF3, 0x16, 0xE8, 0xD9, 0x40, 0xAF, 0x0A, 0xD3]);
+    }
+
+    #[test]
+    fn decrypts_aes256_documents_to_private_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("statement.pdf");
+        let mut document = statement();
+        encrypt_r6(&mut document, "s3cret", "0wner");
+        pdf::save(&mut document, &path).unwrap();
+
+        let info = pdf::inspect(&path, None);
+        assert!(info.valid && info.encrypted && info.locked);
+        assert_eq!(pdf::inspect(&path, Some("s3cret")).title.as_deref(), Some("Statement"));
+        assert_eq!(decrypt(&path, None).err(), Some(DecryptError::PasswordRequired));
+        assert_eq!(decrypt(&path, Some("wrong")).err(), Some(DecryptError::IncorrectPassword));
+        // 只知道所有者密码也能打开
+        assert_eq!(first_page_content(&decrypt(&path, Some("0wner")).unwrap()), b"BT /F1 12 Tf (Balance) Tj ET");
+
+        let output = decrypt_file(&path, Some("s3cret")).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&output).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        let decrypted = pdf::load(&output).unwrap();
+        assert_eq!(decrypted.get_pages().len(), 2);
+        assert_eq!(first_page_content(&decrypted), b"BT /F1 12 Tf (Balance) Tj ET");
+        let info = pdf::inspect(&output, None);
+        assert_eq!((info.encrypted, info.pages), (false, 2));
+
+        secure_remove(&output).unwrap();
+        assert!(!output.exists());
+    }
+
+    #[test]
+    fn decrypts_rc4_and_aes128_documents() {
+        let dir = tempfile::tempdir().unwrap();
+        for (revision, aes) in [(2, false), (3, false), (4, false), (4, true)] {
+            let path = dir.path().join(format!("r{}-{}.pdf", revision, aes));
+            let mut document = statement();
+            encrypt_legacy(&mut document, revision, aes, "user", "owner");
+            pdf::save(&mut document, &path).unwrap();
+
+            let case = format!("R{} aes={}", revision, aes);
+            assert!(pdf::inspect(&path, None).locked, "{}", case);
+            assert_eq!(decrypt(&path, None).err(), Some(DecryptError::PasswordRequired), "{}", case);
+            assert_eq!(decrypt(&path, Some("wrong")).err(), Some(DecryptError::IncorrectPassword), "{}", case);
+            for password in ["user", "owner"] {
+                let decrypted = decrypt(&path, Some(password)).unwrap();
+                assert_eq!(first_page_content(&decrypted), b"BT /F1 12 Tf (Balance) Tj ET", "{} {}", case, password);
+                assert!(decrypted.trailer.get(b"Encrypt").is_err());
+            }
+            assert_eq!(pdf::inspect(&path, Some("owner")).title.as_deref(), Some("Statement"), "{}", case);
+        }
+    }
+}
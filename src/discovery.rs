@@ -0,0 +1,384 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use serde::Serialize;
+
+use crate::declare::{IppSettings, PrinterBackend, RawSocketSettings, VirtualPrinter};
+
+/// 浏览的服务类型，按优先级排列：同一台打印机同时发布多个服务时保留靠前的
+pub const SERVICE_TYPES: [&str; 3] = ["_ipp._tcp.local.", "_ipps._tcp.local.", "_pdl-datastream._tcp.local."];
+
+const DEFAULT_TIMEOUT_MS: u64 = 3000;
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// 局域网中发现的打印机
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveredPrinter {
+    /// 服务实例名，如 "HP LaserJet M404"
+    pub name: String,
+    /// "ipp" / "ipps" / "pdl-datastream"
+    pub service_type: String,
+    pub host: String,
+    pub addresses: Vec<String>,
+    pub port: u16,
+    pub make_and_model: Option<String>,
+    /// 支持的文档格式（TXT 记录 pdl）
+    pub formats: Vec<String>,
+    pub color: Option<bool>,
+    pub duplex: Option<bool>,
+    /// IPP 资源路径（TXT 记录 rp）
+    pub resource_path: Option<String>,
+    pub uuid: Option<String>,
+    /// 可直接传给 `add_virtual_printer` 的配置；仅发现 ipps 时为空（暂不支持 TLS）
+    pub printer: Option<VirtualPrinter>,
+}
+
+/// 一条已解析的 DNS-SD 服务记录
+#[derive(Debug, Clone, Default)]
+pub struct ServiceRecord {
+    pub service_type: String,
+    pub fullname: String,
+    pub host: String,
+    pub addresses: Vec<IpAddr>,
+    pub port: u16,
+    /// TXT 记录，键统一为小写
+    pub txt: HashMap<String, String>,
+}
+
+impl From<&ServiceInfo> for ServiceRecord {
+    fn from(info: &ServiceInfo) -> Self {
+        ServiceRecord {
+            service_type: info.get_type().to_string(),
+            fullname: info.get_fullname().to_string(),
+            host: info.get_hostname().trim_end_matches('.').to_string(),
+            addresses: info.get_addresses().iter().copied().collect(),
+            port: info.get_port(),
+            txt: info
+                .get_properties()
+                .iter()
+                .map(|p| (p.key().to_ascii_lowercase(), p.val_str().to_string()))
+                .collect(),
+        }
+    }
+}
+
+/**
+ * 通过 mDNS/DNS-SD 发现局域网中的打印机
+ * @param timeout_ms 浏览时长，默认 3000 毫秒
+ */
+pub fn discover(timeout_ms: Option<u64>) -> Result<Vec<DiscoveredPrinter>, String> {
+    let daemon = ServiceDaemon::new().map_err(|e| format!("启动 mDNS 失败: {}", e))?;
+    let printers = browse(&daemon, Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS)));
+    let _ = daemon.shutdown();
+    printers
+}
+
+/// 在给定的 mDNS 守护进程上浏览打印机服务
+fn browse(daemon: &ServiceDaemon, timeout: Duration) -> Result<Vec<DiscoveredPrinter>, String> {
+    let receivers = SERVICE_TYPES
+        .iter()
+        .map(|ty| daemon.browse(ty))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("mDNS 浏览失败: {}", e))?;
+
+    let deadline = Instant::now() + timeout;
+    let mut records = Vec::new();
+    while Instant::now() < deadline {
+        let mut idle = true;
+        for receiver in &receivers {
+            while let Ok(event) = receiver.try_recv() {
+                idle = false;
+                if let ServiceEvent::ServiceResolved(info) = event {
+                    records.push(ServiceRecord::from(&info));
+                }
+            }
+        }
+        if idle {
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    for ty in SERVICE_TYPES {
+        let _ = daemon.stop_browse(ty);
+    }
+
+    Ok(merge(records.iter().map(to_discovered).collect()))
+}
+
+/// 去掉服务类型后缀，得到实例名
+fn instance_name(record: &ServiceRecord) -> String {
+    let suffix = format!(".{}", record.service_type);
+    record
+        .fullname
+        .strip_suffix(&suffix)
+        .unwrap_or(&record.fullname)
+        .replace("\\032", " ")
+        .replace('\\', "")
+}
+
+fn service_kind(service_type: &str) -> &str {
+    service_type
+        .trim_start_matches('_')
+        .split("._")
+        .next()
+        .unwrap_or(service_type)
+}
+
+/// TXT 记录中 T/F 形式的布尔值
+fn txt_bool(record: &ServiceRecord, key: &str) -> Option<bool> {
+    match record.txt.get(key)?.as_str() {
+        "T" | "t" | "true" | "1" => Some(true),
+        "F" | "f" | "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn txt_value(record: &ServiceRecord, key: &str) -> Option<String> {
+    record.txt.get(key).map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+/// 优先 ty，其次 usb_MFG + usb_MDL，最后 product（形如 "(LaserJet M404)"）
+fn make_and_model(record: &ServiceRecord) -> Option<String> {
+    txt_value(record, "ty")
+        .or_else(|| match (txt_value(record, "usb_mfg"), txt_value(record, "usb_mdl")) {
+            (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+            (make, model) => make.or(model),
+        })
+        .or_else(|| {
+            txt_value(record, "product")
+                .map(|p| p.trim_start_matches('(').trim_end_matches(')').to_string())
+                .filter(|p| !p.is_empty())
+        })
+}
+
+/// 选一个用于连接的地址，优先 IPv4
+fn preferred_host(record: &ServiceRecord) -> String {
+    let address = record
+        .addresses
+        .iter()
+        .find(|ip| ip.is_ipv4())
+        .or_else(|| record.addresses.first());
+    match address {
+        Some(IpAddr::V6(ip)) => format!("[{}]", ip),
+        Some(ip) => ip.to_string(),
+        None => record.host.clone(),
+    }
+}
+
+/**
+ * 将一条服务记录转换为发现结果，并给出可注册的虚拟打印机配置
+ */
+pub fn to_discovered(record: &ServiceRecord) -> DiscoveredPrinter {
+    let name = instance_name(record);
+    let kind = service_kind(&record.service_type).to_string();
+    let resource_path = txt_value(record, "rp");
+    let formats = txt_value(record, "pdl")
+        .map(|pdl| pdl.split(',').map(|f| f.trim().to_string()).filter(|f| !f.is_empty()).collect())
+        .unwrap_or_default();
+
+    let mut addresses: Vec<IpAddr> = record.addresses.clone();
+    addresses.sort_by_key(|ip| (!ip.is_ipv4(), *ip));
+
+    let backend = match kind.as_str() {
+        "ipp" => Some(PrinterBackend::Ipp(IppSettings {
+            uri: format!(
+                "ipp://{}:{}/{}",
+                preferred_host(record),
+                record.port,
+                resource_path.as_deref().unwrap_or("ipp/print")
+            ),
+            user: None,
+            document_format: None,
+            connect_timeout_ms: None,
+        })),
+        "pdl-datastream" => Some(PrinterBackend::Raw(RawSocketSettings {
+            host: preferred_host(record).trim_start_matches('[').trim_end_matches(']').to_string(),
+            port: Some(record.port),
+            connect_timeout_ms: None,
+            read_timeout_ms: None,
+            retries: None,
+            retry_delay_ms: None,
+            status_query: None,
+        })),
+        _ => None,
+    };
+
+    DiscoveredPrinter {
+        printer: backend.map(|backend| VirtualPrinter { name: name.clone(), backend }),
+        name,
+        service_type: kind,
+        host: record.host.clone(),
+        addresses: addresses.iter().map(IpAddr::to_string).collect(),
+        port: record.port,
+        make_and_model: make_and_model(record),
+        formats,
+        color: txt_bool(record, "color"),
+        duplex: txt_bool(record, "duplex"),
+        resource_path,
+        uuid: txt_value(record, "uuid"),
+    }
+}
+
+/**
+ * 合并同一台打印机发布的多个服务
+ * 以 UUID（没有时用实例名）判断是否同一台，保留优先级高的服务，缺失的信息从其它服务补齐
+ */
+pub fn merge(printers: Vec<DiscoveredPrinter>) -> Vec<DiscoveredPrinter> {
+    let rank = |p: &DiscoveredPrinter| {
+        SERVICE_TYPES
+            .iter()
+            .position(|ty| service_kind(ty) == p.service_type)
+            .unwrap_or(SERVICE_TYPES.len())
+    };
+    let key = |p: &DiscoveredPrinter| p.uuid.clone().unwrap_or_else(|| p.name.clone()).to_ascii_lowercase();
+
+    let mut merged: Vec<DiscoveredPrinter> = Vec::new();
+    for printer in printers {
+        let Some(existing) = merged.iter_mut().find(|p| key(p) == key(&printer)) else {
+            merged.push(printer);
+            continue;
+        };
+        let (mut primary, secondary) = if rank(&printer) < rank(existing) {
+            (printer, existing.clone())
+        } else {
+            (existing.clone(), printer)
+        };
+        primary.make_and_model = primary.make_and_model.or(secondary.make_and_model);
+        primary.color = primary.color.or(secondary.color);
+        primary.duplex = primary.duplex.or(secondary.duplex);
+        if primary.formats.is_empty() {
+            primary.formats = secondary.formats;
+        }
+        if primary.printer.is_none() {
+            primary.printer = secondary.printer;
+        }
+        *existing = primary;
+    }
+    merged.sort_by(|a, b| a.name.cmp(&b.name));
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(service_type: &str, txt: &[(&str, &str)]) -> ServiceRecord {
+        ServiceRecord {
+            service_type: service_type.to_string(),
+            fullname: format!("Office Printer.{}", service_type),
+            host: "office.local".to_string(),
+            addresses: vec!["fe80::1".parse().unwrap(), "192.168.1.20".parse().unwrap()],
+            port: 631,
+            txt: txt.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        }
+    }
+
+    #[test]
+    fn converts_ipp_record() {
+        let printer = to_discovered(&record(
+            "_ipp._tcp.local.",
+            &[
+                ("ty", "HP LaserJet M404"),
+                ("pdl", "application/pdf,image/urf"),
+                ("color", "F"),
+                ("duplex", "T"),
+                ("rp", "ipp/print"),
+                ("uuid", "1234"),
+            ],
+        ));
+        assert_eq!(printer.name, "Office Printer");
+        assert_eq!(printer.service_type, "ipp");
+        assert_eq!(printer.addresses, vec!["192.168.1.20", "fe80::1"]);
+        assert_eq!(printer.make_and_model.as_deref(), Some("HP LaserJet M404"));
+        assert_eq!(printer.formats, vec!["application/pdf", "image/urf"]);
+        assert_eq!((printer.color, printer.duplex), (Some(false), Some(true)));
+        match printer.printer.unwrap().backend {
+            PrinterBackend::Ipp(settings) => assert_eq!(settings.uri, "ipp://192.168.1.20:631/ipp/print"),
+            backend => panic!("unexpected backend {:?}", backend),
+        }
+    }
+
+    #[test]
+    fn falls_back_to_usb_and_product_keys() {
+        let usb = record("_pdl-datastream._tcp.local.", &[("usb_mfg", "Zebra"), ("usb_mdl", "ZD421")]);
+        assert_eq!(make_and_model(&usb).as_deref(), Some("Zebra ZD421"));
+        let product = record("_pdl-datastream._tcp.local.", &[("product", "(Epson TM-T88VI)")]);
+        assert_eq!(make_and_model(&product).as_deref(), Some("Epson TM-T88VI"));
+
+        let raw = to_discovered(&usb);
+        assert!(matches!(raw.printer.unwrap().backend, PrinterBackend::Raw(s) if s.host == "192.168.1.20"));
+        assert!(to_discovered(&record("_ipps._tcp.local.", &[])).printer.is_none());
+    }
+
+    #[test]
+    fn merges_services_of_same_printer() {
+        let ipps = to_discovered(&record("_ipps._tcp.local.", &[("uuid", "abc"), ("ty", "Brother HL")]));
+        let ipp = to_discovered(&record("_ipp._tcp.local.", &[("uuid", "ABC")]));
+        let other = to_discovered(&ServiceRecord {
+            fullname: "Label._pdl-datastream._tcp.local.".to_string(),
+            ..record("_pdl-datastream._tcp.local.", &[])
+        });
+
+        let merged = merge(vec![ipps, other, ipp]);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].name, "Label");
+        assert_eq!(merged[1].service_type, "ipp");
+        assert_eq!(merged[1].make_and_model.as_deref(), Some("Brother HL"));
+        assert!(merged[1].printer.is_some());
+    }
+
+    #[test]
+    fn browses_services_on_loopback() {
+        use mdns_sd::IfKind;
+
+        let loopback = || {
+            let daemon = ServiceDaemon::new().unwrap();
+            daemon.disable_interface(IfKind::All).unwrap();
+            daemon.enable_interface(IfKind::LoopbackV4).unwrap();
+            daemon
+        };
+        let publisher = loopback();
+        let ipp = ServiceInfo::new(
+            "_ipp._tcp.local.",
+            "Test Laser",
+            "test-laser.local.",
+            "127.0.0.1",
+            8631,
+            &[("ty", "Acme Laser 100"), ("pdl", "application/pdf,image/pwg-raster"), ("Color", "T"), ("rp", "ipp/print")][..],
+        )
+        .unwrap();
+        let raw = ServiceInfo::new(
+            "_pdl-datastream._tcp.local.",
+            "Test Label",
+            "test-label.local.",
+            "127.0.0.1",
+            9100,
+            &[("usb_MFG", "Zebra"), ("usb_MDL", "ZD421")][..],
+        )
+        .unwrap();
+        publisher.register(ipp).unwrap();
+        publisher.register(raw).unwrap();
+
+        let browser = loopback();
+        let printers = browse(&browser, Duration::from_secs(2)).unwrap();
+        let _ = browser.shutdown();
+        let _ = publisher.shutdown();
+
+        let laser = printers.iter().find(|p| p.name == "Test Laser").expect("未发现 IPP 服务");
+        assert_eq!((laser.service_type.as_str(), laser.host.as_str(), laser.port), ("ipp", "test-laser.local", 8631));
+        assert_eq!(laser.make_and_model.as_deref(), Some("Acme Laser 100"));
+        assert_eq!(laser.formats, vec!["application/pdf", "image/pwg-raster"]);
+        assert_eq!(laser.color, Some(true));
+        match &laser.printer.as_ref().unwrap().backend {
+            PrinterBackend::Ipp(settings) => assert_eq!(settings.uri, "ipp://127.0.0.1:8631/ipp/print"),
+            backend => panic!("unexpected backend {:?}", backend),
+        }
+
+        let label = printers.iter().find(|p| p.name == "Test Label").expect("未发现 RAW 服务");
+        assert_eq!((label.service_type.as_str(), label.port), ("pdl-datastream", 9100));
+        assert_eq!(label.make_and_model.as_deref(), Some("Zebra ZD421"));
+        assert!(matches!(&label.printer.as_ref().unwrap().backend, PrinterBackend::Raw(s) if s.host == "127.0.0.1" && s.port == Some(9100)));
+    }
+}
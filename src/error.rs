@@ -6,6 +6,40 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub enum Error {
   #[error(transparent)]
   Io(#[from] std::io::Error),
+  #[error(transparent)]
+  Base64(#[from] base64::DecodeError),
+  #[error("图片转换失败: {0}")]
+  Raster(String),
+  #[error("文本转换失败: {0}")]
+  Text(String),
+  #[error("表格转换失败: {0}")]
+  Table(String),
+  #[error("模板: {0}")]
+  Template(String),
+  #[error("条码生成失败: {0}")]
+  Barcode(String),
+  #[error("未找到打印机: {0}")]
+  PrinterNotFound(String),
+  #[error("打印机通信失败: {0}")]
+  Backend(String),
+  #[error("{0}")]
+  InvalidConfig(String),
+  #[error("当前平台不支持该操作")]
+  UnsupportedPlatform,
+  #[error("打印历史: {0}")]
+  History(String),
+  #[error("打印队列: {0}")]
+  Queue(String),
+  #[error("打印计划: {0}")]
+  Schedule(String),
+  #[error("PDF 处理失败: {0}")]
+  Pdf(String),
+  #[error("无效的 PDF 文档: {0}")]
+  InvalidDocument(String),
+  #[error("PDF 需要密码: {0}")]
+  PasswordRequired(String),
+  #[error("PDF 密码错误: {0}")]
+  IncorrectPassword(String),
   #[cfg(mobile)]
   #[error(transparent)]
   PluginInvoke(#[from] tauri::plugin::mobile::PluginInvokeError),
@@ -0,0 +1,111 @@
+use lopdf::{dictionary, Document, Object, ObjectId};
+
+use crate::declare::{CjkFont, TextFont};
+
+/// Helvetica 字符宽度（1/1000 em），从空格到 ~
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, // 空格 - /
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, // 0 - ?
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778, // @ - O
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, // P - _
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, // ` - o
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584, // p - ~
+];
+
+/// Times-Roman 字符宽度（1/1000 em），从空格到 ~
+const TIMES_WIDTHS: [u16; 95] = [
+    250, 333, 408, 500, 500, 833, 778, 180, 333, 333, 500, 564, 250, 333, 250, 278, // 空格 - /
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 278, 278, 564, 564, 564, 444, // 0 - ?
+    921, 722, 667, 667, 722, 611, 556, 722, 722, 333, 389, 722, 611, 889, 722, 722, // @ - O
+    556, 722, 667, 556, 611, 722, 722, 944, 722, 722, 611, 333, 278, 333, 469, 500, // P - _
+    333, 444, 500, 444, 500, 444, 333, 500, 500, 278, 278, 500, 278, 778, 500, 500, // ` - o
+    500, 500, 333, 389, 278, 500, 500, 722, 500, 500, 444, 480, 200, 480, 541, // p - ~
+];
+
+/// 标准字体按 WinAnsi 单字节编码可以显示的字符：ASCII 和 Latin-1
+pub fn is_latin(c: char) -> bool {
+    matches!(c as u32, 0x20..=0x7E | 0xA0..=0xFF)
+}
+
+/// 标准字体中字符的宽度（1/1000 em），Latin-1 部分按平均宽度估算
+pub fn latin_width(font: TextFont, c: char) -> u16 {
+    let index = (c as u32).wrapping_sub(0x20) as usize;
+    match font {
+        TextFont::Courier => 600,
+        TextFont::Helvetica => HELVETICA_WIDTHS.get(index).copied().unwrap_or(556),
+        TextFont::Times => TIMES_WIDTHS.get(index).copied().unwrap_or(500),
+    }
+}
+
+/// CJK 字体中字符的宽度（1/1000 em），ASCII 为半角
+pub fn cjk_width(c: char) -> u16 {
+    if c.is_ascii() { 500 } else { 1000 }
+}
+
+/// 标准字体（Type1，不嵌入）
+pub fn add_latin(document: &mut Document, font: TextFont) -> ObjectId {
+    let name = match font {
+        TextFont::Courier => "Courier",
+        TextFont::Helvetica => "Helvetica",
+        TextFont::Times => "Times-Roman",
+    };
+    document.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => name,
+        "Encoding" => "WinAnsiEncoding",
+    })
+}
+
+/// CJK 字体（Adobe 字符集，不嵌入，由阅读器替换为系统中的同类字体），文字按 UCS-2 编码
+pub fn add_cjk(document: &mut Document, font: CjkFont) -> ObjectId {
+    // 字体名、字符集、补充版本、编码、FontBBox、CapHeight、StemV
+    let (name, ordering, supplement, encoding, bbox, cap_height, stem_v) = match font {
+        CjkFont::SimplifiedChinese => ("STSong-Light", "GB1", 2, "UniGB-UCS2-H", [-25, -254, 1000, 880], 880, 93),
+        CjkFont::TraditionalChinese => ("MSung-Light", "CNS1", 0, "UniCNS-UCS2-H", [-160, -249, 1015, 1071], 880, 93),
+        CjkFont::Japanese => ("KozMinPro-Regular-Acro", "Japan1", 4, "UniJIS-UCS2-H", [-195, -272, 1110, 1075], 740, 86),
+        CjkFont::Korean => ("HYSMyeongJo-Medium", "Korea1", 1, "UniKS-UCS2-H", [0, -148, 1001, 880], 880, 93),
+    };
+    let descriptor = document.add_object(dictionary! {
+        "Type" => "FontDescriptor",
+        "FontName" => name,
+        "Flags" => 6,
+        "FontBBox" => bbox.iter().map(|&n| Object::Integer(n)).collect::<Vec<_>>(),
+        "ItalicAngle" => 0,
+        "Ascent" => 880,
+        "Descent" => -120,
+        "CapHeight" => cap_height,
+        "StemV" => stem_v,
+    });
+    let descendant = document.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "CIDFontType0",
+        "BaseFont" => name,
+        "CIDSystemInfo" => dictionary! {
+            "Registry" => Object::string_literal("Adobe"),
+            "Ordering" => Object::string_literal(ordering),
+            "Supplement" => supplement,
+        },
+        "FontDescriptor" => descriptor,
+        "DW" => 1000,
+        // CID 1-95 为半角 ASCII
+        "W" => vec![1.into(), 95.into(), 500.into()],
+    });
+    document.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type0",
+        "BaseFont" => Object::Name(format!("{}-{}", name, encoding).into_bytes()),
+        "Encoding" => encoding,
+        "DescendantFonts" => vec![descendant.into()],
+    })
+}
+
+/// 标准字体的十六进制字符串，按 Latin-1 单字节，其他字符替换为 ?
+pub fn encode_latin(text: &str) -> String {
+    text.chars().map(|c| format!("{:02X}", if is_latin(c) { c as u32 } else { '?' as u32 })).collect()
+}
+
+/// CJK 字体的十六进制字符串，按 UCS-2，超出基本平面的字符替换为 ?
+pub fn encode_ucs2(text: &str) -> String {
+    text.chars().map(|c| format!("{:04X}", if (c as u32) <= 0xFFFF { c as u32 } else { '?' as u32 })).collect()
+}
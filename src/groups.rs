@@ -0,0 +1,164 @@
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+use serde_json::Value;
+
+use crate::declare::{GroupStrategy, PrinterGroup};
+use crate::status::{PrinterState, PrinterStatus};
+
+/**
+ * 打印机组注册表
+ *
+ * 按组名打印时，作业发往第一台可用的成员，失败时依次换下一台。
+ */
+fn registry() -> MutexGuard<'static, Vec<PrinterGroup>> {
+    static REGISTRY: OnceLock<Mutex<Vec<PrinterGroup>>> = OnceLock::new();
+    REGISTRY
+        .get_or_init(|| Mutex::new(Vec::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// 注册打印机组，同名的会被替换
+pub fn register(group: PrinterGroup) -> Result<(), String> {
+    if group.name.trim().is_empty() {
+        return Err("打印机组名称不能为空".to_string());
+    }
+    if group.members.is_empty() {
+        return Err(format!("打印机组 {} 没有成员", group.name));
+    }
+    if group.members.contains(&group.name) {
+        return Err(format!("打印机组 {} 不能包含自己", group.name));
+    }
+    let mut groups = registry();
+    groups.retain(|g| g.name != group.name);
+    groups.push(group);
+    Ok(())
+}
+
+pub fn unregister(name: &str) -> bool {
+    let mut groups = registry();
+    let before = groups.len();
+    groups.retain(|g| g.name != name);
+    groups.len() != before
+}
+
+pub fn find(name: &str) -> Option<PrinterGroup> {
+    registry().iter().find(|g| g.name == name).cloned()
+}
+
+pub fn list() -> Vec<PrinterGroup> {
+    registry().clone()
+}
+
+/// 打印前查询到的成员状态
+#[derive(Debug, Clone)]
+pub struct MemberState {
+    pub name: String,
+    pub status: PrinterStatus,
+    /// 只在 least_busy 策略下查询
+    pub job_count: Option<u32>,
+}
+
+/// 离线、卡纸、缺纸、盖板打开和故障的打印机不参与选择
+pub fn is_available(status: &PrinterStatus) -> bool {
+    !status.conditions.iter().any(|state| {
+        matches!(
+            state,
+            PrinterState::Offline
+                | PrinterState::PaperJam
+                | PrinterState::DoorOpen
+                | PrinterState::OutOfPaper
+                | PrinterState::Error
+        )
+    })
+}
+
+/**
+ * 按策略排列可用的成员
+ * 状态未知的成员排在状态正常的成员之后；least_busy 时作业数未知的排在最后
+ */
+pub fn candidates(group: &PrinterGroup, members: &[MemberState]) -> Vec<String> {
+    let mut available: Vec<(usize, &MemberState)> =
+        members.iter().enumerate().filter(|(_, m)| is_available(&m.status)).collect();
+    available.sort_by_key(|(position, member)| {
+        let unknown = member.status.state == PrinterState::Unknown;
+        let load = match group.strategy {
+            GroupStrategy::Failover => 0,
+            GroupStrategy::LeastBusy => member.job_count.unwrap_or(u32::MAX),
+        };
+        (unknown, load, *position)
+    });
+    available.into_iter().map(|(_, m)| m.name.clone()).collect()
+}
+
+/**
+ * 从 `get_printers_by_name` 的输出中读取 JobCount
+ */
+pub fn job_count_from_json(json: &str) -> Option<u32> {
+    let value: Value = serde_json::from_str(json.trim()).ok()?;
+    let printer = match &value {
+        Value::Array(items) => items.first()?,
+        other => other,
+    };
+    printer["JobCount"].as_u64().map(|n| n as u32)
+}
+
+/// 所有成员都不可用时的说明
+pub fn unavailable_message(group: &PrinterGroup, members: &[MemberState]) -> String {
+    let states: Vec<String> = members
+        .iter()
+        .map(|m| format!("{}: {}", m.name, m.status.message.as_deref().unwrap_or(m.status.state.as_str())))
+        .collect();
+    format!("打印机组 {} 中没有可用的打印机（{}）", group.name, states.join("; "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(name: &str, state: PrinterState, job_count: Option<u32>) -> MemberState {
+        MemberState { name: name.to_string(), status: PrinterStatus::new(name, vec![state], None), job_count }
+    }
+
+    fn group(strategy: GroupStrategy) -> PrinterGroup {
+        PrinterGroup {
+            name: "Station 1".to_string(),
+            members: vec!["primary".to_string(), "secondary".to_string(), "spare".to_string()],
+            strategy,
+        }
+    }
+
+    #[test]
+    fn orders_members_by_health_and_load() {
+        let members = vec![
+            member("primary", PrinterState::OutOfPaper, Some(0)),
+            member("secondary", PrinterState::Unknown, Some(0)),
+            member("spare", PrinterState::Printing, Some(3)),
+        ];
+        assert_eq!(candidates(&group(GroupStrategy::Failover), &members), vec!["spare", "secondary"]);
+
+        let members = vec![
+            member("primary", PrinterState::Printing, Some(4)),
+            member("secondary", PrinterState::Idle, None),
+            member("spare", PrinterState::TonerLow, Some(1)),
+        ];
+        assert_eq!(candidates(&group(GroupStrategy::Failover), &members), vec!["primary", "secondary", "spare"]);
+        assert_eq!(candidates(&group(GroupStrategy::LeastBusy), &members), vec!["spare", "primary", "secondary"]);
+
+        let offline = vec![member("primary", PrinterState::Offline, None)];
+        assert!(candidates(&group(GroupStrategy::Failover), &offline).is_empty());
+        assert!(unavailable_message(&group(GroupStrategy::Failover), &offline).contains("primary: offline"));
+    }
+
+    #[test]
+    fn reads_job_count_and_validates_groups() {
+        assert_eq!(job_count_from_json(r#"{ "Name": "HP", "JobCount": 2 }"#), Some(2));
+        assert_eq!(job_count_from_json(r#"[{ "Name": "HP", "JobCount": 5 }]"#), Some(5));
+        assert_eq!(job_count_from_json("Unsupported OS"), None);
+
+        let mut invalid = group(GroupStrategy::Failover);
+        invalid.members.push("Station 1".to_string());
+        assert!(register(invalid).is_err());
+        assert!(register(PrinterGroup { members: Vec::new(), ..group(GroupStrategy::Failover) }).is_err());
+    }
+}
@@ -0,0 +1,457 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, Row};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::declare::{HistoryConfig, PrintSettings};
+
+/// 数据库文件名，位于应用数据目录下
+pub const DATABASE_FILE: &str = "print_history.sqlite3";
+
+const DEFAULT_RETENTION_DAYS: u32 = 365;
+const DEFAULT_LIMIT: u32 = 1000;
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS print_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    job_id TEXT,
+    document_name TEXT NOT NULL,
+    document_hash TEXT,
+    printer TEXT NOT NULL,
+    settings TEXT,
+    pages INTEGER,
+    user TEXT,
+    outcome TEXT NOT NULL,
+    message TEXT,
+    submitted_at INTEGER NOT NULL,
+    completed_at INTEGER
+);
+CREATE INDEX IF NOT EXISTS idx_print_history_submitted_at ON print_history (submitted_at);
+CREATE INDEX IF NOT EXISTS idx_print_history_printer ON print_history (printer);
+";
+
+const COLUMNS: &str = "id, job_id, document_name, document_hash, printer, settings, pages, user, outcome, message, submitted_at, completed_at";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobOutcome {
+    /// 已提交给打印机或打印后台
+    Submitted,
+    Failed,
+}
+
+impl JobOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobOutcome::Submitted => "submitted",
+            JobOutcome::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+/// 一条打印记录，时间为毫秒时间戳
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryEntry {
+    pub id: i64,
+    pub job_id: Option<String>,
+    pub document_name: String,
+    /// 文档内容的 SHA-256
+    pub document_hash: Option<String>,
+    pub printer: String,
+    pub settings: Option<PrintSettings>,
+    pub pages: Option<u32>,
+    pub user: Option<String>,
+    pub outcome: JobOutcome,
+    pub message: Option<String>,
+    pub submitted_at: i64,
+    pub completed_at: Option<i64>,
+}
+
+/// 查询条件，均为可选
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HistoryFilter {
+    pub printer: Option<String>,
+    pub user: Option<String>,
+    pub outcome: Option<JobOutcome>,
+    pub document_name: Option<String>,  // 文档名包含该文本
+    pub document_hash: Option<String>,
+    pub from: Option<i64>,  // 提交时间下限（含）
+    pub to: Option<i64>,  // 提交时间上限（不含）
+    pub limit: Option<u32>,  // 默认 1000，导出时不限
+    pub offset: Option<u32>,
+}
+
+/// 提交前读取的文档信息
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    pub name: String,
+    pub hash: Option<String>,
+    pub pages: Option<u32>,
+}
+
+impl Document {
+    pub fn from_bytes(name: &str, bytes: &[u8]) -> Self {
+        let pages = if bytes.starts_with(b"%PDF") { count_pdf_pages(bytes) } else { None };
+        Document { name: name.to_string(), hash: Some(sha256_hex(bytes)), pages }
+    }
+
+    /// 文件读取失败时只记录文件名
+    pub fn from_path(path: &str) -> Self {
+        let name = Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string());
+        match fs::read(path) {
+            Ok(bytes) => Document::from_bytes(&name, &bytes),
+            Err(_) => Document { name, ..Document::default() },
+        }
+    }
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 按页面树统计 PDF 页数，文档无法解析或没有页面时返回 None
+pub fn count_pdf_pages(bytes: &[u8]) -> Option<u32> {
+    let document = lopdf::Document::load_mem(bytes).ok()?;
+    let pages = document.get_pages().len() as u32;
+    (pages > 0).then_some(pages)
+}
+
+pub fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+pub fn current_user() -> Option<String> {
+    env::var("USERNAME").ok().or_else(|| env::var("USER").ok())
+}
+
+/// 毫秒时间戳转为 UTC 的 RFC 3339 时间，如 2024-03-01T08:30:00Z
+pub fn format_timestamp(ms: i64) -> String {
+    let seconds = ms.div_euclid(1000);
+    let (days, secs) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+    // 公历日期换算（Howard Hinnant 的 civil_from_days）
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, secs / 3600, secs % 3600 / 60, secs % 60)
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/**
+ * 导出为 CSV，时间转为 UTC 时间
+ */
+pub fn to_csv(entries: &[HistoryEntry]) -> String {
+    let mut out = String::from(
+        "id,job_id,document_name,document_hash,printer,settings,pages,user,outcome,message,submitted_at,completed_at\n",
+    );
+    for entry in entries {
+        let settings = entry.settings.as_ref().and_then(|s| serde_json::to_string(s).ok());
+        let fields = [
+            entry.id.to_string(),
+            entry.job_id.clone().unwrap_or_default(),
+            entry.document_name.clone(),
+            entry.document_hash.clone().unwrap_or_default(),
+            entry.printer.clone(),
+            settings.unwrap_or_default(),
+            entry.pages.map(|p| p.to_string()).unwrap_or_default(),
+            entry.user.clone().unwrap_or_default(),
+            entry.outcome.as_str().to_string(),
+            entry.message.clone().unwrap_or_default(),
+            format_timestamp(entry.submitted_at),
+            entry.completed_at.map(format_timestamp).unwrap_or_default(),
+        ];
+        let line: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&line.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// SQLite 存储
+pub struct HistoryStore {
+    connection: Connection,
+    /// 0 表示永久保留
+    retention_days: u32,
+}
+
+impl HistoryStore {
+    pub fn open(path: &Path, retention_days: u32) -> Result<Self, String> {
+        let connection = Connection::open(path).map_err(|e| format!("打开 {} 失败: {}", path.display(), e))?;
+        connection.execute_batch(SCHEMA).map_err(|e| e.to_string())?;
+        let store = HistoryStore { connection, retention_days };
+        store.apply_retention()?;
+        Ok(store)
+    }
+
+    /**
+     * 删除超过保留天数的记录
+     * @returns 删除的条数
+     */
+    pub fn apply_retention(&self) -> Result<usize, String> {
+        if self.retention_days == 0 {
+            return Ok(0);
+        }
+        let cutoff = now_ms() - i64::from(self.retention_days) * DAY_MS;
+        self.connection
+            .execute("DELETE FROM print_history WHERE submitted_at < ?1", params![cutoff])
+            .map_err(|e| e.to_string())
+    }
+
+    /// 写入一条记录，忽略 entry.id
+    pub fn insert(&self, entry: &HistoryEntry) -> Result<i64, String> {
+        let settings = entry.settings.as_ref().and_then(|s| serde_json::to_string(s).ok());
+        self.connection
+            .execute(
+                "INSERT INTO print_history (job_id, document_name, document_hash, printer, settings, pages, user, outcome, message, submitted_at, completed_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                params![
+                    entry.job_id,
+                    entry.document_name,
+                    entry.document_hash,
+                    entry.printer,
+                    settings,
+                    entry.pages,
+                    entry.user,
+                    entry.outcome.as_str(),
+                    entry.message,
+                    entry.submitted_at,
+                    entry.completed_at,
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(self.connection.last_insert_rowid())
+    }
+
+    /**
+     * 按条件查询，最新的在前
+     * @param limit 为空时不限条数
+     */
+    pub fn query(&self, filter: &HistoryFilter, limit: Option<u32>) -> Result<Vec<HistoryEntry>, String> {
+        let mut sql = format!("SELECT {} FROM print_history WHERE 1 = 1", COLUMNS);
+        let mut values: Vec<Value> = Vec::new();
+        let mut condition = |clause: &str, value: Value| {
+            sql.push_str(clause);
+            values.push(value);
+        };
+        if let Some(printer) = &filter.printer {
+            condition(" AND printer = ?", Value::Text(printer.clone()));
+        }
+        if let Some(user) = &filter.user {
+            condition(" AND user = ?", Value::Text(user.clone()));
+        }
+        if let Some(outcome) = filter.outcome {
+            condition(" AND outcome = ?", Value::Text(outcome.as_str().to_string()));
+        }
+        if let Some(name) = &filter.document_name {
+            condition(" AND instr(document_name, ?) > 0", Value::Text(name.clone()));
+        }
+        if let Some(hash) = &filter.document_hash {
+            condition(" AND document_hash = ?", Value::Text(hash.to_ascii_lowercase()));
+        }
+        if let Some(from) = filter.from {
+            condition(" AND submitted_at >= ?", Value::Integer(from));
+        }
+        if let Some(to) = filter.to {
+            condition(" AND submitted_at < ?", Value::Integer(to));
+        }
+        // SQLite 中 LIMIT -1 表示不限
+        let limit = limit.map(i64::from).unwrap_or(-1);
+        condition(" ORDER BY submitted_at DESC, id DESC LIMIT ?", Value::Integer(limit));
+        condition(" OFFSET ?", Value::Integer(i64::from(filter.offset.unwrap_or(0))));
+
+        let mut statement = self.connection.prepare(&sql).map_err(|e| e.to_string())?;
+        let rows = statement.query_map(params_from_iter(values), row_to_entry).map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+    }
+}
+
+fn row_to_entry(row: &Row) -> rusqlite::Result<HistoryEntry> {
+    let settings: Option<String> = row.get(5)?;
+    let outcome: String = row.get(8)?;
+    Ok(HistoryEntry {
+        id: row.get(0)?,
+        job_id: row.get(1)?,
+        document_name: row.get(2)?,
+        document_hash: row.get(3)?,
+        printer: row.get(4)?,
+        settings: settings.and_then(|s| serde_json::from_str(&s).ok()),
+        pages: row.get(6)?,
+        user: row.get(7)?,
+        outcome: if outcome == "failed" { JobOutcome::Failed } else { JobOutcome::Submitted },
+        message: row.get(9)?,
+        submitted_at: row.get(10)?,
+        completed_at: row.get(11)?,
+    })
+}
+
+fn store() -> &'static OnceLock<Mutex<HistoryStore>> {
+    static STORE: OnceLock<Mutex<HistoryStore>> = OnceLock::new();
+    &STORE
+}
+
+/**
+ * 打开应用数据目录下的历史库，只在插件初始化时调用一次
+ */
+pub fn init(data_dir: &Path, config: &HistoryConfig) -> Result<(), String> {
+    if !config.enabled.unwrap_or(true) {
+        return Ok(());
+    }
+    fs::create_dir_all(data_dir).map_err(|e| format!("创建 {} 失败: {}", data_dir.display(), e))?;
+    let retention_days = config.retention_days.unwrap_or(DEFAULT_RETENTION_DAYS);
+    let history = HistoryStore::open(&data_dir.join(DATABASE_FILE), retention_days)?;
+    store()
+        .set(Mutex::new(history))
+        .map_err(|_| "打印历史已经初始化".to_string())
+}
+
+fn with_store<T>(f: impl FnOnce(&HistoryStore) -> Result<T, String>) -> Result<T, String> {
+    let history = store().get().ok_or("打印历史未启用")?;
+    let history = history.lock().unwrap_or_else(|e| e.into_inner());
+    f(&history)
+}
+
+/**
+ * 记录一次提交，历史未启用时忽略
+ */
+pub fn record(entry: HistoryEntry) {
+    if store().get().is_none() {
+        return;
+    }
+    let result = with_store(|history| {
+        history.insert(&entry)?;
+        history.apply_retention()
+    });
+    if let Err(e) = result {
+        eprintln!("记录打印历史失败: {}", e);
+    }
+}
+
+pub fn query(filter: &HistoryFilter) -> Result<Vec<HistoryEntry>, String> {
+    with_store(|history| history.query(filter, Some(filter.limit.unwrap_or(DEFAULT_LIMIT))))
+}
+
+/**
+ * 导出到文件
+ * @returns 导出的条数
+ */
+pub fn export(filter: &HistoryFilter, format: ExportFormat, path: &Path) -> Result<usize, String> {
+    let entries = with_store(|history| history.query(filter, filter.limit))?;
+    let content = match format {
+        ExportFormat::Csv => to_csv(&entries),
+        ExportFormat::Json => serde_json::to_string_pretty(&entries).map_err(|e| e.to_string())?,
+    };
+    fs::write(path, content).map_err(|e| format!("写入 {} 失败: {}", path.display(), e))?;
+    Ok(entries.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(printer: &str, outcome: JobOutcome, submitted_at: i64) -> HistoryEntry {
+        HistoryEntry {
+            id: 0,
+            job_id: Some("42".to_string()),
+            document_name: "invoice, March.pdf".to_string(),
+            document_hash: Some(sha256_hex(b"%PDF-1.4")),
+            printer: printer.to_string(),
+            settings: Some(PrintSettings { copies: Some(2), ..PrintSettings::default() }),
+            pages: Some(3),
+            user: Some("alice".to_string()),
+            outcome,
+            message: None,
+            submitted_at,
+            completed_at: Some(submitted_at + 1500),
+        }
+    }
+
+    #[test]
+    fn stores_filters_and_expires_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::open(&dir.path().join(DATABASE_FILE), 30).unwrap();
+        let now = now_ms();
+        store.insert(&entry("Office", JobOutcome::Submitted, now - 1000)).unwrap();
+        store.insert(&entry("Office", JobOutcome::Failed, now)).unwrap();
+        store.insert(&entry("Kitchen", JobOutcome::Submitted, now - 500)).unwrap();
+        store.insert(&entry("Office", JobOutcome::Submitted, now - 40 * DAY_MS)).unwrap();
+
+        let office = store.query(&HistoryFilter { printer: Some("Office".to_string()), ..Default::default() }, None).unwrap();
+        assert_eq!(office.len(), 3);
+        assert_eq!(office[0].outcome, JobOutcome::Failed);
+        assert_eq!(office[0].settings.as_ref().and_then(|s| s.copies), Some(2));
+
+        let filter = HistoryFilter { outcome: Some(JobOutcome::Submitted), from: Some(now - 2000), ..Default::default() };
+        let recent = store.query(&filter, None).unwrap();
+        assert_eq!(recent.iter().map(|e| e.printer.as_str()).collect::<Vec<_>>(), vec!["Kitchen", "Office"]);
+
+        assert_eq!(store.apply_retention().unwrap(), 1);
+        assert_eq!(store.query(&HistoryFilter::default(), Some(2)).unwrap().len(), 2);
+        assert_eq!(store.query(&HistoryFilter::default(), None).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn exports_csv_with_utc_times() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(1_709_281_800_000), "2024-03-01T08:30:00Z");
+
+        let csv = to_csv(&[entry("Office", JobOutcome::Submitted, 1_709_281_800_000)]);
+        let line = csv.lines().nth(1).unwrap();
+        assert!(line.starts_with("0,42,\"invoice, March.pdf\",e16fa5d9"));
+        assert!(line.contains(",\"{\"\"page_size\"\":null,"));
+        assert!(line.ends_with(",3,alice,submitted,,2024-03-01T08:30:00Z,2024-03-01T08:30:01Z"));
+    }
+
+    #[test]
+    fn reads_document_info() {
+        let mut pdf = lopdf::Document::with_version("1.5");
+        let pages_id = pdf.new_object_id();
+        let kids: Vec<lopdf::Object> = (0..2)
+            .map(|_| pdf.add_object(lopdf::dictionary! { "Type" => "Page", "Parent" => pages_id }).into())
+            .collect();
+        pdf.objects.insert(pages_id, lopdf::dictionary! { "Type" => "Pages", "Kids" => kids, "Count" => 2 }.into());
+        let catalog_id = pdf.add_object(lopdf::dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        pdf.trailer.set("Root", catalog_id);
+        let mut bytes = Vec::new();
+        pdf.save_to(&mut bytes).unwrap();
+
+        let document = Document::from_bytes("a.pdf", &bytes);
+        assert_eq!(document.pages, Some(2));
+        assert_eq!(document.hash.as_deref().map(str::len), Some(64));
+        assert_eq!(Document::from_bytes("raw", b"\x1b@hello").pages, None);
+        // 只有 /Type /Page 字样而没有页面树的不是有效文档
+        assert_eq!(Document::from_bytes("bad.pdf", b"%PDF-1.4\n2 0 obj << /Type /Page >>").pages, None);
+    }
+}
@@ -0,0 +1,412 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageFormat};
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream};
+
+use crate::declare::{ImageFit, ImageLayout, ImageOrientation};
+use crate::layout::{multiply, A4};
+use crate::pdf;
+use crate::tiff;
+
+/// 图片没有分辨率信息时使用
+const DEFAULT_DPI: f64 = 96.0;
+
+/// 与纸张边缘的默认距离（pt）
+const DEFAULT_MARGIN: f64 = 18.0;
+
+/// 图像的一段：CCITT 压缩的 TIFF 每个条带单独嵌入，其他图片只有一段
+pub struct Band {
+    pub width: u32,
+    pub rows: u32,
+    pub image: Dictionary,  // ColorSpace、BitsPerComponent 等，设置了 Filter 时 data 为压缩后的数据
+    pub data: Vec<u8>,
+    pub mask: Option<Vec<u8>>,  // 8 位透明度
+}
+
+/// 图片的一页
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub dpi: Option<(f64, f64)>,
+    pub orientation: u16,  // EXIF 方向，1 为正向
+    pub bands: Vec<Band>,
+}
+
+/// 拆出每个像素的颜色分量和第一个额外分量
+pub fn split_alpha(pixels: &[u8], channels: usize, samples: usize) -> (Vec<u8>, Vec<u8>) {
+    let mut color = Vec::with_capacity(pixels.len() / samples * channels);
+    let mut alpha = Vec::with_capacity(pixels.len() / samples);
+    for pixel in pixels.chunks_exact(samples) {
+        color.extend_from_slice(&pixel[..channels]);
+        alpha.push(pixel[channels]);
+    }
+    (color, alpha)
+}
+
+/// 按文件内容识别格式，读取所有页面
+pub fn read_frames(bytes: &[u8]) -> Result<Vec<Frame>, String> {
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        return jpeg(bytes).map(|frame| vec![frame]);
+    }
+    if tiff::is_tiff(bytes) {
+        return tiff::read(bytes);
+    }
+    let format = image::guess_format(bytes).map_err(|_| "不支持的图片格式".to_string())?;
+    let image = image::load_from_memory_with_format(bytes, format).map_err(|e| format!("图片解码失败: {}", e))?;
+    let dpi = if format == ImageFormat::Png { png_dpi(bytes) } else { None };
+    Ok(vec![decoded(image, dpi)])
+}
+
+/// JPEG 以 DCTDecode 原样嵌入，不重新编码
+fn jpeg(bytes: &[u8]) -> Result<Frame, String> {
+    let mut size = None;
+    let mut dpi = None;
+    let mut orientation = 1;
+    let mut adobe = false;
+    let mut offset = 2;
+    while offset + 4 <= bytes.len() {
+        if bytes[offset] != 0xFF {
+            return Err("JPEG 数据无效".to_string());
+        }
+        let marker = bytes[offset + 1];
+        match marker {
+            // 填充字节
+            0xFF => {
+                offset += 1;
+                continue;
+            }
+            0x01 | 0xD0..=0xD8 => {
+                offset += 2;
+                continue;
+            }
+            0xD9 | 0xDA => break,
+            _ => {}
+        }
+        let length = u16::from_be_bytes([bytes[offset + 2], bytes[offset + 3]]) as usize;
+        let segment = bytes
+            .get(offset + 4..offset + 2 + length.max(2))
+            .ok_or_else(|| "JPEG 数据不完整".to_string())?;
+        let be16 = |i: usize| u16::from_be_bytes([segment[i], segment[i + 1]]);
+        match marker {
+            0xE0 if segment.starts_with(b"JFIF\0") && segment.len() >= 12 => {
+                let (x, y) = (be16(8) as f64, be16(10) as f64);
+                dpi = match segment[7] {
+                    1 if x > 0.0 && y > 0.0 => Some((x, y)),
+                    2 if x > 0.0 && y > 0.0 => Some((x * 2.54, y * 2.54)),
+                    _ => None,
+                };
+            }
+            0xE1 if segment.starts_with(b"Exif\0\0") => orientation = Orientation::from_exif_chunk(&segment[6..]).map_or(1, |o| o.to_exif().into()),
+            0xEE if segment.starts_with(b"Adobe") => adobe = true,
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) && segment.len() >= 6 => {
+                size = Some((segment[0], be16(3) as u32, be16(1) as u32, segment[5]));
+            }
+            _ => {}
+        }
+        offset += 2 + length;
+    }
+    let (precision, width, height, components) = size.ok_or_else(|| "JPEG 缺少图像尺寸".to_string())?;
+    if precision != 8 || width == 0 || height == 0 {
+        return Err(format!("不支持的 JPEG: {} 位 {}x{}", precision, width, height));
+    }
+    let color_space = match components {
+        1 => "DeviceGray",
+        3 => "DeviceRGB",
+        4 => "DeviceCMYK",
+        _ => return Err(format!("不支持 {} 个颜色分量的 JPEG", components)),
+    };
+    let mut image = dictionary! { "ColorSpace" => color_space, "BitsPerComponent" => 8, "Filter" => "DCTDecode" };
+    // Photoshop 保存的 CMYK JPEG 是反相的
+    if components == 4 && adobe {
+        image.set("Decode", [1, 0, 1, 0, 1, 0, 1, 0].iter().map(|&n| Object::Integer(n)).collect::<Vec<_>>());
+    }
+    let band = Band { width, rows: height, image, data: bytes.to_vec(), mask: None };
+    Ok(Frame { width, height, dpi, orientation, bands: vec![band] })
+}
+
+/// PNG pHYs 块中的分辨率，单位为像素/米
+fn png_dpi(bytes: &[u8]) -> Option<(f64, f64)> {
+    let be32 = |b: &[u8]| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as f64;
+    let mut offset = 8;
+    while let Some(header) = bytes.get(offset..offset + 8) {
+        let length = be32(header) as usize;
+        let data = bytes.get(offset + 8..offset + 8 + length)?;
+        match &header[4..] {
+            b"pHYs" if length == 9 && data[8] == 1 => {
+                let (x, y) = (be32(&data[..4]), be32(&data[4..8]));
+                return (x > 0.0 && y > 0.0).then_some((x * 0.0254, y * 0.0254));
+            }
+            b"IDAT" => return None,
+            _ => {}
+        }
+        offset += 12 + length;
+    }
+    None
+}
+
+/// 解码后的图片，灰度图保持单通道，透明度作为蒙版
+fn decoded(image: DynamicImage, dpi: Option<(f64, f64)>) -> Frame {
+    let (width, height) = (image.width(), image.height());
+    let color = image.color();
+    let (color_space, data, alpha) = match (color.has_color(), color.has_alpha()) {
+        (true, true) => {
+            let (rgb, alpha) = split_alpha(image.to_rgba8().as_raw(), 3, 4);
+            ("DeviceRGB", rgb, Some(alpha))
+        }
+        (true, false) => ("DeviceRGB", image.to_rgb8().into_raw(), None),
+        (false, true) => {
+            let (gray, alpha) = split_alpha(image.to_luma_alpha8().as_raw(), 1, 2);
+            ("DeviceGray", gray, Some(alpha))
+        }
+        (false, false) => ("DeviceGray", image.to_luma8().into_raw(), None),
+    };
+    let mask = alpha.filter(|alpha| alpha.iter().any(|&a| a < 255));
+    let image = dictionary! { "ColorSpace" => color_space, "BitsPerComponent" => 8 };
+    let band = Band { width, rows: height, image, data, mask };
+    Frame { width, height, dpi, orientation: 1, bands: vec![band] }
+}
+
+/// EXIF 方向对应的变换，把单位正方形中的图像转为正向
+fn orientation_matrix(orientation: u16) -> [f64; 6] {
+    match orientation {
+        2 => [-1.0, 0.0, 0.0, 1.0, 1.0, 0.0],
+        3 => [-1.0, 0.0, 0.0, -1.0, 1.0, 1.0],
+        4 => [1.0, 0.0, 0.0, -1.0, 0.0, 1.0],
+        5 => [0.0, -1.0, -1.0, 0.0, 1.0, 1.0],
+        6 => [0.0, -1.0, 1.0, 0.0, 0.0, 1.0],
+        7 => [0.0, 1.0, 1.0, 0.0, 0.0, 0.0],
+        8 => [0.0, 1.0, -1.0, 0.0, 1.0, 0.0],
+        _ => [1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+    }
+}
+
+/// 页面宽高、可打印区域和图片所在的矩形，矩形为 [x, y, 宽, 高]
+struct Placement {
+    page: (f64, f64),
+    area: [f64; 4],
+    rect: [f64; 4],
+}
+
+/**
+ * 图片在纸张上的位置
+ * @param size 正向显示时的尺寸（pt）
+ * @param paper 纸张宽高（pt），按 orientation 调整方向
+ */
+fn placement(size: (f64, f64), paper: (f64, f64), layout: &ImageLayout) -> Result<Placement, String> {
+    let landscape = match layout.orientation {
+        ImageOrientation::Auto => size.0 > size.1,
+        ImageOrientation::Portrait => false,
+        ImageOrientation::Landscape => true,
+    };
+    let (short, long) = (paper.0.min(paper.1), paper.0.max(paper.1));
+    let page = if landscape { (long, short) } else { (short, long) };
+    let margin = layout.margin.unwrap_or(DEFAULT_MARGIN).max(0.0);
+    let area = [margin, margin, page.0 - margin * 2.0, page.1 - margin * 2.0];
+    if area[2] <= 0.0 || area[3] <= 0.0 {
+        return Err(format!("边距 {} 超出纸张大小", margin));
+    }
+    let scale = match layout.fit {
+        ImageFit::Fit => (area[2] / size.0).min(area[3] / size.1),
+        ImageFit::Fill => (area[2] / size.0).max(area[3] / size.1),
+        ImageFit::ActualSize => 1.0,
+    };
+    let (width, height) = (size.0 * scale, size.1 * scale);
+    // 居中，超出可打印区域的部分裁掉
+    let rect = [area[0] + (area[2] - width) / 2.0, area[1] + (area[3] - height) / 2.0, width, height];
+    Ok(Placement { page, area, rect })
+}
+
+fn add_band(document: &mut Document, band: Band) -> ObjectId {
+    let compressed = |dict: Dictionary, data: Vec<u8>| {
+        let mut stream = Stream::new(dict, data);
+        let _ = stream.compress();
+        stream
+    };
+    let mut image = dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Image",
+        "Width" => band.width,
+        "Height" => band.rows,
+    };
+    image.extend(&band.image);
+    if let Some(alpha) = band.mask {
+        let mask = dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => band.width,
+            "Height" => band.rows,
+            "ColorSpace" => "DeviceGray",
+            "BitsPerComponent" => 8,
+        };
+        let mask_id = document.add_object(compressed(mask, alpha));
+        image.set("SMask", mask_id);
+    }
+    if image.has(b"Filter") {
+        document.add_object(Stream::new(image, band.data))
+    } else {
+        document.add_object(compressed(image, band.data))
+    }
+}
+
+/**
+ * 生成每页一张图片的文档，页面为纸张大小
+ * @param paper 纸张宽高（pt），为空时使用 A4
+ */
+pub fn to_pdf(frames: Vec<Frame>, paper: Option<(f64, f64)>, layout: &ImageLayout) -> Result<Document, String> {
+    let mut document = Document::with_version("1.5");
+    let pages_id = document.new_object_id();
+    let mut kids: Vec<Object> = Vec::new();
+    for frame in frames {
+        let dpi = layout
+            .dpi
+            .map(|dpi| (dpi, dpi))
+            .or(frame.dpi)
+            .filter(|&(x, y)| x > 0.0 && y > 0.0)
+            .unwrap_or((DEFAULT_DPI, DEFAULT_DPI));
+        let mut size = (frame.width as f64 * 72.0 / dpi.0, frame.height as f64 * 72.0 / dpi.1);
+        // 5-8 需要旋转 90 度显示
+        if frame.orientation >= 5 {
+            size = (size.1, size.0);
+        }
+        let Placement { page, area, rect } = placement(size, paper.unwrap_or(A4), layout)?;
+        let m = multiply(orientation_matrix(frame.orientation), [rect[2], 0.0, 0.0, rect[3], rect[0], rect[1]]);
+
+        let mut content = format!(
+            "q\n{:.3} {:.3} {:.3} {:.3} re W n\n{:.3} {:.3} {:.3} {:.3} {:.3} {:.3} cm\n",
+            area[0], area[1], area[2], area[3], m[0], m[1], m[2], m[3], m[4], m[5]
+        );
+        let mut xobjects = Dictionary::new();
+        let height = frame.height as f64;
+        let mut top = 0.0;
+        for (i, band) in frame.bands.into_iter().enumerate() {
+            let rows = band.rows as f64;
+            let name = format!("Im{}", i);
+            content.push_str(&format!("q 1 0 0 {:.6} 0 {:.6} cm /{} Do Q\n", rows / height, (height - top - rows) / height, name));
+            top += rows;
+            xobjects.set(name, add_band(&mut document, band));
+        }
+        content.push_str("Q\n");
+
+        let content_id = document.add_object(Stream::new(Dictionary::new(), content.into_bytes()));
+        let media_box: Vec<Object> = vec![0.into(), 0.into(), (page.0 as f32).into(), (page.1 as f32).into()];
+        let page_id = document.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => media_box,
+            "Contents" => content_id,
+            "Resources" => dictionary! { "XObject" => xobjects },
+        });
+        kids.push(page_id.into());
+    }
+    pdf::set_pages(&mut document, pages_id, kids);
+    Ok(document)
+}
+
+/**
+ * 把图片文件转换为 PDF，每张图片（多页 TIFF 的每一页）一页
+ * @param paper 纸张宽高（pt），为空时使用 A4
+ * @returns 输出的临时文件
+ */
+pub fn convert_files(paths: &[String], paper: Option<(f64, f64)>, layout: &ImageLayout) -> Result<PathBuf, String> {
+    let Some(first) = paths.first() else {
+        return Err("没有要打印的图片".to_string());
+    };
+    let mut frames = Vec::new();
+    for path in paths {
+        let bytes = fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+        frames.extend(read_frames(&bytes).map_err(|e| format!("{}: {}", path, e))?);
+    }
+    let mut document = to_pdf(frames, paper, layout)?;
+    let output = pdf::spool_path(&pdf::file_stem(Path::new(first)))?;
+    pdf::save(&mut document, &output)?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    use ::tiff::tags::Tag;
+    use image::{codecs::jpeg::JpegEncoder, GrayImage, RgbaImage};
+
+    #[test]
+    fn places_images_on_paper() {
+        let a4 = A4;
+        let layout = ImageLayout { margin: Some(0.0), ..Default::default() };
+
+        // 横向图片自动使用横向页面，铺满宽度并垂直居中
+        let Placement { page, rect, .. } = placement((800.0, 400.0), a4, &layout).unwrap();
+        assert_eq!(page, (a4.1, a4.0));
+        assert!(rect[0].abs() < 1e-6 && (rect[2] - a4.1).abs() < 1e-6);
+        assert!((rect[1] - (a4.0 - a4.1 / 2.0) / 2.0).abs() < 1e-6);
+
+        // fill 铺满后超出部分裁掉，actual_size 保持原始尺寸
+        let fill = ImageLayout { fit: ImageFit::Fill, orientation: ImageOrientation::Portrait, margin: Some(36.0), dpi: None };
+        let Placement { page, area, rect } = placement((800.0, 400.0), a4, &fill).unwrap();
+        assert_eq!(page, (a4.0, a4.1));
+        assert_eq!(rect[3], area[3]);
+        assert!(rect[0] < area[0]);
+        let actual = ImageLayout { fit: ImageFit::ActualSize, ..fill };
+        let rect = placement((144.0, 72.0), a4, &actual).unwrap().rect;
+        assert_eq!((rect[2], rect[3]), (144.0, 72.0));
+
+        let too_wide = ImageLayout { margin: Some(300.0), ..Default::default() };
+        assert!(placement((1.0, 1.0), a4, &too_wide).is_err());
+
+        // 方向 6：单位正方形左上角转到右上角
+        let m = orientation_matrix(6);
+        assert_eq!((m[2] + m[4], m[3] + m[5]), (1.0, 1.0));
+    }
+
+    #[test]
+    fn converts_images_to_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut jpeg = Vec::new();
+        JpegEncoder::new_with_quality(&mut jpeg, 90).encode_image(&GrayImage::new(300, 150)).unwrap();
+        let jpeg_path = dir.path().join("photo.jpg");
+        fs::write(&jpeg_path, &jpeg).unwrap();
+
+        let mut png = Cursor::new(Vec::new());
+        let mut rgba = RgbaImage::new(20, 40);
+        rgba.get_pixel_mut(0, 0).0[3] = 0;
+        rgba.write_to(&mut png, ImageFormat::Png).unwrap();
+        let png_path = dir.path().join("scan.png");
+        fs::write(&png_path, png.into_inner()).unwrap();
+
+        let tiff = tiff::tests::encode(&[
+            (vec![(Tag::ImageWidth, 4, vec![2]), (Tag::ImageLength, 4, vec![2]), (Tag::BitsPerSample, 3, vec![8]), (Tag::PhotometricInterpretation, 3, vec![1])], vec![vec![0; 4]]),
+            (vec![(Tag::ImageWidth, 4, vec![2]), (Tag::ImageLength, 4, vec![1]), (Tag::BitsPerSample, 3, vec![8]), (Tag::PhotometricInterpretation, 3, vec![0])], vec![vec![0; 2]]),
+        ]);
+        let tiff_path = dir.path().join("fax.tif");
+        fs::write(&tiff_path, tiff).unwrap();
+
+        let paths: Vec<String> = [&jpeg_path, &png_path, &tiff_path].iter().map(|p| p.to_string_lossy().to_string()).collect();
+        let layout = ImageLayout { fit: ImageFit::ActualSize, dpi: Some(72.0), ..Default::default() };
+        let output = convert_files(&paths, crate::layout::paper_size("A5"), &layout).unwrap();
+        let document = pdf::load(&output).unwrap();
+        let pages: Vec<_> = document.get_pages().into_values().collect();
+        assert_eq!(pages.len(), 4);
+
+        // JPEG 原样嵌入，横向图片使用横向 A5
+        let (bounds, _) = pdf::page_geometry(&document, pages[0]);
+        assert_eq!((bounds[2].round(), bounds[3].round()), (595.0, 420.0));
+        let resources = document.get_dictionary(pages[0]).unwrap().get(b"Resources").unwrap().as_dict().unwrap();
+        let image_id = resources.get(b"XObject").unwrap().as_dict().unwrap().get(b"Im0").unwrap().as_reference().unwrap();
+        let image = document.get_object(image_id).unwrap().as_stream().unwrap();
+        assert_eq!(image.dict.get(b"Filter").unwrap().as_name().unwrap(), b"DCTDecode");
+        assert_eq!(image.content, jpeg);
+        let content = String::from_utf8(document.get_page_content(pages[0]).unwrap()).unwrap();
+        assert!(content.contains("300.000 0.000 0.000 150.000 147.638 134.764 cm"), "{}", content);
+
+        // PNG 的透明度作为蒙版
+        let resources = document.get_dictionary(pages[1]).unwrap().get(b"Resources").unwrap().as_dict().unwrap();
+        let image_id = resources.get(b"XObject").unwrap().as_dict().unwrap().get(b"Im0").unwrap().as_reference().unwrap();
+        assert!(document.get_object(image_id).unwrap().as_stream().unwrap().dict.has(b"SMask"));
+
+        assert!(convert_files(&[png_path.to_string_lossy().to_string(), dir.path().join("missing.png").to_string_lossy().to_string()], None, &layout).is_err());
+        fs::remove_file(output).unwrap();
+    }
+}
@@ -0,0 +1,687 @@
+use std::env;
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use crate::declare::{IppSettings, JobEntry};
+
+/// IPP 默认端口
+pub const DEFAULT_PORT: u16 = 631;
+
+const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 5000;
+const IO_TIMEOUT: Duration = Duration::from_secs(60);
+
+// 操作
+pub const PRINT_JOB: u16 = 0x0002;
+pub const CANCEL_JOB: u16 = 0x0008;
+pub const GET_JOBS: u16 = 0x000A;
+pub const GET_PRINTER_ATTRIBUTES: u16 = 0x000B;
+
+// 分组标签
+pub const OPERATION_ATTRIBUTES: u8 = 0x01;
+pub const JOB_ATTRIBUTES: u8 = 0x02;
+const END_OF_ATTRIBUTES: u8 = 0x03;
+pub const PRINTER_ATTRIBUTES: u8 = 0x04;
+
+// 值标签
+pub const INTEGER: u8 = 0x21;
+pub const BOOLEAN: u8 = 0x22;
+pub const ENUM: u8 = 0x23;
+const RESOLUTION: u8 = 0x32;
+const RANGE: u8 = 0x33;
+const BEG_COLLECTION: u8 = 0x34;
+const TEXT_WITH_LANGUAGE: u8 = 0x35;
+const NAME_WITH_LANGUAGE: u8 = 0x36;
+const END_COLLECTION: u8 = 0x37;
+pub const TEXT: u8 = 0x41;
+pub const NAME: u8 = 0x42;
+pub const KEYWORD: u8 = 0x44;
+pub const URI: u8 = 0x45;
+const CHARSET: u8 = 0x47;
+const NATURAL_LANGUAGE: u8 = 0x48;
+pub const MIME_MEDIA_TYPE: u8 = 0x49;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IppValue {
+    Integer(i32),
+    Boolean(bool),
+    Enum(i32),
+    Text(String),
+    Range(i32, i32),
+    Resolution { x: i32, y: i32, units: u8 },
+    /// 带外值（unsupported/unknown/no-value）
+    OutOfBand(u8),
+    /// 未解析的值，如 octetString、dateTime
+    Other(u8, Vec<u8>),
+}
+
+impl IppValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            IppValue::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_i32(&self) -> Option<i32> {
+        match self {
+            IppValue::Integer(n) | IppValue::Enum(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            IppValue::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IppAttribute {
+    pub name: String,
+    pub values: Vec<IppValue>,
+}
+
+impl IppAttribute {
+    pub fn strings(&self) -> Vec<String> {
+        self.values.iter().filter_map(|v| v.as_str().map(str::to_string)).collect()
+    }
+
+    pub fn integers(&self) -> Vec<i32> {
+        self.values.iter().filter_map(IppValue::as_i32).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IppGroup {
+    pub tag: u8,
+    pub attributes: Vec<IppAttribute>,
+}
+
+impl IppGroup {
+    pub fn get(&self, name: &str) -> Option<&IppAttribute> {
+        self.attributes.iter().find(|a| a.name == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IppResponse {
+    pub status: u16,
+    pub request_id: u32,
+    pub groups: Vec<IppGroup>,
+}
+
+impl IppResponse {
+    /// 状态码 0x0000-0x00FF 表示成功
+    pub fn is_success(&self) -> bool {
+        self.status < 0x0100
+    }
+
+    pub fn groups(&self, tag: u8) -> impl Iterator<Item = &IppGroup> {
+        self.groups.iter().filter(move |g| g.tag == tag)
+    }
+
+    /// 在指定分组中查找属性
+    pub fn attribute(&self, tag: u8, name: &str) -> Option<&IppAttribute> {
+        self.groups(tag).find_map(|g| g.get(name))
+    }
+}
+
+/// IPP 请求，自动带上 attributes-charset、attributes-natural-language 和 printer-uri
+#[derive(Debug)]
+pub struct IppRequest {
+    operation: u16,
+    request_id: u32,
+    groups: Vec<(u8, Vec<u8>)>,
+    data: Vec<u8>,
+}
+
+impl IppRequest {
+    pub fn new(operation: u16, printer_uri: &str) -> Self {
+        static REQUEST_ID: AtomicU32 = AtomicU32::new(1);
+        let mut request = IppRequest {
+            operation,
+            request_id: REQUEST_ID.fetch_add(1, Ordering::SeqCst),
+            groups: Vec::new(),
+            data: Vec::new(),
+        };
+        request
+            .string(OPERATION_ATTRIBUTES, CHARSET, "attributes-charset", &["utf-8"])
+            .string(OPERATION_ATTRIBUTES, NATURAL_LANGUAGE, "attributes-natural-language", &["en"])
+            .string(OPERATION_ATTRIBUTES, URI, "printer-uri", &[printer_uri]);
+        request
+    }
+
+    fn group(&mut self, tag: u8) -> &mut Vec<u8> {
+        if self.groups.last().map(|(t, _)| *t) != Some(tag) {
+            self.groups.push((tag, Vec::new()));
+        }
+        &mut self.groups.last_mut().unwrap().1
+    }
+
+    fn value(&mut self, group: u8, value_tag: u8, name: &str, value: &[u8]) -> &mut Self {
+        let buffer = self.group(group);
+        buffer.push(value_tag);
+        buffer.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        buffer.extend_from_slice(name.as_bytes());
+        buffer.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        buffer.extend_from_slice(value);
+        self
+    }
+
+    /// 添加字符串类属性，多个值时后续值的名称长度为 0
+    pub fn string(&mut self, group: u8, value_tag: u8, name: &str, values: &[&str]) -> &mut Self {
+        for (index, value) in values.iter().enumerate() {
+            let name = if index == 0 { name } else { "" };
+            self.value(group, value_tag, name, value.as_bytes());
+        }
+        self
+    }
+
+    pub fn integer(&mut self, group: u8, value_tag: u8, name: &str, value: i32) -> &mut Self {
+        self.value(group, value_tag, name, &value.to_be_bytes())
+    }
+
+    pub fn boolean(&mut self, group: u8, name: &str, value: bool) -> &mut Self {
+        self.value(group, BOOLEAN, name, &[value as u8])
+    }
+
+    pub fn document(&mut self, data: Vec<u8>) -> &mut Self {
+        self.data = data;
+        self
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(64 + self.data.len());
+        out.extend_from_slice(&[0x01, 0x01]);
+        out.extend_from_slice(&self.operation.to_be_bytes());
+        out.extend_from_slice(&self.request_id.to_be_bytes());
+        for (tag, attributes) in &self.groups {
+            out.push(*tag);
+            out.extend_from_slice(attributes);
+        }
+        out.push(END_OF_ATTRIBUTES);
+        out.extend_from_slice(&self.data);
+        out
+    }
+}
+
+/// 解析 IPP 响应报文
+pub fn parse_response(bytes: &[u8]) -> Result<IppResponse, String> {
+    let mut reader = Reader { bytes, position: 0 };
+    reader.take(2)?;
+    let status = u16::from_be_bytes(reader.take(2)?.try_into().unwrap());
+    let request_id = u32::from_be_bytes(reader.take(4)?.try_into().unwrap());
+
+    let mut groups: Vec<IppGroup> = Vec::new();
+    let mut collection_depth = 0;
+    loop {
+        let tag = reader.byte()?;
+        if tag == END_OF_ATTRIBUTES {
+            break;
+        }
+        if tag < 0x10 {
+            groups.push(IppGroup { tag, attributes: Vec::new() });
+            continue;
+        }
+
+        let name = String::from_utf8_lossy(reader.sized()?).to_string();
+        let value = reader.sized()?;
+
+        // 集合类型（如 media-col）的成员不展开
+        if tag == BEG_COLLECTION {
+            collection_depth += 1;
+            continue;
+        }
+        if collection_depth > 0 {
+            if tag == END_COLLECTION {
+                collection_depth -= 1;
+            }
+            continue;
+        }
+
+        let group = groups.last_mut().ok_or("IPP 响应缺少属性分组")?;
+        let value = decode_value(tag, value);
+        if name.is_empty() {
+            if let Some(attribute) = group.attributes.last_mut() {
+                attribute.values.push(value);
+            }
+        } else {
+            group.attributes.push(IppAttribute { name, values: vec![value] });
+        }
+    }
+
+    Ok(IppResponse { status, request_id, groups })
+}
+
+fn decode_value(tag: u8, value: &[u8]) -> IppValue {
+    let int = |bytes: &[u8]| i32::from_be_bytes(bytes.try_into().unwrap());
+    match tag {
+        0x10..=0x1F => IppValue::OutOfBand(tag),
+        INTEGER if value.len() == 4 => IppValue::Integer(int(value)),
+        ENUM if value.len() == 4 => IppValue::Enum(int(value)),
+        BOOLEAN if value.len() == 1 => IppValue::Boolean(value[0] != 0),
+        RANGE if value.len() == 8 => IppValue::Range(int(&value[..4]), int(&value[4..])),
+        RESOLUTION if value.len() == 9 => IppValue::Resolution {
+            x: int(&value[..4]),
+            y: int(&value[4..8]),
+            units: value[8],
+        },
+        // 带语言的文本：2 字节语言长度 + 语言 + 2 字节文本长度 + 文本
+        TEXT_WITH_LANGUAGE | NAME_WITH_LANGUAGE => {
+            let mut reader = Reader { bytes: value, position: 0 };
+            let text = reader.sized().and_then(|_| reader.sized()).unwrap_or_default();
+            IppValue::Text(String::from_utf8_lossy(text).to_string())
+        }
+        0x40..=0x4F => IppValue::Text(String::from_utf8_lossy(value).to_string()),
+        _ => IppValue::Other(tag, value.to_vec()),
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let end = self.position + count;
+        if end > self.bytes.len() {
+            return Err("IPP 响应被截断".to_string());
+        }
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// 读取 2 字节长度前缀的数据
+    fn sized(&mut self) -> Result<&'a [u8], String> {
+        let length = u16::from_be_bytes(self.take(2)?.try_into().unwrap()) as usize;
+        self.take(length)
+    }
+}
+
+/// `ipp://host:port/path` 拆分为 (host, port, path)
+pub fn parse_uri(uri: &str) -> Result<(String, u16, String), String> {
+    let (scheme, rest) = uri.split_once("://").ok_or(format!("无效的打印机地址: {}", uri))?;
+    let default_port = match scheme.to_ascii_lowercase().as_str() {
+        "ipp" => DEFAULT_PORT,
+        "http" => 80,
+        "ipps" | "https" => return Err("暂不支持 ipps (TLS)，请使用 ipp:// 地址".to_string()),
+        _ => return Err(format!("不支持的协议: {}", scheme)),
+    };
+
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], rest[index..].to_string()),
+        None => (rest, "/".to_string()),
+    };
+    let (host, port) = if let Some(stripped) = authority.strip_prefix('[') {
+        // IPv6 地址
+        let end = stripped.find(']').ok_or(format!("无效的打印机地址: {}", uri))?;
+        let port = stripped[end + 1..].strip_prefix(':');
+        (stripped[..end].to_string(), port)
+    } else {
+        match authority.rsplit_once(':') {
+            Some((host, port)) => (host.to_string(), Some(port)),
+            None => (authority.to_string(), None),
+        }
+    };
+    let port = match port {
+        Some(port) => port.parse().map_err(|_| format!("无效的端口: {}", port))?,
+        None => default_port,
+    };
+    Ok((host, port, path))
+}
+
+/**
+ * 通过 HTTP POST 发送 IPP 请求
+ */
+pub fn send(settings: &IppSettings, request: &IppRequest) -> Result<IppResponse, String> {
+    let (host, port, path) = parse_uri(&settings.uri)?;
+    let host_header = if host.contains(':') { format!("[{}]:{}", host, port) } else { format!("{}:{}", host, port) };
+    let timeout = Duration::from_millis(settings.connect_timeout_ms.unwrap_or(DEFAULT_CONNECT_TIMEOUT_MS));
+
+    let addrs = (host.as_str(), port)
+        .to_socket_addrs()
+        .map_err(|e| format!("解析地址 {} 失败: {}", host_header, e))?;
+    let mut stream = None;
+    let mut last_error = format!("地址 {} 没有可用的 IP", host_header);
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(s) => {
+                stream = Some(s);
+                break;
+            }
+            Err(e) => last_error = format!("连接 {} 失败: {}", addr, e),
+        }
+    }
+    let mut stream = stream.ok_or(last_error)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT)).map_err(|e| e.to_string())?;
+    stream.set_write_timeout(Some(IO_TIMEOUT)).map_err(|e| e.to_string())?;
+
+    let body = request.encode();
+    let header = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/ipp\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        path,
+        host_header,
+        body.len()
+    );
+    stream
+        .write_all(header.as_bytes())
+        .and_then(|_| stream.write_all(&body))
+        .map_err(|e| format!("发送 IPP 请求失败: {}", e))?;
+
+    let mut raw = Vec::new();
+    stream
+        .read_to_end(&mut raw)
+        .map_err(|e| format!("读取 IPP 响应失败: {}", e))?;
+    let _ = stream.shutdown(Shutdown::Both);
+
+    parse_response(&http_body(&raw)?)
+}
+
+/// 取出 HTTP 响应体，跳过 `100 Continue`，并处理分块传输
+fn http_body(raw: &[u8]) -> Result<Vec<u8>, String> {
+    let mut rest = raw;
+    loop {
+        let split = rest
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .ok_or("HTTP 响应不完整")?;
+        let head = String::from_utf8_lossy(&rest[..split]).to_string();
+        let body = &rest[split + 4..];
+
+        let status_line = head.lines().next().unwrap_or_default();
+        let code = status_line.split_whitespace().nth(1).unwrap_or_default();
+        if code == "100" {
+            rest = body;
+            continue;
+        }
+        if code != "200" {
+            return Err(format!("打印机返回 HTTP 错误: {}", status_line));
+        }
+
+        let chunked = head.lines().any(|line| {
+            let line = line.to_ascii_lowercase();
+            line.starts_with("transfer-encoding:") && line.contains("chunked")
+        });
+        return if chunked { decode_chunked(body) } else { Ok(body.to_vec()) };
+    }
+}
+
+fn decode_chunked(mut body: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    loop {
+        let line_end = body.windows(2).position(|w| w == b"\r\n").ok_or("分块数据不完整")?;
+        let size_text = String::from_utf8_lossy(&body[..line_end]).to_string();
+        let size_text = size_text.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size_text, 16).map_err(|_| format!("无效的分块长度: {}", size_text))?;
+        body = &body[line_end + 2..];
+        if size == 0 {
+            return Ok(out);
+        }
+        if body.len() < size {
+            return Err("分块数据不完整".to_string());
+        }
+        out.extend_from_slice(&body[..size]);
+        body = body.get(size + 2..).unwrap_or_default();
+    }
+}
+
+fn user_name(settings: &IppSettings) -> String {
+    settings
+        .user
+        .clone()
+        .or_else(|| env::var("USERNAME").ok())
+        .or_else(|| env::var("USER").ok())
+        .unwrap_or_else(|| "tauri".to_string())
+}
+
+fn check(response: IppResponse) -> Result<IppResponse, String> {
+    if response.is_success() {
+        return Ok(response);
+    }
+    let message = response
+        .attribute(OPERATION_ATTRIBUTES, "status-message")
+        .and_then(|a| a.values.first())
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default();
+    Err(format!("IPP 错误 0x{:04X} {}", response.status, message).trim_end().to_string())
+}
+
+/**
+ * 提交打印作业（Print-Job）
+ * @returns 作业号
+ */
+pub fn print_job(settings: &IppSettings, data: &[u8], job_name: &str, copies: u32) -> Result<i32, String> {
+    let format = settings.document_format.as_deref().unwrap_or("application/octet-stream");
+    let mut request = IppRequest::new(PRINT_JOB, &settings.uri);
+    request
+        .string(OPERATION_ATTRIBUTES, NAME, "requesting-user-name", &[&user_name(settings)])
+        .string(OPERATION_ATTRIBUTES, NAME, "job-name", &[job_name])
+        .string(OPERATION_ATTRIBUTES, MIME_MEDIA_TYPE, "document-format", &[format]);
+    if copies > 1 {
+        request.integer(JOB_ATTRIBUTES, INTEGER, "copies", copies as i32);
+    }
+    request.document(data.to_vec());
+
+    let response = check(send(settings, &request)?)?;
+    response
+        .attribute(JOB_ATTRIBUTES, "job-id")
+        .and_then(|a| a.values.first())
+        .and_then(IppValue::as_i32)
+        .ok_or("打印机未返回 job-id".to_string())
+}
+
+/**
+ * 获取打印机属性（Get-Printer-Attributes）
+ * @param requested 需要的属性名，为空时返回全部
+ */
+pub fn get_printer_attributes(settings: &IppSettings, requested: &[&str]) -> Result<IppGroup, String> {
+    let mut request = IppRequest::new(GET_PRINTER_ATTRIBUTES, &settings.uri);
+    request.string(OPERATION_ATTRIBUTES, NAME, "requesting-user-name", &[&user_name(settings)]);
+    if !requested.is_empty() {
+        request.string(OPERATION_ATTRIBUTES, KEYWORD, "requested-attributes", requested);
+    }
+    let response = check(send(settings, &request)?)?;
+    let group = response.groups(PRINTER_ATTRIBUTES).next().cloned();
+    group.ok_or("打印机未返回属性".to_string())
+}
+
+/// 获取未完成的作业（Get-Jobs）
+pub fn get_jobs(settings: &IppSettings, printer: &str) -> Result<Vec<JobEntry>, String> {
+    let mut request = IppRequest::new(GET_JOBS, &settings.uri);
+    request
+        .string(OPERATION_ATTRIBUTES, NAME, "requesting-user-name", &[&user_name(settings)])
+        .string(OPERATION_ATTRIBUTES, KEYWORD, "which-jobs", &["not-completed"])
+        .string(
+            OPERATION_ATTRIBUTES,
+            KEYWORD,
+            "requested-attributes",
+            &["job-id", "job-name", "job-state", "job-originating-user-name", "job-k-octets"],
+        );
+    let response = check(send(settings, &request)?)?;
+
+    let jobs = response
+        .groups(JOB_ATTRIBUTES)
+        .enumerate()
+        .filter_map(|(position, group)| {
+            let text = |name: &str| {
+                group
+                    .get(name)
+                    .and_then(|a| a.values.first())
+                    .and_then(|v| v.as_str().map(str::to_string))
+                    .unwrap_or_default()
+            };
+            let int = |name: &str| group.get(name).and_then(|a| a.values.first()).and_then(IppValue::as_i32);
+            Some(JobEntry {
+                id: int("job-id")?.to_string(),
+                document_name: text("job-name"),
+                user_name: text("job-originating-user-name"),
+                size: int("job-k-octets").unwrap_or(0).max(0) as u64 * 1024,
+                position: position as u32,
+                job_status: job_state_name(int("job-state").unwrap_or(0)).to_string(),
+                printer_name: printer.to_string(),
+            })
+        })
+        .collect();
+    Ok(jobs)
+}
+
+/// 取消作业（Cancel-Job）
+pub fn cancel_job(settings: &IppSettings, job_id: &str) -> Result<(), String> {
+    let job_id: i32 = job_id.parse().map_err(|_| format!("无效的作业号: {}", job_id))?;
+    let mut request = IppRequest::new(CANCEL_JOB, &settings.uri);
+    request
+        .integer(OPERATION_ATTRIBUTES, INTEGER, "job-id", job_id)
+        .string(OPERATION_ATTRIBUTES, NAME, "requesting-user-name", &[&user_name(settings)]);
+    check(send(settings, &request)?).map(|_| ())
+}
+
+/// RFC 8011 job-state
+fn job_state_name(state: i32) -> &'static str {
+    match state {
+        3 => "Pending",
+        4 => "Held",
+        5 => "Printing",
+        6 => "Stopped",
+        7 => "Canceled",
+        8 => "Aborted",
+        9 => "Completed",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn settings(uri: String) -> IppSettings {
+        IppSettings {
+            uri,
+            user: Some("alice".to_string()),
+            document_format: Some("application/pdf".to_string()),
+            connect_timeout_ms: Some(500),
+        }
+    }
+
+    /// 构造一个响应：操作属性 + 一个额外分组
+    fn response(status: u16, group: u8, attributes: &[(u8, &str, &[u8])]) -> Vec<u8> {
+        let mut out = vec![0x01, 0x01];
+        out.extend_from_slice(&status.to_be_bytes());
+        out.extend_from_slice(&7u32.to_be_bytes());
+        out.push(OPERATION_ATTRIBUTES);
+        out.push(group);
+        for (tag, name, value) in attributes {
+            out.push(*tag);
+            out.extend_from_slice(&(name.len() as u16).to_be_bytes());
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            out.extend_from_slice(value);
+        }
+        out.push(END_OF_ATTRIBUTES);
+        out
+    }
+
+    #[test]
+    fn parses_uris() {
+        assert_eq!(parse_uri("ipp://printer.local/ipp/print").unwrap(), ("printer.local".to_string(), 631, "/ipp/print".to_string()));
+        assert_eq!(parse_uri("ipp://10.0.0.5:8631").unwrap(), ("10.0.0.5".to_string(), 8631, "/".to_string()));
+        assert_eq!(parse_uri("ipp://[fe80::1]:631/ipp").unwrap(), ("fe80::1".to_string(), 631, "/ipp".to_string()));
+        assert!(parse_uri("ipps://printer.local/ipp/print").is_err());
+    }
+
+    #[test]
+    fn encodes_multi_valued_attributes() {
+        let mut request = IppRequest::new(GET_PRINTER_ATTRIBUTES, "ipp://p/ipp");
+        request.string(OPERATION_ATTRIBUTES, KEYWORD, "requested-attributes", &["a", "bb"]);
+        let bytes = request.encode();
+
+        assert_eq!(&bytes[..4], &[0x01, 0x01, 0x00, 0x0B]);
+        let tail = [KEYWORD, 0, 20];
+        let start = bytes.windows(3).position(|w| w == tail).unwrap();
+        let expected = b"requested-attributes\x00\x01a\x44\x00\x00\x00\x02bb\x03";
+        assert_eq!(&bytes[start + 3..], &expected[..]);
+    }
+
+    #[test]
+    fn parses_values_and_skips_collections() {
+        let bytes = response(
+            0x0000,
+            PRINTER_ATTRIBUTES,
+            &[
+                (KEYWORD, "sides-supported", b"one-sided"),
+                (KEYWORD, "", b"two-sided-long-edge"),
+                (BEG_COLLECTION, "media-col-default", b""),
+                (0x4A, "", b"media-size"),
+                (BEG_COLLECTION, "", b""),
+                (END_COLLECTION, "", b""),
+                (END_COLLECTION, "", b""),
+                (BOOLEAN, "color-supported", &[1]),
+                (RANGE, "copies-supported", &[0, 0, 0, 1, 0, 0, 0, 99]),
+                (ENUM, "printer-state", &[0, 0, 0, 3]),
+            ],
+        );
+        let response = parse_response(&bytes).unwrap();
+        assert!(response.is_success());
+        let group = response.groups(PRINTER_ATTRIBUTES).next().unwrap();
+        assert_eq!(group.get("sides-supported").unwrap().strings(), vec!["one-sided", "two-sided-long-edge"]);
+        assert_eq!(group.get("color-supported").unwrap().values, vec![IppValue::Boolean(true)]);
+        assert_eq!(group.get("copies-supported").unwrap().values, vec![IppValue::Range(1, 99)]);
+        assert_eq!(group.get("printer-state").unwrap().integers(), vec![3]);
+        assert!(group.get("media-col-default").is_none());
+    }
+
+    #[test]
+    fn decodes_chunked_http_body() {
+        let raw = b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n2\r\nde\r\n0\r\n\r\n";
+        assert_eq!(http_body(raw).unwrap(), b"abcde");
+        assert!(http_body(b"HTTP/1.1 404 Not Found\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn print_job_returns_job_id() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 4096];
+            // 读到文档内容末尾为止
+            while !request.ends_with(b"%PDF-1.4 test") {
+                let n = socket.read(&mut buffer).unwrap();
+                assert!(n > 0);
+                request.extend_from_slice(&buffer[..n]);
+            }
+            let body = response(0x0000, JOB_ATTRIBUTES, &[(INTEGER, "job-id", &[0, 0, 0, 42])]);
+            let header = format!("HTTP/1.1 200 OK\r\nContent-Type: application/ipp\r\nContent-Length: {}\r\n\r\n", body.len());
+            socket.write_all(header.as_bytes()).unwrap();
+            socket.write_all(&body).unwrap();
+            String::from_utf8_lossy(&request).to_string()
+        });
+
+        let uri = format!("ipp://127.0.0.1:{}/ipp/print", port);
+        let job_id = print_job(&settings(uri), b"%PDF-1.4 test", "invoice.pdf", 2).unwrap();
+        assert_eq!(job_id, 42);
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /ipp/print HTTP/1.1\r\n"));
+        assert!(request.contains("application/pdf") && request.contains("invoice.pdf") && request.contains("copies"));
+    }
+
+    #[test]
+    fn reports_ipp_errors() {
+        let bytes = response(0x040A, OPERATION_ATTRIBUTES, &[(TEXT, "status-message", b"document-format-not-supported")]);
+        let error = check(parse_response(&bytes).unwrap()).unwrap_err();
+        assert_eq!(error, "IPP 错误 0x040A document-format-not-supported");
+    }
+}
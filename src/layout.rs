@@ -0,0 +1,337 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use lopdf::{Dictionary, Document, Object, Stream};
+
+use crate::capabilities::standard_media_size;
+use crate::declare::{PrintSettings, Scaling};
+use crate::pdf;
+
+const POINTS_PER_MM: f64 = 72.0 / 25.4;
+
+/// A4 纸张宽高（pt），生成的文档没有指定纸张时使用
+pub const A4: (f64, f64) = (595.276, 841.89);
+
+/// 调整后页面上不再适用的区域
+const PAGE_BOXES: [&[u8]; 4] = [b"CropBox", b"BleedBox", b"TrimBox", b"ArtBox"];
+
+/// 纸张尺寸（pt），只支持常用名称和 PWG 名称
+pub fn paper_size(name: &str) -> Option<(f64, f64)> {
+    standard_media_size(name).map(|(width, height)| (width * POINTS_PER_MM, height * POINTS_PER_MM))
+}
+
+/// 先执行 a 再执行 b 的变换矩阵
+pub fn multiply(a: [f64; 6], b: [f64; 6]) -> [f64; 6] {
+    [
+        a[0] * b[0] + a[1] * b[2],
+        a[0] * b[1] + a[1] * b[3],
+        a[2] * b[0] + a[3] * b[2],
+        a[2] * b[1] + a[3] * b[3],
+        a[4] * b[0] + a[5] * b[2] + b[4],
+        a[4] * b[1] + a[5] * b[3] + b[5],
+    ]
+}
+
+fn invert(m: [f64; 6]) -> [f64; 6] {
+    let det = m[0] * m[3] - m[1] * m[2];
+    [
+        m[3] / det,
+        -m[1] / det,
+        -m[2] / det,
+        m[0] / det,
+        (m[2] * m[5] - m[3] * m[4]) / det,
+        (m[1] * m[4] - m[0] * m[5]) / det,
+    ]
+}
+
+/// 自动旋转时，方向与纸张不同的内容逆时针旋转 90 度，返回旋转矩阵和旋转后的宽高
+fn orient(width: f64, height: f64, (paper_width, paper_height): (f64, f64), auto_rotate: bool) -> ([f64; 6], f64, f64) {
+    let rotated = auto_rotate && width != height && paper_width != paper_height && (width > height) != (paper_width > paper_height);
+    if rotated {
+        ([0.0, 1.0, -1.0, 0.0, height, 0.0], height, width)
+    } else {
+        ([1.0, 0.0, 0.0, 1.0, 0.0, 0.0], width, height)
+    }
+}
+
+/**
+ * 页面内容在纸张上的位置
+ * @param paper 纸张宽高，为空时使用页面自身的尺寸（自动旋转时取纵向）
+ * @returns 页面坐标到纸张坐标的变换矩阵和纸张宽高
+ */
+fn placement(
+    bounds: [f64; 4],
+    rotate: i64,
+    paper: Option<(f64, f64)>,
+    scaling: Scaling,
+    auto_rotate: bool,
+) -> ([f64; 6], f64, f64) {
+    let (to_page, width, height) = pdf::display_matrix(bounds, rotate);
+    let (paper_width, paper_height) = match paper {
+        Some(paper) => paper,
+        None if auto_rotate => (width.min(height), width.max(height)),
+        None => (width, height),
+    };
+    let (mut to_paper, content_width, content_height) = orient(width, height, (paper_width, paper_height), auto_rotate);
+    let fit = (paper_width / content_width).min(paper_height / content_height);
+    let scale = match scaling {
+        Scaling::Fit => fit,
+        Scaling::Shrink => fit.min(1.0),
+        Scaling::None | Scaling::Tile => 1.0,
+        Scaling::Custom(percent) => percent / 100.0,
+    };
+    // 居中放置
+    let offset_x = (paper_width - content_width * scale) / 2.0;
+    let offset_y = (paper_height - content_height * scale) / 2.0;
+    to_paper = multiply(to_paper, [scale, 0.0, 0.0, scale, offset_x, offset_y]);
+    (multiply(invert(to_page), to_paper), paper_width, paper_height)
+}
+
+/**
+ * 平铺时每张纸的变换矩阵，按原始尺寸从左上角开始逐行切分
+ * 内容在拼接后的区域中居中
+ */
+fn tiles(bounds: [f64; 4], rotate: i64, paper: (f64, f64), auto_rotate: bool) -> Vec<[f64; 6]> {
+    let (to_page, width, height) = pdf::display_matrix(bounds, rotate);
+    let (paper_width, paper_height) = paper;
+    let (to_paper, content_width, content_height) = orient(width, height, paper, auto_rotate);
+    // 允许少量误差，与纸张一样大的页面不会多出一张
+    let columns = (content_width / paper_width - 1e-3).ceil().max(1.0);
+    let rows = (content_height / paper_height - 1e-3).ceil().max(1.0);
+    let offset_x = (columns * paper_width - content_width) / 2.0;
+    let offset_y = (rows * paper_height - content_height) / 2.0;
+    let to_sheets = multiply(invert(to_page), to_paper);
+
+    let mut result = Vec::new();
+    for row in 0..rows as usize {
+        for column in 0..columns as usize {
+            let x = column as f64 * paper_width;
+            let y = (rows - 1.0 - row as f64) * paper_height;
+            result.push(multiply(to_sheets, [1.0, 0.0, 0.0, 1.0, offset_x - x, offset_y - y]));
+        }
+    }
+    result
+}
+
+fn page_contents(page: &Dictionary) -> Vec<Object> {
+    match page.get(b"Contents") {
+        Ok(Object::Array(items)) => items.clone(),
+        Ok(item @ Object::Reference(_)) => vec![item.clone()],
+        _ => Vec::new(),
+    }
+}
+
+/// 用变换矩阵包住原有内容，页面尺寸改为纸张尺寸
+fn place_on_paper(document: &mut Document, page: &mut Dictionary, m: [f64; 6], (paper_width, paper_height): (f64, f64)) {
+    let begin = format!("q\n{:.6} {:.6} {:.6} {:.6} {:.3} {:.3} cm\n", m[0], m[1], m[2], m[3], m[4], m[5]);
+    let mut contents: Vec<Object> = vec![document.add_object(Stream::new(Dictionary::new(), begin.into_bytes())).into()];
+    contents.extend(page_contents(page));
+    contents.push(document.add_object(Stream::new(Dictionary::new(), b"\nQ\n".to_vec())).into());
+
+    page.set("Contents", contents);
+    page.set("MediaBox", vec![0.into(), 0.into(), (paper_width as f32).into(), (paper_height as f32).into()]);
+    page.set("Rotate", 0);
+    for key in PAGE_BOXES {
+        page.remove(key);
+    }
+}
+
+/**
+ * 把每一页缩放、旋转到纸张上，页面尺寸改为纸张尺寸
+ * 注释（如表单域）的位置不随页面调整
+ */
+pub fn apply(document: &mut Document, paper: Option<(f64, f64)>, scaling: Scaling, auto_rotate: bool) -> Result<(), String> {
+    let mut done = HashSet::new();
+    let page_ids: Vec<_> = document.get_pages().into_values().filter(|id| done.insert(*id)).collect();
+    for page_id in page_ids {
+        let (bounds, rotate) = pdf::page_geometry(document, page_id);
+        let (m, paper_width, paper_height) = placement(bounds, rotate, paper, scaling, auto_rotate);
+        let mut page = document.get_dictionary(page_id).map_err(|e| e.to_string())?.clone();
+        place_on_paper(document, &mut page, m, (paper_width, paper_height));
+        document.objects.insert(page_id, Object::Dictionary(page));
+    }
+    Ok(())
+}
+
+/**
+ * 海报打印：把每一页按原始尺寸拆分到多张纸上，从左上角开始逐行排列
+ * 拆出的页面共用原页面的内容流；注释只保留在每页的第一张纸上，位置不随页面调整
+ */
+pub fn tile(document: &mut Document, paper: (f64, f64), auto_rotate: bool) -> Result<(), String> {
+    let page_ids: Vec<_> = document.get_pages().into_values().collect();
+    let pages_id = document.new_object_id();
+    let mut used = HashSet::new();
+    let mut kids: Vec<Object> = Vec::new();
+    for page_id in page_ids {
+        let (bounds, rotate) = pdf::page_geometry(document, page_id);
+        let mut source = pdf::flatten_page(document, page_id)?;
+        source.set("Parent", pages_id);
+        for (index, m) in tiles(bounds, rotate, paper, auto_rotate).into_iter().enumerate() {
+            let mut page = source.clone();
+            if index > 0 {
+                page.remove(b"Annots");
+            }
+            place_on_paper(document, &mut page, m, paper);
+            let target = if used.insert(page_id) { page_id } else { document.new_object_id() };
+            document.objects.insert(target, Object::Dictionary(page));
+            kids.push(target.into());
+        }
+    }
+    pdf::set_pages(document, pages_id, kids);
+    document.prune_objects();
+    Ok(())
+}
+
+/**
+ * 按打印参数中的 scaling 和 auto_rotate 调整文档，平铺时纸张默认为 A4
+ * @param native 后端（SumatraPDF）自己支持 fit / shrink / none 和自动旋转，这时只处理自定义比例和平铺
+ * @returns 调整后的临时文件，不需要调整时返回 None
+ */
+pub fn prepare(path: &Path, settings: Option<&PrintSettings>, native: bool) -> Result<Option<PathBuf>, String> {
+    let Some(settings) = settings else {
+        return Ok(None);
+    };
+    let auto_rotate = settings.auto_rotate.unwrap_or(false);
+    let transform = match settings.scaling {
+        Some(Scaling::Custom(_)) | Some(Scaling::Tile) => true,
+        Some(_) => !native,
+        None => auto_rotate && !native,
+    };
+    if !transform {
+        return Ok(None);
+    }
+    let paper = settings.page_size.as_deref().and_then(paper_size);
+    let scaling = settings.scaling.unwrap_or(Scaling::None);
+    if scaling == Scaling::Tile {
+        let paper = paper.unwrap_or(A4);
+        return pdf::transform_file(path, |document, _| tile(document, paper, auto_rotate)).map(Some);
+    }
+    pdf::transform_file(path, |document, _| apply(document, paper, scaling, auto_rotate)).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply_point(m: [f64; 6], (x, y): (f64, f64)) -> (f64, f64) {
+        let round = |v: f64| (v * 100.0).round() / 100.0;
+        (round(m[0] * x + m[2] * y + m[4]), round(m[1] * x + m[3] * y + m[5]))
+    }
+
+    #[test]
+    fn places_pages_on_paper() {
+        let a4 = paper_size("A4").unwrap();
+        assert_eq!((a4.0.round(), a4.1.round()), (595.0, 842.0));
+        let letter_landscape = [0.0, 0.0, 792.0, 612.0];
+
+        // 缩小到 A4 宽度并垂直居中
+        let (m, width, height) = placement(letter_landscape, 0, Some(a4), Scaling::Fit, false);
+        assert_eq!((width, height), a4);
+        let scale = a4.0 / 792.0;
+        assert_eq!(apply_point(m, (0.0, 0.0)), apply_point([1.0, 0.0, 0.0, 1.0, 0.0, (a4.1 - 612.0 * scale) / 2.0], (0.0, 0.0)));
+        assert_eq!(apply_point(m, (792.0, 612.0)).0, apply_point([1.0, 0.0, 0.0, 1.0, 0.0, 0.0], (a4.0, 0.0)).0);
+
+        // 自动旋转后铺满高度：左下角转到右下角
+        let (m, _, _) = placement(letter_landscape, 0, Some(a4), Scaling::Fit, true);
+        let scale = (a4.0 / 612.0).min(a4.1 / 792.0);
+        let x = (a4.0 + 612.0 * scale) / 2.0;
+        let y = (a4.1 - 792.0 * scale) / 2.0;
+        assert_eq!(apply_point(m, (0.0, 0.0)), apply_point([1.0, 0.0, 0.0, 1.0, x, y], (0.0, 0.0)));
+
+        // 小页面 shrink 时不放大，none 与 custom 按比例居中
+        let small = [0.0, 0.0, 298.0, 421.0];
+        let (m, _, _) = placement(small, 0, Some(a4), Scaling::Shrink, false);
+        assert_eq!(m[0], 1.0);
+        let (m, _, _) = placement(small, 0, Some(a4), Scaling::Fit, false);
+        assert!(m[0] > 1.9);
+        let (m, _, _) = placement([0.0, 0.0, 600.0, 800.0], 0, None, Scaling::Custom(50.0), false);
+        assert_eq!(apply_point(m, (0.0, 0.0)), (150.0, 200.0));
+
+        // 已旋转 90 度的纵向页面按横向显示，自动旋转后内容不再旋转
+        let (m, width, height) = placement([0.0, 0.0, 600.0, 800.0], 90, None, Scaling::None, true);
+        assert_eq!((width, height), (600.0, 800.0));
+        assert_eq!(apply_point(m, (0.0, 0.0)), (0.0, 0.0));
+        assert_eq!(apply_point(m, (600.0, 800.0)), (600.0, 800.0));
+    }
+
+    #[test]
+    fn splits_large_pages_into_tiles() {
+        let a4 = paper_size("A4").unwrap();
+
+        // 两倍宽的页面拆成左右两张
+        let tiled = tiles([0.0, 0.0, a4.0 * 2.0, a4.1], 0, a4, false);
+        assert_eq!(tiled.len(), 2);
+        assert_eq!(apply_point(tiled[0], (0.0, 0.0)), (0.0, 0.0));
+        assert_eq!(apply_point(tiled[1], (a4.0, 0.0)), (0.0, 0.0));
+        assert_eq!(apply_point(tiled[1], (a4.0 * 2.0, a4.1)), apply_point(tiled[0], (a4.0, a4.1)));
+
+        // 两倍高的页面先打印上半部分
+        let tiled = tiles([0.0, 0.0, a4.0, a4.1 * 2.0], 0, a4, false);
+        assert_eq!(tiled.len(), 2);
+        assert_eq!(apply_point(tiled[0], (0.0, a4.1)), (0.0, 0.0));
+        assert_eq!(apply_point(tiled[1], (0.0, 0.0)), (0.0, 0.0));
+
+        // 横向 A3 自动旋转后是纵向，需要 2x2 张
+        let a3 = paper_size("A3").unwrap();
+        assert_eq!(tiles([0.0, 0.0, a3.1, a3.0], 0, a4, false).len(), 2);
+        assert_eq!(tiles([0.0, 0.0, a3.1, a3.0], 0, a4, true).len(), 4);
+
+        // 小页面只占一张并居中
+        let tiled = tiles([0.0, 0.0, 298.0, 421.0], 0, a4, false);
+        assert_eq!(tiled.len(), 1);
+        assert_eq!(apply_point(tiled[0], (0.0, 0.0)), apply_point([1.0, 0.0, 0.0, 1.0, (a4.0 - 298.0) / 2.0, (a4.1 - 421.0) / 2.0], (0.0, 0.0)));
+    }
+
+    #[test]
+    fn tiles_documents_onto_paper() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("poster.pdf");
+        let mut document = pdf::merge(
+            vec![pdf::tests::sample(1, [0, 0, 1191, 842]), pdf::tests::sample(1, [0, 0, 595, 842])],
+            false,
+        )
+        .unwrap();
+        pdf::save(&mut document, &path).unwrap();
+
+        // SumatraPDF 不支持平铺，同样在PDF中处理
+        let settings = PrintSettings { scaling: Some(Scaling::Tile), ..Default::default() };
+        let output = prepare(&path, Some(&settings), true).unwrap().unwrap();
+        let tiled = pdf::load(&output).unwrap();
+        let pages = tiled.get_pages();
+        assert_eq!(pages.len(), 3);
+        for page_id in pages.into_values() {
+            let (bounds, _) = pdf::page_geometry(&tiled, page_id);
+            assert_eq!((bounds[2].round(), bounds[3].round()), (595.0, 842.0));
+            let content = String::from_utf8(tiled.get_page_content(page_id).unwrap()).unwrap();
+            assert!(content.starts_with("q\n") && content.ends_with("\nQ\n"), "{}", content);
+        }
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn rewrites_pages_to_paper_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mixed.pdf");
+        let mut document = pdf::merge(
+            vec![pdf::tests::sample(1, [0, 0, 842, 595]), pdf::tests::sample(1, [0, 0, 298, 421])],
+            false,
+        )
+        .unwrap();
+        pdf::save(&mut document, &path).unwrap();
+
+        let settings = PrintSettings { scaling: Some(Scaling::Fit), ..Default::default() };
+        assert_eq!(prepare(&path, Some(&settings), true).unwrap(), None);
+        assert_eq!(prepare(&path, None, false).unwrap(), None);
+
+        let settings = PrintSettings { page_size: Some("A4".to_string()), auto_rotate: Some(true), ..settings };
+        let output = prepare(&path, Some(&settings), false).unwrap().unwrap();
+        let adjusted = pdf::load(&output).unwrap();
+        for page_id in adjusted.get_pages().into_values() {
+            let (bounds, rotate) = pdf::page_geometry(&adjusted, page_id);
+            assert_eq!((bounds[2].round(), bounds[3].round(), rotate), (595.0, 842.0, 0));
+            let content = String::from_utf8(adjusted.get_page_content(page_id).unwrap()).unwrap();
+            assert!(content.starts_with("q\n") && content.ends_with("\nQ\n"), "{}", content);
+        }
+        std::fs::remove_file(output).unwrap();
+    }
+}
@@ -1,16 +1,63 @@
+mod barcode;
+mod batch;
+mod capabilities;
 mod declare;
+mod decrypt;
+mod discovery;
+mod fonts;
 mod fsys;
+mod groups;
+mod history;
+mod images;
+mod ipp;
+mod layout;
+mod lpd;
+mod markdown;
+mod monitor;
+mod pdf;
+mod pdf417;
+mod ppd;
+mod queue;
+mod raster;
+mod raw_socket;
+mod schedule;
+mod snmp;
+mod status;
+mod supplies;
+mod table;
+mod templates;
+mod text;
+mod tiff;
+mod validation;
+mod virtual_printer;
+mod watermark;
 mod windows;
 
 use tauri::{
     plugin::{Builder, TauriPlugin},
-    Manager, Runtime,
+    Emitter, Manager, Runtime,
 };
 
 use std::env;
+use base64::{Engine as _, engine::general_purpose};
 
 pub use crate::models::*;
-use crate::declare::PrintHtmlOptions;
+pub use crate::declare::Config;
+use crate::declare::{
+    BarcodeOptions, BatchDocument, GroupStrategy, ImageLayout, MarkdownStyle, PrintHtmlOptions, PrintMarkdownOptions, PrintSettings,
+    PrintTableOptions, PrintTemplateOptions, PrinterCapabilities, PrinterGroup, RasterImageOptions, TemplateInfo, TemplateSource, TextOptions, VirtualPrinter,
+    Watermark,
+};
+use crate::batch::BatchResult;
+use crate::pdf::PdfInfo;
+use crate::discovery::DiscoveredPrinter;
+use crate::history::{Document, ExportFormat, HistoryEntry, HistoryFilter, JobOutcome};
+use crate::queue::QueuedJob;
+use crate::schedule::{Schedule, ScheduleFired};
+use crate::status::PrinterStatus;
+use crate::supplies::Supply;
+use crate::validation::{ValidationMode, ValidationReport};
+use crate::virtual_printer::RawPrintResult;
 
 #[cfg(desktop)]
 mod desktop;
@@ -41,7 +88,146 @@ async fn ping<R: Runtime>(app: tauri::AppHandle<R>, payload: PingRequest) -> Res
 #[tauri::command(rename_all = "snake_case")]
 async fn print_html<R: Runtime>(app: tauri::AppHandle<R>, options: PrintHtmlOptions) -> Result<String> {
     println!("print_html: {:?}", options.print_settings);
-    app.printer().print_html(options)
+    let document = Document::from_bytes("HTML", options.html.as_bytes());
+    submit_html(&app, options, document)
+}
+
+/**
+ * 打印 Markdown（表格、代码块、图片），转换为 HTML 后按 print_html 提交
+ * @param options markdown 与 path 二选一，style 为打印样式表，其余参数与 print_html 相同
+ * @returns 打印结果
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|print_markdown')`.
+async fn print_markdown<R: Runtime>(app: tauri::AppHandle<R>, options: PrintMarkdownOptions) -> Result<String> {
+    let (html, name) = render_markdown(options.markdown, options.path, &options.style)?;
+    let document = Document::from_bytes(&name, html.as_bytes());
+    submit_html(&app, options.job.with_html(html), document)
+}
+
+/**
+ * 把 Markdown 导出为 HTML，用于预览或保存
+ * @param markdown Markdown 内容，与 path 二选一
+ * @param path Markdown 文件路径，与 markdown 二选一
+ * @param style 打印样式表（可选）
+ * @param output 保存 HTML 的文件路径（可选）
+ * @returns 完整的 HTML 文档
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|export_markdown')`.
+fn export_markdown(markdown: Option<String>, path: Option<String>, style: Option<MarkdownStyle>, output: Option<String>) -> Result<String> {
+    let (html, _) = render_markdown(markdown, path, &style.unwrap_or_default())?;
+    if let Some(output) = output {
+        std::fs::write(output, &html)?;
+    }
+    Ok(html)
+}
+
+/**
+ * 注册 HTML 模板（Jinja2 语法），同名的会被替换并增加版本号
+ * 模板之间可以 include / extends，提供 currency、number、date 过滤器和 now() 函数
+ * @param template 模板名称和内容（source）或模板文件（path）
+ * @returns 模板的版本、内容哈希和注册时间，语法错误时返回 Template 错误
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|register_template')`.
+fn register_template(template: TemplateSource) -> Result<TemplateInfo> {
+    templates::register(template).map_err(Error::Template)
+}
+
+/**
+ * 删除模板
+ * @param name 模板名称
+ * @returns 是否删除
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|remove_template')`.
+fn remove_template(name: String) -> bool {
+    templates::unregister(&name)
+}
+
+/**
+ * 获取已注册的模板
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|get_templates')`.
+fn get_templates() -> Vec<TemplateInfo> {
+    templates::list()
+}
+
+/**
+ * 用 JSON 数据渲染模板，用于预览或保存
+ * @param name 模板名称
+ * @param data 模板数据（可选）
+ * @param output 保存 HTML 的文件路径（可选）
+ * @returns 渲染后的 HTML
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|render_template')`.
+fn render_template(name: String, data: Option<serde_json::Value>, output: Option<String>) -> Result<String> {
+    let html = templates::render(&name, &data.unwrap_or_default()).map_err(Error::Template)?;
+    if let Some(output) = output {
+        std::fs::write(output, &html)?;
+    }
+    Ok(html)
+}
+
+/**
+ * 用 JSON 数据渲染模板后按 print_html 提交
+ * @param options 模板名称和数据，其余参数与 print_html 相同
+ * @returns 打印结果
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|print_template')`.
+async fn print_template<R: Runtime>(app: tauri::AppHandle<R>, options: PrintTemplateOptions) -> Result<String> {
+    let html = templates::render(&options.template, &options.data).map_err(Error::Template)?;
+    let document = Document::from_bytes(&options.template, html.as_bytes());
+    submit_html(&app, options.job.with_html(html), document)
+}
+
+/**
+ * 打印表格（CSV 或 JSON 数组），转换为 HTML 后按 print_html 提交，分页时每页重复表头
+ * @param options 数据、列定义（表头、宽度、对齐、数字格式、合计）和标题，其余参数与 print_html 相同
+ * @returns 打印结果，数据无法解析时返回 Table 错误
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|print_table')`.
+async fn print_table<R: Runtime>(app: tauri::AppHandle<R>, options: PrintTableOptions) -> Result<String> {
+    let html = table::to_html(&options.table).map_err(Error::Table)?;
+    let name = options.table.title.clone().unwrap_or_else(|| "表格".to_string());
+    let document = Document::from_bytes(&name, html.as_bytes());
+    submit_html(&app, options.job.with_html(html), document)
+}
+
+/// 转换 Markdown，返回 HTML 和打印历史中的文档名；从文件读取时相对图片路径按文件所在目录
+fn render_markdown(markdown: Option<String>, path: Option<String>, style: &MarkdownStyle) -> Result<(String, String)> {
+    match (markdown, path) {
+        (Some(text), None) => {
+            let name = style.title.clone().unwrap_or_else(|| "Markdown".to_string());
+            Ok((markdown::to_html(&text, style, None), name))
+        }
+        (None, Some(path)) => {
+            let text = std::fs::read_to_string(&path)?;
+            let mut style = style.clone();
+            let name = style.title.get_or_insert_with(|| file_name(&path)).clone();
+            Ok((markdown::to_html(&text, &style, std::path::Path::new(&path).parent()), name))
+        }
+        _ => Err(Error::InvalidConfig("markdown 和 path 必须且只能提供一个".to_string())),
+    }
+}
+
+/// 按 print_html_internal 转换并打印，记录打印历史
+fn submit_html<R: Runtime>(app: &tauri::AppHandle<R>, options: PrintHtmlOptions, document: Document) -> Result<String> {
+    let submitted_at = history::now_ms();
+    let printer = options.printer_id.clone().unwrap_or_default();
+    let settings = options.copies.map(|copies| PrintSettings { copies: Some(copies), ..PrintSettings::default() });
+    let result = app.printer().print_html(options);
+    let text = match &result {
+        Ok(text) => text.clone(),
+        Err(e) => format!("打印失败: {}", e),
+    };
+    record_history(None, document, &printer, settings, submitted_at, &text);
+    result
 }
 
 
@@ -97,12 +283,216 @@ fn remove_temp_file(filename: String) -> bool {
  */
 #[tauri::command]
 // this will be accessible with `invoke('plugin:printer|get_printers')`.
-fn get_printers() -> String {
+async fn get_printers() -> Result<String> {
+    // 虚拟打印机的作业数和状态需要联网查询
+    tauri::async_runtime::spawn_blocking(list_printers)
+        .await
+        .map_err(|e| Error::Backend(e.to_string()))
+}
+
+fn list_printers() -> String {
+    let system = if cfg!(windows) {
+        windows::get_printers()
+    } else {
+        "Unsupported OS".to_string()
+    };
+
+    // 虚拟打印机与系统打印机一起返回
+    return virtual_printer::merge_printer_list(&system);
+}
+
+/**
+ * 查询打印机能力：纸张、纸盒、双面、彩色、分辨率、最大份数和支持的文档格式
+ * @param printername 打印机名称
+ * @returns 打印机能力
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|get_printer_capabilities')`.
+async fn get_printer_capabilities(printername: String) -> Result<PrinterCapabilities> {
+    tauri::async_runtime::spawn_blocking(move || printer_capabilities(&printername))
+        .await
+        .map_err(|e| Error::Backend(e.to_string()))?
+}
+
+fn printer_capabilities(printername: &str) -> Result<PrinterCapabilities> {
+    if let Some(printer) = virtual_printer::find(printername) {
+        return capabilities::for_virtual(&printer).map_err(Error::Backend);
+    }
     if cfg!(windows) {
-        return windows::get_printers();
+        let output = windows::get_printer_capabilities(printername.to_string());
+        return capabilities::from_windows_json(printername, &output)
+            .map_err(Error::Backend)?
+            .ok_or(Error::PrinterNotFound(printername.to_string()));
+    }
+    if cfg!(unix) {
+        // CUPS 队列的能力来自 /etc/cups/ppd 下的 PPD
+        let ppd = ppd::load_queue(printername).map_err(Error::Backend)?;
+        return ppd
+            .map(|ppd| ppd.capabilities(printername))
+            .ok_or(Error::PrinterNotFound(printername.to_string()));
     }
+    Err(Error::UnsupportedPlatform)
+}
 
-    return "Unsupported OS".to_string();
+/**
+ * 按打印机能力校验打印参数
+ * @param printername 打印机名称
+ * @param settings 打印参数
+ * @param mode strict：不支持的参数视为冲突；lenient（默认）：自动替换为可用值
+ * @returns 冲突、替换以及替换后的参数
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|validate_print_job')`.
+async fn validate_print_job(
+    printername: String,
+    settings: PrintSettings,
+    mode: Option<ValidationMode>,
+) -> Result<ValidationReport> {
+    tauri::async_runtime::spawn_blocking(move || validate_settings(&printername, &settings, mode.unwrap_or_default()))
+        .await
+        .map_err(|e| Error::Backend(e.to_string()))?
+}
+
+fn validate_settings(printername: &str, settings: &PrintSettings, mode: ValidationMode) -> Result<ValidationReport> {
+    let capabilities = printer_capabilities(printername)?;
+    let mut report = validation::validate(settings, &capabilities, mode);
+    if virtual_printer::find(printername).is_none() && cfg!(unix) {
+        if let Ok(Some(ppd)) = ppd::load_queue(printername) {
+            validation::check_constraints(&mut report, &ppd);
+        }
+    }
+    Ok(report)
+}
+
+/**
+ * 提交前的检查：返回替换后的参数
+ * 能力查询失败时，宽松模式去掉未经校验的纸张和纸盒后提交，严格模式拒绝打印
+ */
+fn checked_settings(
+    printername: &str,
+    settings: Option<PrintSettings>,
+    mode: Option<ValidationMode>,
+) -> std::result::Result<Option<PrintSettings>, String> {
+    let Some(settings) = settings else {
+        return Ok(None);
+    };
+    let mode = mode.unwrap_or_default();
+    match validate_settings(printername, &settings, mode) {
+        Ok(report) if report.valid => Ok(Some(report.settings)),
+        Ok(report) => Err(report.conflict_message()),
+        Err(e) if mode == ValidationMode::Strict => Err(e.to_string()),
+        Err(_) => Ok(Some(PrintSettings { page_size: None, tray: None, ..settings })),
+    }
+}
+
+/**
+ * 通过 mDNS/DNS-SD 发现局域网中的 IPP 和 RAW 打印机
+ * @param timeout_ms 浏览时长（可选，默认 3000 毫秒）
+ * @returns 发现的打印机，`printer` 字段可直接传给 add_virtual_printer
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|discover_printers')`.
+async fn discover_printers(timeout_ms: Option<u64>) -> Result<Vec<DiscoveredPrinter>> {
+    tauri::async_runtime::spawn_blocking(move || discovery::discover(timeout_ms))
+        .await
+        .map_err(|e| Error::Backend(e.to_string()))?
+        .map_err(Error::Backend)
+}
+
+/**
+ * 查询打印机状态：空闲、打印中、卡纸、缺纸、离线、盖板打开、碳粉不足等
+ * @param printername 打印机名称
+ * @returns 打印机状态
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|get_printer_status')`.
+async fn get_printer_status(printername: String) -> Result<PrinterStatus> {
+    tauri::async_runtime::spawn_blocking(move || printer_status(&printername))
+        .await
+        .map_err(|e| Error::Backend(e.to_string()))
+}
+
+fn printer_status(printername: &str) -> PrinterStatus {
+    if let Some(printer) = virtual_printer::find(printername) {
+        return status::for_virtual(&printer);
+    }
+    if cfg!(windows) {
+        let output = windows::get_printers_by_name(printername.to_string());
+        return status::from_windows_json(printername, &output);
+    }
+    if cfg!(unix) {
+        return status::query_lpstat(printername);
+    }
+    PrinterStatus::new(printername, Vec::new(), Some(Error::UnsupportedPlatform.to_string()))
+}
+
+/**
+ * 启动后台状态监控，状态变化时发出 `printer://status-changed` 事件
+ * 已在运行的监控会被替换
+ * @param printers 打印机名称列表
+ * @param interval_ms 轮询间隔（可选，默认 5000 毫秒）
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|start_status_monitor')`.
+async fn start_status_monitor<R: Runtime>(app: tauri::AppHandle<R>, printers: Vec<String>, interval_ms: Option<u64>) -> Result<()> {
+    // 替换旧监控时要等待它的线程结束
+    tauri::async_runtime::spawn_blocking(move || start_monitor(app, printers, interval_ms))
+        .await
+        .map_err(|e| Error::Backend(e.to_string()))
+}
+
+fn start_monitor<R: Runtime>(app: tauri::AppHandle<R>, printers: Vec<String>, interval_ms: Option<u64>) {
+    monitor::start(printers, interval_ms, printer_status, move |change| {
+        if let Err(e) = app.emit(monitor::STATUS_CHANGED_EVENT, &change) {
+            eprintln!("状态事件发送失败: {}", e);
+        }
+    });
+}
+
+/**
+ * 停止后台状态监控
+ * @returns 之前是否在运行
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|stop_status_monitor')`.
+async fn stop_status_monitor() -> Result<bool> {
+    tauri::async_runtime::spawn_blocking(monitor::stop)
+        .await
+        .map_err(|e| Error::Backend(e.to_string()))
+}
+
+/**
+ * 查询耗材（碳粉、墨盒、硒鼓、废粉盒等）和纸盒余量
+ * @param printername 打印机名称
+ * @param community SNMP community（可选，默认 public）
+ * @returns 耗材列表
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|get_printer_supplies')`.
+async fn get_printer_supplies(printername: String, community: Option<String>) -> Result<Vec<Supply>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        printer_supplies(&printername, community.as_deref().unwrap_or(snmp::DEFAULT_COMMUNITY))
+    })
+    .await
+    .map_err(|e| Error::Backend(e.to_string()))?
+}
+
+fn printer_supplies(printername: &str, community: &str) -> Result<Vec<Supply>> {
+    if let Some(printer) = virtual_printer::find(printername) {
+        return supplies::for_virtual(&printer, community).map_err(Error::Backend);
+    }
+    if cfg!(windows) {
+        // 只有 TCP/IP 端口的打印机可以通过 SNMP 查询
+        let host = windows::get_printer_host(printername.to_string());
+        if host.is_empty() {
+            return Err(Error::Backend(format!("{} 不是网络打印机，无法查询耗材", printername)));
+        }
+        return supplies::query_snmp(&host, snmp::DEFAULT_PORT, community).map_err(Error::Backend);
+    }
+    if cfg!(unix) {
+        return supplies::query_cups(printername).map_err(Error::Backend);
+    }
+    Err(Error::UnsupportedPlatform)
 }
 
 /**
@@ -112,8 +502,17 @@ fn get_printers() -> String {
  */
 #[tauri::command(rename_all = "snake_case")]
 // this will be accessible with `invoke('plugin:printer|get_printer_by_name')`.
-fn get_printers_by_name(printername: String) -> String {
+async fn get_printers_by_name(printername: String) -> Result<String> {
+    tauri::async_runtime::spawn_blocking(move || find_printer(printername))
+        .await
+        .map_err(|e| Error::Backend(e.to_string()))
+}
+
+fn find_printer(printername: String) -> String {
     println!("获取打印机列表: {}", printername);
+    if let Some(printer) = virtual_printer::printer_json(&printername) {
+        return printer;
+    }
     if cfg!(windows) {
         return windows::get_printers_by_name(printername);
     }
@@ -127,8 +526,13 @@ fn get_printers_by_name(printername: String) -> String {
  * @param path PDF文件路径
  * @param printer_setting 打印机设置
  * @param remove_after_print 打印完成后删除文件
- * @returns 打印结果
+ * @param settings 打印参数（可选），提交前会按打印机能力校验
+ * @param validation 校验模式（可选，默认 lenient）
+ * @param watermark 水印（可选），加在副本上，原文件不变
+ * @param password 加密文档的打开密码（可选），解密后的临时副本打印后覆盖删除
+ * @returns 打印结果，文件不是有效的 PDF 时返回 InvalidDocument 错误，密码缺失或错误时返回 PasswordRequired / IncorrectPassword，水印失败时返回 Pdf 错误
  */
+#[allow(clippy::too_many_arguments)]
 #[tauri::command(rename_all = "snake_case")]    
 // this will be accessible with `invoke('plugin:printer|print_pdf')`.
 fn print_pdf(
@@ -136,14 +540,292 @@ fn print_pdf(
     path: String,
     printer_setting: String,
     remove_after_print: bool,
+    settings: Option<PrintSettings>,
+    validation: Option<ValidationMode>,
+    watermark: Option<Watermark>,
+    password: Option<String>,
+) -> Result<String> {
+    let info = preflight(&path)?;
+    // 打印后文件可能被删除，先读取文档信息
+    let document = Document::from_path(&path);
+    // 加密文档和水印都在临时副本上处理，原文件不变
+    let mut copies = Vec::new();
+    if info.encrypted {
+        copies.push(decrypt_pdf(&path, password.as_deref())?);
+    }
+    if let Some(watermark) = &watermark {
+        let source = copies.last().cloned().unwrap_or_else(|| std::path::PathBuf::from(&path));
+        match watermark::stamp_file(&source, watermark) {
+            Ok(stamped) => copies.push(stamped),
+            Err(e) => {
+                remove_copies(&copies);
+                let message = format!("水印: {}", e);
+                let result = format!("打印失败: {}", message);
+                record_history(Some(id), document, &printer_setting, settings, history::now_ms(), &result);
+                return Err(Error::Pdf(message));
+            }
+        }
+    }
+    let Some(copy) = copies.last() else {
+        return Ok(print_document(id, path, document, printer_setting, remove_after_print, settings, validation));
+    };
+    let result = print_document(id, path_string(copy.clone()), document, printer_setting, false, settings, validation);
+    remove_copies(&copies);
+    if remove_after_print && !is_failure(&result) {
+        let _ = fsys::remove_file(&path);
+    }
+    Ok(result)
+}
+
+/**
+ * 打印图片（PNG、JPEG、TIFF，多页 TIFF 每页一页），先转换为PDF再按 print_pdf 提交
+ * @param id 打印机ID
+ * @param paths 图片文件路径，每张图片一页
+ * @param printer_setting 打印机设置
+ * @param remove_after_print 打印完成后删除图片
+ * @param layout 排版（可选）：fit / fill / actual_size、方向、边距和分辨率，纸张取 settings.page_size，默认 A4
+ * @param settings 打印参数（可选），提交前会按打印机能力校验
+ * @param validation 校验模式（可选，默认 lenient）
+ * @returns 打印结果，图片无法读取时返回 Raster 错误
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|print_image')`.
+fn print_image(
+    id: String,
+    paths: Vec<String>,
+    printer_setting: String,
+    remove_after_print: bool,
+    layout: Option<ImageLayout>,
+    settings: Option<PrintSettings>,
+    validation: Option<ValidationMode>,
+) -> Result<String> {
+    let paper = settings.as_ref().and_then(|s| s.page_size.as_deref()).and_then(layout::paper_size);
+    let output = images::convert_files(&paths, paper, &layout.unwrap_or_default()).map_err(Error::Raster)?;
+    let output = path_string(output);
+    let mut document = Document::from_path(&output);
+    document.name = paths.iter().map(|path| file_name(path)).collect::<Vec<_>>().join(", ");
+    let result = print_document(id, output.clone(), document, printer_setting, false, settings, validation);
+    let _ = fsys::remove_file(&output);
+    if remove_after_print && !is_failure(&result) {
+        for path in &paths {
+            let _ = fsys::remove_file(path);
+        }
+    }
+    Ok(result)
+}
+
+/**
+ * 打印纯文本，按字号和纸张排版为PDF后按 print_pdf 提交，中日韩文字使用 CJK 字体
+ * @param id 打印机ID
+ * @param text 文本内容，与 path 二选一
+ * @param path 文本文件路径，与 text 二选一，按 options.encoding 解码
+ * @param printer_setting 打印机设置
+ * @param options 排版（可选）：字体、字号、行号、制表符宽度、折行方式、页眉、编码，纸张取 settings.page_size，默认 A4
+ * @param settings 打印参数（可选），提交前会按打印机能力校验
+ * @param validation 校验模式（可选，默认 lenient）
+ * @returns 打印结果
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|print_text')`.
+fn print_text(
+    id: String,
+    text: Option<String>,
+    path: Option<String>,
+    printer_setting: String,
+    options: Option<TextOptions>,
+    settings: Option<PrintSettings>,
+    validation: Option<ValidationMode>,
+) -> Result<String> {
+    let options = options.unwrap_or_default();
+    let (text, title) = match (text, path) {
+        (Some(text), None) => (text, options.title.clone().unwrap_or_default()),
+        (None, Some(path)) => {
+            let text = text::decode(&std::fs::read(&path)?, options.encoding);
+            (text, options.title.clone().unwrap_or_else(|| file_name(&path)))
+        }
+        _ => return Err(Error::InvalidConfig("text 和 path 必须且只能提供一个".to_string())),
+    };
+    let paper = settings.as_ref().and_then(|s| s.page_size.as_deref()).and_then(layout::paper_size);
+    let output = path_string(text::convert(&text, &title, paper, &options).map_err(Error::Text)?);
+    let mut document = Document::from_path(&output);
+    document.name = if title.is_empty() { "文本".to_string() } else { title };
+    let result = print_document(id, output.clone(), document, printer_setting, false, settings, validation);
+    let _ = fsys::remove_file(&output);
+    Ok(result)
+}
+
+/// 解密到临时副本，密码缺失或错误时返回对应的错误
+fn decrypt_pdf(path: &str, password: Option<&str>) -> Result<std::path::PathBuf> {
+    decrypt::decrypt_file(std::path::Path::new(path), password).map_err(|e| match e {
+        decrypt::DecryptError::PasswordRequired => Error::PasswordRequired(path.to_string()),
+        decrypt::DecryptError::IncorrectPassword => Error::IncorrectPassword(path.to_string()),
+        decrypt::DecryptError::Failed(e) => Error::Pdf(e),
+    })
+}
+
+/// 打印后删除临时副本，解密的内容先覆盖再删除
+fn remove_copies(copies: &[std::path::PathBuf]) {
+    for copy in copies {
+        let _ = decrypt::secure_remove(copy);
+    }
+}
+
+/// 提交前检查文件是否为有效的 PDF，避免交给打印后台后才失败
+fn preflight(path: &str) -> Result<PdfInfo> {
+    let info = pdf::inspect(std::path::Path::new(path), None);
+    if info.valid {
+        return Ok(info);
+    }
+    Err(Error::InvalidDocument(format!("{}: {}", path, info.error.unwrap_or_default())))
+}
+
+/// 提交PDF并记录打印历史，document 为原文档的信息
+fn print_document(
+    id: String,
+    path: String,
+    document: Document,
+    printer_setting: String,
+    remove_after_print: bool,
+    settings: Option<PrintSettings>,
+    validation: Option<ValidationMode>,
 ) -> String {
-     
+    if let Some(group) = groups::find(&printer_setting) {
+        return print_pdf_to_group(&group, id, path, document, remove_after_print, settings, validation);
+    }
+    let submitted_at = history::now_ms();
+    let job_id = Some(id.clone());
+    let printer = printer_setting.clone();
+    let settings = match checked_settings(&printer_setting, settings.clone(), validation) {
+        Ok(settings) => settings,
+        Err(e) => {
+            let result = format!("打印失败: {}", e);
+            record_history(job_id, document, &printer, settings, submitted_at, &result);
+            return result;
+        }
+    };
+    let recorded_settings = settings.clone();
+    let result = submit_pdf(id, path, printer_setting, remove_after_print, settings);
+    record_history(job_id, document, &printer, recorded_settings, submitted_at, &result);
+    result
+}
+
+/**
+ * 按组打印：依次尝试可用的成员，直到提交成功
+ * 每个成员按自己的能力校验参数，每次尝试都记录到打印历史
+ */
+fn print_pdf_to_group(
+    group: &PrinterGroup,
+    id: String,
+    path: String,
+    document: Document,
+    remove_after_print: bool,
+    settings: Option<PrintSettings>,
+    validation: Option<ValidationMode>,
+) -> String {
+    let members: Vec<groups::MemberState> =
+        group.members.iter().map(|name| member_state(name, group.strategy)).collect();
+    let candidates = groups::candidates(group, &members);
+    if candidates.is_empty() {
+        return format!("打印失败: {}", groups::unavailable_message(group, &members));
+    }
+    let mut errors = Vec::new();
+    for member in candidates {
+        let submitted_at = history::now_ms();
+        let result = match checked_settings(&member, settings.clone(), validation) {
+            Ok(checked) => {
+                let result = submit_pdf(id.clone(), path.clone(), member.clone(), false, checked.clone());
+                record_history(Some(id.clone()), document.clone(), &member, checked, submitted_at, &result);
+                result
+            }
+            Err(e) => {
+                let result = format!("打印失败: {}", e);
+                record_history(Some(id.clone()), document.clone(), &member, settings.clone(), submitted_at, &result);
+                result
+            }
+        };
+        if !is_failure(&result) {
+            if remove_after_print {
+                let _ = fsys::remove_file(&path);
+            }
+            return result;
+        }
+        errors.push(format!("{}: {}", member, result.trim()));
+    }
+    format!("打印失败: 打印机组 {} 的成员均未能打印（{}）", group.name, errors.join("; "))
+}
+
+/// 查询组成员的状态，least_busy 时同时查询作业数
+fn member_state(name: &str, strategy: GroupStrategy) -> groups::MemberState {
+    let least_busy = strategy == GroupStrategy::LeastBusy;
+    if let Some(printer) = virtual_printer::find(name) {
+        let job_count = least_busy.then(|| virtual_printer::job_count(&printer)).flatten().map(|count| count as u32);
+        return groups::MemberState { name: name.to_string(), status: status::for_virtual(&printer), job_count };
+    }
+    if cfg!(windows) {
+        // get_printers 的输出同时包含状态和 JobCount
+        let output = windows::get_printers_by_name(name.to_string());
+        return groups::MemberState {
+            name: name.to_string(),
+            status: status::from_windows_json(name, &output),
+            job_count: groups::job_count_from_json(&output),
+        };
+    }
+    groups::MemberState { name: name.to_string(), status: printer_status(name), job_count: None }
+}
+
+fn submit_pdf(
+    id: String,
+    path: String,
+    printer_setting: String,
+    remove_after_print: bool,
+    settings: Option<PrintSettings>,
+) -> String {
+    let printer = virtual_printer::find(&printer_setting);
+    if printer.is_none() && !cfg!(windows) {
+        return "Unsupported OS".to_string();
+    }
+    // SumatraPDF 自己支持 fit / shrink / noscale 和自动旋转，其余的在PDF中调整
+    let adjusted = match layout::prepare(std::path::Path::new(&path), settings.as_ref(), printer.is_none()) {
+        Ok(adjusted) => adjusted,
+        Err(e) => return format!("打印失败: 缩放: {}", e),
+    };
+    let Some(adjusted) = adjusted else {
+        return submit_to_backend(id, path, printer_setting, remove_after_print, settings);
+    };
+    let result = submit_to_backend(id, path_string(adjusted.clone()), printer_setting, false, settings);
+    // 调整后的副本可能来自解密的文档
+    let _ = decrypt::secure_remove(&adjusted);
+    if remove_after_print && !is_failure(&result) {
+        let _ = fsys::remove_file(&path);
+    }
+    result
+}
+
+fn submit_to_backend(
+    id: String,
+    path: String,
+    printer_setting: String,
+    remove_after_print: bool,
+    settings: Option<PrintSettings>,
+) -> String {
+    if let Some(printer) = virtual_printer::find(&printer_setting) {
+        let options = declare::PrintOptions {
+            id,
+            path,
+            print_setting: printer_setting,
+            remove_after_print: remove_after_print,
+            settings,
+        };
+        return virtual_printer::print_pdf(&printer, options);
+    }
+
     if cfg!(windows) {
         let options = declare::PrintOptions { 
             id,
             path,
             print_setting: printer_setting,
             remove_after_print: remove_after_print,
+            settings,
         };
         return windows::print_pdf(options);
     }
@@ -154,6 +836,9 @@ fn print_pdf(
 #[tauri::command(rename_all = "snake_case")]
 // this will be accessible with `invoke('plugin:printer|get_jobs')`.
 fn get_jobs(printername: String) -> String {
+    if let Some(printer) = virtual_printer::find(&printername) {
+        return virtual_printer::get_jobs(&printer).unwrap_or_else(|e| format!("获取任务失败: {}", e));
+    }
     if cfg!(windows) {
         return windows::get_jobs(printername);
     }
@@ -169,6 +854,9 @@ fn get_jobs(printername: String) -> String {
 #[tauri::command(rename_all = "snake_case")]
 // this will be accessible with `invoke('plugin:printer|get_jobs_by_id')`.
 fn get_jobs_by_id(printername: String, jobid: String) -> String {
+    if let Some(printer) = virtual_printer::find(&printername) {
+        return virtual_printer::get_job_by_id(&printer, &jobid).unwrap_or_else(|e| format!("获取任务失败: {}", e));
+    }
     if cfg!(windows) {
         return windows::get_jobs_by_id(printername, jobid);
     }
@@ -229,18 +917,577 @@ fn pause_job(printername: String, jobid: String) -> String {
 #[tauri::command(rename_all = "snake_case")]
 // this will be accessible with `invoke('plugin:printer|remove_job')`.
 fn remove_job(printername: String, jobid: String) -> String {
+    if let Some(printer) = virtual_printer::find(&printername) {
+        return virtual_printer::remove_job(&printer, &jobid).unwrap_or_else(|e| format!("删除任务失败: {}", e));
+    }
     if cfg!(windows) {
         return windows::remove_job(printername, jobid);
     }
     return "Unsupported OS".to_string();
 }
 
+/**
+ * 图片转热敏/标签打印机点阵指令
+ * @param buffer_data 图片 base64 字符串（PNG/JPEG）
+ * @param options 点宽、抖动算法、指令语言（escpos/zpl）
+ * @returns 打印机指令的 base64 字符串
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|raster_image')`.
+fn raster_image(buffer_data: String, options: RasterImageOptions) -> Result<String> {
+    let bytes = general_purpose::STANDARD.decode(buffer_data)?;
+    let commands = raster::image_to_commands(&bytes, &options).map_err(Error::Raster)?;
+    Ok(general_purpose::STANDARD.encode(commands))
+}
+
+/**
+ * 生成条码（QR、Code128、EAN-13、PDF417）
+ * @param options 条码类型、内容和输出格式
+ * @returns escpos/zpl 为指令的 base64 字符串，svg 为 SVG 标记，png 为 data URI
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|generate_barcode')`.
+fn generate_barcode(options: BarcodeOptions) -> Result<String> {
+    barcode::generate(&options).map_err(Error::Barcode)
+}
+
+/**
+ * 注册虚拟打印机（如 TCP 9100 网络打印机），同名的会被替换
+ * @param printer 打印机名称与后端连接参数
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|add_virtual_printer')`.
+fn add_virtual_printer(printer: VirtualPrinter) -> Result<()> {
+    virtual_printer::register(printer).map_err(Error::InvalidConfig)
+}
+
+/**
+ * 删除虚拟打印机
+ * @param printername 打印机名称
+ * @returns 是否删除
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|remove_virtual_printer')`.
+fn remove_virtual_printer(printername: String) -> bool {
+    virtual_printer::unregister(&printername)
+}
+
+/**
+ * 发送原始数据（ESC/POS、ZPL、PCL 等）到虚拟打印机
+ * @param printername 打印机名称
+ * @param buffer_data 原始数据 base64 字符串
+ * @param job_name 作业名称（可选）
+ * @param copies 份数（可选，默认 1）
+ * @returns 发送结果
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|print_raw')`.
+async fn print_raw(
+    printername: String,
+    buffer_data: String,
+    job_name: Option<String>,
+    copies: Option<u32>,
+) -> Result<RawPrintResult> {
+    tauri::async_runtime::spawn_blocking(move || print_raw_bytes(printername, buffer_data, job_name, copies))
+        .await
+        .map_err(|e| Error::Backend(e.to_string()))?
+}
+
+fn print_raw_bytes(
+    printername: String,
+    buffer_data: String,
+    job_name: Option<String>,
+    copies: Option<u32>,
+) -> Result<RawPrintResult> {
+    let printer = virtual_printer::find(&printername).ok_or(Error::PrinterNotFound(printername))?;
+    let data = general_purpose::STANDARD.decode(buffer_data)?;
+    let job_name = job_name.unwrap_or_else(|| "raw".to_string());
+    let submitted_at = history::now_ms();
+    let result = virtual_printer::print_bytes(&printer, &data, &job_name, copies.unwrap_or(1)).map_err(Error::Backend);
+    let text = match &result {
+        Ok(_) => String::new(),
+        Err(e) => format!("打印失败: {}", e),
+    };
+    let settings = copies.map(|copies| PrintSettings { copies: Some(copies), ..PrintSettings::default() });
+    record_history(None, Document::from_bytes(&job_name, &data), &printer.name, settings, submitted_at, &text);
+    result
+}
+
+/// 各后端以 "打印失败" 或 "Unsupported OS" 开头的文本表示失败
+fn is_failure(result: &str) -> bool {
+    result.starts_with("打印失败") || result.starts_with("Unsupported OS")
+}
+
+/**
+ * 记录到打印历史
+ * @param result 后端返回的文本
+ */
+fn record_history(
+    job_id: Option<String>,
+    document: Document,
+    printer: &str,
+    settings: Option<PrintSettings>,
+    submitted_at: i64,
+    result: &str,
+) {
+    let failed = is_failure(result);
+    let message = result.trim();
+    history::record(HistoryEntry {
+        id: 0,
+        job_id,
+        document_name: document.name,
+        document_hash: document.hash,
+        printer: printer.to_string(),
+        settings,
+        pages: document.pages,
+        user: history::current_user(),
+        outcome: if failed { JobOutcome::Failed } else { JobOutcome::Submitted },
+        message: (!message.is_empty()).then(|| message.to_string()),
+        submitted_at,
+        completed_at: Some(history::now_ms()),
+    });
+}
+
+/**
+ * 查询打印历史，最新的在前
+ * @param filter 打印机、用户、结果、文档名、文档哈希、时间范围（毫秒时间戳）和分页，均为可选
+ * @returns 打印记录
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|get_print_history')`.
+fn get_print_history(filter: Option<HistoryFilter>) -> Result<Vec<HistoryEntry>> {
+    history::query(&filter.unwrap_or_default()).map_err(Error::History)
+}
+
+/**
+ * 导出打印历史
+ * @param path 导出文件路径
+ * @param format csv（默认）或 json
+ * @param filter 查询条件（可选），不指定 limit 时导出全部
+ * @returns 导出的条数
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|export_print_history')`.
+fn export_print_history(path: String, format: Option<ExportFormat>, filter: Option<HistoryFilter>) -> Result<usize> {
+    history::export(&filter.unwrap_or_default(), format.unwrap_or_default(), std::path::Path::new(&path))
+        .map_err(Error::History)
+}
+
+/**
+ * 加入插件管理的打印队列，打印机离线时按退避时间自动重试
+ * 文档会复制到队列目录，应用重启后继续打印
+ * @param printer_setting 打印机名称
+ * @param path PDF文件路径
+ * @param settings 打印参数（可选），加入队列前按打印机能力校验
+ * @param validation 校验模式（可选，默认 lenient）
+ * @param priority 优先级（可选，默认 0），数值大的先打印
+ * @param remove_after_print 加入队列后删除原文件
+ * @returns 队列中的作业
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|queue_print_pdf')`.
+fn queue_print_pdf(
+    printer_setting: String,
+    path: String,
+    settings: Option<PrintSettings>,
+    validation: Option<ValidationMode>,
+    priority: Option<i32>,
+    remove_after_print: Option<bool>,
+) -> Result<QueuedJob> {
+    // 队列和计划不保存密码，需要密码的文档只能用 print_pdf 打印
+    if preflight(&path)?.locked {
+        return Err(Error::PasswordRequired(path));
+    }
+    let settings = deferred_settings(&printer_setting, settings, validation)?;
+    let job = queue::enqueue(std::path::Path::new(&path), &printer_setting, settings, priority.unwrap_or(0))
+        .map_err(Error::Queue)?;
+    if remove_after_print.unwrap_or(false) {
+        let _ = fsys::remove_file(&path);
+    }
+    Ok(job)
+}
+
+/// 加入队列或计划前校验参数，打印机组在提交时按选中的成员校验
+fn deferred_settings(
+    printer_setting: &str,
+    settings: Option<PrintSettings>,
+    validation: Option<ValidationMode>,
+) -> Result<Option<PrintSettings>> {
+    if groups::find(printer_setting).is_some() {
+        return Ok(settings);
+    }
+    checked_settings(printer_setting, settings, validation).map_err(Error::InvalidConfig)
+}
+
+/**
+ * 查看打印队列，按打印顺序排列
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|list_queue')`.
+fn list_queue() -> Result<Vec<QueuedJob>> {
+    queue::list().map_err(Error::Queue)
+}
+
+/**
+ * 取消队列中的作业，正在打印的作业不能取消
+ * @param id 作业ID
+ * @returns 是否找到该作业
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|cancel_queued')`.
+fn cancel_queued(id: String) -> Result<bool> {
+    queue::cancel(&id).map_err(Error::Queue)
+}
+
+/**
+ * 立即重试队列中的作业，包括已达到最多尝试次数的作业
+ * @param id 作业ID
+ * @returns 是否找到该作业
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|retry_now')`.
+fn retry_now(id: String) -> Result<bool> {
+    queue::retry_now(&id).map_err(Error::Queue)
+}
+
+/**
+ * 批量打印：按顺序提交，返回每个文档的结果
+ * @param id 批量打印ID，用于取消
+ * @param printer_setting 打印机名称或打印机组名
+ * @param documents 文档路径和各自的打印参数
+ * @param merge 合并为一个打印作业（可选，默认 false），只合并相邻且打印参数相同的文档
+ * @param separator_pages 合并时在文档之间插入空白分隔页（可选，默认 false）
+ * @param validation 校验模式（可选，默认 lenient）
+ * @param remove_after_print 提交成功后删除文件（可选，默认 false）
+ * @returns 每个文档的结果
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|print_batch')`.
+async fn print_batch(
+    id: String,
+    printer_setting: String,
+    documents: Vec<BatchDocument>,
+    merge: Option<bool>,
+    separator_pages: Option<bool>,
+    validation: Option<ValidationMode>,
+    remove_after_print: Option<bool>,
+) -> Result<BatchResult> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let temp_dir = env::temp_dir();
+        let batch = batch::Batch {
+            id: &id,
+            documents: &documents,
+            merge: merge.unwrap_or(false),
+            separator_pages: separator_pages.unwrap_or(false),
+            temp_dir: &temp_dir,
+        };
+        let result = batch::run(
+            batch,
+            |job_id, path, settings| {
+                print_pdf(job_id, path, printer_setting.clone(), false, settings, validation, None, None)
+                    .unwrap_or_else(|e| format!("打印失败: {}", e))
+            },
+            is_failure,
+        )
+        .map_err(Error::InvalidConfig)?;
+        if remove_after_print.unwrap_or(false) {
+            for item in result.items.iter().filter(|item| item.status == batch::BatchItemStatus::Submitted) {
+                let _ = fsys::remove_file(&item.path);
+            }
+        }
+        Ok(result)
+    })
+    .await
+    .map_err(|e| Error::Backend(e.to_string()))?
+}
+
+/**
+ * 取消批量打印，尚未提交的文档不再打印，已提交的作业不会撤回
+ * @param id 批量打印ID
+ * @returns 是否找到正在进行的批量打印
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|cancel_batch')`.
+fn cancel_batch(id: String) -> bool {
+    batch::cancel(&id)
+}
+
+/// 在后台线程处理 PDF
+async fn pdf_task<T: Send + 'static>(
+    task: impl FnOnce() -> std::result::Result<T, String> + Send + 'static,
+) -> Result<T> {
+    tauri::async_runtime::spawn_blocking(task)
+        .await
+        .map_err(|e| Error::Backend(e.to_string()))?
+        .map_err(Error::Pdf)
+}
+
+fn path_string(path: std::path::PathBuf) -> String {
+    path.to_string_lossy().to_string()
+}
+
+/// 路径中的文件名，用于打印历史中的文档名
+fn file_name(path: &str) -> String {
+    std::path::Path::new(path).file_name().map_or(path.to_string(), |name| name.to_string_lossy().to_string())
+}
+
+/**
+ * 按顺序合并PDF，输出到临时目录下的 tauri-plugin-printer-spool
+ * @param paths PDF文件路径
+ * @param separator_pages 在文档之间插入空白分隔页（可选，默认 false）
+ * @returns 输出文件路径，可直接传给 print_pdf
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|merge_pdfs')`.
+async fn merge_pdfs(paths: Vec<String>, separator_pages: Option<bool>) -> Result<String> {
+    pdf_task(move || {
+        let stem = paths.first().map(|p| pdf::file_stem(std::path::Path::new(p))).unwrap_or_default();
+        let output = pdf::spool_path(&format!("{}-merged", stem))?;
+        let inputs: Vec<&std::path::Path> = paths.iter().map(std::path::Path::new).collect();
+        pdf::merge_files(&inputs, separator_pages.unwrap_or(false), &output)?;
+        Ok(path_string(output))
+    })
+    .await
+}
+
+/**
+ * 提取页面，按页码范围的书写顺序输出，也可用于重排页面
+ * @param path PDF文件路径
+ * @param pages 页码范围，如 "1-3,5,8-"；"3,1,2" 重排页面；"5-1" 倒序
+ * @returns 输出文件路径
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|extract_pdf_pages')`.
+async fn extract_pdf_pages(path: String, pages: String) -> Result<String> {
+    pdf_task(move || {
+        pdf::transform_file(std::path::Path::new(&path), |document, count| {
+            pdf::select_pages(document, &pdf::parse_pages(&pages, count)?)
+        })
+        .map(path_string)
+    })
+    .await
+}
+
+/**
+ * 删除页面，如空白页
+ * @param path PDF文件路径
+ * @param pages 要删除的页码范围
+ * @returns 输出文件路径
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|remove_pdf_pages')`.
+async fn remove_pdf_pages(path: String, pages: String) -> Result<String> {
+    pdf_task(move || {
+        pdf::transform_file(std::path::Path::new(&path), |document, count| {
+            pdf::remove_pages(document, &pdf::parse_pages(&pages, count)?)
+        })
+        .map(path_string)
+    })
+    .await
+}
+
+/**
+ * 顺时针旋转页面
+ * @param path PDF文件路径
+ * @param degrees 角度，90 的倍数，可为负数
+ * @param pages 页码范围（可选，默认全部页面）
+ * @returns 输出文件路径
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|rotate_pdf_pages')`.
+async fn rotate_pdf_pages(path: String, degrees: i64, pages: Option<String>) -> Result<String> {
+    pdf_task(move || {
+        pdf::transform_file(std::path::Path::new(&path), |document, count| {
+            let pages = pdf::parse_pages(pages.as_deref().unwrap_or(""), count)?;
+            pdf::rotate_pages(document, &pages, degrees)
+        })
+        .map(path_string)
+    })
+    .await
+}
+
+/**
+ * 拆分PDF
+ * @param path PDF文件路径
+ * @param ranges 每个文件的页码范围（可选，默认每页一个文件）
+ * @returns 输出文件路径
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|split_pdf')`.
+async fn split_pdf(path: String, ranges: Option<Vec<String>>) -> Result<Vec<String>> {
+    pdf_task(move || {
+        let outputs = pdf::split_file(std::path::Path::new(&path), &ranges.unwrap_or_default())?;
+        Ok(outputs.into_iter().map(path_string).collect())
+    })
+    .await
+}
+
+/**
+ * 检查PDF：页数、页面尺寸和方向、标题作者等元数据、是否加密以及文件结构是否完整
+ * @param path PDF文件路径
+ * @param password 加密文档的打开密码（可选）
+ * @returns 文档信息，文件无效时 valid 为 false，缺少密码时 locked 为 true
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|inspect_pdf')`.
+async fn inspect_pdf(path: String, password: Option<String>) -> Result<PdfInfo> {
+    pdf_task(move || Ok(pdf::inspect(std::path::Path::new(&path), password.as_deref()))).await
+}
+
+/**
+ * 给PDF添加水印或印章，原文件不变
+ * @param path PDF文件路径
+ * @param watermark 文字或图片水印、透明度、角度、位置和页码范围
+ * @returns 输出文件路径
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|stamp_pdf')`.
+async fn stamp_pdf(path: String, watermark: Watermark) -> Result<String> {
+    pdf_task(move || watermark::stamp_file(std::path::Path::new(&path), &watermark).map(path_string)).await
+}
+
+/**
+ * 注册打印机组，同名的会被替换
+ * 按组名调用 print_pdf / queue_print_pdf 时，作业发往第一台可用的成员，失败时换下一台
+ * @param group 组名、成员（按优先顺序）和选择策略（failover 或 least_busy）
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|add_printer_group')`.
+fn add_printer_group(group: PrinterGroup) -> Result<()> {
+    groups::register(group).map_err(Error::InvalidConfig)
+}
+
+/**
+ * 删除打印机组
+ * @param name 组名
+ * @returns 是否删除
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|remove_printer_group')`.
+fn remove_printer_group(name: String) -> bool {
+    groups::unregister(&name)
+}
+
+/**
+ * 获取已注册的打印机组
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|get_printer_groups')`.
+fn get_printer_groups() -> Vec<PrinterGroup> {
+    groups::list()
+}
+
+/**
+ * 计划打印：到时间后加入打印队列，打印机离线时按队列的规则重试
+ * 文档会复制到队列目录，应用重启后计划仍然有效；应用未运行期间错过的执行在启动后补一次
+ * @param printer_setting 打印机名称或打印机组名
+ * @param path PDF文件路径
+ * @param not_before 最早执行时间（可选，毫秒时间戳）
+ * @param cron 重复执行的 cron 表达式（可选，本地时间），如 "0 6 * * 1-5" 表示工作日 6:00
+ * @param settings 打印参数（可选），添加计划前按打印机能力校验
+ * @param validation 校验模式（可选，默认 lenient）
+ * @param priority 加入队列时的优先级（可选，默认 0）
+ * @param remove_after_print 添加计划后删除原文件
+ * @returns 计划
+ */
+#[allow(clippy::too_many_arguments)]
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|schedule_print_pdf')`.
+fn schedule_print_pdf(
+    printer_setting: String,
+    path: String,
+    not_before: Option<i64>,
+    cron: Option<String>,
+    settings: Option<PrintSettings>,
+    validation: Option<ValidationMode>,
+    priority: Option<i32>,
+    remove_after_print: Option<bool>,
+) -> Result<Schedule> {
+    // 队列和计划不保存密码，需要密码的文档只能用 print_pdf 打印
+    if preflight(&path)?.locked {
+        return Err(Error::PasswordRequired(path));
+    }
+    let settings = deferred_settings(&printer_setting, settings, validation)?;
+    let schedule = schedule::add(
+        std::path::Path::new(&path),
+        &printer_setting,
+        settings,
+        priority.unwrap_or(0),
+        not_before,
+        cron.as_deref(),
+    )
+    .map_err(Error::Schedule)?;
+    if remove_after_print.unwrap_or(false) {
+        let _ = fsys::remove_file(&path);
+    }
+    Ok(schedule)
+}
+
+/**
+ * 查看打印计划，按下次执行时间排列
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|list_schedules')`.
+fn list_schedules() -> Result<Vec<Schedule>> {
+    schedule::list().map_err(Error::Schedule)
+}
+
+/**
+ * 取消打印计划，已加入队列的作业不受影响
+ * @param id 计划ID
+ * @returns 是否找到该计划
+ */
+#[tauri::command(rename_all = "snake_case")]
+// this will be accessible with `invoke('plugin:printer|cancel_schedule')`.
+fn cancel_schedule(id: String) -> Result<bool> {
+    schedule::cancel(&id).map_err(Error::Schedule)
+}
+
+/// 计划到期时加入打印队列，并发出 `printer://schedule-fired` 事件
+fn fire_schedule<R: Runtime>(app: &tauri::AppHandle<R>, schedule: &Schedule) -> std::result::Result<(), String> {
+    let result = queue::enqueue(
+        std::path::Path::new(&schedule.path),
+        &schedule.printer,
+        schedule.settings.clone(),
+        schedule.priority,
+    );
+    let event = ScheduleFired {
+        schedule: schedule.clone(),
+        job_id: result.as_ref().ok().map(|job| job.id.clone()),
+        error: result.as_ref().err().cloned(),
+    };
+    if let Err(e) = app.emit(schedule::SCHEDULE_FIRED_EVENT, &event) {
+        eprintln!("计划事件发送失败: {}", e);
+    }
+    result.map(|_| ())
+}
+
+/// 队列后台线程提交作业，每次尝试都记录到打印历史
+fn submit_queued(job: &QueuedJob) -> std::result::Result<(), String> {
+    let document = Document { name: job.document_name.clone(), ..Document::from_path(&job.path) };
+    if let Some(group) = groups::find(&job.printer) {
+        let (id, path, settings) = (job.id.clone(), job.path.clone(), job.settings.clone());
+        let result = print_pdf_to_group(&group, id, path, document, false, settings, None);
+        return if is_failure(&result) { Err(result) } else { Ok(()) };
+    }
+    let submitted_at = history::now_ms();
+    let result = submit_pdf(job.id.clone(), job.path.clone(), job.printer.clone(), false, job.settings.clone());
+    record_history(Some(job.id.clone()), document, &job.printer, job.settings.clone(), submitted_at, &result);
+    if is_failure(&result) {
+        return Err(result);
+    }
+    Ok(())
+}
+
 /**
  * 获取打印机列表
  * @param printername 打印机名称
  * @returns 打印机列表
  */
 pub fn custom_get_printers_by_name(printername: String) -> String {
+    if let Some(printer) = virtual_printer::printer_json(&printername) {
+        return printer;
+    }
     if cfg!(windows) {
         return windows::get_printers_by_name(printername);
     }
@@ -262,17 +1509,17 @@ pub fn custom_print_pdf(
     printer_setting: String,
     remove_after_print: bool,
 ) -> String {
-    if cfg!(windows) {
-        let options = declare::PrintOptions {
-            id,
-            path,
-            print_setting: printer_setting,
-            remove_after_print: remove_after_print,
-        };
-        return windows::print_pdf(options);
+    if let Some(group) = groups::find(&printer_setting) {
+        let document = Document::from_path(&path);
+        return print_pdf_to_group(&group, id, path, document, remove_after_print, None, None);
     }
-
-    return "Unsupported OS".to_string();
+    let submitted_at = history::now_ms();
+    let document = Document::from_path(&path);
+    let job_id = Some(id.clone());
+    let printer = printer_setting.clone();
+    let result = submit_pdf(id, path, printer_setting, remove_after_print, None);
+    record_history(job_id, document, &printer, None, submitted_at, &result);
+    result
 }
 
 /**
@@ -280,11 +1527,11 @@ pub fn custom_print_pdf(
  * @returns 初始化结果
  */
 /// Initializes the plugin.
-pub fn init<R: Runtime>() -> TauriPlugin<R> {
+pub fn init<R: Runtime>() -> TauriPlugin<R, Option<Config>> {
   if cfg!(windows) {
     windows::init_windows();
   }
-    Builder::new("printer")
+    Builder::<R, Option<Config>>::new("printer")
         .invoke_handler(tauri::generate_handler![
             ping,
             print_html,
@@ -298,9 +1545,88 @@ pub fn init<R: Runtime>() -> TauriPlugin<R> {
             resume_job,
             restart_job,
             pause_job,
-            remove_job
+            remove_job,
+            raster_image,
+            generate_barcode,
+            add_virtual_printer,
+            remove_virtual_printer,
+            print_raw,
+            discover_printers,
+            get_printer_capabilities,
+            validate_print_job,
+            get_printer_status,
+            start_status_monitor,
+            stop_status_monitor,
+            get_printer_supplies,
+            get_print_history,
+            export_print_history,
+            queue_print_pdf,
+            list_queue,
+            cancel_queued,
+            retry_now,
+            add_printer_group,
+            remove_printer_group,
+            get_printer_groups,
+            print_batch,
+            cancel_batch,
+            schedule_print_pdf,
+            list_schedules,
+            cancel_schedule,
+            merge_pdfs,
+            extract_pdf_pages,
+            remove_pdf_pages,
+            rotate_pdf_pages,
+            split_pdf,
+            stamp_pdf,
+            inspect_pdf,
+            print_image,
+            print_text,
+            print_markdown,
+            export_markdown,
+            register_template,
+            remove_template,
+            get_templates,
+            render_template,
+            print_template,
+            print_table
         ])
         .setup(|app, api| {
+            if let Some(config) = api.config() {
+                for printer in config.virtual_printers.iter().cloned() {
+                    virtual_printer::register(printer).map_err(Error::InvalidConfig)?;
+                }
+                for group in config.printer_groups.iter().cloned() {
+                    groups::register(group).map_err(Error::InvalidConfig)?;
+                }
+                for template in config.templates.iter().cloned() {
+                    templates::register(template).map_err(Error::Template)?;
+                }
+                if let Some(monitor) = config.status_monitor.clone() {
+                    start_monitor(app.clone(), monitor.printers, monitor.interval_ms);
+                }
+            }
+            // 打印历史和队列打不开时只提示，不影响直接打印
+            let (history_config, queue_config) = api
+                .config()
+                .as_ref()
+                .map(|c| (c.history.clone(), c.queue.clone()))
+                .unwrap_or_default();
+            match app.path().app_data_dir() {
+                Ok(dir) => {
+                    if let Err(e) = history::init(&dir, &history_config) {
+                        eprintln!("打印历史初始化失败: {}", e);
+                    }
+                    if let Err(e) = queue::init(&dir.join(queue::QUEUE_DIR), queue_config, submit_queued) {
+                        eprintln!("打印队列初始化失败: {}", e);
+                    }
+                    let handle = app.clone();
+                    let fire = move |schedule: &Schedule| fire_schedule(&handle, schedule);
+                    if let Err(e) = schedule::init(&dir.join(queue::QUEUE_DIR), fire) {
+                        eprintln!("打印计划初始化失败: {}", e);
+                    }
+                }
+                Err(e) => eprintln!("应用数据目录不可用，打印历史、队列和计划未启用: {}", e),
+            }
             #[cfg(mobile)]
             let printer = mobile::init(app, api)?;
             #[cfg(desktop)]
@@ -0,0 +1,369 @@
+use std::env;
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::declare::{JobEntry, LpdSettings};
+
+/// RFC 1179 默认端口
+pub const DEFAULT_PORT: u16 = 515;
+
+const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 5000;
+const IO_TIMEOUT: Duration = Duration::from_secs(60);
+/// 控制文件中主机名最长 31 个字符
+const MAX_HOST_LEN: usize = 31;
+
+/// 一个 LPD 打印作业
+#[derive(Debug)]
+pub struct LpdJob<'a> {
+    pub name: &'a str,
+    pub copies: u32,
+}
+
+pub fn address(settings: &LpdSettings) -> String {
+    format!("{}:{}", settings.host, settings.port.unwrap_or(DEFAULT_PORT))
+}
+
+/**
+ * 提交打印作业：先发送控制文件，再发送数据文件
+ * @returns 作业号
+ */
+pub fn submit(settings: &LpdSettings, data: &[u8], job: &LpdJob) -> Result<u32, String> {
+    let job_number = next_job_number();
+    let host = local_host_name();
+    let user = user_name(settings);
+    let data_file = format!("dfA{:03}{}", job_number, host);
+    let control = control_file(settings, job, job_number, &host, &user);
+
+    let mut stream = connect(settings)?;
+    command(&mut stream, &format!("\x02{}\n", settings.queue))
+        .map_err(|e| format!("打印队列 {} 拒绝接收作业: {}", settings.queue, e))?;
+
+    command(
+        &mut stream,
+        &format!("\x02{} cfA{:03}{}\n", control.len(), job_number, host),
+    )?;
+    send_file(&mut stream, control.as_bytes())?;
+
+    command(&mut stream, &format!("\x03{} {}\n", data.len(), data_file))?;
+    send_file(&mut stream, data)?;
+
+    let _ = stream.shutdown(Shutdown::Both);
+    Ok(job_number)
+}
+
+/// 生成控制文件，每份拷贝对应一行打印指令
+pub fn control_file(settings: &LpdSettings, job: &LpdJob, job_number: u32, host: &str, user: &str) -> String {
+    let data_file = format!("dfA{:03}{}", job_number, host);
+    let format = settings.format.unwrap_or('l');
+    let name = sanitize_line(job.name);
+
+    let mut control = format!("H{}\nP{}\nJ{}\nN{}\n", host, sanitize_line(user), name, name);
+    if settings.banner.unwrap_or(false) {
+        control.push_str(&format!("C{}\nL{}\n", host, sanitize_line(user)));
+    }
+    for _ in 0..job.copies.max(1) {
+        control.push_str(&format!("{}{}\n", format, data_file));
+    }
+    control.push_str(&format!("U{}\n", data_file));
+    control
+}
+
+/**
+ * 查询打印队列
+ * @param long true 为长格式（`\x04`），false 为短格式（`\x03`）
+ */
+pub fn query_queue(settings: &LpdSettings, long: bool) -> Result<String, String> {
+    let code = if long { '\x04' } else { '\x03' };
+    let mut stream = connect(settings)?;
+    stream
+        .write_all(format!("{}{}\n", code, settings.queue).as_bytes())
+        .map_err(|e| format!("发送查询失败: {}", e))?;
+    read_to_end(&mut stream)
+}
+
+/**
+ * 删除作业
+ * @param jobs 作业号列表，为空时删除该用户的当前作业
+ */
+pub fn remove_jobs(settings: &LpdSettings, jobs: &[String]) -> Result<String, String> {
+    let mut line = format!("\x05{} {}", settings.queue, sanitize_word(&user_name(settings)));
+    for job in jobs {
+        line.push(' ');
+        line.push_str(&sanitize_word(job));
+    }
+    line.push('\n');
+
+    let mut stream = connect(settings)?;
+    stream
+        .write_all(line.as_bytes())
+        .map_err(|e| format!("发送删除指令失败: {}", e))?;
+    read_to_end(&mut stream)
+}
+
+/**
+ * 解析 BSD `lpq` 短格式队列列表
+ *
+ * ```text
+ * Rank   Owner      Job  Files                                 Total Size
+ * active alice      12   report.pdf                            10240 bytes
+ * 1st    bob        13   label.zpl                             512 bytes
+ * ```
+ */
+pub fn parse_queue(text: &str, printer: &str) -> Vec<JobEntry> {
+    let mut entries = Vec::new();
+    for line in text.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 5 || fields.last() != Some(&"bytes") {
+            continue;
+        }
+        let Ok(size) = fields[fields.len() - 2].parse::<u64>() else {
+            continue;
+        };
+        if fields[2].parse::<u32>().is_err() {
+            continue;
+        }
+
+        let rank = fields[0];
+        let position = if rank == "active" {
+            0
+        } else {
+            rank.trim_end_matches(|c: char| c.is_ascii_alphabetic()).parse().unwrap_or(0)
+        };
+        entries.push(JobEntry {
+            id: fields[2].to_string(),
+            document_name: fields[3..fields.len() - 2].join(" "),
+            user_name: fields[1].to_string(),
+            size,
+            position,
+            job_status: if rank == "active" { "Printing" } else { "Queued" }.to_string(),
+            printer_name: printer.to_string(),
+        });
+    }
+    entries
+}
+
+fn connect(settings: &LpdSettings) -> Result<TcpStream, String> {
+    let address = address(settings);
+    let timeout = Duration::from_millis(settings.connect_timeout_ms.unwrap_or(DEFAULT_CONNECT_TIMEOUT_MS));
+    let addrs = address
+        .to_socket_addrs()
+        .map_err(|e| format!("解析地址 {} 失败: {}", address, e))?;
+
+    let mut last_error = format!("地址 {} 没有可用的 IP", address);
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => {
+                stream.set_read_timeout(Some(IO_TIMEOUT)).map_err(|e| e.to_string())?;
+                stream.set_write_timeout(Some(IO_TIMEOUT)).map_err(|e| e.to_string())?;
+                return Ok(stream);
+            }
+            Err(e) => last_error = format!("连接 {} 失败: {}", addr, e),
+        }
+    }
+    Err(last_error)
+}
+
+/// 发送一行指令并等待服务端回复 0 确认
+fn command(stream: &mut TcpStream, line: &str) -> Result<(), String> {
+    stream
+        .write_all(line.as_bytes())
+        .map_err(|e| format!("发送指令失败: {}", e))?;
+    acknowledge(stream)
+}
+
+/// 发送文件内容，以一个 0 字节结尾
+fn send_file(stream: &mut TcpStream, content: &[u8]) -> Result<(), String> {
+    stream
+        .write_all(content)
+        .and_then(|_| stream.write_all(&[0]))
+        .map_err(|e| format!("发送文件失败: {}", e))?;
+    acknowledge(stream)
+}
+
+fn acknowledge(stream: &mut TcpStream) -> Result<(), String> {
+    let mut ack = [0u8; 1];
+    stream
+        .read_exact(&mut ack)
+        .map_err(|e| format!("等待服务端确认失败: {}", e))?;
+    if ack[0] != 0 {
+        return Err(format!("服务端返回错误码 {}", ack[0]));
+    }
+    Ok(())
+}
+
+fn read_to_end(stream: &mut TcpStream) -> Result<String, String> {
+    let mut response = Vec::new();
+    stream
+        .read_to_end(&mut response)
+        .map_err(|e| format!("读取响应失败: {}", e))?;
+    Ok(String::from_utf8_lossy(&response).to_string())
+}
+
+/// 作业号为 000-999 循环
+fn next_job_number() -> u32 {
+    static COUNTER: AtomicU32 = AtomicU32::new(u32::MAX);
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_millis())
+        .unwrap_or(0);
+    let _ = COUNTER.compare_exchange(u32::MAX, seed, Ordering::SeqCst, Ordering::SeqCst);
+    COUNTER.fetch_add(1, Ordering::SeqCst) % 1000
+}
+
+fn local_host_name() -> String {
+    let host = env::var("COMPUTERNAME")
+        .or_else(|_| env::var("HOSTNAME"))
+        .unwrap_or_else(|_| "tauri".to_string());
+    let host: String = host
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '.')
+        .take(MAX_HOST_LEN)
+        .collect();
+    if host.is_empty() { "tauri".to_string() } else { host }
+}
+
+fn user_name(settings: &LpdSettings) -> String {
+    settings
+        .user
+        .clone()
+        .or_else(|| env::var("USERNAME").ok())
+        .or_else(|| env::var("USER").ok())
+        .unwrap_or_else(|| "tauri".to_string())
+}
+
+/// 控制文件的每一行不能包含换行
+fn sanitize_line(value: &str) -> String {
+    value.chars().filter(|c| !c.is_control()).take(99).collect()
+}
+
+/// 指令中的参数以空格分隔，不能包含空白
+fn sanitize_word(value: &str) -> String {
+    value.chars().filter(|c| !c.is_whitespace() && !c.is_control()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn settings(port: u16) -> LpdSettings {
+        LpdSettings {
+            host: "127.0.0.1".to_string(),
+            port: Some(port),
+            queue: "raw".to_string(),
+            user: Some("alice".to_string()),
+            format: None,
+            banner: None,
+            connect_timeout_ms: Some(500),
+        }
+    }
+
+    fn read_line(socket: &mut TcpStream) -> String {
+        let mut line = Vec::new();
+        let mut byte = [0u8; 1];
+        while socket.read(&mut byte).unwrap() == 1 && byte[0] != b'\n' {
+            line.push(byte[0]);
+        }
+        String::from_utf8(line).unwrap()
+    }
+
+    fn read_file(socket: &mut TcpStream, header: &str) -> Vec<u8> {
+        let size: usize = header[1..].split(' ').next().unwrap().parse().unwrap();
+        socket.write_all(&[0]).unwrap();
+        let mut content = vec![0u8; size + 1];
+        socket.read_exact(&mut content).unwrap();
+        assert_eq!(content.pop(), Some(0));
+        socket.write_all(&[0]).unwrap();
+        content
+    }
+
+    #[test]
+    fn control_file_lists_one_line_per_copy() {
+        let mut settings = settings(DEFAULT_PORT);
+        settings.format = Some('o');
+        let job = LpdJob { name: "report.ps", copies: 2 };
+
+        let control = control_file(&settings, &job, 7, "pos01", "alice");
+        assert_eq!(
+            control,
+            "Hpos01\nPalice\nJreport.ps\nNreport.ps\nodfA007pos01\nodfA007pos01\nUdfA007pos01\n"
+        );
+    }
+
+    #[test]
+    fn submits_control_and_data_files() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            assert_eq!(read_line(&mut socket), "\x02raw");
+            socket.write_all(&[0]).unwrap();
+
+            let header = read_line(&mut socket);
+            assert!(header.starts_with('\x02') && header.contains(" cfA"), "{:?}", header);
+            let control = read_file(&mut socket, &header);
+
+            let header = read_line(&mut socket);
+            assert!(header.starts_with('\x03') && header.contains(" dfA"), "{:?}", header);
+            let data = read_file(&mut socket, &header);
+            (String::from_utf8(control).unwrap(), data)
+        });
+
+        let job = LpdJob { name: "label.zpl", copies: 1 };
+        submit(&settings(port), b"^XA^XZ", &job).unwrap();
+
+        let (control, data) = server.join().unwrap();
+        assert!(control.contains("Palice\n") && control.contains("Jlabel.zpl\n"));
+        assert!(control.contains("\nldfA"));
+        assert_eq!(data, b"^XA^XZ");
+    }
+
+    #[test]
+    fn rejected_queue_is_reported() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            read_line(&mut socket);
+            socket.write_all(&[1]).unwrap();
+        });
+
+        let job = LpdJob { name: "x", copies: 1 };
+        let error = submit(&settings(port), b"x", &job).unwrap_err();
+        assert!(error.contains("拒绝"), "{}", error);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn removes_jobs_with_agent() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            read_line(&mut socket)
+        });
+
+        remove_jobs(&settings(port), &["12".to_string(), "13".to_string()]).unwrap();
+        assert_eq!(server.join().unwrap(), "\x05raw alice 12 13");
+    }
+
+    #[test]
+    fn parses_lpq_listing() {
+        let listing = "printer is ready and printing\n\
+Rank   Owner      Job  Files                                 Total Size\n\
+active alice      12   annual report.pdf                     10240 bytes\n\
+1st    bob        13   label.zpl                             512 bytes\n";
+
+        let entries = parse_queue(listing, "Branch LPR");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].id, "12");
+        assert_eq!(entries[0].document_name, "annual report.pdf");
+        assert_eq!(entries[0].job_status, "Printing");
+        assert_eq!(entries[1].position, 1);
+        assert_eq!(entries[1].size, 512);
+        assert_eq!(entries[1].printer_name, "Branch LPR");
+    }
+}
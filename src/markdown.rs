@@ -0,0 +1,133 @@
+use std::path::Path;
+
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+
+use crate::declare::MarkdownStyle;
+
+/// 内置的打印样式表，wkhtmltopdf 使用较旧的 WebKit，不使用 flex、grid 和 CSS 变量
+const STYLESHEET: &str = r#"
+body { font-family: "Helvetica Neue", Arial, "Microsoft YaHei", "PingFang SC", "Noto Sans CJK SC", sans-serif; font-size: 11pt; line-height: 1.6; color: #222; }
+h1, h2, h3, h4, h5, h6 { line-height: 1.3; margin: 1.2em 0 0.5em; page-break-after: avoid; }
+h1 { font-size: 20pt; border-bottom: 1px solid #ccc; padding-bottom: 0.2em; }
+h2 { font-size: 16pt; border-bottom: 1px solid #eee; padding-bottom: 0.2em; }
+h3 { font-size: 13pt; }
+p, ul, ol, dl, blockquote, pre, table { margin: 0 0 0.8em; }
+a { color: #0645ad; text-decoration: none; }
+blockquote { margin-left: 0; padding: 0 1em; color: #555; border-left: 3px solid #ccc; }
+code, pre { font-family: Consolas, "Courier New", monospace; font-size: 9.5pt; }
+code { background: #f4f4f4; padding: 0 0.2em; }
+pre { background: #f6f8fa; border: 1px solid #ddd; padding: 0.6em; white-space: pre-wrap; word-wrap: break-word; page-break-inside: avoid; }
+pre code { background: none; padding: 0; }
+table { border-collapse: collapse; width: 100%; }
+thead { display: table-header-group; }
+tr { page-break-inside: avoid; }
+th, td { border: 1px solid #bbb; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }
+th { background: #f0f0f0; }
+img { max-width: 100%; page-break-inside: avoid; }
+hr { border: 0; border-top: 1px solid #ccc; }
+li input[type=checkbox] { margin-right: 0.4em; }
+"#;
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// 相对路径的图片转换为 file:// 地址，HTML 写到临时目录后仍能找到图片
+fn resolve_image(url: &str, base_dir: Option<&Path>) -> Option<String> {
+    // http:、data:、file: 等带协议的地址保持不变，C: 这样的单个字母是 Windows 盘符
+    let scheme = url.split_once(':').map(|(scheme, _)| scheme).unwrap_or_default();
+    let drive = scheme.len() == 1 && scheme.chars().all(|c| c.is_ascii_alphabetic());
+    let has_scheme = scheme.len() > 1 && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+    if url.is_empty() || url.starts_with('#') || has_scheme {
+        return None;
+    }
+    let path = Path::new(url);
+    let path = if drive || path.is_absolute() || url.starts_with('/') || url.starts_with('\\') {
+        path.to_path_buf()
+    } else {
+        base_dir?.join(path)
+    };
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut encoded = String::from("file:///");
+    for c in path.trim_start_matches('/').chars() {
+        match c {
+            ' ' => encoded.push_str("%20"),
+            '#' => encoded.push_str("%23"),
+            '%' => encoded.push_str("%25"),
+            '?' => encoded.push_str("%3F"),
+            c => encoded.push(c),
+        }
+    }
+    Some(encoded)
+}
+
+/**
+ * 把 Markdown（含表格、删除线、任务列表、脚注）转换为完整的 HTML 文档
+ * @param base_dir 相对图片路径的目录，style.base_dir 优先
+ */
+pub fn to_html(markdown: &str, style: &MarkdownStyle, base_dir: Option<&Path>) -> String {
+    let base_dir = style.base_dir.as_deref().map(Path::new).or(base_dir);
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS | Options::ENABLE_FOOTNOTES;
+    let events = Parser::new_ext(markdown, options).map(|event| match event {
+        Event::Start(Tag::Image { link_type, dest_url, title, id }) => {
+            let dest_url = resolve_image(&dest_url, base_dir).map(CowStr::from).unwrap_or(dest_url);
+            Event::Start(Tag::Image { link_type, dest_url, title, id })
+        }
+        event => event,
+    });
+    let mut body = String::new();
+    html::push_html(&mut body, events);
+
+    let stylesheet = style.stylesheet.as_deref().unwrap_or(STYLESHEET);
+    let extra_css = style.extra_css.as_deref().unwrap_or_default();
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}\n{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(style.title.as_deref().unwrap_or_default()),
+        stylesheet,
+        extra_css,
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_tables_and_code_blocks() {
+        let markdown = "# 说明\n\n| 名称 | 数量 |\n|---|--:|\n| A4 | 2 |\n\n```rust\nfn main() {}\n```\n\n- [x] ~~done~~\n";
+        let html = to_html(markdown, &MarkdownStyle { title: Some("a < b".to_string()), ..Default::default() }, None);
+        assert!(html.contains("<title>a &lt; b</title>"));
+        assert!(html.contains("<h1>说明</h1>"));
+        assert!(html.contains("<th>名称</th>"));
+        assert!(html.contains("<td style=\"text-align: right\">2</td>"));
+        assert!(html.contains("<pre><code class=\"language-rust\">fn main() {}\n</code></pre>"));
+        assert!(html.contains("<del>done</del>"));
+        assert!(html.contains("thead { display: table-header-group; }"));
+
+        let style = MarkdownStyle {
+            stylesheet: Some("body { margin: 0; }".to_string()),
+            extra_css: Some("h1 { color: red; }".to_string()),
+            ..Default::default()
+        };
+        let html = to_html("text", &style, None);
+        assert!(html.contains("<style>body { margin: 0; }\nh1 { color: red; }</style>"));
+        assert!(!html.contains("table-header-group"));
+    }
+
+    #[test]
+    fn resolves_relative_images() {
+        let base = Path::new("/notes/help");
+        assert_eq!(resolve_image("img/a b.png", Some(base)).as_deref(), Some("file:///notes/help/img/a%20b.png"));
+        assert_eq!(resolve_image("/var/logo.png", None).as_deref(), Some("file:///var/logo.png"));
+        assert_eq!(resolve_image("C:\\images\\logo.png", None).as_deref(), Some("file:///C:/images/logo.png"));
+        assert_eq!(resolve_image("https://example.com/a.png", Some(base)), None);
+        assert_eq!(resolve_image("data:image/png;base64,AAAA", Some(base)), None);
+        assert_eq!(resolve_image("a.png", None), None);
+
+        let html = to_html("![logo](logo.png \"Logo\")", &MarkdownStyle::default(), Some(base));
+        assert!(html.contains("<img src=\"file:///notes/help/logo.png\" alt=\"logo\" title=\"Logo\" />"));
+        let style = MarkdownStyle { base_dir: Some("/other".to_string()), ..Default::default() };
+        assert!(to_html("![](logo.png)", &style, Some(base)).contains("file:///other/logo.png"));
+    }
+}
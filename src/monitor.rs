@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::status::PrinterStatus;
+
+/// 状态变化时发出的事件
+pub const STATUS_CHANGED_EVENT: &str = "printer://status-changed";
+
+pub const DEFAULT_INTERVAL_MS: u64 = 5000;
+const MIN_INTERVAL_MS: u64 = 500;
+/// 停止时最多等待一个检查间隔
+const STOP_CHECK: Duration = Duration::from_millis(100);
+
+/// `printer://status-changed` 事件的内容
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusChange {
+    pub printer: String,
+    /// 首次查询时为空
+    pub previous: Option<PrinterStatus>,
+    pub current: PrinterStatus,
+}
+
+struct MonitorHandle {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+fn handle() -> MutexGuard<'static, Option<MonitorHandle>> {
+    static HANDLE: OnceLock<Mutex<Option<MonitorHandle>>> = OnceLock::new();
+    HANDLE
+        .get_or_init(|| Mutex::new(None))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// 只比较状态本身，原始描述（如状态字节）变化不算
+fn changed(previous: Option<&PrinterStatus>, current: &PrinterStatus) -> bool {
+    previous.map_or(true, |p| p.state != current.state || p.conditions != current.conditions)
+}
+
+/**
+ * 启动后台监控，已有的监控会先停止
+ * @param poll 查询单台打印机状态
+ * @param on_change 状态变化（包括首次查询）时回调
+ */
+pub fn start<P, F>(printers: Vec<String>, interval_ms: Option<u64>, poll: P, on_change: F)
+where
+    P: Fn(&str) -> PrinterStatus + Send + 'static,
+    F: Fn(StatusChange) + Send + 'static,
+{
+    // 整个过程持有同一把锁，并发调用时不会漏掉正在运行的线程
+    let mut current = handle();
+    if let Some(monitor) = current.take() {
+        shutdown(monitor);
+    }
+
+    let interval = Duration::from_millis(interval_ms.unwrap_or(DEFAULT_INTERVAL_MS).max(MIN_INTERVAL_MS));
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let flag = stop_flag.clone();
+    let thread = thread::spawn(move || {
+        let mut last: HashMap<String, PrinterStatus> = HashMap::new();
+        while !flag.load(Ordering::SeqCst) {
+            let started = Instant::now();
+            for printer in &printers {
+                if flag.load(Ordering::SeqCst) {
+                    return;
+                }
+                let current = poll(printer);
+                let previous = last.get(printer);
+                if changed(previous, &current) {
+                    on_change(StatusChange {
+                        printer: printer.clone(),
+                        previous: previous.cloned(),
+                        current: current.clone(),
+                    });
+                }
+                last.insert(printer.clone(), current);
+            }
+            while started.elapsed() < interval && !flag.load(Ordering::SeqCst) {
+                thread::sleep(STOP_CHECK.min(interval));
+            }
+        }
+    });
+
+    *current = Some(MonitorHandle { stop: stop_flag, thread });
+}
+
+fn shutdown(monitor: MonitorHandle) {
+    monitor.stop.store(true, Ordering::SeqCst);
+    let _ = monitor.thread.join();
+}
+
+/**
+ * 停止后台监控
+ * @returns 之前是否在运行
+ */
+pub fn stop() -> bool {
+    let mut current = handle();
+    let Some(monitor) = current.take() else {
+        return false;
+    };
+    shutdown(monitor);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::PrinterState;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::{mpsc, Barrier};
+
+    /// 监控是全局的，测试之间不能并行
+    fn serial() -> MutexGuard<'static, ()> {
+        static LOCK: Mutex<()> = Mutex::new(());
+        LOCK.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    #[test]
+    fn emits_only_on_change() {
+        let _serial = serial();
+        let polls = Arc::new(AtomicUsize::new(0));
+        let counter = polls.clone();
+        let (sender, receiver) = mpsc::channel();
+
+        // 第 1、2 次空闲，之后缺纸
+        start(
+            vec!["receipt".to_string()],
+            Some(MIN_INTERVAL_MS),
+            move |printer| {
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                let state = if n < 2 { PrinterState::Idle } else { PrinterState::OutOfPaper };
+                PrinterStatus::new(printer, vec![state], Some(format!("poll {}", n)))
+            },
+            move |change| sender.send(change).unwrap(),
+        );
+
+        let first = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(first.previous.is_none());
+        assert_eq!(first.current.state, PrinterState::Idle);
+
+        let second = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(second.previous.unwrap().state, PrinterState::Idle);
+        assert_eq!(second.current.state, PrinterState::OutOfPaper);
+        assert!(polls.load(Ordering::SeqCst) >= 3);
+
+        assert!(stop());
+        assert!(!stop());
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn concurrent_starts_leave_one_monitor() {
+        let _serial = serial();
+        let polls = Arc::new(AtomicUsize::new(0));
+        let barrier = Arc::new(Barrier::new(2));
+
+        let starters: Vec<_> = (0..2)
+            .map(|_| {
+                let polls = polls.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    start(
+                        vec!["receipt".to_string()],
+                        Some(MIN_INTERVAL_MS),
+                        move |printer| {
+                            polls.fetch_add(1, Ordering::SeqCst);
+                            PrinterStatus::new(printer, vec![PrinterState::Idle], None)
+                        },
+                        |_| {},
+                    );
+                })
+            })
+            .collect();
+        for starter in starters {
+            starter.join().unwrap();
+        }
+
+        assert!(stop());
+        assert!(!stop());
+        let after_stop = polls.load(Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(MIN_INTERVAL_MS * 3));
+        assert_eq!(polls.load(Ordering::SeqCst), after_stop);
+    }
+}
@@ -0,0 +1,595 @@
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId};
+use serde::Serialize;
+
+use crate::decrypt::{self, DecryptError};
+use crate::history::now_ms;
+
+/// 处理结果的输出目录，位于系统临时目录下
+pub const SPOOL_DIR: &str = "tauri-plugin-printer-spool";
+
+/// A4，页面没有 MediaBox 时使用
+const DEFAULT_MEDIA_BOX: [i64; 4] = [0, 0, 595, 842];
+
+/// 页面可以从页面树父节点继承的属性
+const INHERITABLE: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+pub fn load(path: &Path) -> Result<Document, String> {
+    let document = Document::load(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if document.is_encrypted() {
+        return Err(format!("{}: 文档已加密", path.display()));
+    }
+    Ok(document)
+}
+
+pub fn save(document: &mut Document, path: &Path) -> Result<(), String> {
+    document.save(path).map(|_| ()).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// 沿 Parent 查找页面继承的属性
+pub fn inherited(document: &Document, page_id: ObjectId, key: &[u8]) -> Option<Object> {
+    let mut node = document.get_dictionary(page_id).ok()?;
+    // 限制层数，避免损坏的文档中 Parent 成环
+    for _ in 0..64 {
+        if let Ok(value) = node.get(key) {
+            return Some(value.clone());
+        }
+        let parent = node.get(b"Parent").and_then(Object::as_reference).ok()?;
+        node = document.get_dictionary(parent).ok()?;
+    }
+    None
+}
+
+/// 页面可见区域与旋转：[x0, y0, 宽, 高]，宽高为旋转前的尺寸
+pub fn page_geometry(document: &Document, page_id: ObjectId) -> ([f64; 4], i64) {
+    let bounds = inherited(document, page_id, b"CropBox")
+        .or_else(|| inherited(document, page_id, b"MediaBox"))
+        .and_then(|b| b.as_array().ok().cloned())
+        .map(|b| b.iter().filter_map(|n| n.as_float().ok()).map(f64::from).collect::<Vec<f64>>())
+        .filter(|b| b.len() == 4)
+        .unwrap_or_else(|| vec![0.0, 0.0, 595.0, 842.0]);
+    let rotate = inherited(document, page_id, b"Rotate").and_then(|r| r.as_i64().ok()).unwrap_or(0);
+    let (x0, y0) = (bounds[0].min(bounds[2]), bounds[1].min(bounds[3]));
+    let (width, height) = ((bounds[2] - bounds[0]).abs(), (bounds[3] - bounds[1]).abs());
+    ([x0, y0, width, height], rotate.rem_euclid(360))
+}
+
+/**
+ * 显示方向（考虑 /Rotate）到页面坐标的变换矩阵
+ * 水印和缩放按页面显示的方向处理，旋转过的页面上内容仍然是正的
+ */
+pub fn display_matrix(bounds: [f64; 4], rotate: i64) -> ([f64; 6], f64, f64) {
+    let [x0, y0, width, height] = bounds;
+    match rotate {
+        90 => ([0.0, 1.0, -1.0, 0.0, x0 + width, y0], height, width),
+        180 => ([-1.0, 0.0, 0.0, -1.0, x0 + width, y0 + height], width, height),
+        270 => ([0.0, -1.0, 1.0, 0.0, x0, y0 + height], height, width),
+        _ => ([1.0, 0.0, 0.0, 1.0, x0, y0], width, height),
+    }
+}
+
+/// 复制页面字典，并补上从父节点继承的属性
+pub fn flatten_page(document: &Document, page_id: ObjectId) -> Result<Dictionary, String> {
+    let mut page = document.get_dictionary(page_id).map_err(|e| e.to_string())?.clone();
+    for key in INHERITABLE {
+        if !page.has(key) {
+            if let Some(value) = inherited(document, page_id, key) {
+                page.set(key, value);
+            }
+        }
+    }
+    Ok(page)
+}
+
+/**
+ * 解析页码范围，按书写顺序返回页码（从 1 开始）
+ * 如 "1-3,5,8-"；"5-3" 表示倒序；空字符串表示全部页面
+ * @param count 文档页数
+ */
+pub fn parse_pages(spec: &str, count: u32) -> Result<Vec<u32>, String> {
+    if spec.trim().is_empty() {
+        return Ok((1..=count).collect());
+    }
+    let mut pages = Vec::new();
+    for part in spec.split(',').map(str::trim) {
+        let parse = |value: &str, default: u32| -> Result<u32, String> {
+            let value = value.trim();
+            if value.is_empty() {
+                return Ok(default);
+            }
+            let page: u32 = value.parse().map_err(|_| format!("无效的页码: {}", part))?;
+            if page == 0 || page > count {
+                return Err(format!("页码超出范围 1-{}: {}", count, part));
+            }
+            Ok(page)
+        };
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (parse(start, 1)?, parse(end, count)?);
+                if start <= end {
+                    pages.extend(start..=end);
+                } else {
+                    pages.extend((end..=start).rev());
+                }
+            }
+            None if part.is_empty() => return Err(format!("无效的页码范围: {}", spec)),
+            None => pages.push(parse(part, 0)?),
+        }
+    }
+    Ok(pages)
+}
+
+pub fn page_ids(document: &Document, pages: &[u32]) -> Result<Vec<ObjectId>, String> {
+    let all = document.get_pages();
+    pages
+        .iter()
+        .map(|n| all.get(n).copied().ok_or_else(|| format!("页码超出范围 1-{}: {}", all.len(), n)))
+        .collect()
+}
+
+/**
+ * 按给定顺序重建页面树，用于提取、删除和重排页面
+ * 页码可以重复，未选中的页面从文档中删除
+ */
+pub fn select_pages(document: &mut Document, pages: &[u32]) -> Result<(), String> {
+    if pages.is_empty() {
+        return Err("没有选中任何页面".to_string());
+    }
+    let ids = page_ids(document, pages)?;
+    let flattened = ids.iter().map(|&id| flatten_page(document, id)).collect::<Result<Vec<_>, _>>()?;
+    let pages_id = document.new_object_id();
+    let mut used = HashSet::new();
+    let mut kids: Vec<Object> = Vec::new();
+    for (id, mut page) in ids.into_iter().zip(flattened) {
+        page.set("Parent", pages_id);
+        // 重复的页面复制一份新的页面对象，内容流共用
+        let target = if used.insert(id) { id } else { document.new_object_id() };
+        document.objects.insert(target, Object::Dictionary(page));
+        kids.push(target.into());
+    }
+    let count = kids.len() as i64;
+    document.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! { "Type" => "Pages", "Kids" => kids, "Count" => count }),
+    );
+    let catalog = document.catalog_mut().map_err(|e| e.to_string())?;
+    catalog.set("Pages", pages_id);
+    // 书签可能指向删除的页面
+    catalog.remove(b"Outlines");
+    document.prune_objects();
+    Ok(())
+}
+
+/// 删除页面，不能删除全部页面
+pub fn remove_pages(document: &mut Document, pages: &[u32]) -> Result<(), String> {
+    let count = document.get_pages().len() as u32;
+    let keep: Vec<u32> = (1..=count).filter(|n| !pages.contains(n)).collect();
+    page_ids(document, pages)?;
+    select_pages(document, &keep)
+}
+
+/// 顺时针旋转页面，角度为 90 的倍数，可为负数
+pub fn rotate_pages(document: &mut Document, pages: &[u32], degrees: i64) -> Result<(), String> {
+    if degrees % 90 != 0 {
+        return Err(format!("旋转角度必须是 90 的倍数: {}", degrees));
+    }
+    for id in page_ids(document, pages)? {
+        let current = inherited(document, id, b"Rotate").and_then(|r| r.as_i64().ok()).unwrap_or(0);
+        let page = document.get_dictionary_mut(id).map_err(|e| e.to_string())?;
+        page.set("Rotate", (current + degrees).rem_euclid(360));
+    }
+    Ok(())
+}
+
+/**
+ * 在输出目录中生成不重名的文件路径
+ * 输出文件不会自动删除，由调用方在打印后删除
+ * @param stem 文件名前缀，通常是源文件名
+ */
+pub fn spool_path(stem: &str) -> Result<PathBuf, String> {
+    static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+    let dir = env::temp_dir().join(SPOOL_DIR);
+    fs::create_dir_all(&dir).map_err(|e| format!("创建 {} 失败: {}", dir.display(), e))?;
+    let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    Ok(dir.join(format!("{}-{}-{}.pdf", stem, now_ms(), sequence)))
+}
+
+/// 源文件名（不含扩展名），用作输出文件名前缀
+pub fn file_stem(path: &Path) -> String {
+    path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "document".to_string())
+}
+
+/// 新建文档的页面树和目录，kids 中的页面以 pages_id 为 Parent
+pub fn set_pages(document: &mut Document, pages_id: ObjectId, kids: Vec<Object>) {
+    let count = kids.len() as i64;
+    document.objects.insert(pages_id, Object::Dictionary(dictionary! { "Type" => "Pages", "Kids" => kids, "Count" => count }));
+    let catalog_id = document.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+    document.trailer.set("Root", catalog_id);
+}
+
+/**
+ * 按顺序合并多个文档
+ * @param separator 为 true 时在文档之间插入一张空白页，尺寸与下一个文档的首页相同
+ */
+pub fn merge(documents: Vec<Document>, separator: bool) -> Result<Document, String> {
+    let mut merged = Document::with_version("1.7");
+    let pages_id = merged.new_object_id();
+    let mut kids: Vec<Object> = Vec::new();
+
+    for (index, mut document) in documents.into_iter().enumerate() {
+        document.renumber_objects_with(merged.max_id + 1);
+        let pages = document.get_pages();
+        if pages.is_empty() {
+            continue;
+        }
+
+        let mut page_objects = Vec::new();
+        for page_id in pages.into_values() {
+            let mut page = flatten_page(&document, page_id)?;
+            page.set("Parent", pages_id);
+            page_objects.push((page_id, page));
+        }
+
+        // 原文档的目录和页面树节点由新的页面树代替
+        merged.max_id = merged.max_id.max(document.objects.keys().map(|id| id.0).max().unwrap_or(0));
+        for (id, object) in document.objects {
+            if !matches!(object.type_name(), Ok(b"Catalog") | Ok(b"Pages")) {
+                merged.objects.insert(id, object);
+            }
+        }
+
+        if separator && index > 0 && !kids.is_empty() {
+            let media_box = page_objects[0]
+                .1
+                .get(b"MediaBox")
+                .cloned()
+                .unwrap_or_else(|_| DEFAULT_MEDIA_BOX.iter().map(|&n| Object::Integer(n)).collect::<Vec<_>>().into());
+            let blank = merged.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "MediaBox" => media_box,
+                "Resources" => dictionary! {},
+            });
+            kids.push(blank.into());
+        }
+        for (id, page) in page_objects {
+            merged.objects.insert(id, Object::Dictionary(page));
+            kids.push(id.into());
+        }
+    }
+
+    if kids.is_empty() {
+        return Err("没有可合并的页面".to_string());
+    }
+    set_pages(&mut merged, pages_id, kids);
+    Ok(merged)
+}
+
+/// 合并文件并保存
+pub fn merge_files(inputs: &[&Path], separator: bool, output: &Path) -> Result<(), String> {
+    let documents = inputs.iter().map(|path| load(path)).collect::<Result<Vec<_>, _>>()?;
+    let mut merged = merge(documents, separator)?;
+    save(&mut merged, output)
+}
+
+/**
+ * 拆分文档，每个页码范围生成一个文件
+ * @param ranges 页码范围，为空时每页一个文件
+ * @returns 输出文件路径
+ */
+pub fn split_file(path: &Path, ranges: &[String]) -> Result<Vec<PathBuf>, String> {
+    let document = load(path)?;
+    let count = document.get_pages().len() as u32;
+    let ranges: Vec<String> =
+        if ranges.is_empty() { (1..=count).map(|n| n.to_string()).collect() } else { ranges.to_vec() };
+    let stem = file_stem(path);
+    let mut outputs = Vec::new();
+    for range in ranges {
+        let mut part = document.clone();
+        select_pages(&mut part, &parse_pages(&range, count)?)?;
+        let output = spool_path(&stem)?;
+        save(&mut part, &output)?;
+        outputs.push(output);
+    }
+    Ok(outputs)
+}
+
+/**
+ * 读取文档，修改后保存到输出目录
+ * @returns 输出文件路径
+ */
+pub fn transform_file(
+    path: &Path,
+    transform: impl FnOnce(&mut Document, u32) -> Result<(), String>,
+) -> Result<PathBuf, String> {
+    let mut document = load(path)?;
+    let count = document.get_pages().len() as u32;
+    transform(&mut document, count)?;
+    let output = spool_path(&file_stem(path))?;
+    save(&mut document, &output)?;
+    Ok(output)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PageOrientation {
+    Portrait,
+    Landscape,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PdfPage {
+    pub number: u32,
+    /// [x0, y0, x1, y1]，单位 pt
+    pub media_box: [f64; 4],
+    pub rotate: i64,
+    /// 按显示方向（考虑 rotate）判断，正方形视为纵向
+    pub orientation: PageOrientation,
+}
+
+/// PDF 文档信息，valid 为 false 时 error 为原因
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PdfInfo {
+    pub valid: bool,
+    pub error: Option<String>,
+    pub version: Option<String>,
+    pub pages: u32,
+    pub page_sizes: Vec<PdfPage>,
+    pub encrypted: bool,
+    /// 加密且没有提供正确的打开密码，页面和元数据无法读取
+    pub locked: bool,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub subject: Option<String>,
+    pub creator: Option<String>,
+    pub producer: Option<String>,
+}
+
+/// 页面的 MediaBox，缺失或无效时为 A4
+fn media_box(document: &Document, page_id: ObjectId) -> [f64; 4] {
+    let values: Vec<f64> = inherited(document, page_id, b"MediaBox")
+        .and_then(|b| b.as_array().ok().cloned())
+        .map(|b| b.iter().filter_map(|n| n.as_float().ok()).map(f64::from).collect())
+        .unwrap_or_default();
+    match values[..] {
+        [x0, y0, x1, y1] if x0 != x1 && y0 != y1 => [x0, y0, x1, y1],
+        _ => DEFAULT_MEDIA_BOX.map(|n| n as f64),
+    }
+}
+
+/// 文档信息字典中的文本，支持 UTF-16BE（带 BOM）和 PDFDocEncoding 中的 ASCII/Latin-1 部分
+fn info_text(info: &Dictionary, key: &[u8]) -> Option<String> {
+    let bytes = info.get(key).and_then(Object::as_str).ok()?;
+    let text = if let Some(utf16) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = utf16.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+        String::from_utf16_lossy(&units)
+    } else if let Some(utf8) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        String::from_utf8_lossy(utf8).to_string()
+    } else {
+        bytes.iter().map(|&b| b as char).collect()
+    };
+    let text = text.trim_matches(char::from(0)).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/**
+ * 检查文档并读取页数、页面尺寸和元数据
+ * 文件不是 PDF 或结构损坏时返回 valid 为 false 的结果，不返回错误
+ * @param password 加密文档的打开密码，只设置了权限密码的文档不需要
+ */
+pub fn inspect(path: &Path, password: Option<&str>) -> PdfInfo {
+    let invalid = |error: String| PdfInfo { error: Some(error), ..PdfInfo::default() };
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => return invalid(e.to_string()),
+    };
+    // 规范允许文件头前有少量其他字节
+    if !bytes[..bytes.len().min(1024)].windows(5).any(|w| w == b"%PDF-") {
+        return invalid("不是 PDF 文件".to_string());
+    }
+    let mut document = match Document::load_mem(&bytes) {
+        Ok(document) => document,
+        Err(e) => return invalid(format!("文档结构损坏: {}", e)),
+    };
+    let encrypted = document.is_encrypted();
+    let version = Some(document.version.clone());
+    if encrypted {
+        // 加密文档的对象流要解密后才能读取
+        document = match decrypt::decrypt(path, password) {
+            Ok(document) => document,
+            Err(DecryptError::Failed(e)) => return invalid(e),
+            Err(_) => return PdfInfo { valid: true, version, encrypted, locked: true, ..PdfInfo::default() },
+        };
+    }
+    let pages = document.get_pages();
+    let mut info = PdfInfo { version, pages: pages.len() as u32, encrypted, ..PdfInfo::default() };
+    if document.catalog().and_then(|c| c.get(b"Pages")).is_err() {
+        info.error = Some("文档缺少页面树".to_string());
+        return info;
+    }
+    if pages.is_empty() {
+        info.error = Some("文档没有页面".to_string());
+        return info;
+    }
+    info.page_sizes = pages
+        .iter()
+        .map(|(&number, &page_id)| {
+            let media_box = media_box(&document, page_id);
+            let rotate = inherited(&document, page_id, b"Rotate").and_then(|r| r.as_i64().ok()).unwrap_or(0);
+            let (width, height) = ((media_box[2] - media_box[0]).abs(), (media_box[3] - media_box[1]).abs());
+            let sideways = rotate.rem_euclid(180) == 90;
+            let orientation = if (width > height) != sideways && width != height {
+                PageOrientation::Landscape
+            } else {
+                PageOrientation::Portrait
+            };
+            PdfPage { number, media_box, rotate: rotate.rem_euclid(360), orientation }
+        })
+        .collect();
+    let metadata = document
+        .trailer
+        .get(b"Info")
+        .and_then(|info| match info {
+            Object::Reference(id) => document.get_dictionary(*id),
+            _ => info.as_dict(),
+        })
+        .ok();
+    if let Some(metadata) = metadata {
+        info.title = info_text(metadata, b"Title");
+        info.author = info_text(metadata, b"Author");
+        info.subject = info_text(metadata, b"Subject");
+        info.creator = info_text(metadata, b"Creator");
+        info.producer = info_text(metadata, b"Producer");
+    }
+    info.valid = true;
+    info
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// 生成指定页数的测试文档，页面尺寸放在页面树根节点上，由页面继承
+    pub(crate) fn sample(pages: usize, media_box: [i64; 4]) -> Document {
+        let mut document = Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+        let kids: Vec<Object> = (0..pages)
+            .map(|_| {
+                document
+                    .add_object(dictionary! { "Type" => "Page", "Parent" => pages_id })
+                    .into()
+            })
+            .collect();
+        let media_box: Vec<Object> = media_box.iter().map(|&n| Object::Integer(n)).collect();
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => kids,
+                "Count" => pages as i64,
+                "MediaBox" => media_box,
+            }),
+        );
+        let catalog_id = document.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        document.trailer.set("Root", catalog_id);
+        document
+    }
+
+    fn media_width(document: &Document, page_id: ObjectId) -> i64 {
+        let media_box = document.get_dictionary(page_id).unwrap().get(b"MediaBox").unwrap().as_array().unwrap();
+        media_box[2].as_i64().unwrap()
+    }
+
+    #[test]
+    fn merges_in_order_with_separators() {
+        let documents = vec![sample(2, [0, 0, 595, 842]), sample(1, [0, 0, 612, 792]), sample(3, [0, 0, 420, 595])];
+        let mut merged = merge(documents, true).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("merged.pdf");
+        save(&mut merged, &path).unwrap();
+        let reloaded = load(&path).unwrap();
+        let pages: Vec<ObjectId> = reloaded.get_pages().into_values().collect();
+        // 2 + 空白 + 1 + 空白 + 3
+        assert_eq!(pages.len(), 8);
+        let widths: Vec<i64> = pages.iter().map(|&id| media_width(&reloaded, id)).collect();
+        assert_eq!(widths, vec![595, 595, 612, 612, 420, 420, 420, 420]);
+        assert!(!reloaded.get_dictionary(pages[2]).unwrap().has(b"Contents"));
+
+        let merged = merge(vec![sample(1, [0, 0, 595, 842]), sample(1, [0, 0, 595, 842])], false).unwrap();
+        assert_eq!(merged.get_pages().len(), 2);
+        assert!(merge(Vec::new(), false).is_err());
+    }
+
+    #[test]
+    fn parses_page_ranges() {
+        assert_eq!(parse_pages("1-3,5,8-", 9).unwrap(), vec![1, 2, 3, 5, 8, 9]);
+        assert_eq!(parse_pages("3-1, -2", 4).unwrap(), vec![3, 2, 1, 1, 2]);
+        assert_eq!(parse_pages("", 3).unwrap(), vec![1, 2, 3]);
+        for invalid in ["0", "5", "1-5", "a", "2,,3"] {
+            assert!(parse_pages(invalid, 4).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn reorders_removes_and_rotates_pages() {
+        // 用 MediaBox 宽度区分页面
+        let mut document =
+            merge((1..=4).map(|n| sample(1, [0, 0, n * 100, 842])).collect(), false).unwrap();
+        let widths = |document: &Document| -> Vec<i64> {
+            document.get_pages().into_values().map(|id| media_width(document, id)).collect()
+        };
+
+        select_pages(&mut document, &[4, 1, 1]).unwrap();
+        assert_eq!(widths(&document), vec![400, 100, 100]);
+        // 未选中的页面被删除
+        assert_eq!(document.objects.values().filter(|o| o.type_name().ok() == Some(b"Page")).count(), 3);
+
+        remove_pages(&mut document, &[1]).unwrap();
+        assert_eq!(widths(&document), vec![100, 100]);
+        assert!(remove_pages(&mut document, &[1, 2]).is_err());
+        assert!(select_pages(&mut document, &[3]).is_err());
+
+        rotate_pages(&mut document, &[2], -90).unwrap();
+        rotate_pages(&mut document, &[2], 180).unwrap();
+        let pages: Vec<ObjectId> = document.get_pages().into_values().collect();
+        let rotation = |id| document.get_dictionary(id).unwrap().get(b"Rotate").and_then(Object::as_i64).ok();
+        assert_eq!((rotation(pages[0]), rotation(pages[1])), (None, Some(90)));
+        assert!(rotate_pages(&mut document, &[1], 45).is_err());
+    }
+
+    #[test]
+    fn inspects_pages_and_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.pdf");
+        let mut document = merge(vec![sample(1, [0, 0, 595, 842]), sample(1, [0, 0, 842, 595])], false).unwrap();
+        rotate_pages(&mut document, &[2], 90).unwrap();
+        let title = Object::String(
+            [0xFE, 0xFF, 0x62, 0xA5, 0x88, 0x68].to_vec(),
+            lopdf::StringFormat::Hexadecimal,
+        );
+        let info_id = document.add_object(dictionary! { "Title" => title, "Author" => Object::string_literal("Li Lei") });
+        document.trailer.set("Info", info_id);
+        save(&mut document, &path).unwrap();
+
+        let info = inspect(&path, None);
+        assert!(info.valid, "{:?}", info.error);
+        assert_eq!((info.pages, info.encrypted), (2, false));
+        assert_eq!(info.title.as_deref(), Some("报表"));
+        assert_eq!(info.author.as_deref(), Some("Li Lei"));
+        assert_eq!(info.page_sizes[0].media_box, [0.0, 0.0, 595.0, 842.0]);
+        assert_eq!(info.page_sizes[0].orientation, PageOrientation::Portrait);
+        // 横向页面旋转 90 度后按纵向显示
+        assert_eq!((info.page_sizes[1].rotate, info.page_sizes[1].orientation), (90, PageOrientation::Portrait));
+
+        let text = dir.path().join("notes.pdf");
+        fs::write(&text, "hello").unwrap();
+        assert_eq!(inspect(&text, None).error.as_deref(), Some("不是 PDF 文件"));
+        fs::write(&text, "%PDF-1.4\ngarbage").unwrap();
+        assert!(!inspect(&text, None).valid);
+        assert!(!inspect(&dir.path().join("missing.pdf"), None).valid);
+    }
+
+    #[test]
+    fn splits_into_spool_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.pdf");
+        save(&mut sample(3, [0, 0, 595, 842]), &path).unwrap();
+
+        let parts = split_file(&path, &["1-2".to_string(), "3".to_string()]).unwrap();
+        let counts: Vec<usize> = parts.iter().map(|p| load(p).unwrap().get_pages().len()).collect();
+        assert_eq!(counts, vec![2, 1]);
+        assert!(parts[0].starts_with(env::temp_dir().join(SPOOL_DIR)));
+        assert!(file_stem(&parts[0]).starts_with("report-"));
+        let pages = split_file(&path, &[]).unwrap();
+        assert_eq!(pages.len(), 3);
+        for part in parts.into_iter().chain(pages) {
+            fs::remove_file(part).unwrap();
+        }
+    }
+}
@@ -0,0 +1,559 @@
+use std::collections::HashMap;
+
+use crate::capabilities::{same_size, standard_media_size};
+use crate::declare::{ColorMode, DuplexMode, MediaSize, PrintSettings, PrinterCapabilities, Resolution};
+
+/// CUPS 队列的 PPD 所在目录
+pub const CUPS_PPD_DIR: &str = "/etc/cups/ppd";
+
+/// CUPS 过滤器可以直接处理的格式
+const CUPS_FORMATS: [&str; 6] = [
+    "application/pdf",
+    "application/postscript",
+    "text/plain",
+    "image/jpeg",
+    "image/png",
+    "application/vnd.cups-raw",
+];
+
+/// PPD 中的可选值，如 `*PageSize A4/A4: "..."`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PpdChoice {
+    pub name: String,
+    pub text: String,
+}
+
+/// `*OpenUI` 声明的选项
+#[derive(Debug, Clone, PartialEq)]
+pub struct PpdOption {
+    pub keyword: String,
+    pub text: String,
+    /// PickOne / PickMany / Boolean
+    pub ui: String,
+    pub default: Option<String>,
+    pub choices: Vec<PpdChoice>,
+}
+
+impl PpdOption {
+    /// 按名称或显示文本查找可选值，不区分大小写
+    pub fn choice(&self, name: &str) -> Option<&PpdChoice> {
+        self.choices
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name))
+            .or_else(|| self.choices.iter().find(|c| c.text.eq_ignore_ascii_case(name)))
+    }
+}
+
+/// `*UIConstraints: *Duplex *InputSlot Envelope`，choice 为空表示除关闭外的任意值
+#[derive(Debug, Clone, PartialEq)]
+pub struct PpdConstraint {
+    pub option1: String,
+    pub choice1: Option<String>,
+    pub option2: String,
+    pub choice2: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Ppd {
+    pub manufacturer: Option<String>,
+    pub model_name: Option<String>,
+    pub nick_name: Option<String>,
+    pub color_device: bool,
+    pub options: Vec<PpdOption>,
+    /// 纸张名称到尺寸（点，1/72 英寸）
+    pub paper_dimensions: HashMap<String, (f64, f64)>,
+    pub constraints: Vec<PpdConstraint>,
+}
+
+/// 一条 PPD 主关键字语句
+struct Statement {
+    keyword: String,
+    option: Option<(String, String)>,
+    value: String,
+}
+
+/**
+ * 解析 PPD 文件
+ *
+ * 只处理能力查询和选项映射需要的关键字，其余内容（如 PostScript 代码）会被忽略
+ */
+pub fn parse(text: &str) -> Ppd {
+    let mut ppd = Ppd::default();
+    let mut defaults: HashMap<String, String> = HashMap::new();
+    let mut choices: Vec<(String, PpdChoice)> = Vec::new();
+
+    for statement in statements(text) {
+        let keyword = statement.keyword.as_str();
+        match keyword {
+            "Manufacturer" => ppd.manufacturer = Some(unquote(&statement.value)),
+            "ModelName" => ppd.model_name = Some(unquote(&statement.value)),
+            "NickName" => ppd.nick_name = Some(unquote(&statement.value)),
+            "ColorDevice" => ppd.color_device = statement.value.trim().eq_ignore_ascii_case("True"),
+            "OpenUI" | "JCLOpenUI" => {
+                if let Some((name, text)) = statement.option {
+                    let keyword = name.trim_start_matches('*').to_string();
+                    let text = if text.is_empty() { keyword.clone() } else { text };
+                    ppd.options.push(PpdOption {
+                        keyword,
+                        text,
+                        ui: statement.value.trim().to_string(),
+                        default: None,
+                        choices: Vec::new(),
+                    });
+                }
+            }
+            "UIConstraints" | "NonUIConstraints" => {
+                if let Some(constraint) = parse_constraint(&statement.value) {
+                    ppd.constraints.push(constraint);
+                }
+            }
+            "PaperDimension" => {
+                if let Some((name, _)) = &statement.option {
+                    let numbers: Vec<f64> = unquote(&statement.value)
+                        .split_whitespace()
+                        .filter_map(|n| n.parse().ok())
+                        .collect();
+                    if let [width, height] = numbers[..] {
+                        ppd.paper_dimensions.insert(name.clone(), (width, height));
+                    }
+                }
+            }
+            _ => {
+                if let Some(option) = keyword.strip_prefix("Default") {
+                    defaults.insert(option.to_string(), statement.value.trim().to_string());
+                } else if let Some((name, text)) = statement.option {
+                    let text = if text.is_empty() { name.clone() } else { text };
+                    choices.push((keyword.to_string(), PpdChoice { name, text }));
+                }
+            }
+        }
+    }
+
+    for option in ppd.options.iter_mut() {
+        option.default = defaults.get(&option.keyword).cloned();
+        option.choices = choices
+            .iter()
+            .filter(|(keyword, _)| *keyword == option.keyword)
+            .map(|(_, choice)| choice.clone())
+            .collect();
+    }
+    ppd
+}
+
+/// 拆分语句，引号内的值可以跨行
+fn statements(text: &str) -> Vec<Statement> {
+    let mut result = Vec::new();
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        let Some(line) = line.strip_prefix('*') else {
+            continue;
+        };
+        if line.starts_with('%') || line.starts_with("End") {
+            continue;
+        }
+        let Some((head, value)) = split_head(line) else {
+            continue;
+        };
+
+        let mut value = value.trim_start().to_string();
+        if value.starts_with('"') && value.matches('"').count() < 2 {
+            for next in lines.by_ref() {
+                value.push('\n');
+                value.push_str(next);
+                if next.contains('"') {
+                    break;
+                }
+            }
+        }
+
+        let (keyword, option) = match head.split_once(char::is_whitespace) {
+            Some((keyword, option)) => {
+                let option = option.trim();
+                let (name, text) = option.split_once('/').unwrap_or((option, ""));
+                (keyword, Some((name.trim().to_string(), text.trim().to_string())))
+            }
+            None => (head, None),
+        };
+        result.push(Statement { keyword: keyword.to_string(), option, value });
+    }
+    result
+}
+
+/// 在第一个不属于选项名的冒号处分割（选项名本身可能带有 `*`）
+fn split_head(line: &str) -> Option<(&str, &str)> {
+    let index = line.find(':')?;
+    Some((line[..index].trim_end(), &line[index + 1..]))
+}
+
+fn unquote(value: &str) -> String {
+    value.trim().trim_matches('"').to_string()
+}
+
+fn parse_constraint(value: &str) -> Option<PpdConstraint> {
+    let mut tokens = value.split_whitespace().peekable();
+    let mut next_pair = || {
+        let option = tokens.next()?.strip_prefix('*')?.to_string();
+        let choice = match tokens.peek() {
+            Some(token) if !token.starts_with('*') => tokens.next().map(str::to_string),
+            _ => None,
+        };
+        Some((option, choice))
+    };
+    let (option1, choice1) = next_pair()?;
+    let (option2, choice2) = next_pair()?;
+    Some(PpdConstraint { option1, choice1, option2, choice2 })
+}
+
+/// 表示“关闭”的选项值，约束中未指定值时这些值不算冲突
+fn is_off(choice: &str) -> bool {
+    ["None", "False", "Off"].iter().any(|off| off.eq_ignore_ascii_case(choice))
+}
+
+fn points_to_mm(points: f64) -> f64 {
+    (points * 25.4 / 72.0 * 10.0).round() / 10.0
+}
+
+/// 解析 `600dpi`、`600x1200dpi`
+fn parse_resolution(choice: &str) -> Option<Resolution> {
+    let value = choice.to_ascii_lowercase();
+    let value = value.strip_suffix("dpi")?;
+    let (x, y) = value.split_once('x').unwrap_or((value, value));
+    Some(Resolution { x_dpi: x.parse().ok()?, y_dpi: y.parse().ok()? })
+}
+
+fn is_gray_choice(choice: &PpdChoice) -> bool {
+    let name = choice.name.to_ascii_lowercase();
+    ["gray", "grey", "mono", "black"].iter().any(|k| name.contains(k))
+}
+
+impl Ppd {
+    pub fn option(&self, keyword: &str) -> Option<&PpdOption> {
+        self.options.iter().find(|o| o.keyword == keyword)
+    }
+
+    fn page_size_mm(&self, name: &str) -> Option<(f64, f64)> {
+        self.paper_dimensions
+            .get(name)
+            .map(|(width, height)| (points_to_mm(*width), points_to_mm(*height)))
+    }
+
+    /**
+     * 从 PPD 中提取打印机能力
+     */
+    pub fn capabilities(&self, printer: &str) -> PrinterCapabilities {
+        let choices = |keyword: &str| self.option(keyword).map(|o| o.choices.clone()).unwrap_or_default();
+
+        let media_sizes = choices("PageSize")
+            .into_iter()
+            .map(|choice| {
+                let (width_mm, height_mm) = self.page_size_mm(&choice.name).unzip();
+                MediaSize { name: choice.name, width_mm, height_mm }
+            })
+            .collect();
+
+        let mut duplex_modes = vec!["one-sided".to_string()];
+        for choice in choices("Duplex") {
+            let mode = match choice.name.as_str() {
+                "DuplexNoTumble" => DuplexMode::TwoSidedLongEdge,
+                "DuplexTumble" => DuplexMode::TwoSidedShortEdge,
+                _ => continue,
+            };
+            duplex_modes.push(mode.as_str().to_string());
+        }
+
+        let color_choices = choices("ColorModel");
+        let mut color_modes = vec![ColorMode::Monochrome.as_str().to_string()];
+        if self.color_device || color_choices.iter().any(|c| !is_gray_choice(c)) {
+            color_modes.push(ColorMode::Color.as_str().to_string());
+        }
+
+        PrinterCapabilities {
+            printer: printer.to_string(),
+            media_sizes,
+            default_media: self.option("PageSize").and_then(|o| o.default.clone()),
+            trays: choices("InputSlot").into_iter().map(|c| c.name).collect(),
+            duplex_modes,
+            color_modes,
+            resolutions: choices("Resolution").iter().filter_map(|c| parse_resolution(&c.name)).collect(),
+            max_copies: None,
+            document_formats: CUPS_FORMATS.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    /**
+     * 检查选中的选项是否违反 UIConstraints，未选中的选项按默认值计算
+     * @param selected (关键字, 值) 列表
+     */
+    pub fn conflicts(&self, selected: &[(String, String)]) -> Vec<&PpdConstraint> {
+        let value = |keyword: &str| {
+            selected
+                .iter()
+                .find(|(k, _)| k == keyword)
+                .map(|(_, v)| v.clone())
+                .or_else(|| self.option(keyword).and_then(|o| o.default.clone()))
+        };
+        let matches = |keyword: &str, choice: &Option<String>| match (value(keyword), choice) {
+            (Some(value), Some(choice)) => value == *choice,
+            (Some(value), None) => !is_off(&value),
+            (None, _) => false,
+        };
+
+        self.constraints
+            .iter()
+            .filter(|c| matches(&c.option1, &c.choice1) && matches(&c.option2, &c.choice2))
+            // 只报告至少涉及一个显式选择的冲突，默认值之间的冲突由驱动自行处理
+            .filter(|c| selected.iter().any(|(k, _)| *k == c.option1 || *k == c.option2))
+            .collect()
+    }
+
+    /**
+     * 将打印参数转换为 CUPS 的 `-o key=value` 选项
+     * 打印机不支持或违反约束时返回错误
+     */
+    pub fn to_cups_options(&self, settings: &PrintSettings) -> Result<Vec<String>, String> {
+        let mut selected: Vec<(String, String)> = Vec::new();
+
+        if let Some(page_size) = &settings.page_size {
+            selected.push(("PageSize".to_string(), self.page_size_choice(page_size)?));
+        }
+        if let Some(duplex) = settings.duplex {
+            let name = match duplex {
+                DuplexMode::OneSided => "None",
+                DuplexMode::TwoSidedLongEdge => "DuplexNoTumble",
+                DuplexMode::TwoSidedShortEdge => "DuplexTumble",
+            };
+            match self.option("Duplex").and_then(|o| o.choice(name)) {
+                Some(choice) => selected.push(("Duplex".to_string(), choice.name.clone())),
+                None if duplex == DuplexMode::OneSided => {}
+                None => return Err(format!("打印机不支持双面打印: {}", duplex.as_str())),
+            }
+        }
+        if let Some(color) = settings.color {
+            if let Some(option) = self.option("ColorModel") {
+                let choice = option
+                    .choices
+                    .iter()
+                    .find(|c| is_gray_choice(c) == (color == ColorMode::Monochrome))
+                    .ok_or(format!("打印机不支持颜色模式: {}", color.as_str()))?;
+                selected.push(("ColorModel".to_string(), choice.name.clone()));
+            } else if color == ColorMode::Color && !self.color_device {
+                return Err("打印机不支持彩色打印".to_string());
+            }
+        }
+        if let Some(tray) = &settings.tray {
+            let choice = self
+                .option("InputSlot")
+                .and_then(|o| o.choice(tray))
+                .ok_or(format!("打印机没有纸盒: {}", tray))?;
+            selected.push(("InputSlot".to_string(), choice.name.clone()));
+        }
+        if let Some(dpi) = settings.resolution {
+            let choice = self
+                .option("Resolution")
+                .and_then(|o| {
+                    o.choices
+                        .iter()
+                        .find(|c| parse_resolution(&c.name).is_some_and(|r| r.x_dpi == dpi))
+                })
+                .ok_or(format!("打印机不支持分辨率: {}dpi", dpi))?;
+            selected.push(("Resolution".to_string(), choice.name.clone()));
+        }
+
+        if let Some(conflict) = self.conflicts(&selected).first() {
+            let describe = |option: &str, choice: &Option<String>| match choice {
+                Some(choice) => format!("{}={}", option, choice),
+                None => option.to_string(),
+            };
+            return Err(format!(
+                "选项冲突: {} 与 {}",
+                describe(&conflict.option1, &conflict.choice1),
+                describe(&conflict.option2, &conflict.choice2)
+            ));
+        }
+
+        let mut options: Vec<String> = selected.into_iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        if let Some(copies) = settings.copies.filter(|c| *c > 1) {
+            options.push(format!("copies={}", copies));
+        }
+        Ok(options)
+    }
+
+    /// 先按名称匹配 PageSize，再按尺寸匹配（如 "A4" 对应 "iso_a4_210x297mm"）
+    fn page_size_choice(&self, page_size: &str) -> Result<String, String> {
+        let option = self.option("PageSize").ok_or("PPD 中没有 PageSize 选项")?;
+        if let Some(choice) = option.choice(page_size) {
+            return Ok(choice.name.clone());
+        }
+        let size = standard_media_size(page_size).ok_or(format!("打印机不支持纸张: {}", page_size))?;
+        option
+            .choices
+            .iter()
+            .find(|c| self.page_size_mm(&c.name).is_some_and(|s| same_size(s, size)))
+            .map(|c| c.name.clone())
+            .ok_or(format!("打印机不支持纸张: {}", page_size))
+    }
+}
+
+/**
+ * 读取 CUPS 队列的 PPD
+ */
+pub fn load_queue(printer: &str) -> Result<Option<Ppd>, String> {
+    let path = std::path::Path::new(CUPS_PPD_DIR).join(format!("{}.ppd", printer));
+    if !path.exists() {
+        return Ok(None);
+    }
+    let bytes = std::fs::read(&path).map_err(|e| format!("读取 {} 失败: {}", path.display(), e))?;
+    Ok(Some(parse(&String::from_utf8_lossy(&bytes))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LASER_PPD: &str = r#"*PPD-Adobe: "4.3"
+*% Sample office laser
+*Manufacturer: "HP"
+*ModelName: "HP LaserJet M404"
+*NickName: "HP LaserJet M404, hpcups 3.22"
+*ColorDevice: False
+
+*OpenUI *PageSize/Media Size: PickOne
+*OrderDependency: 10 AnySetup *PageSize
+*DefaultPageSize: Letter
+*PageSize Letter/US Letter: "<</PageSize[612 792]/ImagingBBox null>>setpagedevice"
+*PageSize A4/A4: "<</PageSize[595 842]
+/ImagingBBox null>>setpagedevice"
+*PageSize Env10/Envelope #10: "<</PageSize[297 684]/ImagingBBox null>>setpagedevice"
+*CloseUI: *PageSize
+
+*PaperDimension Letter/US Letter: "612 792"
+*PaperDimension A4/A4: "595 842"
+*PaperDimension Env10/Envelope #10: "297 684"
+
+*OpenUI *InputSlot/Media Source: PickOne
+*DefaultInputSlot: Auto
+*InputSlot Auto/Automatic: ""
+*InputSlot Tray1/Tray 1: ""
+*InputSlot Manual/Manual Feed: ""
+*CloseUI: *InputSlot
+
+*OpenUI *Duplex/2-Sided Printing: PickOne
+*DefaultDuplex: None
+*Duplex None/Off: ""
+*Duplex DuplexNoTumble/Long Edge: ""
+*Duplex DuplexTumble/Short Edge: ""
+*CloseUI: *Duplex
+
+*OpenUI *Resolution/Resolution: PickOne
+*DefaultResolution: 600dpi
+*Resolution 600dpi/600 DPI: ""
+*Resolution 1200x600dpi/1200x600 DPI: ""
+*CloseUI: *Resolution
+
+*UIConstraints: *PageSize Env10 *Duplex
+*UIConstraints: *Duplex *PageSize Env10
+*UIConstraints: *InputSlot Manual *Duplex DuplexTumble
+"#;
+
+    const COLOR_PPD: &str = r#"*PPD-Adobe: "4.3"
+*ModelName: "Generic Color"
+*ColorDevice: True
+*OpenUI *PageSize: PickOne
+*DefaultPageSize: iso_a4_210x297mm
+*PageSize iso_a4_210x297mm/A4: ""
+*CloseUI: *PageSize
+*PaperDimension iso_a4_210x297mm/A4: "595.28 841.89"
+*OpenUI *ColorModel/Color Mode: PickOne
+*DefaultColorModel: RGB
+*ColorModel Gray/Grayscale: ""
+*ColorModel RGB/Color: ""
+*CloseUI: *ColorModel
+"#;
+
+    #[test]
+    fn parses_options_and_constraints() {
+        let ppd = parse(LASER_PPD);
+        assert_eq!(ppd.model_name.as_deref(), Some("HP LaserJet M404"));
+        assert!(!ppd.color_device);
+
+        let page_size = ppd.option("PageSize").unwrap();
+        assert_eq!(page_size.text, "Media Size");
+        assert_eq!(page_size.default.as_deref(), Some("Letter"));
+        let names: Vec<&str> = page_size.choices.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Letter", "A4", "Env10"]);
+        assert_eq!(page_size.choices[2].text, "Envelope #10");
+
+        assert_eq!(ppd.constraints.len(), 3);
+        assert_eq!(
+            ppd.constraints[2],
+            PpdConstraint {
+                option1: "InputSlot".to_string(),
+                choice1: Some("Manual".to_string()),
+                option2: "Duplex".to_string(),
+                choice2: Some("DuplexTumble".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn extracts_capabilities() {
+        let capabilities = parse(LASER_PPD).capabilities("office");
+        assert_eq!(capabilities.media_sizes[1], MediaSize { name: "A4".to_string(), width_mm: Some(209.9), height_mm: Some(297.0) });
+        assert_eq!(capabilities.default_media.as_deref(), Some("Letter"));
+        assert_eq!(capabilities.trays, vec!["Auto", "Tray1", "Manual"]);
+        assert_eq!(capabilities.duplex_modes, vec!["one-sided", "two-sided-long-edge", "two-sided-short-edge"]);
+        assert_eq!(capabilities.color_modes, vec!["monochrome"]);
+        assert_eq!(capabilities.resolutions[1], Resolution { x_dpi: 1200, y_dpi: 600 });
+
+        let color = parse(COLOR_PPD).capabilities("color");
+        assert_eq!(color.color_modes, vec!["monochrome", "color"]);
+        assert_eq!(color.duplex_modes, vec!["one-sided"]);
+    }
+
+    #[test]
+    fn maps_settings_to_cups_options() {
+        let ppd = parse(LASER_PPD);
+        let settings = PrintSettings {
+            page_size: Some("a4".to_string()),
+            duplex: Some(DuplexMode::TwoSidedLongEdge),
+            tray: Some("Tray 1".to_string()),
+            copies: Some(2),
+            resolution: Some(600),
+            ..Default::default()
+        };
+        assert_eq!(
+            ppd.to_cups_options(&settings).unwrap(),
+            vec!["PageSize=A4", "Duplex=DuplexNoTumble", "InputSlot=Tray1", "Resolution=600dpi", "copies=2"]
+        );
+
+        // 按尺寸匹配 PWG 名称，按灰度匹配 ColorModel
+        let color = parse(COLOR_PPD);
+        let settings = PrintSettings {
+            page_size: Some("A4".to_string()),
+            color: Some(ColorMode::Monochrome),
+            ..Default::default()
+        };
+        assert_eq!(color.to_cups_options(&settings).unwrap(), vec!["PageSize=iso_a4_210x297mm", "ColorModel=Gray"]);
+    }
+
+    #[test]
+    fn rejects_unsupported_and_conflicting_settings() {
+        let ppd = parse(LASER_PPD);
+        let unsupported = PrintSettings { page_size: Some("A3".to_string()), ..Default::default() };
+        assert_eq!(ppd.to_cups_options(&unsupported).unwrap_err(), "打印机不支持纸张: A3");
+
+        let color = PrintSettings { color: Some(ColorMode::Color), ..Default::default() };
+        assert!(ppd.to_cups_options(&color).is_err());
+
+        let envelope = PrintSettings {
+            page_size: Some("Env10".to_string()),
+            duplex: Some(DuplexMode::TwoSidedLongEdge),
+            ..Default::default()
+        };
+        assert_eq!(ppd.to_cups_options(&envelope).unwrap_err(), "选项冲突: PageSize=Env10 与 Duplex");
+
+        // 单面时 Duplex=None 不触发约束
+        let single = PrintSettings { page_size: Some("Env10".to_string()), duplex: Some(DuplexMode::OneSided), ..Default::default() };
+        assert_eq!(ppd.to_cups_options(&single).unwrap(), vec!["PageSize=Env10", "Duplex=None"]);
+    }
+}
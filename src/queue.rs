@@ -0,0 +1,353 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::declare::{PrintSettings, QueueConfig};
+use crate::history::now_ms;
+
+/// 队列目录名，位于应用数据目录下
+pub const QUEUE_DIR: &str = "print_queue";
+const QUEUE_FILE: &str = "queue.json";
+const DOCUMENTS_DIR: &str = "documents";
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 10;
+const DEFAULT_RETRY_DELAY_MS: u64 = 5000;
+const DEFAULT_MAX_RETRY_DELAY_MS: u64 = 300_000;
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueStatus {
+    /// 等待打印或等待重试
+    Pending,
+    Printing,
+    /// 达到最多尝试次数，需要 retry_now
+    Failed,
+}
+
+/// 队列中的作业，打印成功后移出队列（结果见打印历史）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedJob {
+    pub id: String,
+    pub printer: String,
+    pub document_name: String,
+    /// 队列目录中的文档副本
+    pub path: String,
+    pub settings: Option<PrintSettings>,
+    /// 数值大的先打印
+    pub priority: i32,
+    pub status: QueueStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub created_at: i64,
+    pub next_attempt_at: i64,
+}
+
+/// 持久化在 queue.json 中的队列
+pub struct Queue {
+    dir: PathBuf,
+    config: QueueConfig,
+    jobs: Vec<QueuedJob>,
+    sequence: u64,
+}
+
+impl Queue {
+    /**
+     * 打开队列目录，上次退出时正在打印的作业重新排队
+     */
+    pub fn open(dir: &Path, config: QueueConfig) -> Result<Self, String> {
+        let documents = dir.join(DOCUMENTS_DIR);
+        fs::create_dir_all(&documents).map_err(|e| format!("创建 {} 失败: {}", documents.display(), e))?;
+        let file = dir.join(QUEUE_FILE);
+        let mut jobs: Vec<QueuedJob> = match fs::read_to_string(&file) {
+            Ok(text) => serde_json::from_str(&text).map_err(|e| format!("读取 {} 失败: {}", file.display(), e))?,
+            Err(_) => Vec::new(),
+        };
+        for job in jobs.iter_mut().filter(|job| job.status == QueueStatus::Printing) {
+            job.status = QueueStatus::Pending;
+        }
+        Ok(Queue { dir: dir.to_path_buf(), config, jobs, sequence: 0 })
+    }
+
+    /// 先写临时文件再替换，避免退出时写坏队列
+    fn save(&self) -> Result<(), String> {
+        let file = self.dir.join(QUEUE_FILE);
+        let temp = self.dir.join(format!("{}.tmp", QUEUE_FILE));
+        let text = serde_json::to_string_pretty(&self.jobs).map_err(|e| e.to_string())?;
+        fs::write(&temp, text).map_err(|e| format!("写入 {} 失败: {}", temp.display(), e))?;
+        fs::rename(&temp, &file).map_err(|e| format!("写入 {} 失败: {}", file.display(), e))
+    }
+
+    /// 第 n 次失败后的等待时间，按 2 的幂递增
+    pub fn retry_delay_ms(&self, attempts: u32) -> u64 {
+        let base = self.config.retry_delay_ms.unwrap_or(DEFAULT_RETRY_DELAY_MS);
+        let max = self.config.max_retry_delay_ms.unwrap_or(DEFAULT_MAX_RETRY_DELAY_MS);
+        base.saturating_mul(1u64 << attempts.saturating_sub(1).min(20)).min(max)
+    }
+
+    /**
+     * 复制文档到队列目录并加入队列
+     */
+    pub fn add(
+        &mut self,
+        source: &Path,
+        printer: &str,
+        settings: Option<PrintSettings>,
+        priority: i32,
+        now: i64,
+    ) -> Result<QueuedJob, String> {
+        let id = loop {
+            self.sequence += 1;
+            let id = format!("{}-{}", now, self.sequence);
+            if !self.jobs.iter().any(|job| job.id == id) {
+                break id;
+            }
+        };
+        let document_name = source
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| id.clone());
+        let extension = source.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+        let path = self.dir.join(DOCUMENTS_DIR).join(format!("{}{}", id, extension));
+        fs::copy(source, &path).map_err(|e| format!("复制 {} 失败: {}", source.display(), e))?;
+
+        let job = QueuedJob {
+            id,
+            printer: printer.to_string(),
+            document_name,
+            path: path.to_string_lossy().to_string(),
+            settings,
+            priority,
+            status: QueueStatus::Pending,
+            attempts: 0,
+            last_error: None,
+            created_at: now,
+            next_attempt_at: now,
+        };
+        self.jobs.push(job.clone());
+        if let Err(e) = self.save() {
+            self.jobs.pop();
+            let _ = fs::remove_file(&path);
+            return Err(e);
+        }
+        Ok(job)
+    }
+
+    /// 按打印顺序：优先级高的在前，同优先级先进先出
+    pub fn list(&self) -> Vec<QueuedJob> {
+        let mut jobs = self.jobs.clone();
+        jobs.sort_by(|a, b| b.priority.cmp(&a.priority).then(a.created_at.cmp(&b.created_at)).then(a.id.cmp(&b.id)));
+        jobs
+    }
+
+    /**
+     * 取出下一个到期的作业并标记为打印中
+     */
+    pub fn take_due(&mut self, now: i64) -> Option<QueuedJob> {
+        let due = self
+            .list()
+            .into_iter()
+            .find(|job| job.status == QueueStatus::Pending && job.next_attempt_at <= now)?;
+        let job = self.jobs.iter_mut().find(|job| job.id == due.id)?;
+        job.status = QueueStatus::Printing;
+        job.attempts += 1;
+        let job = job.clone();
+        if let Err(e) = self.save() {
+            eprintln!("保存打印队列失败: {}", e);
+        }
+        Some(job)
+    }
+
+    /**
+     * 记录打印结果：成功则移出队列，失败则按退避时间重新排队
+     */
+    pub fn finish(&mut self, id: &str, result: Result<(), String>, now: i64) {
+        let Some(index) = self.jobs.iter().position(|job| job.id == id) else {
+            return;
+        };
+        match result {
+            Ok(()) => {
+                let job = self.jobs.remove(index);
+                let _ = fs::remove_file(&job.path);
+            }
+            Err(e) => {
+                let max_attempts = self.config.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS);
+                let delay = self.retry_delay_ms(self.jobs[index].attempts) as i64;
+                let job = &mut self.jobs[index];
+                job.last_error = Some(e);
+                if max_attempts > 0 && job.attempts >= max_attempts {
+                    job.status = QueueStatus::Failed;
+                } else {
+                    job.status = QueueStatus::Pending;
+                    job.next_attempt_at = now + delay;
+                }
+            }
+        }
+        if let Err(e) = self.save() {
+            eprintln!("保存打印队列失败: {}", e);
+        }
+    }
+
+    /**
+     * 取消作业并删除文档副本
+     * @returns 是否找到该作业
+     */
+    pub fn cancel(&mut self, id: &str) -> Result<bool, String> {
+        let Some(index) = self.jobs.iter().position(|job| job.id == id) else {
+            return Ok(false);
+        };
+        if self.jobs[index].status == QueueStatus::Printing {
+            return Err(format!("作业 {} 正在打印，无法取消", id));
+        }
+        let job = self.jobs.remove(index);
+        self.save()?;
+        let _ = fs::remove_file(&job.path);
+        Ok(true)
+    }
+
+    /**
+     * 立即重试，失败的作业重新计算尝试次数
+     * @returns 是否找到该作业
+     */
+    pub fn retry_now(&mut self, id: &str, now: i64) -> Result<bool, String> {
+        let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) else {
+            return Ok(false);
+        };
+        match job.status {
+            QueueStatus::Printing => return Err(format!("作业 {} 正在打印", id)),
+            QueueStatus::Failed => job.attempts = 0,
+            QueueStatus::Pending => {}
+        }
+        job.status = QueueStatus::Pending;
+        job.next_attempt_at = now;
+        self.save()?;
+        Ok(true)
+    }
+}
+
+static QUEUE: OnceLock<Mutex<Queue>> = OnceLock::new();
+
+fn queue() -> Result<MutexGuard<'static, Queue>, String> {
+    let queue = QUEUE.get().ok_or("打印队列未初始化")?;
+    Ok(queue.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+/**
+ * 打开队列并启动后台线程，只在插件初始化时调用一次
+ * @param submit 提交一个作业，返回 Err 时按退避时间重试
+ */
+pub fn init<F>(dir: &Path, config: QueueConfig, submit: F) -> Result<(), String>
+where
+    F: Fn(&QueuedJob) -> Result<(), String> + Send + 'static,
+{
+    QUEUE
+        .set(Mutex::new(Queue::open(dir, config)?))
+        .map_err(|_| "打印队列已经初始化".to_string())?;
+
+    thread::spawn(move || loop {
+        // 提交时不持有锁，打印期间仍可查询和取消其他作业
+        let job = queue().ok().and_then(|mut queue| queue.take_due(now_ms()));
+        match job {
+            Some(job) => {
+                let result = submit(&job);
+                if let Ok(mut queue) = queue() {
+                    queue.finish(&job.id, result, now_ms());
+                }
+            }
+            None => thread::sleep(POLL_INTERVAL),
+        }
+    });
+    Ok(())
+}
+
+pub fn enqueue(source: &Path, printer: &str, settings: Option<PrintSettings>, priority: i32) -> Result<QueuedJob, String> {
+    queue()?.add(source, printer, settings, priority, now_ms())
+}
+
+pub fn list() -> Result<Vec<QueuedJob>, String> {
+    Ok(queue()?.list())
+}
+
+pub fn cancel(id: &str) -> Result<bool, String> {
+    queue()?.cancel(id)
+}
+
+pub fn retry_now(id: &str) -> Result<bool, String> {
+    queue()?.retry_now(id, now_ms())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> QueueConfig {
+        QueueConfig { max_attempts: Some(2), retry_delay_ms: Some(1000), max_retry_delay_ms: Some(1500) }
+    }
+
+    #[test]
+    fn prints_by_priority_and_retries_with_backoff() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("invoice.pdf");
+        fs::write(&source, b"%PDF-1.4").unwrap();
+        let mut queue = Queue::open(&dir.path().join(QUEUE_DIR), config()).unwrap();
+
+        let low = queue.add(&source, "Office", None, 0, 100).unwrap();
+        let high = queue.add(&source, "Office", None, 5, 200).unwrap();
+        assert_eq!(high.document_name, "invoice.pdf");
+        assert!(Path::new(&high.path).exists());
+
+        let first = queue.take_due(300).unwrap();
+        assert_eq!(first.id, high.id);
+        queue.finish(&first.id, Err("打印机离线".to_string()), 300);
+
+        // 高优先级作业在退避期间，先打印低优先级作业
+        assert_eq!(queue.take_due(400).unwrap().id, low.id);
+        queue.finish(&low.id, Ok(()), 400);
+        assert!(!Path::new(&low.path).exists());
+        assert!(queue.take_due(1299).is_none());
+
+        let retry = queue.take_due(1300).unwrap();
+        assert_eq!(retry.attempts, 2);
+        queue.finish(&retry.id, Err("打印机离线".to_string()), 1300);
+        let failed = &queue.list()[0];
+        assert_eq!((failed.status, failed.last_error.as_deref()), (QueueStatus::Failed, Some("打印机离线")));
+        assert!(queue.take_due(i64::MAX).is_none());
+
+        assert!(queue.retry_now(&high.id, 2000).unwrap());
+        assert_eq!(queue.take_due(2000).unwrap().attempts, 1);
+        assert_eq!(queue.retry_delay_ms(1), 1000);
+        assert_eq!(queue.retry_delay_ms(3), 1500);
+    }
+
+    #[test]
+    fn persists_jobs_and_cancels() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("label.pdf");
+        fs::write(&source, b"%PDF-1.4").unwrap();
+        let queue_dir = dir.path().join(QUEUE_DIR);
+
+        let (printing, waiting) = {
+            let mut queue = Queue::open(&queue_dir, config()).unwrap();
+            let printing = queue.add(&source, "Label", None, 0, 100).unwrap();
+            let waiting = queue.add(&source, "Label", None, 0, 200).unwrap();
+            queue.take_due(300).unwrap();
+            assert!(queue.cancel(&printing.id).is_err());
+            (printing, waiting)
+        };
+
+        // 重新打开后，中断的作业重新排队
+        let mut queue = Queue::open(&queue_dir, config()).unwrap();
+        let jobs = queue.list();
+        assert_eq!(jobs.len(), 2);
+        assert_eq!((jobs[0].id.as_str(), jobs[0].status), (printing.id.as_str(), QueueStatus::Pending));
+
+        assert!(queue.cancel(&waiting.id).unwrap());
+        assert!(!queue.cancel(&waiting.id).unwrap());
+        assert!(!Path::new(&waiting.path).exists());
+        assert_eq!(queue.list().len(), 1);
+    }
+}
@@ -0,0 +1,300 @@
+use image::{imageops::FilterType, GrayImage, Luma};
+
+use crate::declare::{DitherMode, RasterImageOptions, RasterLanguage};
+
+/// 58mm 热敏纸的默认点宽
+pub const DEFAULT_DOT_WIDTH: u32 = 384;
+
+/// ESC/POS `GS v 0` 单次发送的最大行数，超过后分段发送以免撑爆打印机缓冲区
+const ESCPOS_BAND_HEIGHT: u32 = 256;
+
+/// 1 位点阵图，每行按字节对齐，高位在前，1 表示出点（黑）
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonoBitmap {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl MonoBitmap {
+    fn new(width: u32, height: u32) -> Self {
+        let bytes_per_row = width.div_ceil(8) as usize;
+        MonoBitmap {
+            width,
+            height,
+            data: vec![0; bytes_per_row * height as usize],
+        }
+    }
+
+    pub fn bytes_per_row(&self) -> usize {
+        self.width.div_ceil(8) as usize
+    }
+
+    fn set_black(&mut self, x: u32, y: u32) {
+        let index = y as usize * self.bytes_per_row() + (x / 8) as usize;
+        self.data[index] |= 0x80 >> (x % 8);
+    }
+
+    pub fn is_black(&self, x: u32, y: u32) -> bool {
+        let index = y as usize * self.bytes_per_row() + (x / 8) as usize;
+        self.data[index] & (0x80 >> (x % 8)) != 0
+    }
+}
+
+/// 将 PNG/JPEG 等图片转换为打印机点阵指令
+pub fn image_to_commands(bytes: &[u8], options: &RasterImageOptions) -> Result<Vec<u8>, String> {
+    let gray = decode_grayscale(bytes)?;
+    let dot_width = options.dot_width.unwrap_or(DEFAULT_DOT_WIDTH);
+    let scaled = scale_to_width(&gray, dot_width);
+    let bitmap = dither(
+        &scaled,
+        options.dither.unwrap_or_default(),
+        options.threshold.unwrap_or(128),
+    );
+
+    match options.language.unwrap_or_default() {
+        RasterLanguage::Escpos => Ok(encode_escpos(&bitmap)),
+        RasterLanguage::Zpl => Ok(encode_zpl(&bitmap).into_bytes()),
+    }
+}
+
+/// 解码图片为灰度图，透明区域按白色处理
+pub fn decode_grayscale(bytes: &[u8]) -> Result<GrayImage, String> {
+    let image = image::load_from_memory(bytes).map_err(|e| format!("图片解码失败: {}", e))?;
+    let rgba = image.to_rgba8();
+
+    let mut gray = GrayImage::new(rgba.width(), rgba.height());
+    for (x, y, pixel) in rgba.enumerate_pixels() {
+        let [r, g, b, a] = pixel.0;
+        let luma = (299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000;
+        // 与白色背景做 alpha 混合
+        let value = (luma * a as u32 + 255 * (255 - a as u32)) / 255;
+        gray.put_pixel(x, y, Luma([value as u8]));
+    }
+    Ok(gray)
+}
+
+/// 按打印机点宽等比缩放
+pub fn scale_to_width(gray: &GrayImage, dot_width: u32) -> GrayImage {
+    if dot_width == 0 || gray.width() == dot_width || gray.width() == 0 {
+        return gray.clone();
+    }
+    let height = ((gray.height() as u64 * dot_width as u64) / gray.width() as u64).max(1) as u32;
+    image::imageops::resize(gray, dot_width, height, FilterType::Triangle)
+}
+
+/// 灰度图转 1 位点阵
+pub fn dither(gray: &GrayImage, mode: DitherMode, threshold: u8) -> MonoBitmap {
+    match mode {
+        DitherMode::Threshold => threshold_bitmap(gray, threshold),
+        DitherMode::FloydSteinberg => error_diffusion(
+            gray,
+            threshold,
+            &[(1, 0, 7), (-1, 1, 3), (0, 1, 5), (1, 1, 1)],
+            16,
+        ),
+        DitherMode::Atkinson => error_diffusion(
+            gray,
+            threshold,
+            &[(1, 0, 1), (2, 0, 1), (-1, 1, 1), (0, 1, 1), (1, 1, 1), (0, 2, 1)],
+            8,
+        ),
+    }
+}
+
+fn threshold_bitmap(gray: &GrayImage, threshold: u8) -> MonoBitmap {
+    let mut bitmap = MonoBitmap::new(gray.width(), gray.height());
+    for (x, y, pixel) in gray.enumerate_pixels() {
+        if pixel.0[0] < threshold {
+            bitmap.set_black(x, y);
+        }
+    }
+    bitmap
+}
+
+/// 通用误差扩散，`kernel` 为 (dx, dy, 权重)，`divisor` 为权重分母
+fn error_diffusion(
+    gray: &GrayImage,
+    threshold: u8,
+    kernel: &[(i32, i32, i32)],
+    divisor: i32,
+) -> MonoBitmap {
+    let width = gray.width() as i32;
+    let height = gray.height() as i32;
+    let mut buffer: Vec<i32> = gray.pixels().map(|p| p.0[0] as i32).collect();
+    let mut bitmap = MonoBitmap::new(gray.width(), gray.height());
+
+    for y in 0..height {
+        for x in 0..width {
+            let index = (y * width + x) as usize;
+            let old = buffer[index].clamp(0, 255);
+            let new = if old < threshold as i32 { 0 } else { 255 };
+            if new == 0 {
+                bitmap.set_black(x as u32, y as u32);
+            }
+
+            let error = old - new;
+            for &(dx, dy, weight) in kernel {
+                let nx = x + dx;
+                let ny = y + dy;
+                if nx >= 0 && nx < width && ny < height {
+                    buffer[(ny * width + nx) as usize] += error * weight / divisor;
+                }
+            }
+        }
+    }
+    bitmap
+}
+
+/// 编码为 ESC/POS `GS v 0` 光栅位图指令
+pub fn encode_escpos(bitmap: &MonoBitmap) -> Vec<u8> {
+    let bytes_per_row = bitmap.bytes_per_row();
+    let mut out = Vec::with_capacity(bitmap.data.len() + 8 * (bitmap.height / ESCPOS_BAND_HEIGHT + 1) as usize);
+
+    let mut row = 0;
+    while row < bitmap.height {
+        let band = (bitmap.height - row).min(ESCPOS_BAND_HEIGHT);
+        out.extend_from_slice(&[
+            0x1D,
+            0x76,
+            0x30,
+            0x00,
+            (bytes_per_row & 0xFF) as u8,
+            ((bytes_per_row >> 8) & 0xFF) as u8,
+            (band & 0xFF) as u8,
+            ((band >> 8) & 0xFF) as u8,
+        ]);
+        let start = row as usize * bytes_per_row;
+        let end = (row + band) as usize * bytes_per_row;
+        out.extend_from_slice(&bitmap.data[start..end]);
+        row += band;
+    }
+    out
+}
+
+/// 编码为 ZPL `^GF` 图形字段（ASCII 十六进制），包含完整的 `^XA ... ^XZ` 标签
+pub fn encode_zpl(bitmap: &MonoBitmap) -> String {
+    format!("^XA^FO0,0{}^FS^XZ", encode_zpl_graphic_field(bitmap))
+}
+
+/// 仅生成 `^GFA` 字段本身，便于嵌入到已有的标签中
+pub fn encode_zpl_graphic_field(bitmap: &MonoBitmap) -> String {
+    let total = bitmap.data.len();
+    let mut hex = String::with_capacity(total * 2);
+    for byte in &bitmap.data {
+        hex.push_str(&format!("{:02X}", byte));
+    }
+    format!("^GFA,{},{},{},{}", total, total, bitmap.bytes_per_row(), hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat(width: u32, height: u32, value: u8) -> GrayImage {
+        GrayImage::from_pixel(width, height, Luma([value]))
+    }
+
+    fn black_ratio(bitmap: &MonoBitmap) -> f64 {
+        let mut black = 0;
+        for y in 0..bitmap.height {
+            for x in 0..bitmap.width {
+                if bitmap.is_black(x, y) {
+                    black += 1;
+                }
+            }
+        }
+        black as f64 / (bitmap.width * bitmap.height) as f64
+    }
+
+    #[test]
+    fn threshold_splits_on_level() {
+        let mut gray = flat(8, 1, 255);
+        gray.put_pixel(0, 0, Luma([0]));
+        gray.put_pixel(1, 0, Luma([127]));
+        gray.put_pixel(2, 0, Luma([128]));
+
+        let bitmap = dither(&gray, DitherMode::Threshold, 128);
+        assert_eq!(bitmap.data, vec![0b1100_0000]);
+    }
+
+    #[test]
+    fn rows_are_padded_to_whole_bytes() {
+        let bitmap = dither(&flat(10, 2, 0), DitherMode::Threshold, 128);
+        assert_eq!(bitmap.bytes_per_row(), 2);
+        assert_eq!(bitmap.data, vec![0xFF, 0xC0, 0xFF, 0xC0]);
+    }
+
+    #[test]
+    fn error_diffusion_preserves_mid_gray() {
+        let gray = flat(64, 64, 128);
+        for mode in [DitherMode::FloydSteinberg, DitherMode::Atkinson] {
+            let ratio = black_ratio(&dither(&gray, mode, 128));
+            assert!((0.4..0.6).contains(&ratio), "{:?} ratio {}", mode, ratio);
+        }
+    }
+
+    #[test]
+    fn dithering_keeps_pure_white_and_black() {
+        for mode in [DitherMode::Threshold, DitherMode::FloydSteinberg, DitherMode::Atkinson] {
+            assert_eq!(black_ratio(&dither(&flat(16, 16, 255), mode, 128)), 0.0);
+            assert_eq!(black_ratio(&dither(&flat(16, 16, 0), mode, 128)), 1.0);
+        }
+    }
+
+    #[test]
+    fn atkinson_loses_error_on_light_gray() {
+        // Atkinson 只扩散 6/8 的误差，浅灰会比 Floyd–Steinberg 更亮
+        let gray = flat(64, 64, 200);
+        let fs = black_ratio(&dither(&gray, DitherMode::FloydSteinberg, 128));
+        let atkinson = black_ratio(&dither(&gray, DitherMode::Atkinson, 128));
+        assert!(atkinson < fs);
+    }
+
+    #[test]
+    fn scales_to_dot_width_keeping_aspect() {
+        let scaled = scale_to_width(&flat(200, 100, 0), 576);
+        assert_eq!((scaled.width(), scaled.height()), (576, 288));
+    }
+
+    #[test]
+    fn escpos_header_and_bands() {
+        let bitmap = dither(&flat(384, 300, 0), DitherMode::Threshold, 128);
+        let out = encode_escpos(&bitmap);
+
+        assert_eq!(&out[..8], &[0x1D, 0x76, 0x30, 0x00, 48, 0, 0x00, 0x01]);
+        let second = 8 + 48 * 256;
+        assert_eq!(&out[second..second + 8], &[0x1D, 0x76, 0x30, 0x00, 48, 0, 44, 0]);
+        assert_eq!(out.len(), 16 + 48 * 300);
+    }
+
+    #[test]
+    fn zpl_graphic_field_is_hex_encoded() {
+        let mut gray = flat(12, 2, 255);
+        gray.put_pixel(0, 0, Luma([0]));
+        gray.put_pixel(11, 1, Luma([0]));
+
+        let bitmap = dither(&gray, DitherMode::Threshold, 128);
+        assert_eq!(encode_zpl(&bitmap), "^XA^FO0,0^GFA,4,4,2,80000010^FS^XZ");
+    }
+
+    #[test]
+    fn decodes_png_with_transparency_as_white() {
+        let mut rgba = image::RgbaImage::new(2, 1);
+        rgba.put_pixel(0, 0, image::Rgba([0, 0, 0, 255]));
+        rgba.put_pixel(1, 0, image::Rgba([0, 0, 0, 0]));
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(rgba)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let gray = decode_grayscale(&png).unwrap();
+        assert_eq!(gray.get_pixel(0, 0).0[0], 0);
+        assert_eq!(gray.get_pixel(1, 0).0[0], 255);
+    }
+
+    #[test]
+    fn rejects_non_image_input() {
+        assert!(decode_grayscale(b"not an image").is_err());
+    }
+}
@@ -0,0 +1,250 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::Duration;
+
+use base64::{Engine as _, engine::general_purpose};
+
+use crate::declare::RawSocketSettings;
+
+/// AppSocket/JetDirect 默认端口
+pub const DEFAULT_PORT: u16 = 9100;
+
+const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 5000;
+const DEFAULT_READ_TIMEOUT_MS: u64 = 2000;
+const DEFAULT_RETRIES: u32 = 2;
+const DEFAULT_RETRY_DELAY_MS: u64 = 1000;
+/// 打印机缓冲区满时会暂停接收，写超时需要足够长
+const WRITE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 一次发送的结果
+#[derive(Debug)]
+pub struct SendOutcome {
+    pub attempts: u32,
+    /// 发送状态查询指令后打印机回传的数据
+    pub status: Option<Vec<u8>>,
+}
+
+pub fn address(settings: &RawSocketSettings) -> String {
+    format!("{}:{}", settings.host, settings.port.unwrap_or(DEFAULT_PORT))
+}
+
+/**
+ * 发送原始数据到 host:9100，连接或发送数据失败时按配置重试
+ * 数据发送成功后不再重试，避免重复打印；状态读取失败时 status 为空
+ */
+pub fn send(settings: &RawSocketSettings, data: &[u8]) -> Result<SendOutcome, String> {
+    let status_query = status_query(settings)?;
+    let attempts = settings.retries.unwrap_or(DEFAULT_RETRIES) + 1;
+    let retry_delay = Duration::from_millis(settings.retry_delay_ms.unwrap_or(DEFAULT_RETRY_DELAY_MS));
+
+    let mut last_error = String::new();
+    for attempt in 1..=attempts {
+        match send_once(settings, data, status_query.as_deref()) {
+            Ok(status) => return Ok(SendOutcome { attempts: attempt, status }),
+            Err(e) => {
+                last_error = e;
+                if attempt < attempts {
+                    thread::sleep(retry_delay);
+                }
+            }
+        }
+    }
+    Err(format!(
+        "发送到 {} 失败（已尝试 {} 次）: {}",
+        address(settings),
+        attempts,
+        last_error
+    ))
+}
+
+/**
+ * 仅发送状态查询指令并读取回传数据
+ */
+pub fn query_status(settings: &RawSocketSettings) -> Result<Option<Vec<u8>>, String> {
+    let query = status_query(settings)?.ok_or("未配置状态查询指令 status_query")?;
+    let mut stream = connect(settings)?;
+    stream
+        .write_all(&query)
+        .map_err(|e| format!("发送状态查询失败: {}", e))?;
+    let status = read_status(&mut stream, settings)?;
+    let _ = stream.shutdown(Shutdown::Both);
+    Ok(status)
+}
+
+/**
+ * 只建立连接再断开，用于检查打印机是否在线
+ */
+pub fn probe(settings: &RawSocketSettings) -> Result<(), String> {
+    let stream = connect(settings)?;
+    let _ = stream.shutdown(Shutdown::Both);
+    Ok(())
+}
+
+fn status_query(settings: &RawSocketSettings) -> Result<Option<Vec<u8>>, String> {
+    settings
+        .status_query
+        .as_deref()
+        .map(|query| {
+            general_purpose::STANDARD
+                .decode(query)
+                .map_err(|e| format!("status_query 不是合法的 base64: {}", e))
+        })
+        .transpose()
+}
+
+fn connect(settings: &RawSocketSettings) -> Result<TcpStream, String> {
+    let address = address(settings);
+    let timeout = Duration::from_millis(settings.connect_timeout_ms.unwrap_or(DEFAULT_CONNECT_TIMEOUT_MS));
+    let addrs = address
+        .to_socket_addrs()
+        .map_err(|e| format!("解析地址 {} 失败: {}", address, e))?;
+
+    let mut last_error = format!("地址 {} 没有可用的 IP", address);
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => {
+                stream
+                    .set_write_timeout(Some(WRITE_TIMEOUT))
+                    .map_err(|e| e.to_string())?;
+                return Ok(stream);
+            }
+            Err(e) => last_error = format!("连接 {} 失败: {}", addr, e),
+        }
+    }
+    Err(last_error)
+}
+
+fn send_once(
+    settings: &RawSocketSettings,
+    data: &[u8],
+    status_query: Option<&[u8]>,
+) -> Result<Option<Vec<u8>>, String> {
+    let mut stream = connect(settings)?;
+    stream
+        .write_all(data)
+        .and_then(|_| stream.flush())
+        .map_err(|e| format!("发送数据失败: {}", e))?;
+
+    // 数据已经发出，之后的失败不能再重试
+    let status = match status_query {
+        Some(query) => stream
+            .write_all(query)
+            .map_err(|e| format!("发送状态查询失败: {}", e))
+            .and_then(|_| read_status(&mut stream, settings))
+            .unwrap_or_else(|e| {
+                eprintln!("{}: {}", address(settings), e);
+                None
+            }),
+        None => None,
+    };
+
+    // 关闭写端，打印机据此判断作业结束
+    let _ = stream.shutdown(Shutdown::Write);
+    Ok(status)
+}
+
+fn read_status(stream: &mut TcpStream, settings: &RawSocketSettings) -> Result<Option<Vec<u8>>, String> {
+    let timeout = Duration::from_millis(settings.read_timeout_ms.unwrap_or(DEFAULT_READ_TIMEOUT_MS));
+    stream
+        .set_read_timeout(Some(timeout))
+        .map_err(|e| e.to_string())?;
+
+    let mut buffer = [0u8; 256];
+    match stream.read(&mut buffer) {
+        Ok(0) => Ok(None),
+        Ok(n) => Ok(Some(buffer[..n].to_vec())),
+        Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Ok(None),
+        Err(e) => Err(format!("读取打印机状态失败: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    fn settings(port: u16) -> RawSocketSettings {
+        RawSocketSettings {
+            host: "127.0.0.1".to_string(),
+            port: Some(port),
+            connect_timeout_ms: Some(500),
+            read_timeout_ms: Some(500),
+            retries: Some(0),
+            retry_delay_ms: Some(10),
+            status_query: None,
+        }
+    }
+
+    #[test]
+    fn sends_job_to_listener() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let mut received = Vec::new();
+            socket.read_to_end(&mut received).unwrap();
+            received
+        });
+
+        let outcome = send(&settings(port), b"\x1b@hello\n").unwrap();
+        assert_eq!(outcome.attempts, 1);
+        assert!(outcome.status.is_none());
+        assert_eq!(server.join().unwrap(), b"\x1b@hello\n");
+    }
+
+    #[test]
+    fn reads_back_status() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let mut query = [0u8; 3];
+            socket.read_exact(&mut query).unwrap();
+            assert_eq!(query, [0x10, 0x04, 0x01]);
+            socket.write_all(&[0x16]).unwrap();
+        });
+
+        let mut settings = settings(port);
+        settings.status_query = Some(general_purpose::STANDARD.encode([0x10, 0x04, 0x01]));
+        let status = query_status(&settings).unwrap();
+        assert_eq!(status, Some(vec![0x16]));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn does_not_resend_after_status_failure() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let mut data = [0u8; 4];
+            socket.read_exact(&mut data).unwrap();
+            // 状态查询留在接收缓冲区中未读，关闭时对方收到 RST
+            thread::sleep(Duration::from_millis(100));
+            drop(socket);
+            listener
+        });
+
+        let mut settings = settings(port);
+        settings.retries = Some(2);
+        settings.status_query = Some(general_purpose::STANDARD.encode([0x10, 0x04, 0x01]));
+        let outcome = send(&settings, b"data").unwrap();
+        assert_eq!(outcome.attempts, 1);
+        assert!(outcome.status.is_none());
+
+        let listener = server.join().unwrap();
+        listener.set_nonblocking(true).unwrap();
+        assert!(listener.accept().is_err());
+    }
+
+    #[test]
+    fn retries_then_fails_when_nothing_listens() {
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let mut settings = settings(port);
+        settings.retries = Some(1);
+
+        let error = send(&settings, b"data").unwrap_err();
+        assert!(error.contains("已尝试 2 次"), "{}", error);
+    }
+}
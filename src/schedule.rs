@@ -0,0 +1,449 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+
+use crate::declare::PrintSettings;
+use crate::history::now_ms;
+
+/// 计划触发时发出的事件
+pub const SCHEDULE_FIRED_EVENT: &str = "printer://schedule-fired";
+
+const SCHEDULES_FILE: &str = "schedules.json";
+const DOCUMENTS_DIR: &str = "scheduled";
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// 加入队列失败后的重试间隔
+const RETRY_DELAY_MS: i64 = 60_000;
+
+/// cron 表达式：分 时 日 月 周
+/// 支持 `*`、`5`、`1-5`、`*/15`、`1-30/2` 和逗号分隔的列表，周日为 0 或 7，
+/// 以及 @hourly、@daily、@weekly、@monthly、@yearly
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// 日和周都不是 `*` 时，满足其一即可
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| format!("无效的步长: {}", part))?;
+                if step == 0 {
+                    return Err(format!("无效的步长: {}", part));
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let parse = |value: &str| value.parse::<u32>().map_err(|_| format!("无效的值: {}", part));
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            // `5/10` 表示从 5 开始每 10 个，`5/1` 表示从 5 到最大值
+            None if part.contains('/') => (parse(range)?, max),
+            None => {
+                let value = parse(range)?;
+                (value, value)
+            }
+        };
+        if start < min || end > max || start > end {
+            return Err(format!("超出范围 {}-{}: {}", min, max, part));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+impl Cron {
+    pub fn parse(expression: &str) -> Result<Cron, String> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day_of_month, month, day_of_week] = fields[..] else {
+            return Err(format!("cron 表达式需要 5 个字段: {}", expression));
+        };
+        let mut days_of_week = parse_field(day_of_week, 0, 7)?;
+        // 7 也表示周日
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week | 1) & !(1 << 7);
+        }
+        Ok(Cron {
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days_of_month: parse_field(day_of_month, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            days_of_week,
+            any_day_of_month: day_of_month == "*",
+            any_day_of_week: day_of_week == "*",
+        })
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let day_of_month = self.days_of_month & (1 << date.day()) != 0;
+        let day_of_week = self.days_of_week & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (false, false) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week,
+        }
+    }
+
+    /**
+     * 下一次触发时间，严格晚于 after
+     * 夏令时跳过的时刻不会触发，重复的时刻只触发较早的一次
+     */
+    pub fn next_after<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let timezone = after.timezone();
+        let local = after.naive_local();
+        let start = local.date().and_hms_opt(local.hour(), local.minute(), 0)? + chrono::Duration::minutes(1);
+        let mut date = start.date();
+        // 2 月 29 日这样的表达式最多要找 8 年
+        for _ in 0..366 * 8 {
+            if self.matches_date(date) {
+                let first_day = date == start.date();
+                let from_hour = if first_day { start.hour() } else { 0 };
+                for hour in (from_hour..24).filter(|h| self.hours & (1 << h) != 0) {
+                    let from_minute = if first_day && hour == start.hour() { start.minute() } else { 0 };
+                    for minute in (from_minute..60).filter(|m| self.minutes & (1 << m) != 0) {
+                        let time = timezone.from_local_datetime(&date.and_hms_opt(hour, minute, 0)?).earliest();
+                        if let Some(time) = time.filter(|time| time > after) {
+                            return Some(time);
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+}
+
+/// 按本地时间计算下一次触发的毫秒时间戳
+pub fn next_run(cron: &Cron, after_ms: i64) -> Option<i64> {
+    let after = Local.timestamp_millis_opt(after_ms).single()?;
+    cron.next_after(&after).map(|time| time.timestamp_millis())
+}
+
+/// 计划打印，触发时加入打印队列
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    pub id: String,
+    pub printer: String,
+    pub document_name: String,
+    /// 计划目录中的文档副本
+    pub path: String,
+    pub settings: Option<PrintSettings>,
+    pub priority: i32,
+    /// 重复执行的 cron 表达式（本地时间），为空时只执行一次
+    pub cron: Option<String>,
+    pub next_run_at: i64,
+    pub last_run_at: Option<i64>,
+    pub last_error: Option<String>,
+    pub created_at: i64,
+}
+
+/// `printer://schedule-fired` 事件的内容
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduleFired {
+    pub schedule: Schedule,
+    /// 加入打印队列后的作业ID
+    pub job_id: Option<String>,
+    pub error: Option<String>,
+}
+
+/// 持久化在 schedules.json 中的计划
+pub struct Schedules {
+    dir: PathBuf,
+    schedules: Vec<Schedule>,
+    sequence: u64,
+}
+
+impl Schedules {
+    pub fn open(dir: &Path) -> Result<Self, String> {
+        let documents = dir.join(DOCUMENTS_DIR);
+        fs::create_dir_all(&documents).map_err(|e| format!("创建 {} 失败: {}", documents.display(), e))?;
+        let file = dir.join(SCHEDULES_FILE);
+        let schedules = match fs::read_to_string(&file) {
+            Ok(text) => serde_json::from_str(&text).map_err(|e| format!("读取 {} 失败: {}", file.display(), e))?,
+            Err(_) => Vec::new(),
+        };
+        Ok(Schedules { dir: dir.to_path_buf(), schedules, sequence: 0 })
+    }
+
+    /// 先写临时文件再替换，避免退出时写坏计划
+    fn save(&self) -> Result<(), String> {
+        let file = self.dir.join(SCHEDULES_FILE);
+        let temp = self.dir.join(format!("{}.tmp", SCHEDULES_FILE));
+        let text = serde_json::to_string_pretty(&self.schedules).map_err(|e| e.to_string())?;
+        fs::write(&temp, text).map_err(|e| format!("写入 {} 失败: {}", temp.display(), e))?;
+        fs::rename(&temp, &file).map_err(|e| format!("写入 {} 失败: {}", file.display(), e))
+    }
+
+    /**
+     * 复制文档到计划目录并添加计划
+     * @param not_before 最早执行时间（毫秒时间戳），为空时从现在开始
+     * @param cron 重复执行的 cron 表达式
+     */
+    #[allow(clippy::too_many_arguments)]
+    pub fn add(
+        &mut self,
+        source: &Path,
+        printer: &str,
+        settings: Option<PrintSettings>,
+        priority: i32,
+        not_before: Option<i64>,
+        cron: Option<&str>,
+        now: i64,
+    ) -> Result<Schedule, String> {
+        let start = not_before.unwrap_or(now).max(now);
+        let next_run_at = match cron {
+            Some(expression) => {
+                let cron = Cron::parse(expression)?;
+                // 恰好在 not_before 时刻也算
+                next_run(&cron, start - 1).ok_or_else(|| format!("cron 表达式不会触发: {}", expression))?
+            }
+            None if not_before.is_none() => return Err("需要指定 not_before 或 cron".to_string()),
+            None => start,
+        };
+        let id = loop {
+            self.sequence += 1;
+            let id = format!("schedule-{}-{}", now, self.sequence);
+            if !self.schedules.iter().any(|s| s.id == id) {
+                break id;
+            }
+        };
+        let document_name = source
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| id.clone());
+        let extension = source.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+        let path = self.dir.join(DOCUMENTS_DIR).join(format!("{}{}", id, extension));
+        fs::copy(source, &path).map_err(|e| format!("复制 {} 失败: {}", source.display(), e))?;
+
+        let schedule = Schedule {
+            id,
+            printer: printer.to_string(),
+            document_name,
+            path: path.to_string_lossy().to_string(),
+            settings,
+            priority,
+            cron: cron.map(|c| c.trim().to_string()),
+            next_run_at,
+            last_run_at: None,
+            last_error: None,
+            created_at: now,
+        };
+        self.schedules.push(schedule.clone());
+        if let Err(e) = self.save() {
+            self.schedules.pop();
+            let _ = fs::remove_file(&path);
+            return Err(e);
+        }
+        Ok(schedule)
+    }
+
+    /// 按下次执行时间排列
+    pub fn list(&self) -> Vec<Schedule> {
+        let mut schedules = self.schedules.clone();
+        schedules.sort_by(|a, b| a.next_run_at.cmp(&b.next_run_at).then(a.id.cmp(&b.id)));
+        schedules
+    }
+
+    /// 到期的计划，应用未运行期间错过的多次执行只补一次
+    pub fn due(&self, now: i64) -> Vec<Schedule> {
+        self.list().into_iter().filter(|s| s.next_run_at <= now).collect()
+    }
+
+    /**
+     * 记录触发结果：单次计划成功后删除，重复计划计算下一次执行时间
+     * 加入队列失败时一分钟后再试
+     */
+    pub fn fired(&mut self, id: &str, result: Result<(), String>, now: i64) {
+        let Some(index) = self.schedules.iter().position(|s| s.id == id) else {
+            return;
+        };
+        let schedule = &mut self.schedules[index];
+        schedule.last_run_at = Some(now);
+        match result {
+            Err(e) => {
+                schedule.last_error = Some(e);
+                schedule.next_run_at = now + RETRY_DELAY_MS;
+            }
+            Ok(()) => {
+                schedule.last_error = None;
+                let next = schedule.cron.as_deref().and_then(|c| Cron::parse(c).ok()).and_then(|c| next_run(&c, now));
+                match next {
+                    Some(next) => schedule.next_run_at = next,
+                    None => {
+                        let schedule = self.schedules.remove(index);
+                        let _ = fs::remove_file(&schedule.path);
+                    }
+                }
+            }
+        }
+        if let Err(e) = self.save() {
+            eprintln!("保存打印计划失败: {}", e);
+        }
+    }
+
+    /**
+     * 取消计划并删除文档副本，已加入队列的作业不受影响
+     * @returns 是否找到该计划
+     */
+    pub fn cancel(&mut self, id: &str) -> Result<bool, String> {
+        let Some(index) = self.schedules.iter().position(|s| s.id == id) else {
+            return Ok(false);
+        };
+        let schedule = self.schedules.remove(index);
+        self.save()?;
+        let _ = fs::remove_file(&schedule.path);
+        Ok(true)
+    }
+}
+
+static SCHEDULES: OnceLock<Mutex<Schedules>> = OnceLock::new();
+
+fn schedules() -> Result<MutexGuard<'static, Schedules>, String> {
+    let schedules = SCHEDULES.get().ok_or("打印计划未初始化")?;
+    Ok(schedules.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+/**
+ * 打开计划目录并启动后台线程，只在插件初始化时调用一次
+ * @param fire 触发计划，通常是加入打印队列
+ */
+pub fn init<F>(dir: &Path, fire: F) -> Result<(), String>
+where
+    F: Fn(&Schedule) -> Result<(), String> + Send + 'static,
+{
+    SCHEDULES
+        .set(Mutex::new(Schedules::open(dir)?))
+        .map_err(|_| "打印计划已经初始化".to_string())?;
+
+    thread::spawn(move || loop {
+        let due = schedules().map(|s| s.due(now_ms())).unwrap_or_default();
+        for schedule in due {
+            let result = fire(&schedule);
+            if let Ok(mut schedules) = schedules() {
+                schedules.fired(&schedule.id, result, now_ms());
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    });
+    Ok(())
+}
+
+pub fn add(
+    source: &Path,
+    printer: &str,
+    settings: Option<PrintSettings>,
+    priority: i32,
+    not_before: Option<i64>,
+    cron: Option<&str>,
+) -> Result<Schedule, String> {
+    schedules()?.add(source, printer, settings, priority, not_before, cron, now_ms())
+}
+
+pub fn list() -> Result<Vec<Schedule>, String> {
+    Ok(schedules()?.list())
+}
+
+pub fn cancel(id: &str) -> Result<bool, String> {
+    schedules()?.cancel(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, Utc};
+
+    use super::*;
+
+    fn at(text: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(text).unwrap()
+    }
+
+    #[test]
+    fn finds_next_cron_time() {
+        let cron = Cron::parse("0 6 * * 1-5").unwrap();
+        // 2024-03-01 是周五
+        assert_eq!(cron.next_after(&at("2024-03-01T14:30:00+08:00")), Some(at("2024-03-04T06:00:00+08:00")));
+        assert_eq!(cron.next_after(&at("2024-03-01T05:59:59+08:00")), Some(at("2024-03-01T06:00:00+08:00")));
+        assert_eq!(cron.next_after(&at("2024-03-01T06:00:00+08:00")), Some(at("2024-03-04T06:00:00+08:00")));
+
+        let every = Cron::parse("*/15 8-9 * * *").unwrap();
+        assert_eq!(every.next_after(&at("2024-03-01T08:50:10Z")), Some(at("2024-03-01T09:00:00Z")));
+        assert_eq!(every.next_after(&at("2024-03-01T09:45:00Z")), Some(at("2024-03-02T08:00:00Z")));
+        assert_eq!(parse_field("5/1", 0, 59).unwrap(), parse_field("5-59", 0, 59).unwrap());
+        assert_eq!(parse_field("50/5", 0, 59).unwrap(), (1 << 50) | (1 << 55));
+        assert_eq!(parse_field("5", 0, 59).unwrap(), 1 << 5);
+
+        // 日和周都指定时满足其一即可
+        let either = Cron::parse("0 0 13 * 5").unwrap();
+        assert_eq!(either.next_after(&at("2024-03-02T00:00:00Z")), Some(at("2024-03-08T00:00:00Z")));
+        let leap = Cron::parse("0 0 29 2 *").unwrap();
+        assert_eq!(leap.next_after(&at("2024-03-01T00:00:00Z")), Some(at("2028-02-29T00:00:00Z")));
+        assert_eq!(Cron::parse("0 0 * * 7").unwrap(), Cron::parse("@weekly").unwrap());
+        assert_eq!(Cron::parse("@daily").unwrap().next_after(&Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap()));
+
+        for invalid in ["", "* * * *", "60 * * * *", "*/0 * * * *", "5-1 * * * *", "a * * * *"] {
+            assert!(Cron::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn fires_once_or_repeatedly_and_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("report.pdf");
+        fs::write(&source, b"%PDF-1.4").unwrap();
+        let mut schedules = Schedules::open(dir.path()).unwrap();
+
+        let once = schedules.add(&source, "Office", None, 0, Some(5000), None, 1000).unwrap();
+        let repeat = schedules.add(&source, "Office", None, 0, None, Some("* * * * *"), 1000).unwrap();
+        assert!(schedules.add(&source, "Office", None, 0, None, None, 1000).is_err());
+        assert!(schedules.add(&source, "Office", None, 0, None, Some("0 25 * * *"), 1000).is_err());
+        assert_eq!(once.next_run_at, 5000);
+        assert!(repeat.next_run_at > 1000 && repeat.next_run_at <= 61_000);
+        assert!(schedules.due(4999).iter().all(|s| s.id != once.id));
+
+        let reopened = Schedules::open(dir.path()).unwrap();
+        assert_eq!(reopened.list().len(), 2);
+
+        schedules.fired(&once.id, Err("打印队列未初始化".to_string()), 5000);
+        let retried = schedules.list().into_iter().find(|s| s.id == once.id).unwrap();
+        assert_eq!(retried.next_run_at, 5000 + RETRY_DELAY_MS);
+        schedules.fired(&once.id, Ok(()), 70_000);
+        assert!(!Path::new(&once.path).exists());
+
+        schedules.fired(&repeat.id, Ok(()), 70_000);
+        let next = schedules.list()[0].clone();
+        assert_eq!((next.id.as_str(), next.last_run_at), (repeat.id.as_str(), Some(70_000)));
+        assert!(next.next_run_at > 70_000 && next.next_run_at <= 130_000);
+
+        assert!(schedules.cancel(&repeat.id).unwrap());
+        assert!(!schedules.cancel(&repeat.id).unwrap());
+        assert!(Schedules::open(dir.path()).unwrap().list().is_empty());
+    }
+}
@@ -0,0 +1,411 @@
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::time::Duration;
+
+/// SNMP 默认端口
+pub const DEFAULT_PORT: u16 = 161;
+pub const DEFAULT_COMMUNITY: &str = "public";
+
+/// 使用 SNMPv1：打印机普遍支持，Printer-MIB 也不需要 v2c 的 64 位计数器
+const VERSION_1: i64 = 0;
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(2000);
+const DEFAULT_RETRIES: u32 = 1;
+/// 防止异常设备让遍历无法结束
+const MAX_WALK_ROWS: usize = 1000;
+
+// BER 标签
+const INTEGER: u8 = 0x02;
+const OCTET_STRING: u8 = 0x04;
+const NULL: u8 = 0x05;
+const OBJECT_IDENTIFIER: u8 = 0x06;
+const SEQUENCE: u8 = 0x30;
+const COUNTER32: u8 = 0x41;
+const GAUGE32: u8 = 0x42;
+const TIME_TICKS: u8 = 0x43;
+const END_OF_MIB_VIEW: u8 = 0x82;
+
+// PDU 类型
+pub const GET_NEXT_REQUEST: u8 = 0xA1;
+pub const GET_RESPONSE: u8 = 0xA2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnmpValue {
+    Integer(i64),
+    String(Vec<u8>),
+    Oid(Vec<u32>),
+    Null,
+    /// v2c 的 noSuchObject/noSuchInstance/endOfMibView
+    Exception(u8),
+    Other(u8, Vec<u8>),
+}
+
+impl SnmpValue {
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            SnmpValue::Integer(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// 打印机返回的字符串常带结尾的 NUL
+    pub fn as_string(&self) -> Option<String> {
+        match self {
+            SnmpValue::String(bytes) => {
+                Some(String::from_utf8_lossy(bytes).trim_end_matches('\0').trim().to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// SNMP 报文（v1/v2c），请求和响应共用
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub version: i64,
+    pub community: String,
+    pub pdu: u8,
+    pub request_id: i64,
+    pub error_status: i64,
+    pub error_index: i64,
+    pub varbinds: Vec<(Vec<u32>, SnmpValue)>,
+}
+
+/**
+ * 解析点分形式的 OID，如 "1.3.6.1.2.1.43"
+ */
+pub fn parse_oid(text: &str) -> Result<Vec<u32>, String> {
+    let oid = text
+        .trim_start_matches('.')
+        .split('.')
+        .map(|part| part.parse::<u32>().map_err(|_| format!("无效的 OID: {}", text)))
+        .collect::<Result<Vec<u32>, String>>()?;
+    if oid.len() < 2 {
+        return Err(format!("无效的 OID: {}", text));
+    }
+    Ok(oid)
+}
+
+fn push_length(out: &mut Vec<u8>, len: usize) {
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes = (len as u32).to_be_bytes();
+        let skip = bytes.iter().take_while(|&&b| b == 0).count();
+        out.push(0x80 | (4 - skip) as u8);
+        out.extend_from_slice(&bytes[skip..]);
+    }
+}
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    push_length(&mut out, content.len());
+    out.extend_from_slice(content);
+    out
+}
+
+fn encode_integer(tag: u8, value: i64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    // 去掉多余的符号扩展字节
+    let mut start = 0;
+    while start < 7 {
+        let redundant = (bytes[start] == 0x00 && bytes[start + 1] & 0x80 == 0)
+            || (bytes[start] == 0xFF && bytes[start + 1] & 0x80 != 0);
+        if !redundant {
+            break;
+        }
+        start += 1;
+    }
+    tlv(tag, &bytes[start..])
+}
+
+fn encode_oid(oid: &[u32]) -> Vec<u8> {
+    let mut content = Vec::new();
+    let first = oid.first().copied().unwrap_or(0) * 40 + oid.get(1).copied().unwrap_or(0);
+    for &arc in std::iter::once(&first).chain(oid.iter().skip(2)) {
+        let mut chunk = vec![(arc & 0x7F) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0 {
+            chunk.push(0x80 | (rest & 0x7F) as u8);
+            rest >>= 7;
+        }
+        content.extend(chunk.iter().rev());
+    }
+    tlv(OBJECT_IDENTIFIER, &content)
+}
+
+fn encode_value(value: &SnmpValue) -> Vec<u8> {
+    match value {
+        SnmpValue::Integer(n) => encode_integer(INTEGER, *n),
+        SnmpValue::String(bytes) => tlv(OCTET_STRING, bytes),
+        SnmpValue::Oid(oid) => encode_oid(oid),
+        SnmpValue::Null => tlv(NULL, &[]),
+        SnmpValue::Exception(tag) => tlv(*tag, &[]),
+        SnmpValue::Other(tag, bytes) => tlv(*tag, bytes),
+    }
+}
+
+impl Message {
+    pub fn encode(&self) -> Vec<u8> {
+        let varbinds: Vec<u8> = self
+            .varbinds
+            .iter()
+            .flat_map(|(oid, value)| tlv(SEQUENCE, &[encode_oid(oid), encode_value(value)].concat()))
+            .collect();
+        let pdu = [
+            encode_integer(INTEGER, self.request_id),
+            encode_integer(INTEGER, self.error_status),
+            encode_integer(INTEGER, self.error_index),
+            tlv(SEQUENCE, &varbinds),
+        ]
+        .concat();
+        let message = [
+            encode_integer(INTEGER, self.version),
+            tlv(OCTET_STRING, self.community.as_bytes()),
+            tlv(self.pdu, &pdu),
+        ]
+        .concat();
+        tlv(SEQUENCE, &message)
+    }
+}
+
+/// 按 BER 顺序读取 TLV
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    fn read(&mut self) -> Result<(u8, &'a [u8]), String> {
+        let truncated = || "SNMP 报文不完整".to_string();
+        let tag = *self.bytes.get(self.pos).ok_or_else(truncated)?;
+        let first = *self.bytes.get(self.pos + 1).ok_or_else(truncated)?;
+        self.pos += 2;
+        let len = if first & 0x80 == 0 {
+            first as usize
+        } else {
+            let count = (first & 0x7F) as usize;
+            if count == 0 || count > 4 {
+                return Err("不支持的 BER 长度".to_string());
+            }
+            let bytes = self.bytes.get(self.pos..self.pos + count).ok_or_else(truncated)?;
+            self.pos += count;
+            bytes.iter().fold(0usize, |acc, &b| (acc << 8) | b as usize)
+        };
+        let content = self.bytes.get(self.pos..self.pos + len).ok_or_else(truncated)?;
+        self.pos += len;
+        Ok((tag, content))
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<&'a [u8], String> {
+        let (tag, content) = self.read()?;
+        if tag != expected {
+            return Err(format!("SNMP 报文格式错误：期望标签 0x{:02X}，实际 0x{:02X}", expected, tag));
+        }
+        Ok(content)
+    }
+
+    fn integer(&mut self) -> Result<i64, String> {
+        Ok(decode_integer(self.expect(INTEGER)?))
+    }
+}
+
+fn decode_integer(content: &[u8]) -> i64 {
+    let initial = if content.first().is_some_and(|b| b & 0x80 != 0) { -1i64 } else { 0 };
+    content.iter().fold(initial, |acc, &b| (acc << 8) | b as i64)
+}
+
+fn decode_unsigned(content: &[u8]) -> i64 {
+    content.iter().fold(0i64, |acc, &b| (acc << 8) | b as i64)
+}
+
+fn decode_oid(content: &[u8]) -> Vec<u32> {
+    let mut arcs = Vec::new();
+    let mut value: u32 = 0;
+    for &b in content {
+        value = (value << 7) | (b & 0x7F) as u32;
+        if b & 0x80 == 0 {
+            arcs.push(value);
+            value = 0;
+        }
+    }
+    let Some(&first) = arcs.first() else {
+        return arcs;
+    };
+    let (a, b) = if first < 80 { (first / 40, first % 40) } else { (2, first - 80) };
+    let mut oid = vec![a, b];
+    oid.extend_from_slice(&arcs[1..]);
+    oid
+}
+
+fn decode_value(tag: u8, content: &[u8]) -> SnmpValue {
+    match tag {
+        INTEGER => SnmpValue::Integer(decode_integer(content)),
+        COUNTER32 | GAUGE32 | TIME_TICKS => SnmpValue::Integer(decode_unsigned(content)),
+        OCTET_STRING => SnmpValue::String(content.to_vec()),
+        OBJECT_IDENTIFIER => SnmpValue::Oid(decode_oid(content)),
+        NULL => SnmpValue::Null,
+        0x80..=0x82 => SnmpValue::Exception(tag),
+        _ => SnmpValue::Other(tag, content.to_vec()),
+    }
+}
+
+/**
+ * 解析 SNMP 报文
+ */
+pub fn decode(bytes: &[u8]) -> Result<Message, String> {
+    let mut message = Reader::new(Reader::new(bytes).expect(SEQUENCE)?);
+    let version = message.integer()?;
+    let community = String::from_utf8_lossy(message.expect(OCTET_STRING)?).to_string();
+    let (pdu, content) = message.read()?;
+    if !(0xA0..=0xA8).contains(&pdu) {
+        return Err(format!("未知的 SNMP PDU 类型 0x{:02X}", pdu));
+    }
+
+    let mut fields = Reader::new(content);
+    let request_id = fields.integer()?;
+    let error_status = fields.integer()?;
+    let error_index = fields.integer()?;
+    let mut list = Reader::new(fields.expect(SEQUENCE)?);
+    let mut varbinds = Vec::new();
+    while !list.is_empty() {
+        let mut varbind = Reader::new(list.expect(SEQUENCE)?);
+        let oid = decode_oid(varbind.expect(OBJECT_IDENTIFIER)?);
+        let (tag, value) = varbind.read()?;
+        varbinds.push((oid, decode_value(tag, value)));
+    }
+
+    Ok(Message { version, community, pdu, request_id, error_status, error_index, varbinds })
+}
+
+/// 基于 UDP 的 SNMP 客户端，只实现遍历需要的 GetNext
+pub struct Client {
+    socket: UdpSocket,
+    community: String,
+    request_id: i64,
+}
+
+impl Client {
+    pub fn connect(host: &str, port: u16, community: &str) -> Result<Self, String> {
+        let addr = (host, port)
+            .to_socket_addrs()
+            .map_err(|e| format!("无法解析 {}: {}", host, e))?
+            .next()
+            .ok_or_else(|| format!("无法解析 {}", host))?;
+        let bind = if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+        let socket = UdpSocket::bind(bind).map_err(|e| e.to_string())?;
+        socket.connect(addr).map_err(|e| format!("无法连接 {}: {}", addr, e))?;
+        socket.set_read_timeout(Some(DEFAULT_TIMEOUT)).map_err(|e| e.to_string())?;
+        Ok(Client { socket, community: community.to_string(), request_id: 0 })
+    }
+
+    fn get_next(&mut self, oid: &[u32]) -> Result<Message, String> {
+        self.request_id += 1;
+        let request = Message {
+            version: VERSION_1,
+            community: self.community.clone(),
+            pdu: GET_NEXT_REQUEST,
+            request_id: self.request_id,
+            error_status: 0,
+            error_index: 0,
+            varbinds: vec![(oid.to_vec(), SnmpValue::Null)],
+        }
+        .encode();
+
+        let mut buffer = [0u8; 65535];
+        for _ in 0..=DEFAULT_RETRIES {
+            self.socket.send(&request).map_err(|e| format!("SNMP 请求发送失败: {}", e))?;
+            // 丢弃无法解析的数据和重试前迟到的旧响应
+            while let Ok(n) = self.socket.recv(&mut buffer) {
+                match decode(&buffer[..n]) {
+                    Ok(response) if response.pdu == GET_RESPONSE && response.request_id == self.request_id => {
+                        return Ok(response);
+                    }
+                    _ => continue,
+                }
+            }
+        }
+        Err("SNMP 请求超时，请检查 community 和打印机的 SNMP 设置".to_string())
+    }
+
+    /**
+     * 遍历 root 下的所有对象
+     * @returns (去掉 root 前缀后的索引, 值)
+     */
+    pub fn walk(&mut self, root: &[u32]) -> Result<Vec<(Vec<u32>, SnmpValue)>, String> {
+        let mut rows = Vec::new();
+        let mut current = root.to_vec();
+        while rows.len() < MAX_WALK_ROWS {
+            let response = self.get_next(&current)?;
+            // v1 在遍历到末尾时返回 noSuchName
+            if response.error_status != 0 {
+                break;
+            }
+            let Some((oid, value)) = response.varbinds.into_iter().next() else {
+                break;
+            };
+            if !oid.starts_with(root) || oid <= current || value == SnmpValue::Exception(END_OF_MIB_VIEW) {
+                break;
+            }
+            rows.push((oid[root.len()..].to_vec(), value));
+            current = oid;
+        }
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_and_decodes_messages() {
+        let message = Message {
+            version: VERSION_1,
+            community: "public".to_string(),
+            pdu: GET_RESPONSE,
+            request_id: 300,
+            error_status: 0,
+            error_index: 0,
+            varbinds: vec![
+                (parse_oid("1.3.6.1.2.1.43.11.1.1.9.1.1").unwrap(), SnmpValue::Integer(-3)),
+                (parse_oid("1.3.6.1.2.1.43.11.1.1.6.1.1").unwrap(), SnmpValue::String(b"Black Toner\0".to_vec())),
+                (parse_oid("1.3.6.1.2.1.1.2.0").unwrap(), SnmpValue::Oid(vec![1, 3, 6, 1, 4, 1, 11, 2, 3, 9, 1])),
+                (parse_oid("1.3.6.1.2.1.1.3.0").unwrap(), SnmpValue::Integer(70000)),
+            ],
+        };
+        let bytes = message.encode();
+        assert_eq!(&bytes[..2], &[SEQUENCE, bytes.len() as u8 - 2]);
+
+        let decoded = decode(&bytes).unwrap();
+        assert_eq!(decoded, message);
+        assert_eq!(decoded.varbinds[1].1.as_string().as_deref(), Some("Black Toner"));
+        // 70000 编码为 INTEGER，需要三个字节
+        assert_eq!(encode_integer(INTEGER, 70000), vec![INTEGER, 3, 0x01, 0x11, 0x70]);
+        assert_eq!(encode_integer(INTEGER, -3), vec![INTEGER, 1, 0xFD]);
+        assert_eq!(encode_oid(&[1, 3, 6, 1, 4, 1, 2680]), vec![OBJECT_IDENTIFIER, 7, 0x2B, 6, 1, 4, 1, 0x94, 0x78]);
+        assert_eq!(decode_value(GAUGE32, &[0x00, 0xFF]), SnmpValue::Integer(255));
+    }
+
+    #[test]
+    fn rejects_truncated_messages() {
+        let bytes = Message {
+            version: VERSION_1,
+            community: "public".to_string(),
+            pdu: GET_NEXT_REQUEST,
+            request_id: 1,
+            error_status: 0,
+            error_index: 0,
+            varbinds: vec![(vec![1, 3, 6], SnmpValue::Null)],
+        }
+        .encode();
+        assert!(decode(&bytes[..bytes.len() - 3]).is_err());
+        assert!(parse_oid("1.3.x").is_err());
+    }
+}
@@ -0,0 +1,368 @@
+use std::process::Command;
+
+use base64::{Engine as _, engine::general_purpose};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::declare::{PrinterBackend, VirtualPrinter};
+use crate::ipp::{self, IppValue};
+use crate::{lpd, raw_socket};
+
+/// 打印机状态，按严重程度从高到低排列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrinterState {
+    Offline,
+    PaperJam,
+    DoorOpen,
+    OutOfPaper,
+    Error,
+    TonerLow,
+    Printing,
+    Idle,
+    Unknown,
+}
+
+impl PrinterState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PrinterState::Offline => "offline",
+            PrinterState::PaperJam => "paper_jam",
+            PrinterState::DoorOpen => "door_open",
+            PrinterState::OutOfPaper => "out_of_paper",
+            PrinterState::Error => "error",
+            PrinterState::TonerLow => "toner_low",
+            PrinterState::Printing => "printing",
+            PrinterState::Idle => "idle",
+            PrinterState::Unknown => "unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PrinterStatus {
+    pub printer: String,
+    /// 最严重的状态
+    pub state: PrinterState,
+    /// 同时存在的所有状态，如缺纸且碳粉不足
+    pub conditions: Vec<PrinterState>,
+    /// 后端给出的原始描述
+    pub message: Option<String>,
+}
+
+impl PrinterStatus {
+    pub fn new(printer: &str, mut conditions: Vec<PrinterState>, message: Option<String>) -> Self {
+        conditions.sort();
+        conditions.dedup();
+        if conditions.is_empty() {
+            conditions.push(PrinterState::Unknown);
+        }
+        PrinterStatus {
+            printer: printer.to_string(),
+            state: conditions[0],
+            conditions,
+            message,
+        }
+    }
+
+    /// 无法连接或查询失败
+    pub fn offline(printer: &str, message: String) -> Self {
+        PrinterStatus::new(printer, vec![PrinterState::Offline], Some(message))
+    }
+}
+
+/**
+ * 解码 `Get-Printer` 的 PrinterStatus（MSFT_Printer.PrinterStatus）
+ */
+pub fn decode_windows_status(code: u64) -> (PrinterState, &'static str) {
+    match code {
+        0 => (PrinterState::Idle, "Normal"),
+        1 => (PrinterState::Offline, "Paused"),
+        2 => (PrinterState::Error, "Error"),
+        3 => (PrinterState::Error, "PendingDeletion"),
+        4 => (PrinterState::PaperJam, "PaperJam"),
+        5 => (PrinterState::OutOfPaper, "PaperOut"),
+        6 => (PrinterState::Error, "ManualFeed"),
+        7 => (PrinterState::Error, "PaperProblem"),
+        8 => (PrinterState::Offline, "Offline"),
+        9 => (PrinterState::Printing, "IOActive"),
+        10 => (PrinterState::Printing, "Busy"),
+        11 => (PrinterState::Printing, "Printing"),
+        12 => (PrinterState::Error, "OutputBinFull"),
+        13 => (PrinterState::Offline, "NotAvailable"),
+        14 => (PrinterState::Idle, "Waiting"),
+        15 => (PrinterState::Printing, "Processing"),
+        16 => (PrinterState::Idle, "Initialization"),
+        17 => (PrinterState::Idle, "WarmingUp"),
+        18 => (PrinterState::TonerLow, "TonerLow"),
+        19 => (PrinterState::Error, "NoToner"),
+        20 => (PrinterState::Error, "PagePunt"),
+        21 => (PrinterState::Error, "UserIntervention"),
+        22 => (PrinterState::Error, "OutOfMemory"),
+        23 => (PrinterState::DoorOpen, "DoorOpen"),
+        24 => (PrinterState::Unknown, "ServerUnknown"),
+        25 => (PrinterState::Idle, "PowerSave"),
+        _ => (PrinterState::Unknown, "Unknown"),
+    }
+}
+
+/// 与 decode_windows_status 相反，按 `Get-Printer` 的 PrinterStatus 输出虚拟打印机的状态
+pub fn windows_code(state: PrinterState) -> u64 {
+    match state {
+        PrinterState::Idle => 0,
+        PrinterState::Error => 2,
+        PrinterState::PaperJam => 4,
+        PrinterState::OutOfPaper => 5,
+        PrinterState::Offline => 8,
+        PrinterState::Printing => 11,
+        PrinterState::TonerLow => 18,
+        PrinterState::DoorOpen => 23,
+        PrinterState::Unknown => 24,
+    }
+}
+
+/**
+ * 从 `get_printers_by_name` 的输出中读取状态
+ */
+pub fn from_windows_json(printer: &str, json: &str) -> PrinterStatus {
+    let value: Value = serde_json::from_str(json.trim()).unwrap_or(Value::Null);
+    match value["PrinterStatus"].as_u64() {
+        Some(code) => {
+            let (state, name) = decode_windows_status(code);
+            PrinterStatus::new(printer, vec![state], Some(name.to_string()))
+        }
+        None => PrinterStatus::offline(printer, "未找到打印机".to_string()),
+    }
+}
+
+/**
+ * 解码 IPP 的 printer-state 和 printer-state-reasons
+ */
+pub fn from_ipp(printer: &str, state: Option<i32>, reasons: &[String]) -> PrinterStatus {
+    let mut conditions = Vec::new();
+    for reason in reasons {
+        // 去掉 -report/-warning/-error 后缀
+        let keyword = reason
+            .trim_end_matches("-report")
+            .trim_end_matches("-warning")
+            .trim_end_matches("-error");
+        let condition = match keyword {
+            "none" => continue,
+            "media-jam" => PrinterState::PaperJam,
+            "media-empty" | "media-needed" | "input-tray-missing" => PrinterState::OutOfPaper,
+            "door-open" | "cover-open" | "interlock-open" => PrinterState::DoorOpen,
+            "toner-low" | "marker-supply-low" | "developer-low" => PrinterState::TonerLow,
+            "offline" | "shutdown" | "connecting-to-device" | "timed-out" => PrinterState::Offline,
+            "paused" | "moving-to-paused" => PrinterState::Offline,
+            _ if reason.ends_with("-error") => PrinterState::Error,
+            _ => continue,
+        };
+        conditions.push(condition);
+    }
+    match state {
+        Some(3) => conditions.push(PrinterState::Idle),
+        Some(4) => conditions.push(PrinterState::Printing),
+        // stopped 且没有给出原因
+        Some(5) if conditions.is_empty() => conditions.push(PrinterState::Error),
+        _ => {}
+    }
+    let message = (!reasons.is_empty()).then(|| reasons.join(","));
+    PrinterStatus::new(printer, conditions, message)
+}
+
+/**
+ * 解码 ESC/POS `DLE EOT n` 的实时状态回传
+ * @param query 发送的状态查询指令，根据 n 判断回传字节的含义
+ */
+pub fn from_escpos(printer: &str, query: &[u8], response: &[u8]) -> PrinterStatus {
+    let Some(&byte) = response.last() else {
+        return PrinterStatus::new(printer, vec![PrinterState::Idle], None);
+    };
+    let mut conditions = Vec::new();
+    match query {
+        // n=1 打印机状态
+        [0x10, 0x04, 1] if byte & 0x08 != 0 => conditions.push(PrinterState::Offline),
+        // n=2 脱机原因
+        [0x10, 0x04, 2] => {
+            if byte & 0x04 != 0 {
+                conditions.push(PrinterState::DoorOpen);
+            }
+            if byte & 0x20 != 0 {
+                conditions.push(PrinterState::OutOfPaper);
+            }
+            if byte & 0x40 != 0 {
+                conditions.push(PrinterState::Error);
+            }
+        }
+        // n=3 错误原因
+        [0x10, 0x04, 3] => {
+            if byte & 0x08 != 0 {
+                conditions.push(PrinterState::PaperJam);
+            }
+            if byte & 0x60 != 0 {
+                conditions.push(PrinterState::Error);
+            }
+        }
+        // n=4 纸卷传感器，纸将尽按碳粉不足同级提示
+        [0x10, 0x04, 4] => {
+            if byte & 0x60 != 0 {
+                conditions.push(PrinterState::OutOfPaper);
+            } else if byte & 0x0C != 0 {
+                conditions.push(PrinterState::TonerLow);
+            }
+        }
+        _ => {}
+    }
+    if conditions.is_empty() {
+        conditions.push(PrinterState::Idle);
+    }
+    PrinterStatus::new(printer, conditions, Some(format!("0x{:02X}", byte)))
+}
+
+/**
+ * 解析 CUPS `lpstat -p <printer>` 的输出
+ * 如 "printer office is idle.  enabled since ..."、"printer office now printing office-12."
+ */
+pub fn from_lpstat(printer: &str, output: &str) -> PrinterStatus {
+    let text = output.to_lowercase();
+    let mut conditions = Vec::new();
+    if text.contains("disabled") || text.contains("unable to connect") || text.contains("not connected") {
+        conditions.push(PrinterState::Offline);
+    }
+    if text.contains("paper jam") || text.contains("media-jam") {
+        conditions.push(PrinterState::PaperJam);
+    }
+    if text.contains("out of paper") || text.contains("media-empty") {
+        conditions.push(PrinterState::OutOfPaper);
+    }
+    if text.contains("door open") || text.contains("cover open") {
+        conditions.push(PrinterState::DoorOpen);
+    }
+    if text.contains("toner low") || text.contains("toner-low") || text.contains("low on toner") {
+        conditions.push(PrinterState::TonerLow);
+    }
+    if text.contains("now printing") {
+        conditions.push(PrinterState::Printing);
+    } else if text.contains("is idle") {
+        conditions.push(PrinterState::Idle);
+    }
+    let message = output.lines().map(str::trim).find(|line| !line.is_empty()).map(str::to_string);
+    PrinterStatus::new(printer, conditions, message)
+}
+
+/**
+ * 查询虚拟打印机的状态
+ * raw 后端配置了 status_query 时解码回传数据，否则只检查能否连接
+ */
+pub fn for_virtual(printer: &VirtualPrinter) -> PrinterStatus {
+    let name = printer.name.as_str();
+    match &printer.backend {
+        PrinterBackend::Raw(settings) => match settings.status_query.as_deref() {
+            Some(query) => {
+                let query = general_purpose::STANDARD.decode(query).unwrap_or_default();
+                match raw_socket::query_status(settings) {
+                    Ok(response) => from_escpos(name, &query, &response.unwrap_or_default()),
+                    Err(e) => PrinterStatus::offline(name, e),
+                }
+            }
+            None => match raw_socket::probe(settings) {
+                Ok(()) => PrinterStatus::new(name, vec![PrinterState::Idle], None),
+                Err(e) => PrinterStatus::offline(name, e),
+            },
+        },
+        PrinterBackend::Lpd(settings) => match lpd::query_queue(settings, false) {
+            Ok(text) => {
+                let state = if lpd::parse_queue(&text, name).is_empty() {
+                    PrinterState::Idle
+                } else {
+                    PrinterState::Printing
+                };
+                PrinterStatus::new(name, vec![state], None)
+            }
+            Err(e) => PrinterStatus::offline(name, e),
+        },
+        PrinterBackend::Ipp(settings) => {
+            match ipp::get_printer_attributes(settings, &["printer-state", "printer-state-reasons"]) {
+                Ok(attributes) => {
+                    let state = attributes
+                        .get("printer-state")
+                        .and_then(|a| a.values.first())
+                        .and_then(IppValue::as_i32);
+                    let reasons = attributes.get("printer-state-reasons").map(|a| a.strings()).unwrap_or_default();
+                    from_ipp(name, state, &reasons)
+                }
+                Err(e) => PrinterStatus::offline(name, e),
+            }
+        }
+    }
+}
+
+/**
+ * 通过 CUPS 的 lpstat 查询打印机状态
+ */
+pub fn query_lpstat(printer: &str) -> PrinterStatus {
+    match Command::new("lpstat").args(["-l", "-p", printer]).output() {
+        Ok(output) if output.status.success() => from_lpstat(printer, &String::from_utf8_lossy(&output.stdout)),
+        Ok(output) => PrinterStatus::offline(printer, String::from_utf8_lossy(&output.stderr).trim().to_string()),
+        Err(e) => PrinterStatus::new(printer, Vec::new(), Some(format!("无法执行 lpstat: {}", e))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_windows_status() {
+        let status = from_windows_json("HP", r#"{ "Name": "HP", "PrinterStatus": 5 }"#);
+        assert_eq!(status.state, PrinterState::OutOfPaper);
+        assert_eq!(status.message.as_deref(), Some("PaperOut"));
+        assert_eq!(decode_windows_status(23).0, PrinterState::DoorOpen);
+        assert_eq!(from_windows_json("missing", "").state, PrinterState::Offline);
+
+        let states = [
+            PrinterState::Offline,
+            PrinterState::PaperJam,
+            PrinterState::DoorOpen,
+            PrinterState::OutOfPaper,
+            PrinterState::Error,
+            PrinterState::TonerLow,
+            PrinterState::Printing,
+            PrinterState::Idle,
+            PrinterState::Unknown,
+        ];
+        for state in states {
+            assert_eq!(decode_windows_status(windows_code(state)).0, state);
+        }
+    }
+
+    #[test]
+    fn decodes_ipp_reasons() {
+        let reasons = vec!["media-empty-error".to_string(), "toner-low-warning".to_string()];
+        let status = from_ipp("Office", Some(5), &reasons);
+        assert_eq!(status.state, PrinterState::OutOfPaper);
+        assert_eq!(status.conditions, vec![PrinterState::OutOfPaper, PrinterState::TonerLow]);
+
+        let idle = from_ipp("Office", Some(3), &["none".to_string()]);
+        assert_eq!(idle.conditions, vec![PrinterState::Idle]);
+        assert_eq!(from_ipp("Office", Some(5), &[]).state, PrinterState::Error);
+    }
+
+    #[test]
+    fn decodes_escpos_status_bytes() {
+        // n=2：上盖打开 + 缺纸
+        let status = from_escpos("Receipt", &[0x10, 0x04, 2], &[0x12 | 0x04 | 0x20]);
+        assert_eq!(status.conditions, vec![PrinterState::DoorOpen, PrinterState::OutOfPaper]);
+        // n=4：纸将尽
+        assert_eq!(from_escpos("Receipt", &[0x10, 0x04, 4], &[0x12 | 0x0C]).state, PrinterState::TonerLow);
+        assert_eq!(from_escpos("Receipt", &[0x10, 0x04, 1], &[0x16]).state, PrinterState::Idle);
+    }
+
+    #[test]
+    fn parses_lpstat_output() {
+        let printing = from_lpstat("office", "printer office now printing office-12.  enabled since Mon\n");
+        assert_eq!(printing.state, PrinterState::Printing);
+        let disabled = from_lpstat("office", "printer office disabled since Mon -\n\tPaper jam\n");
+        assert_eq!(disabled.conditions, vec![PrinterState::Offline, PrinterState::PaperJam]);
+    }
+}
@@ -0,0 +1,385 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::declare::{IppSettings, PrinterBackend, VirtualPrinter};
+use crate::ipp::{self, IppGroup};
+use crate::snmp::{self, SnmpValue};
+
+/// 打印机没有给出低量阈值时，低于该百分比视为不足
+const DEFAULT_LOW_PERCENT: u8 = 10;
+
+// Printer-MIB（RFC 3805）
+const PRT_INPUT_ENTRY: &[u32] = &[1, 3, 6, 1, 2, 1, 43, 8, 2, 1];
+const PRT_MARKER_SUPPLIES_ENTRY: &[u32] = &[1, 3, 6, 1, 2, 1, 43, 11, 1, 1];
+const PRT_MARKER_COLORANT_VALUE: &[u32] = &[1, 3, 6, 1, 2, 1, 43, 12, 1, 1, 4];
+
+// prtMarkerSuppliesEntry 的列
+const SUPPLIES_COLORANT_INDEX: u32 = 3;
+const SUPPLIES_TYPE: u32 = 5;
+const SUPPLIES_DESCRIPTION: u32 = 6;
+const SUPPLIES_MAX_CAPACITY: u32 = 8;
+const SUPPLIES_LEVEL: u32 = 9;
+
+// prtInputEntry 的列
+const INPUT_MAX_CAPACITY: u32 = 9;
+const INPUT_CURRENT_LEVEL: u32 = 10;
+const INPUT_NAME: u32 = 13;
+
+/// 纸盒的 kind
+pub const PAPER: &str = "paper";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SupplyState {
+    Ok,
+    Low,
+    Empty,
+    Unknown,
+}
+
+/// 耗材或纸盒
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Supply {
+    pub name: String,
+    /// IPP marker-types 关键字，如 toner、ink-cartridge、opc、waste-toner；纸盒为 paper
+    pub kind: String,
+    /// black/cyan/magenta/yellow，其他颜色保留打印机给出的值（如 "#00FFFF#FF00FF"）
+    pub color: Option<String>,
+    /// 余量百分比，打印机只报告“有余量”或未知时为空；废粉盒等回收容器为剩余空间
+    pub level_percent: Option<u8>,
+    pub state: SupplyState,
+}
+
+/// 打印机的原始余量：-1 其他、-2 未知、-3 有余量但数量未知
+fn supply_state(level: Option<u8>, raw: i64, low_percent: u8) -> SupplyState {
+    match level {
+        Some(0) => SupplyState::Empty,
+        Some(level) if level <= low_percent => SupplyState::Low,
+        Some(_) => SupplyState::Ok,
+        None if raw == -3 => SupplyState::Ok,
+        None => SupplyState::Unknown,
+    }
+}
+
+fn supply(name: String, kind: &str, color: Option<String>, level: Option<u8>, raw: i64, low_percent: u8) -> Supply {
+    Supply {
+        name,
+        kind: kind.to_string(),
+        color,
+        level_percent: level,
+        state: supply_state(level, raw, low_percent),
+    }
+}
+
+/// 标准色的 sRGB 值换成名称
+fn color_name(color: &str) -> Option<String> {
+    let color = color.trim();
+    let name = match color.to_ascii_lowercase().as_str() {
+        "" | "none" | "unknown" => return None,
+        "#000000" => "black",
+        "#00ffff" => "cyan",
+        "#ff00ff" => "magenta",
+        "#ffff00" => "yellow",
+        _ => color,
+    };
+    Some(name.to_string())
+}
+
+/// prtMarkerSuppliesType 转为 IPP marker-types 关键字
+fn snmp_supply_kind(code: i64) -> &'static str {
+    match code {
+        3 => "toner",
+        4 => "waste-toner",
+        5 => "ink",
+        6 => "ink-cartridge",
+        7 => "ink-ribbon",
+        8 => "waste-ink",
+        9 => "opc",
+        10 => "developer",
+        11 => "fuser-oil",
+        12 => "solid-wax",
+        13 => "ribbon-wax",
+        14 => "waste-wax",
+        15 => "fuser",
+        16 => "corona-wire",
+        17 => "fuser-oil-wick",
+        18 => "cleaner-unit",
+        19 => "fuser-cleaning-pad",
+        20 => "transfer-unit",
+        21 => "toner-cartridge",
+        22 => "fuser-oiler",
+        23 => "water",
+        24 => "waste-water",
+        26 => "waste-paper",
+        27 => "binding-supply",
+        28 => "banding-supply",
+        29 => "stitching-wire",
+        30 => "shrink-wrap",
+        31 => "paper-wrap",
+        32 => "staples",
+        33 => "inserts",
+        34 => "covers",
+        _ => "other",
+    }
+}
+
+fn percent(level: i64, max: i64) -> Option<u8> {
+    if level < 0 || max <= 0 {
+        return None;
+    }
+    Some((level * 100 / max).clamp(0, 100) as u8)
+}
+
+/**
+ * 从 IPP 的 marker-names、marker-levels、marker-colors、marker-types、marker-low-levels 转换
+ */
+pub fn from_ipp_attributes(group: &IppGroup) -> Vec<Supply> {
+    let strings = |name: &str| group.get(name).map(|a| a.strings()).unwrap_or_default();
+    let integers = |name: &str| group.get(name).map(|a| a.integers()).unwrap_or_default();
+    let names = strings("marker-names");
+    let levels = integers("marker-levels");
+    let colors = strings("marker-colors");
+    let kinds = strings("marker-types");
+    let low_levels = integers("marker-low-levels");
+
+    (0..names.len().max(levels.len()))
+        .map(|i| {
+            let raw = levels.get(i).copied().unwrap_or(-2) as i64;
+            let level = percent(raw, 100);
+            let low = low_levels.get(i).and_then(|&l| u8::try_from(l).ok()).unwrap_or(DEFAULT_LOW_PERCENT);
+            supply(
+                names.get(i).cloned().unwrap_or_else(|| format!("marker-{}", i + 1)),
+                kinds.get(i).map(String::as_str).unwrap_or("other"),
+                colors.get(i).and_then(|c| color_name(c)),
+                level,
+                raw,
+                low,
+            )
+        })
+        .collect()
+}
+
+/**
+ * 通过 IPP Get-Printer-Attributes 查询耗材
+ */
+pub fn query_ipp(settings: &IppSettings) -> Result<Vec<Supply>, String> {
+    let attributes = ipp::get_printer_attributes(
+        settings,
+        &["marker-names", "marker-levels", "marker-colors", "marker-types", "marker-low-levels"],
+    )?;
+    Ok(from_ipp_attributes(&attributes))
+}
+
+/**
+ * 通过本机 CUPS 查询队列的耗材，CUPS 会从后端汇总 marker-* 属性
+ */
+pub fn query_cups(printer: &str) -> Result<Vec<Supply>, String> {
+    let settings = IppSettings {
+        uri: cups_uri(printer),
+        user: None,
+        document_format: None,
+        connect_timeout_ms: None,
+    };
+    query_ipp(&settings)
+}
+
+/// 本机 CUPS 队列的地址，队列名中除字母、数字和 `-._~` 以外的字节按百分号编码
+fn cups_uri(printer: &str) -> String {
+    let mut uri = format!("ipp://localhost:{}/printers/", ipp::DEFAULT_PORT);
+    for byte in printer.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+type Column = BTreeMap<Vec<u32>, SnmpValue>;
+
+fn walk_column(client: &mut snmp::Client, entry: &[u32], column: u32) -> Result<Column, String> {
+    let root = [entry, &[column]].concat();
+    Ok(client.walk(&root)?.into_iter().collect())
+}
+
+fn integer_at(column: &Column, index: &[u32]) -> i64 {
+    column.get(index).and_then(SnmpValue::as_i64).unwrap_or(-2)
+}
+
+/**
+ * 通过 SNMP Printer-MIB 查询耗材（prtMarkerSuppliesTable）和纸盒（prtInputTable）
+ */
+pub fn query_snmp(host: &str, port: u16, community: &str) -> Result<Vec<Supply>, String> {
+    let mut client = snmp::Client::connect(host, port, community)?;
+
+    let descriptions = walk_column(&mut client, PRT_MARKER_SUPPLIES_ENTRY, SUPPLIES_DESCRIPTION)?;
+    let kinds = walk_column(&mut client, PRT_MARKER_SUPPLIES_ENTRY, SUPPLIES_TYPE)?;
+    let max_capacities = walk_column(&mut client, PRT_MARKER_SUPPLIES_ENTRY, SUPPLIES_MAX_CAPACITY)?;
+    let levels = walk_column(&mut client, PRT_MARKER_SUPPLIES_ENTRY, SUPPLIES_LEVEL)?;
+    let colorant_indexes = walk_column(&mut client, PRT_MARKER_SUPPLIES_ENTRY, SUPPLIES_COLORANT_INDEX)?;
+    let colorants: Column = client.walk(PRT_MARKER_COLORANT_VALUE)?.into_iter().collect();
+
+    let mut supplies: Vec<Supply> = descriptions
+        .iter()
+        .map(|(index, description)| {
+            let raw = integer_at(&levels, index);
+            // 着色剂表的索引为 hrDeviceIndex.prtMarkerColorantIndex
+            let color = match (index.first(), integer_at(&colorant_indexes, index)) {
+                (Some(&device), colorant) if colorant > 0 => colorants
+                    .get(&vec![device, colorant as u32])
+                    .and_then(SnmpValue::as_string)
+                    .and_then(|c| color_name(&c)),
+                _ => None,
+            };
+            supply(
+                description.as_string().unwrap_or_default(),
+                snmp_supply_kind(integer_at(&kinds, index)),
+                color,
+                percent(raw, integer_at(&max_capacities, index)),
+                raw,
+                DEFAULT_LOW_PERCENT,
+            )
+        })
+        .collect();
+
+    let names = walk_column(&mut client, PRT_INPUT_ENTRY, INPUT_NAME)?;
+    let max_capacities = walk_column(&mut client, PRT_INPUT_ENTRY, INPUT_MAX_CAPACITY)?;
+    let levels = walk_column(&mut client, PRT_INPUT_ENTRY, INPUT_CURRENT_LEVEL)?;
+    for (index, raw) in levels.iter().map(|(index, level)| (index, level.as_i64().unwrap_or(-2))) {
+        let name = names
+            .get(index)
+            .and_then(SnmpValue::as_string)
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| format!("Tray {}", index.last().copied().unwrap_or(0)));
+        let level = percent(raw, integer_at(&max_capacities, index));
+        supplies.push(supply(name, PAPER, None, level, raw, DEFAULT_LOW_PERCENT));
+    }
+
+    Ok(supplies)
+}
+
+/**
+ * 查询虚拟打印机的耗材：IPP 使用 marker-* 属性，raw/lpd 对同一主机使用 SNMP
+ */
+pub fn for_virtual(printer: &VirtualPrinter, community: &str) -> Result<Vec<Supply>, String> {
+    match &printer.backend {
+        PrinterBackend::Ipp(settings) => query_ipp(settings),
+        PrinterBackend::Raw(settings) => query_snmp(&settings.host, snmp::DEFAULT_PORT, community),
+        PrinterBackend::Lpd(settings) => query_snmp(&settings.host, snmp::DEFAULT_PORT, community),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipp::{IppAttribute, IppValue};
+    use crate::snmp::Message;
+    use std::net::UdpSocket;
+    use std::thread;
+    use std::time::Duration;
+
+    fn attribute(name: &str, values: Vec<IppValue>) -> IppAttribute {
+        IppAttribute { name: name.to_string(), values }
+    }
+
+    fn texts(values: &[&str]) -> Vec<IppValue> {
+        values.iter().map(|v| IppValue::Text(v.to_string())).collect()
+    }
+
+    #[test]
+    fn converts_ipp_marker_attributes() {
+        let group = IppGroup {
+            tag: ipp::PRINTER_ATTRIBUTES,
+            attributes: vec![
+                attribute("marker-names", texts(&["Black Toner", "Tri-color Ink", "Drum"])),
+                attribute("marker-levels", vec![IppValue::Integer(62), IppValue::Integer(4), IppValue::Integer(-3)]),
+                attribute("marker-colors", texts(&["#000000", "#00FFFF#FF00FF#FFFF00", "none"])),
+                attribute("marker-types", texts(&["toner-cartridge", "ink-cartridge", "opc"])),
+                attribute("marker-low-levels", vec![IppValue::Integer(15), IppValue::Integer(5), IppValue::Integer(-1)]),
+            ],
+        };
+
+        let supplies = from_ipp_attributes(&group);
+        assert_eq!(supplies.len(), 3);
+        assert_eq!(supplies[0].color.as_deref(), Some("black"));
+        assert_eq!((supplies[0].level_percent, supplies[0].state), (Some(62), SupplyState::Ok));
+        assert_eq!(supplies[1].color.as_deref(), Some("#00FFFF#FF00FF#FFFF00"));
+        assert_eq!(supplies[1].state, SupplyState::Low);
+        assert_eq!(supplies[2].kind, "opc");
+        assert_eq!((supplies[2].level_percent, supplies[2].state), (None, SupplyState::Ok));
+    }
+
+    /// 在 UDP 端口上按表应答 GetNext 的 SNMP 代理
+    fn agent(table: Vec<(&str, SnmpValue)>) -> u16 {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let port = socket.local_addr().unwrap().port();
+        let table: BTreeMap<Vec<u32>, SnmpValue> =
+            table.into_iter().map(|(oid, value)| (snmp::parse_oid(oid).unwrap(), value)).collect();
+        thread::spawn(move || {
+            let mut buffer = [0u8; 1500];
+            while let Ok((n, peer)) = socket.recv_from(&mut buffer) {
+                let request = snmp::decode(&buffer[..n]).unwrap();
+                assert_eq!((request.pdu, request.community.as_str()), (snmp::GET_NEXT_REQUEST, "private"));
+                let oid = &request.varbinds[0].0;
+                let next = table.range(oid.clone()..).find(|(key, _)| *key > oid);
+                let (error_status, varbinds) = match next {
+                    Some((key, value)) => (0, vec![(key.clone(), value.clone())]),
+                    None => (2, request.varbinds.clone()),
+                };
+                let response = Message { pdu: snmp::GET_RESPONSE, error_status, error_index: 0, varbinds, ..request };
+                socket.send_to(&response.encode(), peer).unwrap();
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn encodes_cups_queue_names() {
+        assert_eq!(cups_uri("HP_LaserJet-4.0"), "ipp://localhost:631/printers/HP_LaserJet-4.0");
+        assert_eq!(cups_uri("Front Desk/2#a?b"), "ipp://localhost:631/printers/Front%20Desk%2F2%23a%3Fb");
+        assert_eq!(cups_uri("标签"), "ipp://localhost:631/printers/%E6%A0%87%E7%AD%BE");
+    }
+
+    #[test]
+    fn reads_printer_mib_over_snmp() {
+        let text = |s: &str| SnmpValue::String(s.as_bytes().to_vec());
+        let port = agent(vec![
+            ("1.3.6.1.2.1.1.5.0", text("laser")),
+            ("1.3.6.1.2.1.43.8.2.1.9.1.1", SnmpValue::Integer(250)),
+            ("1.3.6.1.2.1.43.8.2.1.9.1.2", SnmpValue::Integer(-2)),
+            ("1.3.6.1.2.1.43.8.2.1.10.1.1", SnmpValue::Integer(0)),
+            ("1.3.6.1.2.1.43.8.2.1.10.1.2", SnmpValue::Integer(-3)),
+            ("1.3.6.1.2.1.43.8.2.1.13.1.1", text("Cassette 1")),
+            ("1.3.6.1.2.1.43.8.2.1.13.1.2", text("")),
+            ("1.3.6.1.2.1.43.11.1.1.3.1.1", SnmpValue::Integer(1)),
+            ("1.3.6.1.2.1.43.11.1.1.3.1.2", SnmpValue::Integer(0)),
+            ("1.3.6.1.2.1.43.11.1.1.5.1.1", SnmpValue::Integer(21)),
+            ("1.3.6.1.2.1.43.11.1.1.5.1.2", SnmpValue::Integer(4)),
+            ("1.3.6.1.2.1.43.11.1.1.6.1.1", text("Black Cartridge CF259A\0")),
+            ("1.3.6.1.2.1.43.11.1.1.6.1.2", text("Waste Toner Box")),
+            ("1.3.6.1.2.1.43.11.1.1.8.1.1", SnmpValue::Integer(3000)),
+            ("1.3.6.1.2.1.43.11.1.1.8.1.2", SnmpValue::Integer(-2)),
+            ("1.3.6.1.2.1.43.11.1.1.9.1.1", SnmpValue::Integer(240)),
+            ("1.3.6.1.2.1.43.11.1.1.9.1.2", SnmpValue::Integer(-2)),
+            ("1.3.6.1.2.1.43.12.1.1.4.1.1", text("black")),
+            ("1.3.6.1.2.1.43.13.4.1.1", SnmpValue::Integer(1)),
+        ]);
+
+        let supplies = query_snmp("127.0.0.1", port, "private").unwrap();
+        assert_eq!(supplies.len(), 4);
+
+        let toner = &supplies[0];
+        assert_eq!((toner.name.as_str(), toner.kind.as_str()), ("Black Cartridge CF259A", "toner-cartridge"));
+        assert_eq!((toner.color.as_deref(), toner.level_percent, toner.state), (Some("black"), Some(8), SupplyState::Low));
+
+        let waste = &supplies[1];
+        assert_eq!((waste.kind.as_str(), waste.color.as_deref(), waste.state), ("waste-toner", None, SupplyState::Unknown));
+
+        assert_eq!((supplies[2].name.as_str(), supplies[2].kind.as_str()), ("Cassette 1", PAPER));
+        assert_eq!((supplies[2].level_percent, supplies[2].state), (Some(0), SupplyState::Empty));
+        assert_eq!((supplies[3].name.as_str(), supplies[3].state), ("Tray 2", SupplyState::Ok));
+    }
+}
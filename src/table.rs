@@ -0,0 +1,311 @@
+use serde_json::Value;
+
+use crate::declare::{ColumnAlign, NumberFormat, TableColumn, TableOptions, TableTotal};
+use crate::markdown::escape_html;
+use crate::templates::format_number;
+
+/// 表格的打印样式表，分页时重复表头，行不跨页
+const STYLESHEET: &str = r#"
+body { font-family: "Helvetica Neue", Arial, "Microsoft YaHei", "PingFang SC", "Noto Sans CJK SC", sans-serif; font-size: 10pt; color: #222; }
+h1 { font-size: 16pt; margin: 0 0 0.6em; }
+table { border-collapse: collapse; width: 100%; }
+thead { display: table-header-group; }
+tr { page-break-inside: avoid; }
+th, td { border: 1px solid #999; padding: 0.25em 0.5em; vertical-align: top; word-wrap: break-word; }
+th { background: #eee; }
+tr.total td { font-weight: bold; border-top: 2px solid #333; }
+"#;
+
+/// 确定了数据来源的列
+struct Column {
+    index: usize,
+    header: String,
+    align: ColumnAlign,
+    settings: TableColumn,
+}
+
+fn cell_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn parse_number(text: &str) -> Option<f64> {
+    text.trim().parse::<f64>().ok().filter(|n| n.is_finite())
+}
+
+/// 读取 CSV 或 JSON 数组，返回列名和按列名对齐的行
+fn read_rows(options: &TableOptions) -> Result<(Vec<String>, Vec<Vec<String>>), String> {
+    match (&options.csv, &options.rows) {
+        (Some(csv), None) => {
+            let delimiter = options.delimiter.unwrap_or(',');
+            if !delimiter.is_ascii() {
+                return Err(format!("CSV 分隔符 {} 必须是 ASCII 字符", delimiter));
+            }
+            let mut reader = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .delimiter(delimiter as u8)
+                .from_reader(csv.trim_start_matches('\u{FEFF}').as_bytes());
+            let mut rows = Vec::new();
+            for record in reader.records() {
+                let record = record.map_err(|e| format!("CSV 解析失败: {}", e))?;
+                rows.push(record.iter().map(str::to_string).collect::<Vec<_>>());
+            }
+            let names = if options.csv_header.unwrap_or(true) && !rows.is_empty() { rows.remove(0) } else { Vec::new() };
+            Ok((names, rows))
+        }
+        (None, Some(Value::Array(items))) => {
+            let mut names: Vec<String> = Vec::new();
+            let mut rows = Vec::new();
+            for (i, item) in items.iter().enumerate() {
+                match item {
+                    Value::Object(fields) => {
+                        let mut row = vec![String::new(); names.len()];
+                        for (key, value) in fields {
+                            let index = names.iter().position(|name| name == key).unwrap_or_else(|| {
+                                names.push(key.clone());
+                                row.push(String::new());
+                                names.len() - 1
+                            });
+                            row[index] = cell_text(value);
+                        }
+                        rows.push(row);
+                    }
+                    Value::Array(values) => rows.push(values.iter().map(cell_text).collect()),
+                    _ => return Err(format!("第 {} 行不是对象或数组", i + 1)),
+                }
+            }
+            Ok((names, rows))
+        }
+        (None, Some(_)) => Err("rows 必须是 JSON 数组".to_string()),
+        _ => Err("csv 和 rows 必须且只能提供一个".to_string()),
+    }
+}
+
+/// 数字列：设置了格式或统计方式，或者所有非空单元格都是数字
+fn is_numeric(column: &TableColumn, cells: &[&str]) -> bool {
+    column.format.is_some()
+        || column.total.is_some_and(|total| total != TableTotal::Count)
+        || (cells.iter().any(|cell| !cell.trim().is_empty())
+            && cells.iter().all(|cell| cell.trim().is_empty() || parse_number(cell).is_some()))
+}
+
+fn resolve_columns(options: &TableOptions, names: &[String], rows: &[Vec<String>]) -> Result<Vec<Column>, String> {
+    let settings: Vec<TableColumn> = if options.columns.is_empty() {
+        let count = rows.iter().map(Vec::len).chain([names.len()]).max().unwrap_or(0);
+        vec![TableColumn::default(); count]
+    } else {
+        options.columns.clone()
+    };
+    let mut columns = Vec::new();
+    for (i, settings) in settings.into_iter().enumerate() {
+        let index = match &settings.key {
+            Some(key) => names.iter().position(|name| name == key).ok_or_else(|| format!("找不到列 {}", key))?,
+            None => i,
+        };
+        let header = settings.header.clone().or_else(|| settings.key.clone()).or_else(|| names.get(index).cloned()).unwrap_or_default();
+        let cells: Vec<&str> = rows.iter().map(|row| row.get(index).map_or("", String::as_str)).collect();
+        let align = settings.align.unwrap_or(if is_numeric(&settings, &cells) { ColumnAlign::Right } else { ColumnAlign::Left });
+        columns.push(Column { index, header, align, settings });
+    }
+    Ok(columns)
+}
+
+/// 按格式显示数字，没有设置小数位数时原样显示（去掉浮点误差）
+fn format_value(value: f64, format: &NumberFormat) -> String {
+    let plain = ((value * 1e10).round() / 1e10).to_string();
+    let decimals = match format.decimals {
+        Some(decimals) => decimals as usize,
+        None => plain.split_once('.').map_or(0, |(_, fraction)| fraction.len()),
+    };
+    let mut text = format_number(value, decimals);
+    if !format.thousands {
+        text = text.replace(',', "");
+    }
+    let (sign, text) = match text.strip_prefix('-') {
+        Some(text) => ("-", text),
+        None => ("", text.as_str()),
+    };
+    format!("{}{}{}{}", sign, format.prefix.as_deref().unwrap_or_default(), text, format.suffix.as_deref().unwrap_or_default())
+}
+
+fn display(column: &Column, text: &str) -> String {
+    match (&column.settings.format, parse_number(text)) {
+        (Some(format), Some(value)) => format_value(value, format),
+        _ => text.to_string(),
+    }
+}
+
+/// 合计行中一列的值，没有可统计的数字时为空
+fn total(column: &Column, rows: &[Vec<String>]) -> Option<String> {
+    let kind = column.settings.total?;
+    let cells = rows.iter().filter_map(|row| row.get(column.index)).filter(|cell| !cell.trim().is_empty());
+    if kind == TableTotal::Count {
+        return Some(cells.count().to_string());
+    }
+    let numbers: Vec<f64> = cells.filter_map(|cell| parse_number(cell)).collect();
+    let value = match kind {
+        TableTotal::Sum => numbers.iter().sum(),
+        TableTotal::Average if !numbers.is_empty() => numbers.iter().sum::<f64>() / numbers.len() as f64,
+        TableTotal::Min => numbers.iter().copied().reduce(f64::min)?,
+        TableTotal::Max => numbers.iter().copied().reduce(f64::max)?,
+        _ => return None,
+    };
+    let default_format = NumberFormat::default();
+    Some(format_value(value, column.settings.format.as_ref().unwrap_or(&default_format)))
+}
+
+fn cell(tag: &str, column: &Column, text: &str) -> String {
+    let align = match column.align {
+        ColumnAlign::Left => "left",
+        ColumnAlign::Center => "center",
+        ColumnAlign::Right => "right",
+    };
+    format!("<{tag} style=\"text-align: {}\">{}</{tag}>", align, escape_html(text), tag = tag)
+}
+
+/**
+ * 把 CSV 或 JSON 数组转换为带打印样式的 HTML 表格
+ * 分页时每页重复表头，设置了统计方式的列在最后一行显示合计
+ */
+pub fn to_html(options: &TableOptions) -> Result<String, String> {
+    let (names, rows) = read_rows(options)?;
+    let columns = resolve_columns(options, &names, &rows)?;
+    if columns.is_empty() {
+        return Err("表格没有列".to_string());
+    }
+
+    let mut body = String::new();
+    if let Some(title) = &options.title {
+        body.push_str(&format!("<h1>{}</h1>\n", escape_html(title)));
+    }
+    body.push_str("<table>\n");
+    if columns.iter().any(|column| column.settings.width.is_some()) {
+        let widths: String = columns
+            .iter()
+            .map(|column| match &column.settings.width {
+                Some(width) => format!("<col style=\"width: {}\">", escape_html(width)),
+                None => "<col>".to_string(),
+            })
+            .collect();
+        body.push_str(&format!("<colgroup>{}</colgroup>\n", widths));
+    }
+    if columns.iter().any(|column| !column.header.is_empty()) {
+        let headers: String = columns.iter().map(|column| cell("th", column, &column.header)).collect();
+        body.push_str(&format!("<thead><tr>{}</tr></thead>\n", headers));
+    }
+    body.push_str("<tbody>\n");
+    for row in &rows {
+        let cells: String = columns
+            .iter()
+            .map(|column| cell("td", column, &display(column, row.get(column.index).map_or("", String::as_str))))
+            .collect();
+        body.push_str(&format!("<tr>{}</tr>\n", cells));
+    }
+    if columns.iter().any(|column| column.settings.total.is_some()) {
+        // 合计标题放在第一个没有统计的列
+        let mut label = Some(options.totals_label.clone().unwrap_or_else(|| "合计".to_string()));
+        let cells: String = columns
+            .iter()
+            .map(|column| {
+                let text = match column.settings.total {
+                    Some(_) => total(column, &rows).unwrap_or_default(),
+                    None => label.take().unwrap_or_default(),
+                };
+                cell("td", column, &text)
+            })
+            .collect();
+        body.push_str(&format!("<tr class=\"total\">{}</tr>\n", cells));
+    }
+    body.push_str("</tbody>\n</table>\n");
+
+    Ok(format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}\n{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(options.title.as_deref().unwrap_or_default()),
+        STYLESHEET,
+        options.extra_css.as_deref().unwrap_or_default(),
+        body
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn column(key: &str) -> TableColumn {
+        TableColumn { key: Some(key.to_string()), ..Default::default() }
+    }
+
+    fn td(align: &str, text: &str) -> String {
+        format!("<td style=\"text-align: {}\">{}</td>", align, text)
+    }
+
+    #[test]
+    fn renders_csv_with_formats_and_totals() {
+        let price = NumberFormat { decimals: Some(2), thousands: true, prefix: Some("¥".to_string()), suffix: None };
+        let options = TableOptions {
+            csv: Some("\u{FEFF}名称,数量,单价\n\"纸张, A4\",10,25\n硒鼓,2,1299.9\n\"说明 \"\"备注\"\"\",,\n".to_string()),
+            columns: vec![
+                TableColumn { header: Some("品名".to_string()), width: Some("50%".to_string()), ..column("名称") },
+                TableColumn { total: Some(TableTotal::Sum), ..column("数量") },
+                TableColumn { format: Some(price), total: Some(TableTotal::Max), ..column("单价") },
+            ],
+            title: Some("采购 <清单>".to_string()),
+            ..Default::default()
+        };
+        let html = to_html(&options).unwrap();
+        assert!(html.contains("<h1>采购 &lt;清单&gt;</h1>"));
+        assert!(html.contains("<colgroup><col style=\"width: 50%\"><col><col></colgroup>"));
+        assert!(html.contains("<thead><tr><th style=\"text-align: left\">品名</th><th style=\"text-align: right\">数量</th>"));
+        assert!(html.contains(&format!("<tr>{}{}{}</tr>", td("left", "纸张, A4"), td("right", "10"), td("right", "¥25.00"))));
+        assert!(html.contains(&td("right", "¥1,299.90")));
+        assert!(html.contains(&td("left", "说明 &quot;备注&quot;")));
+        assert!(html.contains(&format!("<tr class=\"total\">{}{}{}</tr>", td("left", "合计"), td("right", "12"), td("right", "¥1,299.90"))));
+
+        let options = TableOptions { csv: Some("a;b\n1;2".to_string()), delimiter: Some(';'), csv_header: Some(false), ..Default::default() };
+        let html = to_html(&options).unwrap();
+        assert!(!html.contains("<thead>"));
+        assert!(html.contains(&format!("<tr>{}{}</tr>", td("left", "a"), td("left", "b"))));
+
+        let missing = TableOptions { csv: Some("a,b\n1,2".to_string()), columns: vec![column("c")], ..Default::default() };
+        assert_eq!(to_html(&missing).unwrap_err(), "找不到列 c");
+    }
+
+    #[test]
+    fn renders_json_rows() {
+        let options = TableOptions {
+            rows: Some(json!([
+                { "name": "纸张", "amount": 0.1 },
+                { "note": null, "amount": 0.2, "name": "硒鼓" },
+                ["墨盒", 3],
+            ])),
+            columns: vec![],
+            totals_label: Some("Total".to_string()),
+            ..Default::default()
+        };
+        let (names, rows) = read_rows(&options).unwrap();
+        assert_eq!(names, vec!["name", "amount", "note"]);
+        assert_eq!(rows[1], vec!["硒鼓", "0.2", ""]);
+        assert_eq!(rows[2], vec!["墨盒", "3"]);
+
+        let options = TableOptions {
+            columns: vec![
+                column("name"),
+                TableColumn { total: Some(TableTotal::Sum), ..column("amount") },
+                TableColumn { total: Some(TableTotal::Average), ..column("note") },
+            ],
+            ..options
+        };
+        let html = to_html(&options).unwrap();
+        // 浮点误差不显示出来，没有数字时平均值为空
+        assert!(html.contains(&format!("<tr class=\"total\">{}{}{}</tr>", td("left", "Total"), td("right", "3.3"), td("right", ""))));
+
+        assert!(to_html(&TableOptions { rows: Some(json!({ "a": 1 })), ..Default::default() }).is_err());
+        assert!(to_html(&TableOptions { rows: Some(json!([1])), ..Default::default() }).is_err());
+        assert!(to_html(&TableOptions::default()).is_err());
+    }
+}
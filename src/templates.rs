@@ -0,0 +1,225 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use minijinja::{AutoEscape, Environment, Error, ErrorKind, Value};
+
+use crate::declare::{TemplateInfo, TemplateSource};
+use crate::history;
+
+struct Template {
+    info: TemplateInfo,
+    source: String,
+}
+
+/**
+ * HTML 模板注册表
+ *
+ * 模板使用 Jinja2 语法，渲染时所有已注册的模板在同一个环境中，可以互相 include / extends。
+ */
+fn registry() -> MutexGuard<'static, Vec<Template>> {
+    static REGISTRY: OnceLock<Mutex<Vec<Template>>> = OnceLock::new();
+    REGISTRY
+        .get_or_init(|| Mutex::new(Vec::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// 注册模板，同名的会被替换并增加版本号；模板有语法错误时不注册
+pub fn register(template: TemplateSource) -> Result<TemplateInfo, String> {
+    if template.name.trim().is_empty() {
+        return Err("模板名称不能为空".to_string());
+    }
+    let source = match (template.source, &template.path) {
+        (Some(source), None) => source,
+        (None, Some(path)) => fs::read_to_string(path).map_err(|e| format!("读取模板 {} 失败: {}", path, e))?,
+        _ => return Err(format!("模板 {} 的 source 和 path 必须且只能提供一个", template.name)),
+    };
+    Environment::new().add_template(&template.name, &source).map_err(|e| e.to_string())?;
+
+    let mut templates = registry();
+    let version = templates.iter().find(|t| t.info.name == template.name).map_or(1, |t| t.info.version + 1);
+    templates.retain(|t| t.info.name != template.name);
+    let info = TemplateInfo {
+        name: template.name,
+        path: template.path,
+        version,
+        hash: history::sha256_hex(source.as_bytes()),
+        registered_at: history::now_ms(),
+    };
+    templates.push(Template { info: info.clone(), source });
+    Ok(info)
+}
+
+pub fn unregister(name: &str) -> bool {
+    let mut templates = registry();
+    let before = templates.len();
+    templates.retain(|t| t.info.name != name);
+    templates.len() != before
+}
+
+pub fn list() -> Vec<TemplateInfo> {
+    registry().iter().map(|t| t.info.clone()).collect()
+}
+
+/**
+ * 用 JSON 数据渲染模板，输出默认按 HTML 转义，使用 `|safe` 输出原始 HTML
+ * @param name 模板名称
+ * @param data 模板中可以访问的数据
+ * @returns 渲染后的 HTML
+ */
+pub fn render(name: &str, data: &serde_json::Value) -> Result<String, String> {
+    let sources: Vec<(String, String)> = registry().iter().map(|t| (t.info.name.clone(), t.source.clone())).collect();
+    if !sources.iter().any(|(n, _)| n == name) {
+        return Err(format!("模板 {} 未注册", name));
+    }
+    let mut env = Environment::new();
+    env.set_auto_escape_callback(|_| AutoEscape::Html);
+    env.add_filter("currency", currency);
+    env.add_filter("number", number);
+    env.add_filter("date", date);
+    env.add_function("now", || Local::now().timestamp_millis());
+    for (name, source) in &sources {
+        env.add_template(name, source).map_err(|e| e.to_string())?;
+    }
+    env.get_template(name).and_then(|template| template.render(data)).map_err(|e| e.to_string())
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidOperation, message)
+}
+
+/// 数字或数字字符串
+fn to_number(value: &Value) -> Result<f64, Error> {
+    match value.as_str() {
+        Some(text) => text.trim().parse().map_err(|_| invalid(format!("{} 不是数字", text))),
+        None => f64::try_from(value.clone()).map_err(|_| invalid(format!("{} 不是数字", value))),
+    }
+}
+
+/// 保留 decimals 位小数，整数部分每三位加逗号
+pub fn format_number(value: f64, decimals: usize) -> String {
+    let text = format!("{:.*}", decimals, value.abs());
+    let (integer, fraction) = text.split_once('.').map_or((text.as_str(), None), |(i, f)| (i, Some(f)));
+    let mut output = String::new();
+    for (i, c) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            output.push(',');
+        }
+        output.push(c);
+    }
+    if let Some(fraction) = fraction {
+        output.push('.');
+        output.push_str(fraction);
+    }
+    // 四舍五入为 0 时不显示负号
+    if value < 0.0 && output.chars().any(|c| c.is_ascii_digit() && c != '0') {
+        output.insert(0, '-');
+    }
+    output
+}
+
+/// `{{ total | currency }}` → ¥1,234.50，`{{ total | currency("$", 0) }}` → $1,235
+fn currency(value: Value, symbol: Option<String>, decimals: Option<usize>) -> Result<String, Error> {
+    let text = format_number(to_number(&value)?, decimals.unwrap_or(2));
+    let symbol = symbol.unwrap_or_else(|| "¥".to_string());
+    Ok(match text.strip_prefix('-') {
+        Some(text) => format!("-{}{}", symbol, text),
+        None => format!("{}{}", symbol, text),
+    })
+}
+
+/// `{{ quantity | number(2) }}` → 1,234.00
+fn number(value: Value, decimals: Option<usize>) -> Result<String, Error> {
+    Ok(format_number(to_number(&value)?, decimals.unwrap_or(0)))
+}
+
+/**
+ * `{{ issued_at | date("%Y年%m月%d日") }}`，格式默认 %Y-%m-%d
+ * 数字按毫秒时间戳，字符串支持 RFC 3339、`2024-03-01 08:30:00` 和 `2024-03-01`，都按本地时间显示
+ */
+fn date(value: Value, format: Option<String>) -> Result<String, Error> {
+    let time: NaiveDateTime = match value.as_str() {
+        Some(text) => {
+            let text = text.trim();
+            DateTime::parse_from_rfc3339(text)
+                .map(|time| time.with_timezone(&Local).naive_local())
+                .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S"))
+                .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S"))
+                .or_else(|_| NaiveDate::parse_from_str(text, "%Y-%m-%d").map(|date| date.and_time(Default::default())))
+                .map_err(|_| invalid(format!("无法识别的日期 {}", text)))?
+        }
+        None => {
+            let ms = i64::try_from(value.clone()).map_err(|_| invalid(format!("无法识别的日期 {}", value)))?;
+            Local.timestamp_millis_opt(ms).single().ok_or_else(|| invalid(format!("无效的时间戳 {}", ms)))?.naive_local()
+        }
+    };
+    let format = format.unwrap_or_else(|| "%Y-%m-%d".to_string());
+    let items: Vec<Item> = StrftimeItems::new(&format).collect();
+    if items.iter().any(|item| matches!(item, Item::Error)) {
+        return Err(invalid(format!("无效的日期格式 {}", format)));
+    }
+    let mut output = String::new();
+    write!(output, "{}", time.format_with_items(items.iter())).map_err(|_| invalid(format!("无效的日期格式 {}", format)))?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn source(name: &str, source: &str) -> TemplateSource {
+        TemplateSource { name: name.to_string(), source: Some(source.to_string()), path: None }
+    }
+
+    #[test]
+    fn formats_numbers_and_dates() {
+        assert_eq!(currency(Value::from(1234.5), None, None).unwrap(), "¥1,234.50");
+        assert_eq!(currency(Value::from("-1234567.891"), Some("$".to_string()), Some(0)).unwrap(), "-$1,234,568");
+        assert_eq!(currency(Value::from(-0.001), None, None).unwrap(), "¥0.00");
+        assert_eq!(number(Value::from(999), Some(1)).unwrap(), "999.0");
+        assert_eq!(number(Value::from(1000), None).unwrap(), "1,000");
+        assert!(number(Value::from("abc"), None).is_err());
+
+        assert_eq!(date(Value::from("2024-03-01"), Some("%Y年%m月%d日".to_string())).unwrap(), "2024年03月01日");
+        assert_eq!(date(Value::from("2024-03-01 08:30:00"), Some("%H:%M".to_string())).unwrap(), "08:30");
+        let ms = Local.with_ymd_and_hms(2024, 3, 1, 23, 59, 0).unwrap().timestamp_millis();
+        assert_eq!(date(Value::from(ms), None).unwrap(), "2024-03-01");
+        assert!(date(Value::from("yesterday"), None).is_err());
+        assert!(date(Value::from("2024-03-01"), Some("%Q".to_string())).is_err());
+    }
+
+    #[test]
+    fn registers_and_renders_templates() {
+        let layout = "<html><body>{% block body %}{% endblock %}</body></html>";
+        register(source("tests-layout", layout)).unwrap();
+        let invoice = r#"{% extends "tests-layout" %}{% block body %}<h1>{{ customer }}</h1>
+{% for item in items %}<tr><td>{{ loop.index }}</td><td>{{ item.name }}</td><td>{{ item.price | currency }}</td></tr>
+{% endfor %}{% if paid %}已付款{% else %}未付款{% endif %}{% endblock %}"#;
+        let info = register(source("tests-invoice", invoice)).unwrap();
+        assert_eq!(info.version, 1);
+        assert_eq!(register(source("tests-invoice", invoice)).unwrap().version, 2);
+        assert!(list().iter().any(|t| t.name == "tests-invoice" && t.version == 2));
+
+        let data = json!({
+            "customer": "<Acme & Co>",
+            "items": [{ "name": "纸张", "price": 25 }, { "name": "硒鼓", "price": 1299.9 }],
+            "paid": false,
+        });
+        let html = render("tests-invoice", &data).unwrap();
+        assert!(html.starts_with("<html><body><h1>&lt;Acme &amp; Co&gt;</h1>"), "{}", html);
+        assert!(html.contains("<tr><td>2</td><td>硒鼓</td><td>¥1,299.90</td></tr>"));
+        assert!(html.ends_with("未付款</body></html>"));
+
+        assert!(register(source("tests-broken", "{% if %}")).is_err());
+        assert!(register(TemplateSource { name: "tests-empty".to_string(), source: None, path: None }).is_err());
+        assert!(render("tests-missing", &data).unwrap_err().contains("未注册"));
+        register(source("tests-bad-filter", "{{ customer | currency }}")).unwrap();
+        assert!(render("tests-bad-filter", &data).is_err());
+        assert!(unregister("tests-bad-filter"));
+        assert!(!unregister("tests-bad-filter"));
+    }
+}
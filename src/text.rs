@@ -0,0 +1,320 @@
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use encoding_rs::{GB18030, UTF_8};
+use lopdf::{dictionary, Dictionary, Document, Object, Stream};
+
+use crate::declare::{TextEncoding, TextFont, TextOptions, TextWrap};
+use crate::fonts;
+use crate::layout::A4;
+use crate::pdf;
+
+const DEFAULT_FONT_SIZE: f64 = 10.0;
+const DEFAULT_MARGIN: f64 = 36.0;
+const DEFAULT_TAB_WIDTH: u32 = 4;
+
+/// 行距与字号的比例
+const LINE_SPACING: f64 = 1.2;
+
+/// 排版后的一行，number 为原文行号，折行后的续行没有行号
+#[derive(Debug, PartialEq)]
+struct Row {
+    number: Option<usize>,
+    text: String,
+}
+
+/// 解码文本文件，有 BOM 时按 BOM，无效的字节替换为 U+FFFD
+pub fn decode(bytes: &[u8], encoding: TextEncoding) -> String {
+    let encoding = match encoding {
+        TextEncoding::Utf8 => UTF_8,
+        TextEncoding::Gbk => GB18030,
+    };
+    encoding.decode(bytes).0.into_owned()
+}
+
+/// 字符宽度（em），标准字体无法显示的字符使用 CJK 字体
+fn char_width(font: TextFont, c: char) -> f64 {
+    let width = if fonts::is_latin(c) { fonts::latin_width(font, c) } else { fonts::cjk_width(c) };
+    width as f64 / 1000.0
+}
+
+fn text_width(font: TextFont, text: &str) -> f64 {
+    text.chars().map(|c| char_width(font, c)).sum()
+}
+
+/// 展开制表符，去掉终端颜色代码和其他控制字符，CJK 字符按两列计算
+fn clean_line(line: &str, tab_width: usize) -> String {
+    let mut output = String::with_capacity(line.len());
+    let mut column = 0;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\t' {
+            let spaces = tab_width - column % tab_width;
+            output.extend(std::iter::repeat(' ').take(spaces));
+            column += spaces;
+        } else if c == '\u{1B}' {
+            // ESC [ 参数 结束字符
+            if chars.peek() == Some(&'[') {
+                chars.next();
+                for c in chars.by_ref() {
+                    if ('\u{40}'..='\u{7E}').contains(&c) {
+                        break;
+                    }
+                }
+            }
+        } else if !c.is_control() {
+            output.push(c);
+            column += if fonts::is_latin(c) { 1 } else { 2 };
+        }
+    }
+    output
+}
+
+/// 按宽度（em）折行，空格之后和 CJK 字符前后可以按词换行
+fn wrap(line: &str, width: f64, font: TextFont, mode: TextWrap) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut rows = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let mut used = 0.0;
+        let mut end = start;
+        let mut word_break = None;
+        while end < chars.len() {
+            let w = char_width(font, chars[end]);
+            // 每行至少一个字符
+            if used + w > width && end > start {
+                break;
+            }
+            used += w;
+            end += 1;
+            if end < chars.len() && (chars[end - 1] == ' ' || !fonts::is_latin(chars[end - 1]) || !fonts::is_latin(chars[end])) {
+                word_break = Some(end);
+            }
+        }
+        let end = match (mode, word_break) {
+            _ if end == chars.len() => end,
+            (TextWrap::Word, Some(word_break)) => word_break,
+            _ => end,
+        };
+        rows.push(chars[start..end].iter().collect::<String>());
+        if mode == TextWrap::None {
+            break;
+        }
+        start = end;
+        if mode == TextWrap::Word {
+            while chars.get(start) == Some(&' ') {
+                start += 1;
+            }
+            if let Some(row) = rows.last_mut() {
+                row.truncate(row.trim_end_matches(' ').len());
+            }
+        }
+    }
+    if rows.is_empty() {
+        rows.push(String::new());
+    }
+    rows
+}
+
+/**
+ * 把文本分页，换页符（\f）开始新的一页
+ * @param width 每行可用宽度（em）
+ */
+fn paginate(text: &str, width: f64, rows_per_page: usize, options: &TextOptions) -> Vec<Vec<Row>> {
+    let tab_width = options.tab_width.unwrap_or(DEFAULT_TAB_WIDTH).clamp(1, 16) as usize;
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    let mut pages: Vec<Vec<Row>> = vec![Vec::new()];
+    for (i, line) in text.strip_suffix('\n').unwrap_or(&text).split('\n').enumerate() {
+        let mut number = Some(i + 1);
+        let parts: Vec<&str> = line.split('\u{0C}').collect();
+        for (j, part) in parts.iter().enumerate() {
+            if j > 0 && pages.last().is_some_and(|page| !page.is_empty()) {
+                pages.push(Vec::new());
+            }
+            if part.is_empty() && parts.len() > 1 {
+                continue;
+            }
+            for row in wrap(&clean_line(part, tab_width), width, options.font, options.wrap) {
+                if pages.last().is_some_and(|page| page.len() >= rows_per_page) {
+                    pages.push(Vec::new());
+                }
+                if let Some(page) = pages.last_mut() {
+                    page.push(Row { number: number.take(), text: row });
+                }
+            }
+        }
+    }
+    // 末尾的换页符不产生空白页
+    if pages.len() > 1 && pages.last().is_some_and(|page| page.is_empty()) {
+        pages.pop();
+    }
+    pages
+}
+
+/// 一行文字的绘制指令，西文和 CJK 字符分别使用 F1、F2
+fn show(text: &str, font_size: f64) -> String {
+    let mut output = String::new();
+    let chars: Vec<char> = text.chars().collect();
+    for run in chars.chunk_by(|a, b| fonts::is_latin(*a) == fonts::is_latin(*b)) {
+        let run: String = run.iter().collect();
+        if run.starts_with(fonts::is_latin) {
+            output.push_str(&format!("/F1 {:.2} Tf <{}> Tj ", font_size, fonts::encode_latin(&run)));
+        } else {
+            output.push_str(&format!("/F2 {:.2} Tf <{}> Tj ", font_size, fonts::encode_ucs2(&run)));
+        }
+    }
+    output
+}
+
+/**
+ * 把文本排版为PDF，页面为纸张大小
+ * @param title 页眉标题
+ * @param paper 纸张宽高（pt），为空时使用 A4
+ */
+pub fn to_pdf(text: &str, title: &str, paper: Option<(f64, f64)>, options: &TextOptions) -> Result<Document, String> {
+    let font_size = options.font_size.unwrap_or(DEFAULT_FONT_SIZE);
+    if !(1.0..=200.0).contains(&font_size) {
+        return Err(format!("字号 {} 超出范围 1-200", font_size));
+    }
+    let margin = options.margin.unwrap_or(DEFAULT_MARGIN).max(0.0);
+    let (page_width, page_height) = paper.unwrap_or(A4);
+    let leading = font_size * LINE_SPACING;
+
+    let line_count = text.lines().count().max(1);
+    let gutter = if options.line_numbers {
+        (line_count.to_string().len() as f64 + 1.0) * char_width(options.font, '0') * font_size
+    } else {
+        0.0
+    };
+    let header_height = if options.header { leading * 2.0 } else { 0.0 };
+    let width = (page_width - margin * 2.0 - gutter) / font_size;
+    let rows_per_page = ((page_height - margin * 2.0 - header_height) / leading).floor();
+    if width < 1.0 || rows_per_page < 1.0 {
+        return Err("边距或字号过大，页面放不下文字".to_string());
+    }
+    let pages = paginate(text, width, rows_per_page as usize, options);
+
+    let mut document = Document::with_version("1.5");
+    let pages_id = document.new_object_id();
+    let mut fonts = dictionary! { "F1" => fonts::add_latin(&mut document, options.font) };
+    if text.chars().chain(title.chars()).any(|c| !fonts::is_latin(c) && !c.is_control()) {
+        fonts.set("F2", fonts::add_cjk(&mut document, options.cjk_font));
+    }
+    let resources = document.add_object(dictionary! { "Font" => fonts });
+
+    let printed_at = Local::now().format("%Y-%m-%d %H:%M").to_string();
+    let total = pages.len();
+    let mut kids: Vec<Object> = Vec::new();
+    for (index, rows) in pages.into_iter().enumerate() {
+        let mut content = String::new();
+        let mut top = page_height - margin;
+        if options.header {
+            // 标题在左，打印时间和页码在右，下方一条分隔线
+            let right = format!("{}  {}/{}", printed_at, index + 1, total);
+            let right_width = text_width(options.font, &right) * font_size;
+            let title_width = (page_width - margin * 2.0 - right_width) / font_size - 2.0;
+            let title = wrap(title, title_width.max(0.0), options.font, TextWrap::None).remove(0);
+            let baseline = top - font_size;
+            content.push_str(&format!("BT {:.2} {:.2} Td {}ET\n", margin, baseline, show(&title, font_size)));
+            content.push_str(&format!("BT {:.2} {:.2} Td {}ET\n", page_width - margin - right_width, baseline, show(&right, font_size)));
+            let rule = baseline - font_size * 0.4;
+            content.push_str(&format!("0.5 w {:.2} {:.2} m {:.2} {:.2} l S\n", margin, rule, page_width - margin, rule));
+            top -= header_height;
+        }
+        for (i, row) in rows.iter().enumerate() {
+            let baseline = top - font_size - i as f64 * leading;
+            if let Some(number) = row.number.filter(|_| options.line_numbers) {
+                let number = number.to_string();
+                let x = margin + gutter - (number.len() as f64 + 1.0) * char_width(options.font, '0') * font_size;
+                content.push_str(&format!("0.5 g BT {:.2} {:.2} Td {}ET 0 g\n", x, baseline, show(&number, font_size)));
+            }
+            if !row.text.is_empty() {
+                content.push_str(&format!("BT {:.2} {:.2} Td {}ET\n", margin + gutter, baseline, show(&row.text, font_size)));
+            }
+        }
+        let mut stream = Stream::new(Dictionary::new(), content.into_bytes());
+        let _ = stream.compress();
+        let content_id = document.add_object(stream);
+        let media_box: Vec<Object> = vec![0.into(), 0.into(), (page_width as f32).into(), (page_height as f32).into()];
+        let page_id = document.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => media_box,
+            "Contents" => content_id,
+            "Resources" => resources,
+        });
+        kids.push(page_id.into());
+    }
+    pdf::set_pages(&mut document, pages_id, kids);
+    Ok(document)
+}
+
+/**
+ * 把文本转换为PDF
+ * @returns 输出的临时文件
+ */
+pub fn convert(text: &str, title: &str, paper: Option<(f64, f64)>, options: &TextOptions) -> Result<PathBuf, String> {
+    let mut document = to_pdf(text, title, paper, options)?;
+    let stem = if title.is_empty() { "text".to_string() } else { pdf::file_stem(Path::new(title)) };
+    let output = pdf::spool_path(&stem)?;
+    pdf::save(&mut document, &output)?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(pages: &[Vec<Row>]) -> Vec<Vec<(Option<usize>, &str)>> {
+        pages.iter().map(|page| page.iter().map(|row| (row.number, row.text.as_str())).collect()).collect()
+    }
+
+    #[test]
+    fn wraps_and_paginates_lines() {
+        // Courier 每个字符 0.6 em，宽 6 em 时每行 10 个字符
+        assert_eq!(wrap("hello world again", 6.0, TextFont::Courier, TextWrap::Char), vec!["hello worl", "d again"]);
+        assert_eq!(wrap("hello world again", 6.0, TextFont::Courier, TextWrap::Word), vec!["hello", "world", "again"]);
+        assert_eq!(wrap("hello world again", 6.0, TextFont::Courier, TextWrap::None), vec!["hello worl"]);
+        // CJK 字符 1 em，可以在字符之间换行
+        assert_eq!(wrap("打印中文字符", 4.0, TextFont::Courier, TextWrap::Word), vec!["打印中文", "字符"]);
+        assert_eq!(wrap("", 4.0, TextFont::Courier, TextWrap::Char), vec![""]);
+
+        assert_eq!(clean_line("a\tb\u{1B}[31mred\u{1B}[0m\t中\tc", 4), "a   bred    中  c");
+
+        let options = TextOptions::default();
+        let pages = paginate("one\r\ntwo\u{0C}three\n\u{0C}\nfour\n", 6.0, 2, &options);
+        assert_eq!(
+            rows(&pages),
+            vec![vec![(Some(1), "one"), (Some(2), "two")], vec![(None, "three")], vec![(Some(4), "four")]]
+        );
+        let pages = paginate("1234567890abc\nx", 6.0, 2, &options);
+        assert_eq!(rows(&pages), vec![vec![(Some(1), "1234567890"), (None, "abc")], vec![(Some(2), "x")]]);
+    }
+
+    #[test]
+    fn renders_text_with_cjk_fallback() {
+        assert_eq!(decode(&[0xD6, 0xD0, 0xCE, 0xC4], TextEncoding::Gbk), "中文");
+        assert_eq!(decode(b"\xEF\xBB\xBFabc", TextEncoding::Utf8), "abc");
+
+        let options = TextOptions { line_numbers: true, header: true, ..Default::default() };
+        let text = "error: 打印失败\n".repeat(100);
+        let document = to_pdf(&text, "app.log", crate::layout::paper_size("A5"), &options).unwrap();
+        let pages: Vec<_> = document.get_pages().into_values().collect();
+        assert!(pages.len() > 1);
+        let content = String::from_utf8(document.get_page_content(pages[0]).unwrap()).unwrap();
+        // 西文和中文分别使用两种字体
+        let error = format!("/F1 10.00 Tf <{}> Tj /F2 10.00 Tf <6253537059318D25> Tj", fonts::encode_latin("error: "));
+        assert!(content.contains(&error), "{}", content);
+        assert!(content.contains(&format!("<{}>", fonts::encode_latin("app.log"))));
+        assert!(content.contains(&format!("{}> Tj", fonts::encode_latin(&format!("  1/{}", pages.len())))));
+
+        let ascii = to_pdf("plain", "", None, &TextOptions::default()).unwrap();
+        let page = ascii.get_pages().into_values().next().unwrap();
+        let resources = ascii.get_dictionary(page).unwrap().get(b"Resources").unwrap().as_reference().unwrap();
+        let fonts = ascii.get_dictionary(resources).unwrap().get(b"Font").unwrap().as_dict().unwrap();
+        assert!(fonts.has(b"F1") && !fonts.has(b"F2"));
+
+        let huge = TextOptions { margin: Some(400.0), ..Default::default() };
+        assert!(to_pdf("x", "", None, &huge).is_err());
+    }
+}
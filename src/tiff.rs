@@ -0,0 +1,349 @@
+use std::io::Cursor;
+
+use lopdf::dictionary;
+use tiff::decoder::ifd::Value;
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::tags::Tag;
+use tiff::{ColorType, TiffError};
+
+use crate::images::{self, Band, Frame};
+
+/// CCITT 压缩的 T4Options 标签，tiff 库中没有定义
+const T4_OPTIONS: u16 = 292;
+
+type TiffDecoder<'a> = Decoder<Cursor<&'a [u8]>>;
+
+pub fn is_tiff(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"II*\0") || bytes.starts_with(b"MM\0*")
+}
+
+fn tiff_error(e: TiffError) -> String {
+    format!("TIFF 解码失败: {}", e)
+}
+
+fn unsigned(decoder: &mut TiffDecoder, tag: Tag) -> Result<Option<u32>, String> {
+    decoder.find_tag_unsigned(tag).map_err(tiff_error)
+}
+
+fn rational(decoder: &mut TiffDecoder, tag: Tag) -> Result<Option<f64>, String> {
+    Ok(match decoder.find_tag(tag).map_err(tiff_error)? {
+        Some(Value::Rational(numerator, denominator)) => (denominator != 0).then(|| numerator as f64 / denominator as f64),
+        Some(value) => value.into_u32().ok().map(f64::from),
+        None => None,
+    })
+}
+
+/**
+ * 读取 TIFF 的所有页面，跳过缩略图
+ * CCITT G3/G4 压缩的页面原样嵌入，其他页面由 tiff 库解码，不支持 JPEG 压缩、调色板和 YCbCr 图像
+ */
+pub fn read(bytes: &[u8]) -> Result<Vec<Frame>, String> {
+    let mut decoder = Decoder::new(Cursor::new(bytes)).map_err(tiff_error)?;
+    let mut frames = Vec::new();
+    loop {
+        if unsigned(&mut decoder, Tag::NewSubfileType)?.unwrap_or(0) & 1 == 0 {
+            frames.push(frame(&mut decoder, bytes).map_err(|e| format!("第 {} 页: {}", frames.len() + 1, e))?);
+        }
+        if !decoder.more_images() {
+            break;
+        }
+        decoder.next_image().map_err(tiff_error)?;
+    }
+    if frames.is_empty() {
+        return Err("TIFF 中没有图像".to_string());
+    }
+    Ok(frames)
+}
+
+fn frame(decoder: &mut TiffDecoder, bytes: &[u8]) -> Result<Frame, String> {
+    let (width, height) = decoder.dimensions().map_err(tiff_error)?;
+    let dpi = match (
+        rational(decoder, Tag::XResolution)?,
+        rational(decoder, Tag::YResolution)?,
+        unsigned(decoder, Tag::ResolutionUnit)?.unwrap_or(2),
+    ) {
+        (Some(x), Some(y), 2) if x > 0.0 && y > 0.0 => Some((x, y)),
+        (Some(x), Some(y), 3) if x > 0.0 && y > 0.0 => Some((x * 2.54, y * 2.54)),
+        _ => None,
+    };
+    let orientation = unsigned(decoder, Tag::Orientation)?.filter(|o| (1..=8).contains(o)).unwrap_or(1) as u16;
+    let compression = unsigned(decoder, Tag::Compression)?.unwrap_or(1);
+    // tiff 库不处理 FillOrder
+    let reversed = unsigned(decoder, Tag::FillOrder)? == Some(2);
+
+    let bands = if matches!(compression, 2..=4) {
+        if decoder.colortype().map_err(tiff_error)? != ColorType::Gray(1) {
+            return Err("CCITT 压缩只支持黑白图像".to_string());
+        }
+        let rows_per_strip = unsigned(decoder, Tag::RowsPerStrip)?.unwrap_or(height).clamp(1, height);
+        let offsets = decoder.get_tag_u64_vec(Tag::StripOffsets).map_err(tiff_error)?;
+        let counts = decoder.get_tag_u64_vec(Tag::StripByteCounts).map_err(tiff_error)?;
+        let strips = offsets
+            .iter()
+            .zip(&counts)
+            .map(|(&offset, &count)| {
+                let start = usize::try_from(offset).ok()?;
+                bytes.get(start..start.checked_add(usize::try_from(count).ok()?)?)
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| "TIFF 数据不完整".to_string())?;
+        let photometric = unsigned(decoder, Tag::PhotometricInterpretation)?.unwrap_or(0);
+        let t4_options = unsigned(decoder, Tag::Unknown(T4_OPTIONS))?.unwrap_or(0);
+        fax_bands(width, height, rows_per_strip, &strips, compression, t4_options, photometric, reversed)
+    } else {
+        vec![decoded_band(decoder, width, height, reversed)?]
+    };
+    Ok(Frame { width, height, dpi, orientation, bands })
+}
+
+/// CCITT 数据直接以 CCITTFaxDecode 嵌入，每个条带单独编码，分别作为一段
+#[allow(clippy::too_many_arguments)]
+fn fax_bands(
+    width: u32,
+    height: u32,
+    rows_per_strip: u32,
+    strips: &[&[u8]],
+    compression: u32,
+    t4_options: u32,
+    photometric: u32,
+    reversed: bool,
+) -> Vec<Band> {
+    let k = match compression {
+        4 => -1,
+        3 if t4_options & 1 == 1 => 4,
+        _ => 0,
+    };
+    let mut bands = Vec::new();
+    let mut top = 0;
+    for strip in strips {
+        if top >= height {
+            break;
+        }
+        let rows = rows_per_strip.min(height - top);
+        top += rows;
+        let mut params = dictionary! { "K" => k, "Columns" => width, "Rows" => rows };
+        // Modified Huffman 每行从字节边界开始
+        if compression == 2 {
+            params.set("EncodedByteAlign", true);
+        }
+        let mut image = dictionary! {
+            "ColorSpace" => "DeviceGray",
+            "BitsPerComponent" => 1,
+            "Filter" => "CCITTFaxDecode",
+            "DecodeParms" => params,
+        };
+        // 解码后 0 为白色，BlackIsZero 时反相
+        if photometric == 1 {
+            image.set("Decode", vec![1.into(), 0.into()]);
+        }
+        let data = if reversed { strip.iter().map(|b| b.reverse_bits()).collect() } else { strip.to_vec() };
+        bands.push(Band { width, rows, image, data, mask: None });
+    }
+    bands
+}
+
+/// 解码后的像素转为图像：16 位降为 8 位，额外通道中的透明度作为蒙版
+fn decoded_band(decoder: &mut TiffDecoder, width: u32, height: u32, reversed: bool) -> Result<Band, String> {
+    let color_type = decoder.colortype().map_err(tiff_error)?;
+    let (color_space, channels, samples, bits) = match color_type {
+        ColorType::Gray(bits) => ("DeviceGray", 1, 1, bits),
+        ColorType::GrayA(bits) => ("DeviceGray", 1, 2, bits),
+        ColorType::Multiband { bit_depth, num_samples } => ("DeviceGray", 1, num_samples as usize, bit_depth),
+        ColorType::RGB(bits) => ("DeviceRGB", 3, 3, bits),
+        ColorType::RGBA(bits) => ("DeviceRGB", 3, 4, bits),
+        ColorType::CMYK(bits) => ("DeviceCMYK", 4, 4, bits),
+        ColorType::CMYKA(bits) => ("DeviceCMYK", 4, 5, bits),
+        other => return Err(format!("不支持的颜色类型 {:?}", other)),
+    };
+    if !matches!(bits, 1 | 2 | 4 | 8 | 16) {
+        return Err(format!("不支持 {} 位图像", bits));
+    }
+    let mut data = match decoder.read_image().map_err(tiff_error)? {
+        DecodingResult::U8(data) => data,
+        DecodingResult::U16(data) => data.iter().map(|v| (v >> 8) as u8).collect(),
+        _ => return Err("只支持整数像素".to_string()),
+    };
+    let bits = bits.min(8);
+    if reversed && bits < 8 {
+        data.iter_mut().for_each(|b| *b = b.reverse_bits());
+    }
+
+    let mut mask = None;
+    if samples > channels {
+        if bits != 8 {
+            return Err("带额外通道的图像只支持 8 位".to_string());
+        }
+        let (color, extra) = images::split_alpha(&data, channels, samples);
+        // 额外通道为 1（预乘）或 2（非预乘）时是透明度
+        if matches!(unsigned(decoder, Tag::ExtraSamples)?, Some(1 | 2)) {
+            mask = Some(extra);
+        }
+        data = color;
+    }
+    let image = dictionary! { "ColorSpace" => color_space, "BitsPerComponent" => i64::from(bits) };
+    Ok(Band { width, rows: height, image, data, mask })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use weezl::BitOrder;
+
+    /// (标签, 类型, 值)，RATIONAL 的值按分子、分母依次排列
+    pub(crate) type Entry = (Tag, u16, Vec<u32>);
+
+    /// 生成小端 TIFF，每页的条带数据放在 IFD 前面，自动补上条带位置
+    pub(crate) fn encode(pages: &[(Vec<Entry>, Vec<Vec<u8>>)]) -> Vec<u8> {
+        let mut out = b"II*\0\0\0\0\0".to_vec();
+        let mut link = 4;
+        for (tags, strips) in pages {
+            let mut offsets = Vec::new();
+            for strip in strips {
+                offsets.push(out.len() as u32);
+                out.extend(strip);
+            }
+            let mut tags: Vec<(u16, u16, Vec<u32>)> = tags.iter().map(|(tag, kind, values)| (tag.to_u16(), *kind, values.clone())).collect();
+            tags.push((Tag::StripOffsets.to_u16(), 4, offsets));
+            tags.push((Tag::StripByteCounts.to_u16(), 4, strips.iter().map(|s| s.len() as u32).collect()));
+            tags.sort_by_key(|tag| tag.0);
+
+            let ifd = out.len() + out.len() % 2;
+            out.resize(ifd, 0);
+            out[link..link + 4].copy_from_slice(&(ifd as u32).to_le_bytes());
+            let mut extra = ifd + 2 + tags.len() * 12 + 4;
+            let mut entries = (tags.len() as u16).to_le_bytes().to_vec();
+            let mut overflow = Vec::new();
+            for (tag, kind, values) in &tags {
+                let bytes: Vec<u8> = match kind {
+                    3 => values.iter().flat_map(|&v| (v as u16).to_le_bytes()).collect(),
+                    _ => values.iter().flat_map(|&v| v.to_le_bytes()).collect(),
+                };
+                let count = if *kind == 5 { values.len() / 2 } else { values.len() };
+                entries.extend(tag.to_le_bytes());
+                entries.extend(kind.to_le_bytes());
+                entries.extend((count as u32).to_le_bytes());
+                if bytes.len() <= 4 {
+                    let mut inline = bytes.clone();
+                    inline.resize(4, 0);
+                    entries.extend(inline);
+                } else {
+                    entries.extend((extra as u32).to_le_bytes());
+                    extra += bytes.len();
+                    overflow.extend(bytes);
+                }
+            }
+            link = ifd + entries.len();
+            entries.extend([0; 4]);
+            out.extend(entries);
+            out.extend(overflow);
+        }
+        out
+    }
+
+    fn header(width: u32, height: u32, bits: u32, samples: u32, photometric: u32) -> Vec<Entry> {
+        vec![
+            (Tag::ImageWidth, 4, vec![width]),
+            (Tag::ImageLength, 4, vec![height]),
+            (Tag::BitsPerSample, 3, vec![bits; samples as usize]),
+            (Tag::PhotometricInterpretation, 3, vec![photometric]),
+            (Tag::SamplesPerPixel, 3, vec![samples]),
+        ]
+    }
+
+    #[test]
+    fn reads_multi_page_tiff() {
+        // 第 1 页：4x2 灰度，不压缩，300 dpi，分两个条带
+        let mut gray = header(4, 2, 8, 1, 1);
+        gray.extend([
+            (Tag::RowsPerStrip, 3, vec![1]),
+            (Tag::XResolution, 5, vec![300, 1]),
+            (Tag::YResolution, 5, vec![300, 1]),
+            (Tag::Orientation, 3, vec![6]),
+        ]);
+        // 缩略图，打印时跳过
+        let mut thumbnail = header(1, 1, 8, 1, 1);
+        thumbnail.push((Tag::NewSubfileType, 4, vec![1]));
+        // 第 2 页：2x2 RGBA，LZW 压缩加水平差分
+        let pixels: Vec<u8> = vec![10, 20, 30, 255, 11, 22, 33, 128, 40, 50, 60, 255, 40, 50, 60, 0];
+        let mut predicted = pixels.clone();
+        for row in predicted.chunks_mut(8) {
+            for i in (4..8).rev() {
+                row[i] = row[i].wrapping_sub(row[i - 4]);
+            }
+        }
+        let lzw = weezl::encode::Encoder::with_tiff_size_switch(BitOrder::Msb, 8).encode(&predicted).unwrap();
+        let mut rgba = header(2, 2, 8, 4, 2);
+        rgba.extend([(Tag::Compression, 3, vec![5]), (Tag::Predictor, 3, vec![2]), (Tag::ExtraSamples, 3, vec![2])]);
+        // 第 3 页：1 位白为 0 的黑白图像，PackBits 压缩，解码后 0 为黑色
+        let mut bilevel = header(8, 3, 1, 1, 0);
+        bilevel.push((Tag::Compression, 3, vec![32773]));
+
+        let bytes = encode(&[
+            (gray, vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]]),
+            (thumbnail, vec![vec![0]]),
+            (rgba, vec![lzw]),
+            (bilevel, vec![vec![0x01, 0xAA, 0x55, 0xFF, 0x0F]]),
+        ]);
+        assert!(is_tiff(&bytes));
+        let frames = read(&bytes).unwrap();
+        assert_eq!(frames.len(), 3);
+
+        assert_eq!((frames[0].width, frames[0].height, frames[0].dpi, frames[0].orientation), (4, 2, Some((300.0, 300.0)), 6));
+        assert_eq!(frames[0].bands[0].data, vec![0, 1, 2, 3, 4, 5, 6, 7]);
+
+        let band = &frames[1].bands[0];
+        assert_eq!(band.data, vec![10, 20, 30, 11, 22, 33, 40, 50, 60, 40, 50, 60]);
+        assert_eq!(band.mask, Some(vec![255, 128, 255, 0]));
+        assert_eq!(frames[1].dpi, None);
+
+        let band = &frames[2].bands[0];
+        assert_eq!(band.data, vec![0x55, 0xAA, 0xF0]);
+        assert_eq!(band.image.get(b"ColorSpace").unwrap().as_name().unwrap(), b"DeviceGray");
+        assert_eq!(band.image.get(b"BitsPerComponent").unwrap().as_i64().unwrap(), 1);
+    }
+
+    #[test]
+    fn rejects_unsupported_or_truncated_images() {
+        use std::io::Write;
+
+        // 4x2 灰度，每个条带 1 行 4 字节
+        let mut tags = header(4, 2, 8, 1, 1);
+        tags.push((Tag::RowsPerStrip, 3, vec![1]));
+        let deflate = |data: &[u8]| {
+            let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        };
+        tags.push((Tag::Compression, 3, vec![8]));
+        let bytes = encode(&[(tags.clone(), vec![deflate(&[9; 4]), deflate(&[8; 4])])]);
+        assert_eq!(read(&bytes).unwrap()[0].bands[0].data, vec![9, 9, 9, 9, 8, 8, 8, 8]);
+        // 条带解压后不足一行
+        let bytes = encode(&[(tags, vec![deflate(&[9; 4]), deflate(&[8; 2])])]);
+        assert!(read(&bytes).is_err());
+
+        // 调色板图像
+        let mut palette = header(8, 1, 1, 1, 3);
+        palette.push((Tag::ColorMap, 3, vec![0, 0xFFFF, 0, 0x8000, 0, 0]));
+        let bytes = encode(&[(palette, vec![vec![0xAA]])]);
+        assert!(read(&bytes).err().unwrap().starts_with("第 1 页"));
+    }
+
+    #[test]
+    fn embeds_fax_strips_without_decoding() {
+        let mut tags = header(1728, 30, 1, 1, 0);
+        tags.extend([(Tag::Compression, 3, vec![4]), (Tag::RowsPerStrip, 3, vec![16]), (Tag::FillOrder, 3, vec![2])]);
+        let bytes = encode(&[(tags, vec![vec![0x01, 0x80], vec![0x02]])]);
+        let frames = read(&bytes).unwrap();
+        let bands = &frames[0].bands;
+        assert_eq!(bands.iter().map(|band| band.rows).collect::<Vec<_>>(), vec![16, 14]);
+        // 位序反转后原样嵌入
+        assert_eq!(bands[0].data, vec![0x80, 0x01]);
+        assert_eq!(bands[1].data, vec![0x40]);
+        let params = bands[1].image.get(b"DecodeParms").unwrap().as_dict().unwrap();
+        assert_eq!(params.get(b"K").unwrap().as_i64().unwrap(), -1);
+        assert_eq!(params.get(b"Rows").unwrap().as_i64().unwrap(), 14);
+        assert!(bands[1].image.get(b"Decode").is_err());
+
+        assert!(read(b"II+\0").is_err());
+    }
+}
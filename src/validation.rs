@@ -0,0 +1,348 @@
+use serde::{Deserialize, Serialize};
+
+use crate::capabilities::{same_size, standard_media_size};
+use crate::declare::{ColorMode, DuplexMode, MediaSize, PrintSettings, PrinterCapabilities, Scaling};
+use crate::ppd::Ppd;
+
+/// 校验模式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationMode {
+    /// 任何不支持的参数都视为冲突
+    Strict,
+    /// 不支持的参数自动替换为最接近的可用值
+    #[default]
+    Lenient,
+}
+
+/// 常见的可互相替换的纸张
+const MEDIA_ALTERNATIVES: [(&str, &str); 4] = [("A4", "Letter"), ("A3", "Tabloid"), ("Legal", "A4"), ("A5", "Statement")];
+
+/// 自定义缩放的百分比范围
+const SCALING_RANGE: std::ops::RangeInclusive<f64> = 1.0..=1000.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValidationIssue {
+    /// 参数名：page_size / duplex / color / tray / copies / resolution
+    pub setting: String,
+    pub requested: String,
+    /// 替换值；严格模式下为建议值，为空表示使用打印机默认值
+    pub substitute: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub mode: ValidationMode,
+    pub conflicts: Vec<ValidationIssue>,
+    pub substitutions: Vec<ValidationIssue>,
+    /// 替换后实际提交的参数
+    pub settings: PrintSettings,
+}
+
+impl ValidationReport {
+    /// 冲突汇总，用于打印失败的提示
+    pub fn conflict_message(&self) -> String {
+        self.conflicts.iter().map(|c| c.message.clone()).collect::<Vec<_>>().join("; ")
+    }
+}
+
+fn media_size_mm(media: &MediaSize) -> Option<(f64, f64)> {
+    Some((media.width_mm?, media.height_mm?))
+}
+
+/// 按名称或尺寸查找打印机支持的纸张
+fn find_media<'a>(capabilities: &'a PrinterCapabilities, name: &str) -> Option<&'a MediaSize> {
+    if let Some(media) = capabilities.media_sizes.iter().find(|m| m.name.eq_ignore_ascii_case(name)) {
+        return Some(media);
+    }
+    let size = standard_media_size(name)?;
+    capabilities
+        .media_sizes
+        .iter()
+        .find(|m| media_size_mm(m).is_some_and(|s| same_size(s, size)))
+}
+
+/// 替换纸张：先查常见替换表，再找面积最接近的，最后用默认纸张
+fn substitute_media(capabilities: &PrinterCapabilities, name: &str) -> Option<String> {
+    let alternative = MEDIA_ALTERNATIVES.iter().find_map(|(a, b)| {
+        if a.eq_ignore_ascii_case(name) {
+            Some(*b)
+        } else if b.eq_ignore_ascii_case(name) {
+            Some(*a)
+        } else {
+            None
+        }
+    });
+    if let Some(media) = alternative.and_then(|alt| find_media(capabilities, alt)) {
+        return Some(media.name.clone());
+    }
+
+    if let Some((width, height)) = standard_media_size(name) {
+        let area = width * height;
+        let closest = capabilities
+            .media_sizes
+            .iter()
+            .filter_map(|m| media_size_mm(m).map(|(w, h)| (m, (w * h - area).abs())))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((media, _)) = closest {
+            return Some(media.name.clone());
+        }
+    }
+    capabilities.default_media.clone()
+}
+
+struct Validator {
+    mode: ValidationMode,
+    conflicts: Vec<ValidationIssue>,
+    substitutions: Vec<ValidationIssue>,
+}
+
+impl Validator {
+    /// 记录一项不支持的参数
+    /// @returns 宽松模式下返回替换值
+    fn unsupported(&mut self, setting: &str, requested: String, substitute: Option<String>, message: String) -> Option<Option<String>> {
+        let issue = ValidationIssue { setting: setting.to_string(), requested, substitute: substitute.clone(), message };
+        match self.mode {
+            ValidationMode::Strict => {
+                self.conflicts.push(issue);
+                None
+            }
+            ValidationMode::Lenient => {
+                self.substitutions.push(issue);
+                Some(substitute)
+            }
+        }
+    }
+}
+
+/**
+ * 按打印机能力校验打印参数
+ * 能力中某项为空（后端没有提供）时不校验该项
+ */
+pub fn validate(settings: &PrintSettings, capabilities: &PrinterCapabilities, mode: ValidationMode) -> ValidationReport {
+    let mut validator = Validator { mode, conflicts: Vec::new(), substitutions: Vec::new() };
+    let mut result = settings.clone();
+
+    if let Some(page_size) = &settings.page_size {
+        if !capabilities.media_sizes.is_empty() {
+            match find_media(capabilities, page_size) {
+                Some(media) => result.page_size = Some(media.name.clone()),
+                None => {
+                    let substitute = substitute_media(capabilities, page_size);
+                    let message = format!("打印机不支持纸张 {}", page_size);
+                    if let Some(substitute) = validator.unsupported("page_size", page_size.clone(), substitute, message) {
+                        result.page_size = substitute;
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(duplex) = settings.duplex {
+        let supported = &capabilities.duplex_modes;
+        if !supported.is_empty() && !supported.iter().any(|m| m == duplex.as_str()) {
+            let message = format!("打印机不支持 {}", duplex.as_str());
+            let substitute = Some(DuplexMode::OneSided.as_str().to_string());
+            if validator.unsupported("duplex", duplex.as_str().to_string(), substitute, message).is_some() {
+                result.duplex = Some(DuplexMode::OneSided);
+            }
+        }
+    }
+
+    if let Some(color) = settings.color {
+        let supported = &capabilities.color_modes;
+        if !supported.is_empty() && !supported.iter().any(|m| m == color.as_str()) {
+            let substitute = if color == ColorMode::Color { ColorMode::Monochrome } else { ColorMode::Color };
+            let message = format!("打印机不支持颜色模式 {}", color.as_str());
+            let requested = color.as_str().to_string();
+            if validator.unsupported("color", requested, Some(substitute.as_str().to_string()), message).is_some() {
+                result.color = Some(substitute);
+            }
+        }
+    }
+
+    if let Some(tray) = &settings.tray {
+        let trays = &capabilities.trays;
+        match trays.iter().find(|t| t.eq_ignore_ascii_case(tray)) {
+            Some(found) => result.tray = Some(found.clone()),
+            None if trays.is_empty() => {}
+            None => {
+                let message = format!("打印机没有纸盒 {}，将由打印机自动选择", tray);
+                if validator.unsupported("tray", tray.clone(), None, message).is_some() {
+                    result.tray = None;
+                }
+            }
+        }
+    }
+
+    if let Some(copies) = settings.copies {
+        let max = capabilities.max_copies.unwrap_or(u32::MAX).max(1);
+        if copies == 0 || copies > max {
+            let substitute = copies.clamp(1, max);
+            let message = format!("份数 {} 超出范围 1-{}", copies, max);
+            if validator.unsupported("copies", copies.to_string(), Some(substitute.to_string()), message).is_some() {
+                result.copies = Some(substitute);
+            }
+        }
+    }
+
+    if let Some(dpi) = settings.resolution {
+        let resolutions = &capabilities.resolutions;
+        if !resolutions.is_empty() && !resolutions.iter().any(|r| r.x_dpi == dpi) {
+            let closest = resolutions.iter().map(|r| r.x_dpi).min_by_key(|x| x.abs_diff(dpi));
+            let message = format!("打印机不支持分辨率 {}dpi", dpi);
+            let substitute = closest.map(|x| x.to_string());
+            if validator.unsupported("resolution", dpi.to_string(), substitute, message).is_some() {
+                result.resolution = closest;
+            }
+        }
+    }
+
+    if let Some(Scaling::Custom(percent)) = settings.scaling {
+        if !SCALING_RANGE.contains(&percent) {
+            let message = format!("缩放比例 {}% 超出范围 1%-1000%", percent);
+            let substitute = Some(Scaling::None.to_string());
+            if validator.unsupported("scaling", format!("{}%", percent), substitute, message).is_some() {
+                result.scaling = Some(Scaling::None);
+            }
+        }
+    }
+
+    ValidationReport {
+        valid: validator.conflicts.is_empty(),
+        mode,
+        conflicts: validator.conflicts,
+        substitutions: validator.substitutions,
+        settings: result,
+    }
+}
+
+/**
+ * CUPS 队列还需要满足 PPD 中的 UIConstraints，冲突无法自动替换，两种模式下都记为冲突
+ */
+pub fn check_constraints(report: &mut ValidationReport, ppd: &Ppd) {
+    if let Err(message) = ppd.to_cups_options(&report.settings) {
+        report.conflicts.push(ValidationIssue {
+            setting: "constraints".to_string(),
+            requested: String::new(),
+            substitute: None,
+            message,
+        });
+        report.valid = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::declare::Resolution;
+
+    fn letter_printer() -> PrinterCapabilities {
+        let media = |name: &str, w: f64, h: f64| MediaSize { name: name.to_string(), width_mm: Some(w), height_mm: Some(h) };
+        PrinterCapabilities {
+            printer: "office".to_string(),
+            media_sizes: vec![media("Letter", 215.9, 279.4), media("Legal", 215.9, 355.6), media("Env10", 104.8, 241.3)],
+            default_media: Some("Letter".to_string()),
+            trays: vec!["Auto".to_string(), "Tray1".to_string()],
+            duplex_modes: vec!["one-sided".to_string()],
+            color_modes: vec!["monochrome".to_string()],
+            resolutions: vec![Resolution { x_dpi: 300, y_dpi: 300 }, Resolution { x_dpi: 600, y_dpi: 600 }],
+            max_copies: Some(99),
+            document_formats: Vec::new(),
+        }
+    }
+
+    fn settings() -> PrintSettings {
+        PrintSettings {
+            page_size: Some("A4".to_string()),
+            duplex: Some(DuplexMode::TwoSidedLongEdge),
+            color: Some(ColorMode::Color),
+            tray: Some("tray1".to_string()),
+            copies: Some(150),
+            resolution: Some(1200),
+            scaling: Some(Scaling::Fit),
+            auto_rotate: Some(true),
+        }
+    }
+
+    #[test]
+    fn lenient_mode_substitutes() {
+        let report = validate(&settings(), &letter_printer(), ValidationMode::Lenient);
+        assert!(report.valid);
+        assert!(report.conflicts.is_empty());
+        let fields: Vec<&str> = report.substitutions.iter().map(|s| s.setting.as_str()).collect();
+        assert_eq!(fields, vec!["page_size", "duplex", "color", "copies", "resolution"]);
+
+        let adjusted = report.settings;
+        assert_eq!(adjusted.page_size.as_deref(), Some("Letter"));
+        assert_eq!(adjusted.duplex, Some(DuplexMode::OneSided));
+        assert_eq!(adjusted.color, Some(ColorMode::Monochrome));
+        assert_eq!(adjusted.tray.as_deref(), Some("Tray1"));
+        assert_eq!(adjusted.copies, Some(99));
+        assert_eq!(adjusted.resolution, Some(600));
+    }
+
+    #[test]
+    fn strict_mode_reports_conflicts() {
+        let report = validate(&settings(), &letter_printer(), ValidationMode::Strict);
+        assert!(!report.valid);
+        assert_eq!(report.conflicts.len(), 5);
+        assert_eq!(report.conflicts[0].substitute.as_deref(), Some("Letter"));
+        assert_eq!(report.settings.page_size.as_deref(), Some("A4"));
+        assert!(report.conflict_message().starts_with("打印机不支持纸张 A4; "));
+    }
+
+    #[test]
+    fn matches_media_by_size_and_falls_back() {
+        let mut capabilities = letter_printer();
+        capabilities.media_sizes.push(MediaSize {
+            name: "iso_a4_210x297mm".to_string(),
+            width_mm: Some(210.0),
+            height_mm: Some(297.0),
+        });
+        let request = PrintSettings { page_size: Some("a4".to_string()), ..Default::default() };
+        let report = validate(&request, &capabilities, ValidationMode::Strict);
+        assert!(report.valid);
+        assert_eq!(report.settings.page_size.as_deref(), Some("iso_a4_210x297mm"));
+
+        // 没有替换表中的纸张时选面积最接近的
+        let request = PrintSettings { page_size: Some("A6".to_string()), tray: Some("Manual".to_string()), ..Default::default() };
+        let report = validate(&request, &letter_printer(), ValidationMode::Lenient);
+        assert_eq!(report.settings.page_size.as_deref(), Some("Env10"));
+        assert_eq!(report.settings.tray, None);
+    }
+
+    #[test]
+    fn rejects_invalid_scaling() {
+        let request = PrintSettings { scaling: Some(Scaling::Custom(0.0)), ..Default::default() };
+        let report = validate(&request, &letter_printer(), ValidationMode::Lenient);
+        assert_eq!(report.settings.scaling, Some(Scaling::None));
+        assert!(!validate(&request, &letter_printer(), ValidationMode::Strict).valid);
+        let request = PrintSettings { scaling: Some(Scaling::Custom(80.0)), ..Default::default() };
+        assert!(validate(&request, &letter_printer(), ValidationMode::Strict).valid);
+    }
+
+    #[test]
+    fn reports_ppd_constraints() {
+        let ppd = crate::ppd::parse(
+            "*OpenUI *PageSize: PickOne\n*PageSize Letter/Letter: \"\"\n*PageSize Env10/Env10: \"\"\n*CloseUI: *PageSize\n\
+             *OpenUI *Duplex: PickOne\n*DefaultDuplex: None\n*Duplex None/Off: \"\"\n*Duplex DuplexNoTumble/Long: \"\"\n*CloseUI: *Duplex\n\
+             *UIConstraints: *PageSize Env10 *Duplex\n",
+        );
+        let mut capabilities = letter_printer();
+        capabilities.duplex_modes.push("two-sided-long-edge".to_string());
+        let request = PrintSettings {
+            page_size: Some("Env10".to_string()),
+            duplex: Some(DuplexMode::TwoSidedLongEdge),
+            ..Default::default()
+        };
+
+        let mut report = validate(&request, &capabilities, ValidationMode::Lenient);
+        assert!(report.valid);
+        check_constraints(&mut report, &ppd);
+        assert!(!report.valid);
+        assert_eq!(report.conflicts[0].message, "选项冲突: PageSize=Env10 与 Duplex");
+    }
+}
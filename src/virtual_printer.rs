@@ -0,0 +1,372 @@
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+use base64::{Engine as _, engine::general_purpose};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::declare::{PrintOptions, PrinterBackend, VirtualPrinter};
+use crate::fsys::remove_file;
+use crate::ipp;
+use crate::lpd::{self, LpdJob};
+use crate::raw_socket;
+use crate::status::{self, PrinterStatus};
+
+/**
+ * 虚拟打印机注册表
+ *
+ * 没有安装驱动的网络打印机以虚拟打印机的形式注册，
+ * 会出现在 `get_printers` 的结果中，并可以像系统打印机一样按名称打印。
+ */
+fn registry() -> MutexGuard<'static, Vec<VirtualPrinter>> {
+    static REGISTRY: OnceLock<Mutex<Vec<VirtualPrinter>>> = OnceLock::new();
+    REGISTRY
+        .get_or_init(|| Mutex::new(Vec::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// 原始数据打印结果
+#[derive(Debug, Serialize)]
+pub struct RawPrintResult {
+    pub printer: String,
+    pub bytes_sent: usize,
+    pub attempts: u32,
+    /// 后端分配的作业号（LPD/IPP）
+    pub job_id: Option<String>,
+    /// 打印机回传的状态数据（base64）
+    pub status: Option<String>,
+}
+
+/// 注册虚拟打印机，同名的会被替换
+pub fn register(printer: VirtualPrinter) -> Result<(), String> {
+    if printer.name.trim().is_empty() {
+        return Err("虚拟打印机名称不能为空".to_string());
+    }
+    let mut printers = registry();
+    printers.retain(|p| p.name != printer.name);
+    printers.push(printer);
+    Ok(())
+}
+
+pub fn unregister(name: &str) -> bool {
+    let mut printers = registry();
+    let before = printers.len();
+    printers.retain(|p| p.name != name);
+    printers.len() != before
+}
+
+pub fn find(name: &str) -> Option<VirtualPrinter> {
+    registry().iter().find(|p| p.name == name).cloned()
+}
+
+pub fn list() -> Vec<VirtualPrinter> {
+    registry().clone()
+}
+
+/**
+ * 发送原始数据到虚拟打印机
+ * @param job_name 作业名称（LPD 控制文件中的 J/N 行、IPP 的 job-name）
+ * @param copies 份数，raw 后端会重复发送数据
+ */
+pub fn print_bytes(printer: &VirtualPrinter, data: &[u8], job_name: &str, copies: u32) -> Result<RawPrintResult, String> {
+    let copies = copies.max(1);
+    match &printer.backend {
+        PrinterBackend::Raw(settings) => {
+            let payload = data.repeat(copies as usize);
+            let outcome = raw_socket::send(settings, &payload)?;
+            Ok(RawPrintResult {
+                printer: printer.name.clone(),
+                bytes_sent: payload.len(),
+                attempts: outcome.attempts,
+                job_id: None,
+                status: outcome.status.map(|s| general_purpose::STANDARD.encode(s)),
+            })
+        }
+        PrinterBackend::Lpd(settings) => {
+            let job_number = lpd::submit(settings, data, &LpdJob { name: job_name, copies })?;
+            Ok(RawPrintResult {
+                printer: printer.name.clone(),
+                bytes_sent: data.len(),
+                attempts: 1,
+                job_id: Some(job_number.to_string()),
+                status: None,
+            })
+        }
+        PrinterBackend::Ipp(settings) => {
+            let job_id = ipp::print_job(settings, data, job_name, copies)?;
+            Ok(RawPrintResult {
+                printer: printer.name.clone(),
+                bytes_sent: data.len(),
+                attempts: 1,
+                job_id: Some(job_id.to_string()),
+                status: None,
+            })
+        }
+    }
+}
+
+/**
+ * 打印文件到虚拟打印机，文件内容按原样发送（打印机需支持 PDF 直接打印）
+ * 只有发送成功才会按 remove_after_print 删除文件
+ */
+pub fn print_pdf(printer: &VirtualPrinter, options: PrintOptions) -> String {
+    let result = std::fs::read(&options.path)
+        .map_err(|e| format!("读取文件 {} 失败: {}", options.path, e))
+        .and_then(|data| {
+            let copies = options.settings.as_ref().and_then(|s| s.copies).unwrap_or(1);
+            print_bytes(printer, &data, &file_name(&options.path), copies)
+        });
+
+    match result {
+        Ok(result) => {
+            if options.remove_after_print {
+                let _ = remove_file(&options.path);
+            }
+            serde_json::to_string(&result).unwrap_or_default()
+        }
+        Err(e) => {
+            eprintln!("虚拟打印机打印失败: {}", e);
+            format!("打印失败: {}", e)
+        }
+    }
+}
+
+fn file_name(path: &str) -> String {
+    std::path::Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+/**
+ * 获取虚拟打印机的作业列表，格式与 `get_jobs` 一致
+ * raw 后端没有队列，始终返回空列表
+ */
+pub fn get_jobs(printer: &VirtualPrinter) -> Result<String, String> {
+    let jobs = match &printer.backend {
+        PrinterBackend::Raw(_) => Vec::new(),
+        PrinterBackend::Lpd(settings) => lpd::parse_queue(&lpd::query_queue(settings, false)?, &printer.name),
+        PrinterBackend::Ipp(settings) => ipp::get_jobs(settings, &printer.name)?,
+    };
+    serde_json::to_string_pretty(&jobs).map_err(|e| e.to_string())
+}
+
+pub fn get_job_by_id(printer: &VirtualPrinter, job_id: &str) -> Result<String, String> {
+    let jobs = match &printer.backend {
+        PrinterBackend::Raw(_) => Vec::new(),
+        PrinterBackend::Lpd(settings) => lpd::parse_queue(&lpd::query_queue(settings, false)?, &printer.name),
+        PrinterBackend::Ipp(settings) => ipp::get_jobs(settings, &printer.name)?,
+    };
+    let job = jobs.into_iter().find(|job| job.id == job_id);
+    match job {
+        Some(job) => serde_json::to_string_pretty(&job).map_err(|e| e.to_string()),
+        None => Ok(String::new()),
+    }
+}
+
+pub fn remove_job(printer: &VirtualPrinter, job_id: &str) -> Result<String, String> {
+    match &printer.backend {
+        PrinterBackend::Raw(_) => Err("raw 后端没有打印队列".to_string()),
+        PrinterBackend::Lpd(settings) => lpd::remove_jobs(settings, &[job_id.to_string()]),
+        PrinterBackend::Ipp(settings) => ipp::cancel_job(settings, job_id).map(|_| format!("作业 {} 已取消", job_id)),
+    }
+}
+
+/// 队列中的作业数，raw 后端没有队列时为 0，查询失败时为 None
+pub fn job_count(printer: &VirtualPrinter) -> Option<usize> {
+    match &printer.backend {
+        PrinterBackend::Raw(_) => Some(0),
+        PrinterBackend::Lpd(settings) => {
+            lpd::query_queue(settings, false).ok().map(|text| lpd::parse_queue(&text, &printer.name).len())
+        }
+        PrinterBackend::Ipp(settings) => ipp::get_jobs(settings, &printer.name).ok().map(|jobs| jobs.len()),
+    }
+}
+
+/// 查询作业数和状态后生成打印机条目
+fn describe(printer: &VirtualPrinter) -> Value {
+    printer_entry(printer, job_count(printer), &status::for_virtual(printer))
+}
+
+/// 与 `Get-Printer | Select-Object ...` 字段一致的打印机条目，作业数未知时 JobCount 为 null
+fn printer_entry(printer: &VirtualPrinter, job_count: Option<usize>, status: &PrinterStatus) -> Value {
+    let (driver, port, host) = match &printer.backend {
+        PrinterBackend::Raw(settings) => (
+            "Raw TCP/IP (AppSocket)",
+            raw_socket::address(settings),
+            settings.host.clone(),
+        ),
+        PrinterBackend::Lpd(settings) => (
+            "LPD/LPR (RFC 1179)",
+            format!("lpd://{}/{}", lpd::address(settings), settings.queue),
+            settings.host.clone(),
+        ),
+        PrinterBackend::Ipp(settings) => (
+            "IPP Everywhere",
+            settings.uri.clone(),
+            ipp::parse_uri(&settings.uri).map(|(host, _, _)| host).unwrap_or_default(),
+        ),
+    };
+    json!({
+        "Name": printer.name,
+        "DriverName": driver,
+        "JobCount": job_count,
+        "PrintProcessor": null,
+        "PortName": port,
+        "ShareName": null,
+        "ComputerName": host,
+        "PrinterStatus": status::windows_code(status.state),
+        "Shared": false,
+        "Type": 1,
+        "Priority": 1,
+        "Backend": printer.backend.kind(),
+    })
+}
+
+/// 单个虚拟打印机的 JSON，格式与 `get_printers_by_name` 一致
+pub fn printer_json(name: &str) -> Option<String> {
+    find(name).map(|printer| describe(&printer).to_string())
+}
+
+/**
+ * 将虚拟打印机合并到系统打印机列表
+ * @param system `Get-Printer | ConvertTo-Json` 的输出，单台打印机时为对象而非数组
+ */
+pub fn merge_printer_list(system: &str) -> String {
+    let printers = list();
+    if printers.is_empty() {
+        return system.to_string();
+    }
+
+    let mut entries = match serde_json::from_str::<Value>(system.trim()) {
+        Ok(Value::Array(list)) => list,
+        Ok(Value::Object(printer)) => vec![Value::Object(printer)],
+        _ => Vec::new(),
+    };
+    entries.extend(printers.iter().map(describe));
+    serde_json::to_string_pretty(&Value::Array(entries)).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::declare::{LpdSettings, RawSocketSettings};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn raw(name: &str, port: u16) -> VirtualPrinter {
+        VirtualPrinter {
+            name: name.to_string(),
+            backend: PrinterBackend::Raw(RawSocketSettings {
+                host: "127.0.0.1".to_string(),
+                port: Some(port),
+                connect_timeout_ms: Some(500),
+                read_timeout_ms: None,
+                retries: Some(0),
+                retry_delay_ms: None,
+                status_query: None,
+            }),
+        }
+    }
+
+    fn entry(list: &Value, name: &str) -> Value {
+        list.as_array().unwrap().iter().find(|printer| printer["Name"] == name).cloned().unwrap()
+    }
+
+    #[test]
+    fn registers_and_replaces_printers() {
+        assert!(register(raw(" ", 9100)).is_err());
+
+        register(raw("vp-register", 9100)).unwrap();
+        register(raw("vp-register", 9101)).unwrap();
+        assert_eq!(list().iter().filter(|p| p.name == "vp-register").count(), 1);
+        let PrinterBackend::Raw(settings) = find("vp-register").unwrap().backend else {
+            panic!("backend changed");
+        };
+        assert_eq!(settings.port, Some(9101));
+
+        assert!(unregister("vp-register"));
+        assert!(!unregister("vp-register"));
+        assert!(find("vp-register").is_none());
+    }
+
+    #[test]
+    fn reports_live_status_and_queue_depth() {
+        // raw 后端能连接时空闲，没有队列
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        register(raw("vp-raw", listener.local_addr().unwrap().port())).unwrap();
+        let printer: Value = serde_json::from_str(&printer_json("vp-raw").unwrap()).unwrap();
+        assert_eq!((printer["JobCount"].as_u64(), printer["PrinterStatus"].as_u64()), (Some(0), Some(0)));
+        assert_eq!(printer["Backend"], "raw");
+
+        // 端口关闭时离线
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        register(raw("vp-offline", closed)).unwrap();
+        let printer: Value = serde_json::from_str(&printer_json("vp-offline").unwrap()).unwrap();
+        assert_eq!(printer["PrinterStatus"].as_u64(), Some(8));
+
+        // LPD 队列中有两个作业，状态查询和作业数各查询一次
+        let lpd = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = lpd.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            for _ in 0..2 {
+                let (mut socket, _) = lpd.accept().unwrap();
+                let mut line = String::new();
+                BufReader::new(&socket).read_line(&mut line).unwrap();
+                assert_eq!(line, "\x03labels\n");
+                socket
+                    .write_all(
+                        b"Rank   Owner      Job  Files        Total Size\n\
+active alice      12   report.pdf   10240 bytes\n\
+1st    bob        13   label.zpl    512 bytes\n",
+                    )
+                    .unwrap();
+            }
+        });
+        register(VirtualPrinter {
+            name: "vp-lpd".to_string(),
+            backend: PrinterBackend::Lpd(LpdSettings {
+                host: "127.0.0.1".to_string(),
+                port: Some(port),
+                queue: "labels".to_string(),
+                user: None,
+                format: None,
+                banner: None,
+                connect_timeout_ms: Some(500),
+            }),
+        })
+        .unwrap();
+        let printer: Value = serde_json::from_str(&printer_json("vp-lpd").unwrap()).unwrap();
+        server.join().unwrap();
+        assert_eq!((printer["JobCount"].as_u64(), printer["PrinterStatus"].as_u64()), (Some(2), Some(11)));
+        assert_eq!(crate::groups::job_count_from_json(&printer.to_string()), Some(2));
+
+        assert!(printer_json("vp-missing").is_none());
+        for name in ["vp-raw", "vp-offline", "vp-lpd"] {
+            unregister(name);
+        }
+    }
+
+    #[test]
+    fn merges_virtual_printers_into_system_list() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        register(raw("vp-merge", port)).unwrap();
+
+        // 只有一台系统打印机时 ConvertTo-Json 输出对象
+        let merged: Value = serde_json::from_str(&merge_printer_list(r#"{ "Name": "HP", "JobCount": 3 }"#)).unwrap();
+        assert_eq!(merged[0]["Name"], "HP");
+        assert_eq!(entry(&merged, "vp-merge")["DriverName"], "Raw TCP/IP (AppSocket)");
+
+        let merged: Value = serde_json::from_str(&merge_printer_list(r#"[{ "Name": "HP" }, { "Name": "Canon" }]"#)).unwrap();
+        assert_eq!(merged[1]["Name"], "Canon");
+        assert_eq!(entry(&merged, "vp-merge")["JobCount"], 0);
+
+        // 非 Windows 平台没有系统打印机
+        let merged: Value = serde_json::from_str(&merge_printer_list("Unsupported OS")).unwrap();
+        assert!(merged.as_array().unwrap().iter().all(|printer| printer["Backend"].is_string()));
+        assert_eq!(entry(&merged, "vp-merge")["PortName"], format!("127.0.0.1:{}", port));
+        unregister("vp-merge");
+    }
+}
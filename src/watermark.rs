@@ -0,0 +1,350 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use base64::{Engine as _, engine::general_purpose};
+use chrono::Local;
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream};
+
+use crate::declare::{CjkFont, TextFont, Watermark, WatermarkPosition};
+use crate::fonts;
+use crate::history;
+use crate::pdf;
+
+const DEFAULT_FONT_SIZE: f64 = 48.0;
+const DEFAULT_OPACITY: f64 = 0.3;
+const DEFAULT_MARGIN: f64 = 36.0;
+const DEFAULT_COLOR: [f64; 3] = [0.5, 0.5, 0.5];
+
+/// 替换 {user}、{date}、{time} 占位符
+pub fn expand_text(text: &str) -> String {
+    let now = Local::now();
+    text.replace("{user}", &history::current_user().unwrap_or_default())
+        .replace("{date}", &now.format("%Y-%m-%d").to_string())
+        .replace("{time}", &now.format("%H:%M").to_string())
+}
+
+/// 解析 #RRGGBB
+fn parse_color(color: Option<&str>) -> Result<[f64; 3], String> {
+    let Some(color) = color else {
+        return Ok(DEFAULT_COLOR);
+    };
+    let hex = color.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("无效的颜色: {}", color));
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(0) as f64 / 255.0;
+    Ok([channel(0), channel(2), channel(4)])
+}
+
+/// 水印文字使用的字体
+struct Font {
+    id: ObjectId,
+    /// 非 ASCII 文字使用 STSong-Light（Adobe-GB1，不嵌入，由阅读器替换）
+    cjk: bool,
+}
+
+impl Font {
+    fn add(document: &mut Document, text: &str) -> Font {
+        if text.is_ascii() {
+            return Font { id: fonts::add_latin(document, TextFont::Helvetica), cjk: false };
+        }
+        Font { id: fonts::add_cjk(document, CjkFont::SimplifiedChinese), cjk: true }
+    }
+
+    /// 文字宽度（em）
+    fn width(&self, text: &str) -> f64 {
+        text.chars()
+            .map(|c| if self.cjk { fonts::cjk_width(c) } else { fonts::latin_width(TextFont::Helvetica, c) })
+            .sum::<u16>() as f64
+            / 1000.0
+    }
+
+    /// 大写字母高度（em），用于垂直居中
+    fn cap_height(&self) -> f64 {
+        if self.cjk { 0.8 } else { 0.72 }
+    }
+
+    /// 十六进制字符串，Helvetica 按 WinAnsi 单字节，STSong 按 UCS-2
+    fn encode(&self, text: &str) -> String {
+        if self.cjk { fonts::encode_ucs2(text) } else { fonts::encode_latin(text) }
+    }
+}
+
+/// 嵌入图片，透明通道作为 SMask
+fn add_image(document: &mut Document, data: &str) -> Result<(ObjectId, u32, u32), String> {
+    let bytes = general_purpose::STANDARD.decode(data.trim()).map_err(|e| format!("图片 base64 无效: {}", e))?;
+    let image = image::load_from_memory(&bytes).map_err(|e| format!("图片解码失败: {}", e))?.to_rgba8();
+    let (width, height) = image.dimensions();
+    let mut rgb = Vec::with_capacity((width * height * 3) as usize);
+    let mut alpha = Vec::with_capacity((width * height) as usize);
+    for pixel in image.pixels() {
+        rgb.extend_from_slice(&pixel.0[..3]);
+        alpha.push(pixel.0[3]);
+    }
+    let compressed = |dict: Dictionary, data: Vec<u8>| {
+        let mut stream = Stream::new(dict, data);
+        let _ = stream.compress();
+        stream
+    };
+    let mut dict = dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Image",
+        "Width" => width,
+        "Height" => height,
+        "ColorSpace" => "DeviceRGB",
+        "BitsPerComponent" => 8,
+    };
+    if alpha.iter().any(|&a| a < 255) {
+        let mask = document.add_object(compressed(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => width,
+                "Height" => height,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+            },
+            alpha,
+        ));
+        dict.set("SMask", mask);
+    }
+    Ok((document.add_object(compressed(dict, rgb)), width, height))
+}
+
+/// 旋转后的内容在显示区域中的中心点
+fn anchor(position: WatermarkPosition, size: (f64, f64), angle: f64, page: (f64, f64), margin: f64) -> (f64, f64) {
+    let (cos, sin) = (angle.cos().abs(), angle.sin().abs());
+    let half_width = (cos * size.0 + sin * size.1) / 2.0;
+    let half_height = (sin * size.0 + cos * size.1) / 2.0;
+    let left = margin + half_width;
+    let right = page.0 - margin - half_width;
+    let top = page.1 - margin - half_height;
+    let bottom = margin + half_height;
+    let (center_x, center_y) = (page.0 / 2.0, page.1 / 2.0);
+    use WatermarkPosition::*;
+    match position {
+        TopLeft => (left, top),
+        Top => (center_x, top),
+        TopRight => (right, top),
+        Left => (left, center_y),
+        Center => (center_x, center_y),
+        Right => (right, center_y),
+        BottomLeft => (left, bottom),
+        Bottom => (center_x, bottom),
+        BottomRight => (right, bottom),
+    }
+}
+
+/// 资源字典中未使用的名称
+fn unique_name(resources: &Dictionary, prefix: &str) -> String {
+    (1..).map(|n| format!("{}{}", prefix, n)).find(|name| !resources.has(name.as_bytes())).unwrap_or_default()
+}
+
+fn resolve_dictionary(document: &Document, object: Object) -> Dictionary {
+    match object {
+        Object::Reference(id) => document.get_dictionary(id).cloned().unwrap_or_default(),
+        Object::Dictionary(dict) => dict,
+        _ => Dictionary::new(),
+    }
+}
+
+/// 把资源加入页面，返回资源名称
+fn add_resources(document: &mut Document, page_id: ObjectId, entries: &[(&[u8], &str, ObjectId)]) -> Vec<String> {
+    let mut resources = pdf::inherited(document, page_id, b"Resources")
+        .map(|r| resolve_dictionary(document, r))
+        .unwrap_or_default();
+    let mut names = Vec::new();
+    for &(category, prefix, id) in entries {
+        let mut dict = resources.get(category).cloned().map(|d| resolve_dictionary(document, d)).unwrap_or_default();
+        let name = unique_name(&dict, prefix);
+        dict.set(name.as_bytes(), id);
+        resources.set(category, dict);
+        names.push(name);
+    }
+    if let Ok(page) = document.get_dictionary_mut(page_id) {
+        page.set("Resources", resources);
+    }
+    names
+}
+
+/// 在原有内容之后绘制，原有内容包在 q/Q 中，不影响水印的图形状态
+fn append_content(document: &mut Document, page_id: ObjectId, content: String) -> Result<(), String> {
+    let existing = match document.get_dictionary(page_id).map_err(|e| e.to_string())?.get(b"Contents") {
+        Ok(Object::Array(items)) => items.clone(),
+        Ok(item @ Object::Reference(_)) => vec![item.clone()],
+        _ => Vec::new(),
+    };
+    let mut contents = Vec::new();
+    let mut stamp = String::new();
+    if !existing.is_empty() {
+        contents.push(document.add_object(Stream::new(Dictionary::new(), b"q\n".to_vec())).into());
+        contents.extend(existing);
+        stamp.push_str("Q\n");
+    }
+    stamp.push_str(&content);
+    contents.push(document.add_object(Stream::new(Dictionary::new(), stamp.into_bytes())).into());
+    document.get_dictionary_mut(page_id).map_err(|e| e.to_string())?.set("Contents", contents);
+    Ok(())
+}
+
+fn matrix(m: [f64; 6]) -> String {
+    format!("{:.4} {:.4} {:.4} {:.4} {:.2} {:.2} cm\n", m[0], m[1], m[2], m[3], m[4], m[5])
+}
+
+/**
+ * 给文档加水印
+ */
+pub fn apply(document: &mut Document, watermark: &Watermark) -> Result<(), String> {
+    let text = watermark.text.as_deref().map(expand_text).filter(|t| !t.trim().is_empty());
+    if text.is_none() && watermark.image.is_none() {
+        return Err("水印需要指定文字或图片".to_string());
+    }
+    let font_size = watermark.font_size.unwrap_or(DEFAULT_FONT_SIZE);
+    let opacity = watermark.opacity.unwrap_or(DEFAULT_OPACITY);
+    if font_size <= 0.0 || !(0.0..=1.0).contains(&opacity) {
+        return Err("字号必须大于 0，透明度必须在 0-1 之间".to_string());
+    }
+    let color = parse_color(watermark.color.as_deref())?;
+    let margin = watermark.margin.unwrap_or(DEFAULT_MARGIN);
+    let angle = watermark.angle.unwrap_or(0.0).to_radians();
+
+    let count = document.get_pages().len() as u32;
+    let pages = pdf::parse_pages(watermark.pages.as_deref().unwrap_or(""), count)?;
+    let page_ids = pdf::page_ids(document, &pages)?;
+
+    let font = text.as_deref().map(|text| Font::add(document, text));
+    let image = watermark.image.as_deref().map(|data| add_image(document, data)).transpose()?;
+    let state = document.add_object(dictionary! {
+        "Type" => "ExtGState",
+        "ca" => opacity as f32,
+        "CA" => opacity as f32,
+    });
+
+    let mut done = HashSet::new();
+    for page_id in page_ids.into_iter().filter(|id| done.insert(*id)) {
+        let (bounds, rotate) = pdf::page_geometry(document, page_id);
+        let (to_page, width, height) = pdf::display_matrix(bounds, rotate);
+        let mut entries: Vec<(&[u8], &str, ObjectId)> = vec![(b"ExtGState", "WmGS", state)];
+        if let Some(font) = &font {
+            entries.push((b"Font", "WmF", font.id));
+        }
+        if let Some((id, _, _)) = image {
+            entries.push((b"XObject", "WmIm", id));
+        }
+        let names = add_resources(document, page_id, &entries);
+
+        let mut content = format!("q\n{}/{} gs\n", matrix(to_page), names[0]);
+        let rotation = |x: f64, y: f64| [angle.cos(), angle.sin(), -angle.sin(), angle.cos(), x, y];
+        if let Some((_, pixel_width, pixel_height)) = image {
+            // 默认按 96 dpi，超出页面时缩小
+            let natural = pixel_width as f64 * 0.75;
+            let image_width = watermark.image_width.unwrap_or(natural.min(width - 2.0 * margin)).max(1.0);
+            let image_height = image_width * pixel_height as f64 / pixel_width as f64;
+            let (x, y) = anchor(watermark.position, (image_width, image_height), angle, (width, height), margin);
+            content.push_str("q\n");
+            content.push_str(&matrix(rotation(x, y)));
+            content.push_str(&matrix([image_width, 0.0, 0.0, image_height, -image_width / 2.0, -image_height / 2.0]));
+            content.push_str(&format!("/{} Do\nQ\n", names[names.len() - 1]));
+        }
+        if let (Some(font), Some(text)) = (&font, &text) {
+            let text_width = font.width(text) * font_size;
+            let text_height = font.cap_height() * font_size;
+            let (x, y) = anchor(watermark.position, (text_width, text_height), angle, (width, height), margin);
+            content.push_str(&matrix(rotation(x, y)));
+            content.push_str(&format!(
+                "{:.3} {:.3} {:.3} rg\nBT\n/{} {:.2} Tf\n{:.2} {:.2} Td\n<{}> Tj\nET\n",
+                color[0],
+                color[1],
+                color[2],
+                names[1],
+                font_size,
+                -text_width / 2.0,
+                -text_height / 2.0,
+                font.encode(text),
+            ));
+        }
+        content.push_str("Q\n");
+        append_content(document, page_id, content)?;
+    }
+    Ok(())
+}
+
+/**
+ * 加水印后保存到输出目录
+ * @returns 输出文件路径
+ */
+pub fn stamp_file(path: &Path, watermark: &Watermark) -> Result<PathBuf, String> {
+    pdf::transform_file(path, |document, _| apply(document, watermark))
+}
+
+/// 直接在原文件上加水印，用于插件生成的临时 PDF
+pub fn stamp_in_place(path: &Path, watermark: &Watermark) -> Result<(), String> {
+    let mut document = pdf::load(path)?;
+    apply(&mut document, watermark)?;
+    pdf::save(&mut document, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content_of(document: &Document, page_id: ObjectId) -> String {
+        let contents = document.get_dictionary(page_id).unwrap().get(b"Contents").unwrap().as_array().unwrap();
+        let last = contents.last().unwrap().as_reference().unwrap();
+        let stream = document.get_object(last).unwrap().as_stream().unwrap();
+        String::from_utf8(stream.content.clone()).unwrap()
+    }
+
+    #[test]
+    fn stamps_selected_pages() {
+        let mut document = pdf::tests::sample(3, [0, 0, 600, 800]);
+        let first = document.get_pages()[&1];
+        // 第一页已有内容
+        let original = document.add_object(Stream::new(Dictionary::new(), b"0 0 m 10 10 l S\n".to_vec()));
+        document.get_dictionary_mut(first).unwrap().set("Contents", original);
+
+        let watermark = Watermark {
+            text: Some("COPY".to_string()),
+            color: Some("#FF0000".to_string()),
+            pages: Some("1-2".to_string()),
+            angle: Some(90.0),
+            ..Watermark::default()
+        };
+        apply(&mut document, &watermark).unwrap();
+
+        let pages = document.get_pages();
+        let contents = document.get_dictionary(pages[&1]).unwrap().get(b"Contents").unwrap().as_array().unwrap();
+        assert_eq!(contents.len(), 3);
+        let stamp = content_of(&document, pages[&1]);
+        assert!(stamp.starts_with("Q\nq\n"));
+        assert!(stamp.contains("1.000 0.000 0.000 rg"));
+        assert!(stamp.contains("<434F5059> Tj"));
+        // 旋转 90° 后以页面中心为中心
+        assert!(stamp.contains("0.0000 1.0000 -1.0000 0.0000 300.00 400.00 cm"));
+        assert!(document.get_dictionary(pages[&3]).unwrap().get(b"Contents").is_err());
+
+        let resources = document.get_dictionary(pages[&2]).unwrap().get(b"Resources").unwrap().as_dict().unwrap();
+        assert!(resources.get(b"Font").unwrap().as_dict().unwrap().has(b"WmF1"));
+        // 再次加水印使用新的资源名称
+        apply(&mut document, &Watermark { text: Some("副本".to_string()), ..Watermark::default() }).unwrap();
+        let stamp = content_of(&document, pages[&2]);
+        assert!(stamp.contains("/WmF2 48.00 Tf"));
+        assert!(stamp.contains("<526F672C>"));
+    }
+
+    #[test]
+    fn positions_on_rotated_pages() {
+        assert_eq!(pdf::display_matrix([0.0, 0.0, 600.0, 800.0], 90), ([0.0, 1.0, -1.0, 0.0, 600.0, 0.0], 800.0, 600.0));
+        let (x, y) = anchor(WatermarkPosition::BottomRight, (100.0, 20.0), 0.0, (800.0, 600.0), 36.0);
+        assert_eq!((x, y), (714.0, 46.0));
+        // 旋转 90° 后宽高互换
+        let (x, y) = anchor(WatermarkPosition::TopLeft, (100.0, 20.0), 90f64.to_radians(), (800.0, 600.0), 36.0);
+        assert!((x - 46.0).abs() < 1e-9 && (y - 514.0).abs() < 1e-9);
+
+        assert_eq!(parse_color(Some("#00ff80")).unwrap()[1], 1.0);
+        assert!(parse_color(Some("red")).is_err());
+        assert!(apply(&mut pdf::tests::sample(1, [0, 0, 600, 800]), &Watermark::default()).is_err());
+        let text = expand_text("{date} {time}");
+        assert_eq!(text.len(), 16);
+    }
+}
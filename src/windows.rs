@@ -6,7 +6,7 @@ use std::thread;
 use std::fs::{File};
 use std::env;
 use std::path::{Path, PathBuf};
-use crate::declare::{PrintOptions, PrintHtmlOptions};
+use crate::declare::{ColorMode, DuplexMode, PrintOptions, PrintHtmlOptions, PrintSettings, Scaling};
 use crate::{ fsys::remove_file};
 /**
  * Create sm.exe to temp
@@ -83,6 +83,103 @@ pub fn get_printers_by_name(printername: String) -> String {
     return result;
 }
 
+/**
+ * Get printer capabilities on windows using powershell
+ * 纸张、纸盒、双面、彩色和分辨率来自 System.Drawing.Printing.PrinterSettings，默认值来自 Get-PrintConfiguration
+ */
+pub fn get_printer_capabilities(printername: String) -> String {
+    let name = printername.replace('\'', "''");
+    let script = format!(
+        "[Console]::OutputEncoding = [System.Text.Encoding]::UTF8; \
+        Add-Type -AssemblyName System.Drawing; \
+        $s = New-Object System.Drawing.Printing.PrinterSettings; $s.PrinterName = '{0}'; \
+        $c = Get-PrintConfiguration -PrinterName '{0}' -ErrorAction SilentlyContinue; \
+        [PSCustomObject]@{{ \
+            IsValid = $s.IsValid; \
+            PaperSizes = @($s.PaperSizes | Select-Object PaperName, Width, Height); \
+            PaperSources = @($s.PaperSources | ForEach-Object {{ $_.SourceName }}); \
+            CanDuplex = $s.CanDuplex; \
+            SupportsColor = $s.SupportsColor; \
+            Resolutions = @($s.PrinterResolutions | Where-Object {{ $_.X -gt 0 }} | Select-Object X, Y); \
+            MaximumCopies = $s.MaximumCopies; \
+            PaperSize = [string]$c.PaperSize \
+        }} | ConvertTo-Json -Depth 3",
+        name
+    );
+
+    let output = Command::new("powershell").args(["-Command", &script]).output().unwrap();
+    return String::from_utf8_lossy(&output.stdout).to_string();
+}
+
+/**
+ * Get the network address of a printer's TCP/IP port using powershell
+ * USB、WSD 等端口返回空字符串
+ */
+pub fn get_printer_host(printername: String) -> String {
+    let name = printername.replace('\'', "''");
+    let script = format!(
+        "$p = Get-Printer -Name '{}' -ErrorAction SilentlyContinue; \
+        if ($p) {{ (Get-PrinterPort -Name $p.PortName -ErrorAction SilentlyContinue).PrinterHostAddress }}",
+        name
+    );
+
+    let output = Command::new("powershell").args(["-Command", &script]).output().unwrap();
+    return String::from_utf8_lossy(&output.stdout).trim().to_string();
+}
+
+/// 纸张和纸盒名称中不能有逗号、等号和控制字符，否则 SumatraPDF 会拆成另一项设置
+fn check_setting_value(name: &str, value: &str) -> Result<(), String> {
+    if value.trim().is_empty() || value.chars().any(|c| c == ',' || c == '=' || c.is_control()) {
+        return Err(format!("{} 包含不支持的字符: {}", name, value));
+    }
+    Ok(())
+}
+
+/**
+ * 转换为 SumatraPDF 的 -print-settings 参数，如 "duplexlong,monochrome,paper=A4,bin=Tray1,2x,fit"
+ * SumatraPDF 不支持设置分辨率，默认按纸张方向自动旋转页面，auto_rotate 为 false 时固定为纵向
+ */
+fn sumatra_print_settings(settings: &PrintSettings) -> Result<String, String> {
+    let mut parts: Vec<String> = Vec::new();
+    if let Some(duplex) = settings.duplex {
+        parts.push(match duplex {
+            DuplexMode::OneSided => "simplex",
+            DuplexMode::TwoSidedLongEdge => "duplexlong",
+            DuplexMode::TwoSidedShortEdge => "duplexshort",
+        }.to_string());
+    }
+    if let Some(color) = settings.color {
+        parts.push(match color {
+            ColorMode::Monochrome => "monochrome",
+            ColorMode::Color => "color",
+        }.to_string());
+    }
+    if let Some(page_size) = &settings.page_size {
+        check_setting_value("page_size", page_size)?;
+        parts.push(format!("paper={}", page_size));
+    }
+    if let Some(tray) = &settings.tray {
+        check_setting_value("tray", tray)?;
+        parts.push(format!("bin={}", tray));
+    }
+    if let Some(copies) = settings.copies.filter(|c| *c > 1) {
+        parts.push(format!("{}x", copies));
+    }
+    // 自定义比例和平铺已经在PDF中按纸张调整好
+    if let Some(scaling) = settings.scaling {
+        parts.push(match scaling {
+            Scaling::Fit => "fit",
+            Scaling::Shrink => "shrink",
+            Scaling::None | Scaling::Tile | Scaling::Custom(_) => "noscale",
+        }.to_string());
+    }
+    // 指定方向后 SumatraPDF 不再逐页旋转
+    if settings.auto_rotate == Some(false) {
+        parts.push("portrait".to_string());
+    }
+    Ok(parts.join(","))
+}
+
 /**
  * Print pdf file 
  */
@@ -92,35 +189,52 @@ pub fn print_pdf (options: PrintOptions) -> String {
 
     let dir: std::path::PathBuf = env::temp_dir();
     let print_setting: String = options.print_setting;
-    let shell_command = if print_setting.is_empty() {
-        format!("{}sm.exe -print-to-default -silent \"{}\"", dir.display(), options.path)
+    // 直接启动 sm.exe，参数不经过 shell 解析
+    let mut args: Vec<String> = if print_setting.is_empty() {
+        vec!["-print-to-default".to_string()]
     } else {
-        format!("{}sm.exe -print-to \"{}\" -silent \"{}\"", dir.display(), print_setting, options.path)
+        vec!["-print-to".to_string(), print_setting]
     };
-    
+    args.push("-silent".to_string());
+    let settings = match options.settings.as_ref().map(sumatra_print_settings).transpose() {
+        Ok(settings) => settings.filter(|s| !s.is_empty()),
+        Err(e) => return format!("打印失败: {}", e),
+    };
+    if let Some(settings) = settings {
+        args.extend(["-print-settings".to_string(), settings]);
+    }
+    args.push(options.path.clone());
+    let program = format!("{}sm.exe", dir.display());
 
     // Create a channel for communication
     let (sender, receiver) = mpsc::channel();
-    println!("{}", shell_command);
+    println!("{} {:?}", program, args);
     // Spawn a new thread
     thread::spawn(move || {
-        // let output: tauri_plugin_shell::process::Output = Command::new("powershell").args([shell_command]).output().unwrap();
-
-        // sender.send(output.stdout.to_string()).unwrap();
-
-        let output = Command::new("powershell").args([shell_command]).output().unwrap();
-
-        sender.send(String::from_utf8(output.stdout).unwrap()).unwrap();
+        let output = match Command::new(&program).args(&args).output() {
+            Ok(output) => output,
+            Err(e) => {
+                sender.send((false, format!("打印失败: {}", e))).unwrap();
+                return;
+            }
+        };
+
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        if output.status.success() {
+            sender.send((true, stdout)).unwrap();
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            sender.send((false, format!("打印失败: {}{}", stdout, stderr.trim()))).unwrap();
+        }
     });
 
     // Do other non-blocking work on the main thread
 
     // Receive the result from the spawned thread
-    let result = receiver.recv().unwrap();
-    
-    
+    let (success, result) = receiver.recv().unwrap();
 
-    if options.remove_after_print == true {
+    // 打印失败时保留文件，便于重新打印
+    if options.remove_after_print == true && success {
         let _ = remove_file(&options.path);
     }
     
@@ -204,12 +318,22 @@ fn print_html_internal(options: PrintHtmlOptions) -> Result<String, String> {
     
     println!("PDF 文件生成成功: {:?}", pdf_path);
 
+    // 添加水印
+    if let Some(watermark) = &options.watermark {
+        if let Err(e) = crate::watermark::stamp_in_place(&pdf_path, watermark) {
+            let _ = remove_file(&html_path.to_string_lossy());
+            let _ = remove_file(&pdf_path.to_string_lossy());
+            return Err(format!("添加水印失败: {}", e));
+        }
+    }
+
     // 创建打印选项并执行打印
     let print_options = PrintOptions {
         path: pdf_path.to_string_lossy().to_string(),
         id: options.printer_id.unwrap_or_default(),
         print_setting: options.print_settings.unwrap_or_default(),
         remove_after_print: options.remove_after_print.unwrap_or(true),
+        settings: None,
     };
 
     // 执行打印
@@ -386,4 +510,39 @@ pub fn remove_job(printername: String, jobid: String) -> String {
     // return output.stdout.to_string();
     let output = Command::new("powershell").args([format!("Remove-PrintJob -PrinterName \"{}\" -ID \"{}\" ", printername, jobid)]).output().unwrap();
     return String::from_utf8(output.stdout).unwrap();
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_unsafe_sumatra_settings() {
+        let settings = PrintSettings {
+            page_size: Some("A4".to_string()),
+            tray: Some("Tray 1".to_string()),
+            duplex: Some(DuplexMode::TwoSidedLongEdge),
+            copies: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(sumatra_print_settings(&settings).unwrap(), "duplexlong,paper=A4,bin=Tray 1,2x");
+        let fixed = PrintSettings { scaling: Some(Scaling::Shrink), auto_rotate: Some(false), ..Default::default() };
+        assert_eq!(sumatra_print_settings(&fixed).unwrap(), "shrink,portrait");
+        let rotated = PrintSettings { auto_rotate: Some(true), ..fixed };
+        assert_eq!(sumatra_print_settings(&rotated).unwrap(), "shrink");
+        // 驱动自己的纸盒和纸张名称
+        let driver = PrintSettings {
+            page_size: Some("Letter (8.5 x 11 in)".to_string()),
+            tray: Some("纸盒 1".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(sumatra_print_settings(&driver).unwrap(), "paper=Letter (8.5 x 11 in),bin=纸盒 1");
+        let envelope = PrintSettings { page_size: Some("Envelope #10".to_string()), tray: Some("手动进纸".to_string()), ..Default::default() };
+        assert_eq!(sumatra_print_settings(&envelope).unwrap(), "paper=Envelope #10,bin=手动进纸");
+        for value in ["A4,landscape", "bin=2", "A4\nx", "", " "] {
+            let page_size = PrintSettings { page_size: Some(value.to_string()), ..Default::default() };
+            assert!(sumatra_print_settings(&page_size).is_err(), "{}", value);
+            let tray = PrintSettings { tray: Some(value.to_string()), ..Default::default() };
+            assert!(sumatra_print_settings(&tray).is_err(), "{}", value);
+        }
+    }
+}